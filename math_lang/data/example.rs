@@ -1,25 +1,434 @@
+#![allow(dead_code)]
+
 use std::io::Write;
 
-#[allow(dead_code)]
-fn input() -> f64 {
+fn read_line() -> String {
     let mut text = String::new();
     eprint!("<input>: ");
     std::io::stderr().flush().unwrap();
-    std::io::stdin()
-        .read_line(&mut text)
-        .expect("Cannot read line.");
-    text.trim().parse::<f64>().unwrap_or(0.)
+    if std::io::stdin().read_line(&mut text).expect("Cannot read line.") == 0 {
+        eprintln!("Error: The input ended before a value was read.");
+        std::process::exit(1);
+    }
+    text
+}
+
+fn input_int_in_range(lo: f64, hi: f64) -> i64 {
+    loop {
+        let text = read_line();
+        match text.trim().parse::<i64>() {
+            Ok(value) if lo <= value as f64 && value as f64 <= hi => return value,
+            Ok(_) => eprintln!("The value must be in [{}, {}].", lo, hi),
+            Err(_) => eprintln!("Cannot read '{}' as an int.", text.trim()),
+        }
+    }
+}
+
+fn checked(value: Option<i64>) -> i64 {
+    value.expect("Integer overflow or division by zero.")
+}
+
+fn int_pow(base: i64, exponent: i64) -> i64 {
+    if exponent < 0 {
+        panic!("Negative exponent {} for an int.", exponent);
+    }
+    checked(base.checked_pow(exponent.min(u32::MAX as i64) as u32))
+}
+
+fn float_to_int(value: f64) -> i64 {
+    let truncated = value.trunc();
+    if truncated >= i64::MIN as f64 && truncated < -(i64::MIN as f64) {
+        truncated as i64
+    } else {
+        panic!("Cannot convert {} to an int.", value)
+    }
+}
+
+// Dual numbers for forward-mode automatic differentiation: a value along
+// with its derivative with respect to one variable, for `diff` and `grad`,
+// and for the uncertainty of functions of measured values.
+//
+// Where a function has no derivative, a subgradient is used: `abs` has the
+// derivative 0 at 0, and `ceil`, `floor` and `sgn` have the derivative 0
+// everywhere, their jumps included. A derivative multiplied by a zero
+// derivative is 0, even if it is infinite, so that constants such as
+// `sqrt(0)` never make a derivative NaN.
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Dual {
+    pub value: f64,
+    pub derivative: f64,
+}
+
+fn scaled(derivative: f64, factor: f64) -> f64 {
+    if derivative == 0. {
+        0.
+    } else {
+        derivative * factor
+    }
+}
+
+impl std::ops::Add for Dual {
+    type Output = Dual;
+    fn add(self, other: Dual) -> Dual {
+        Dual {
+            value: self.value + other.value,
+            derivative: self.derivative + other.derivative,
+        }
+    }
+}
+
+impl std::ops::Sub for Dual {
+    type Output = Dual;
+    fn sub(self, other: Dual) -> Dual {
+        Dual {
+            value: self.value - other.value,
+            derivative: self.derivative - other.derivative,
+        }
+    }
+}
+
+impl std::ops::Mul for Dual {
+    type Output = Dual;
+    fn mul(self, other: Dual) -> Dual {
+        Dual {
+            value: self.value * other.value,
+            derivative: scaled(self.derivative, other.value) + scaled(other.derivative, self.value),
+        }
+    }
+}
+
+impl std::ops::Div for Dual {
+    type Output = Dual;
+    fn div(self, other: Dual) -> Dual {
+        let value = self.value / other.value;
+        Dual {
+            value,
+            derivative: scaled(self.derivative, 1. / other.value)
+                - scaled(other.derivative, value / other.value),
+        }
+    }
+}
+
+// The methods have the names of their f64 counterparts, and programs
+// emitted by `compiler` may only use some of them.
+impl Dual {
+    pub fn constant(value: f64) -> Dual {
+        Dual { value, derivative: 0. }
+    }
+
+    /// The variable of differentiation, whose derivative is 1.
+    pub fn variable(value: f64) -> Dual {
+        Dual { value, derivative: 1. }
+    }
+
+    // f(self), given f and its derivative at the value of `self`.
+    fn chain(self, value: f64, derivative: f64) -> Dual {
+        Dual {
+            value,
+            derivative: scaled(self.derivative, derivative),
+        }
+    }
+
+    pub fn powf(self, exponent: Dual) -> Dual {
+        let (a, b) = (self.value, exponent.value);
+        let value = a.powf(b);
+        Dual {
+            value,
+            derivative: scaled(self.derivative, b * a.powf(b - 1.))
+                + scaled(exponent.derivative, value * a.ln()),
+        }
+    }
+
+    pub fn div_euclid(self, other: Dual) -> Dual {
+        Dual::constant(self.value.div_euclid(other.value))
+    }
+
+    pub fn rem_euclid(self, other: Dual) -> Dual {
+        Dual {
+            value: self.value.rem_euclid(other.value),
+            derivative: self.derivative
+                - scaled(other.derivative, self.value.div_euclid(other.value)),
+        }
+    }
+
+    pub fn sin(self) -> Dual {
+        self.chain(self.value.sin(), self.value.cos())
+    }
+
+    pub fn cos(self) -> Dual {
+        self.chain(self.value.cos(), -self.value.sin())
+    }
+
+    pub fn tan(self) -> Dual {
+        let value = self.value.tan();
+        self.chain(value, 1. + value * value)
+    }
+
+    pub fn asin(self) -> Dual {
+        self.chain(self.value.asin(), 1. / (1. - self.value * self.value).sqrt())
+    }
+
+    pub fn acos(self) -> Dual {
+        self.chain(self.value.acos(), -1. / (1. - self.value * self.value).sqrt())
+    }
+
+    pub fn atan(self) -> Dual {
+        self.chain(self.value.atan(), 1. / (1. + self.value * self.value))
+    }
+
+    pub fn sinh(self) -> Dual {
+        self.chain(self.value.sinh(), self.value.cosh())
+    }
+
+    pub fn cosh(self) -> Dual {
+        self.chain(self.value.cosh(), self.value.sinh())
+    }
+
+    pub fn tanh(self) -> Dual {
+        let value = self.value.tanh();
+        self.chain(value, 1. - value * value)
+    }
+
+    pub fn asinh(self) -> Dual {
+        self.chain(self.value.asinh(), 1. / (self.value * self.value + 1.).sqrt())
+    }
+
+    pub fn acosh(self) -> Dual {
+        self.chain(self.value.acosh(), 1. / (self.value * self.value - 1.).sqrt())
+    }
+
+    pub fn atanh(self) -> Dual {
+        self.chain(self.value.atanh(), 1. / (1. - self.value * self.value))
+    }
+
+    pub fn exp(self) -> Dual {
+        let value = self.value.exp();
+        self.chain(value, value)
+    }
+
+    pub fn exp2(self) -> Dual {
+        let value = self.value.exp2();
+        self.chain(value, value * std::f64::consts::LN_2)
+    }
+
+    pub fn ln(self) -> Dual {
+        self.chain(self.value.ln(), 1. / self.value)
+    }
+
+    pub fn log10(self) -> Dual {
+        self.chain(self.value.log10(), 1. / (self.value * std::f64::consts::LN_10))
+    }
+
+    pub fn log2(self) -> Dual {
+        self.chain(self.value.log2(), 1. / (self.value * std::f64::consts::LN_2))
+    }
+
+    pub fn sqrt(self) -> Dual {
+        let value = self.value.sqrt();
+        self.chain(value, 0.5 / value)
+    }
+
+    pub fn abs(self) -> Dual {
+        let slope = if self.value == 0. { 0. } else { self.value.signum() };
+        self.chain(self.value.abs(), slope)
+    }
+
+    pub fn ceil(self) -> Dual {
+        Dual::constant(self.value.ceil())
+    }
+
+    pub fn floor(self) -> Dual {
+        Dual::constant(self.value.floor())
+    }
+
+    pub fn signum(self) -> Dual {
+        Dual::constant(self.value.signum())
+    }
+
+    // The argument of a real number, 0 or pi.
+    pub fn arg(self) -> Dual {
+        Dual::constant(0f64.atan2(self.value))
+    }
+}
+
+// Measured values with a standard uncertainty, propagated to first order.
+// Every `+-` evaluated and every measured value read is an independent
+// source of uncertainty, and a value keeps the part of its uncertainty that
+// comes from each source. The uses of a variable therefore stay correlated:
+// `g - g` is exactly 0 and `g * g` has twice the relative uncertainty of `g`.
+// The functions are applied by the caller, from the value and the derivative
+// `Dual` gives.
+
+static NEXT_SOURCE: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Uncertain {
+    pub value: f64,
+    // Source of uncertainty -> its contribution, with the sign of the
+    // derivative of the value with respect to it.
+    components: std::collections::BTreeMap<usize, f64>,
+    // Whether the value depends on a measured value, even when no
+    // uncertainty is left, as in `g - g`. Other values print as floats.
+    pub measured: bool,
+}
+
+impl std::ops::Add for Uncertain {
+    type Output = Uncertain;
+    fn add(self, other: Uncertain) -> Uncertain {
+        let value = self.value + other.value;
+        self.combine(&other, value, 1., 1.)
+    }
+}
+
+impl std::ops::Sub for Uncertain {
+    type Output = Uncertain;
+    fn sub(self, other: Uncertain) -> Uncertain {
+        let value = self.value - other.value;
+        self.combine(&other, value, 1., -1.)
+    }
+}
+
+impl std::ops::Mul for Uncertain {
+    type Output = Uncertain;
+    fn mul(self, other: Uncertain) -> Uncertain {
+        let (a, b) = (self.value, other.value);
+        self.combine(&other, a * b, b, a)
+    }
+}
+
+impl std::ops::Div for Uncertain {
+    type Output = Uncertain;
+    fn div(self, other: Uncertain) -> Uncertain {
+        let (a, b) = (self.value, other.value);
+        self.combine(&other, a / b, 1. / b, -a / (b * b))
+    }
+}
+
+impl Uncertain {
+    pub fn exact(value: f64) -> Uncertain {
+        Uncertain {
+            value,
+            components: std::collections::BTreeMap::new(),
+            measured: false,
+        }
+    }
+
+    /// A new measurement, independent from all the others.
+    pub fn measured(value: f64, uncertainty: f64) -> Uncertain {
+        let mut result = Uncertain::exact(value);
+        result.measured = true;
+        if uncertainty != 0. {
+            let source = NEXT_SOURCE.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            result.components.insert(source, uncertainty.abs());
+        }
+        result
+    }
+
+    /// The standard uncertainty, from the independent sources.
+    pub fn uncertainty(&self) -> f64 {
+        self.components.values().fold(0., |total, component| total.hypot(*component))
+    }
+
+    // The value `value` of f(self, other), whose partial derivatives are
+    // `d_self` and `d_other`.
+    fn combine(&self, other: &Uncertain, value: f64, d_self: f64, d_other: f64) -> Uncertain {
+        let mut components = std::collections::BTreeMap::new();
+        for (derivative, operand) in [(d_self, self), (d_other, other)] {
+            for (source, component) in &operand.components {
+                *components.entry(*source).or_insert(0.) += derivative * component;
+            }
+        }
+        components.retain(|_, component| *component != 0.);
+        Uncertain {
+            value,
+            components,
+            measured: self.measured || other.measured,
+        }
+    }
+
+    /// f(self), given the value of f and its derivative at the value of
+    /// `self`.
+    pub fn map(&self, value: f64, derivative: f64) -> Uncertain {
+        self.combine(&Uncertain::exact(0.), value, derivative, 0.)
+    }
+
+    pub fn powf(self, exponent: Uncertain) -> Uncertain {
+        let (a, b) = (self.value, exponent.value);
+        let value = a.powf(b);
+        self.combine(&exponent, value, b * a.powf(b - 1.), value * a.ln())
+    }
+
+    pub fn div_euclid(self, other: Uncertain) -> Uncertain {
+        self.combine(&other, self.value.div_euclid(other.value), 0., 0.)
+    }
+
+    pub fn rem_euclid(self, other: Uncertain) -> Uncertain {
+        let (a, b) = (self.value, other.value);
+        self.combine(&other, a.rem_euclid(b), 1., -a.div_euclid(b))
+    }
+
+    /// `x +- u` has the value of `x`, with `u` as the uncertainty from a new
+    /// source; the uncertainty of `u` itself is ignored.
+    pub fn plus_minus(self, uncertainty: Uncertain) -> Uncertain {
+        let value = self.value;
+        self.combine(&Uncertain::measured(0., uncertainty.value), value, 1., 1.)
+    }
+
+    /// Reads `9.81 +- 0.02` or `9.81 ± 0.02`.
+    pub fn parse(text: &str) -> Option<Uncertain> {
+        let (value, uncertainty) = text.split_once("+-").or_else(|| text.split_once('±'))?;
+        Some(Uncertain::measured(
+            value.trim().parse().ok()?,
+            uncertainty.trim().parse().ok()?,
+        ))
+    }
+}
+
+impl std::fmt::Display for Uncertain {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if self.measured {
+            write!(f, "{} +- {}", self.value, self.uncertainty())
+        } else {
+            write!(f, "{}", self.value)
+        }
+    }
+}
+
+fn uncertain_apply(argument: Uncertain, function: fn(Dual) -> Dual) -> Uncertain {
+    let result = function(Dual::variable(argument.value));
+    argument.map(result.value, result.derivative)
+}
+
+fn input_in_range(lo: f64, hi: f64) -> Uncertain {
+    loop {
+        let text = read_line();
+        let value = Uncertain::parse(text.trim()).or_else(|| text.trim().parse().ok().map(Uncertain::exact));
+        match value {
+            Some(value) if lo <= value.value && value.value <= hi => return value,
+            Some(_) => eprintln!("The value must be in [{}, {}].", lo, hi),
+            None => eprintln!("Cannot read '{}' as a number.", text.trim()),
+        }
+    }
+}
+
+fn input() -> Uncertain {
+    input_in_range(f64::NEG_INFINITY, f64::INFINITY)
 }
 
 fn main() {
     let mut a = 5f64;
     let mut b = 0.0;
-    b = input();
-    println!("<output>: {}", a + b);
-    let mut c = a + 5f64 * (1f64 + b);
-    println!("<output>: {}", c);
-    let mut d = 1f64 / ((a).sin() + 2f64.pow((b).sqrt() - 3f64)) % c;
-    println!("<output>: {}", d);
-    println!("<output>: {}", (d).log10());
-    println!("<output>: {}", (2f64 - ((pi * e).sin()).abs() + (2f64 + (pi / 4f64).atanh()).ln()).floor());
+    let mut b_uncertain = Uncertain::exact(0.);
+    b_uncertain = input();
+    b = b_uncertain.value;
+    println!("<output>: {}", Uncertain::exact(a) + b_uncertain.clone());
+    let mut c_uncertain = Uncertain::exact(a) + Uncertain::exact(5f64) * (Uncertain::exact(1f64) + b_uncertain.clone());
+    let mut c = c_uncertain.value;
+    println!("<output>: {}", c_uncertain.clone());
+    let mut d_uncertain = (Uncertain::exact(1f64) / (uncertain_apply(Uncertain::exact(a), Dual::sin) + (Uncertain::exact(2f64)).powf((uncertain_apply(b_uncertain.clone(), Dual::sqrt) - Uncertain::exact(3f64))))).rem_euclid(c_uncertain.clone());
+    let mut d = d_uncertain.value;
+    println!("<output>: {}", d_uncertain.clone());
+    println!("<output>: {}", uncertain_apply(d_uncertain.clone(), Dual::log10));
+    println!("<output>: {}", 2f64);
 }
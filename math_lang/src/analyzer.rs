@@ -1,17 +1,55 @@
 use crate::parser::{
-    ExprOperator, ParsedExpr, ParsedFactor, ParsedProgram, ParsedStatement, ParsedTerm,
-    TermOperator, FunctionOperator, ParsedFunctionExpr,
+    ExprOperator, ParsedAnnotation, ParsedExpr, ParsedFactor, ParsedProgram, ParsedStatement,
+    ParsedTerm, TermOperator, FunctionOperator, ParsedFunctionExpr, ParsedIntegral, ParsedSolve,
+    ParsedSeries, SeriesOperator, ParsedStatistic, StatisticOperator, ParsedMatrixFunction, MatrixOperator,
+    ParsedPolynomialFunction, PolynomialOperator, ParsedNumberTheoryFunction, NumberTheoryOperator,
+    ParsedRandom, RandomOperator,
 };
+use crate::dataflow::collect_expr_reads;
+use crate::complex::Complex;
+use crate::diagnostics::{Diagnostic, Lint};
+use crate::number_format::Format;
 use crate::symbol_table::SymbolTable;
+use crate::units::{resolve_unit, AngleUnit, Dimension, Unit, DIMENSIONLESS};
+use crate::unparser::{
+    function_name, matrix_function_name, number_theory_name, polynomial_function_name, random_name, series_name,
+    statistic_name,
+};
+use crate::value::{Type, Value};
+use crate::{executor, linear_algebra, number_theory, optimizer};
 
 extern crate nom;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum AnalyzedFactor {
-    Literal(f64),
+    Literal(Value),
     Identifier(usize),
     SubExpression(Box<AnalyzedExpr>),
-    FunctionExpression(Box<AnalyzedFunctionExpr>)
+    FunctionExpression(Box<AnalyzedFunctionExpr>),
+    // The derivative of the expression with respect to the variable, a float.
+    Derivative(Box<AnalyzedExpr>, usize),
+    Integral(Box<AnalyzedIntegral>),
+    Series(Box<AnalyzedSeries>),
+    Statistic(Box<AnalyzedStatistic>),
+    MatrixFunction(Box<AnalyzedMatrixFunction>),
+    // The polynomial computed by the expression from the variable it binds,
+    // which holds the polynomial `x`.
+    Polynomial(Box<AnalyzedExpr>, usize),
+    PolynomialFunction(Box<AnalyzedPolynomialFunction>),
+    NumberTheoryFunction(Box<AnalyzedNumberTheoryFunction>),
+    // Drawn anew each time it is evaluated, so never constant.
+    Random(Box<AnalyzedRandom>),
+    // The elements of a list, floats of the same dimension.
+    List(Vec<AnalyzedExpr>),
+    // The rows of a matrix, lists of the same length.
+    Matrix(Vec<AnalyzedExpr>),
+    // The floats from the first bound to the second by steps of 1.
+    Range(Box<AnalyzedExpr>, Box<AnalyzedExpr>),
+    // A list or a matrix and the int index of one of its elements or rows,
+    // counted from 1.
+    Index(Box<AnalyzedExpr>, Box<AnalyzedExpr>),
+    // The number of elements of a list or of rows of a matrix, an int.
+    Length(Box<AnalyzedExpr>),
 }
 
 pub type AnalyzedTerm = (AnalyzedFactor, Vec<(TermOperator, AnalyzedFactor)>);
@@ -20,12 +58,51 @@ pub type AnalyzedExpr = (AnalyzedTerm, Vec<(ExprOperator, AnalyzedTerm)>);
 
 pub type AnalyzedFunctionExpr = (FunctionOperator, AnalyzedExpr);
 
+// The integrand, the handle of the variable it binds and the bounds.
+pub type AnalyzedIntegral = (AnalyzedExpr, usize, AnalyzedExpr, AnalyzedExpr);
+
+// `sum` or `prod`, the handle of the index it binds, the bounds of the index
+// and the body.
+pub type AnalyzedSeries = (SeriesOperator, usize, AnalyzedExpr, AnalyzedExpr, AnalyzedExpr);
+
+// The statistic and its arguments.
+pub type AnalyzedStatistic = (StatisticOperator, Vec<AnalyzedExpr>);
+
+// The operation of linear algebra and its arguments.
+pub type AnalyzedMatrixFunction = (MatrixOperator, Vec<AnalyzedExpr>);
+
+// The operation on polynomials and its arguments.
+pub type AnalyzedPolynomialFunction = (PolynomialOperator, Vec<AnalyzedExpr>);
+
+// The function of ints, or `gamma` or `lgamma`, and its arguments.
+pub type AnalyzedNumberTheoryFunction = (NumberTheoryOperator, Vec<AnalyzedExpr>);
+
+// The distribution and its parameters.
+pub type AnalyzedRandom = (RandomOperator, Vec<AnalyzedExpr>);
+
+// The two sides of the equation, the handle of the variable that receives
+// the root and the bounds.
+pub type AnalyzedSolve = (AnalyzedExpr, AnalyzedExpr, usize, AnalyzedExpr, AnalyzedExpr);
+
 #[derive(Debug)]
 pub enum AnalyzedStatement {
     
     Declaration(usize),
-    InputOperation(usize),
-    OutputOperation(AnalyzedExpr),
+    InputOperation(usize, Option<(f64, f64)>),
+    // The unit in which to print the value, if it has a dimension, the
+    // format of its numbers, from `--format` if none is given, and the label.
+    OutputOperation(AnalyzedExpr, Option<Unit>, Option<Format>, Option<String>),
+    // The partial derivatives with respect to the variables, in their units.
+    GradientOutput(AnalyzedExpr, Vec<(usize, Option<Unit>)>),
+    // Prints the derivative of the expression with respect to the variable
+    // as source.
+    SymbolicDerivative(AnalyzedExpr, usize),
+    Solve(Box<AnalyzedSolve>),
+    // Restarts the pseudo-random numbers from the int seed.
+    Seed(AnalyzedExpr),
+    // Does nothing when run: the analyzer has converted the angles of the
+    // statements that follow to radians.
+    AngleMode(AngleUnit),
     Assignment(usize, AnalyzedExpr),
     DeclarationToAssignment(usize, AnalyzedExpr),
     
@@ -44,59 +121,1767 @@ pub fn analyze_program(
     Ok(analyzed_program)
 }
 
+// Every expression is analyzed along with the dimension and the type of its
+// value. Quantities are converted to SI base units here, so the analyzed
+// program only handles plain numbers.
+//
+// Literals without a decimal point such as `2` take the type of the
+// operands they are combined with, and are floats when nothing else decides;
+// until then their type is `None`, and they are stored as ints.
+//
+// Lists combined element-wise must have the same length, and matrices the
+// same shape, which is checked here when it is known before running, and
+// else by the executor. The length of a matrix is its number of rows.
+#[derive(Clone, Copy)]
+struct ValueInfo {
+    dimension: Dimension,
+    value_type: Option<Type>,
+    length: Option<usize>,
+    columns: Option<usize>,
+}
+
+fn make_float_factor(variables: &SymbolTable, factor: &mut AnalyzedFactor) {
+    match factor {
+        AnalyzedFactor::Literal(value) => *value = variables.backend().to_float(value.clone()),
+        AnalyzedFactor::Identifier(_)
+        | AnalyzedFactor::Derivative(..)
+        | AnalyzedFactor::Integral(_)
+        | AnalyzedFactor::Statistic(_)
+        | AnalyzedFactor::MatrixFunction(_)
+        | AnalyzedFactor::Polynomial(..)
+        | AnalyzedFactor::PolynomialFunction(_)
+        | AnalyzedFactor::NumberTheoryFunction(_)
+        | AnalyzedFactor::Random(_)
+        | AnalyzedFactor::List(_)
+        | AnalyzedFactor::Matrix(_)
+        | AnalyzedFactor::Range(..)
+        | AnalyzedFactor::Index(..)
+        | AnalyzedFactor::Length(_) => {}
+        AnalyzedFactor::FunctionExpression(function_expr) => {
+            make_float_expr(variables, &mut function_expr.1)
+        }
+        AnalyzedFactor::SubExpression(expr) => make_float_expr(variables, expr),
+        AnalyzedFactor::Series(series) => make_float_expr(variables, &mut series.4),
+    }
+}
+
+fn make_float_term(variables: &SymbolTable, term: &mut AnalyzedTerm) {
+    make_float_factor(variables, &mut term.0);
+    for factor in &mut term.1 {
+        make_float_factor(variables, &mut factor.1);
+    }
+}
+
+// Turns the literals of an expression whose type is still open into floats.
+fn make_float_expr(variables: &SymbolTable, expr: &mut AnalyzedExpr) {
+    make_float_term(variables, &mut expr.0);
+    for term in &mut expr.1 {
+        make_float_term(variables, &mut term.1);
+    }
+}
+
+fn describe_type(value_type: Type) -> &'static str {
+    match value_type {
+        Type::Int => "an int",
+        Type::Float => "a float",
+        Type::Complex => "a complex number",
+        Type::List => "a list",
+        Type::Matrix => "a matrix",
+        Type::Polynomial => "a polynomial",
+    }
+}
+
+// Whether the flexible operands of an operation giving `value_type` must
+// become floats.
+fn needs_float(value_type: Option<Type>) -> bool {
+    matches!(
+        value_type,
+        Some(Type::Float) | Some(Type::Complex) | Some(Type::List) | Some(Type::Matrix) | Some(Type::Polynomial)
+    )
+}
+
+// Type of `left operator right`; an operand whose type is still open takes
+// the type of the other one, a float meeting a complex number becomes
+// complex, a float meeting a list or a matrix is applied to each of its
+// elements, and a float meeting a polynomial is a constant polynomial.
+fn combine_types(
+    operator_name: &str,
+    left: Option<Type>,
+    right: Option<Type>,
+) -> Result<Option<Type>, String> {
+    match (left, right) {
+        (Some(left), Some(right))
+            if left != right && (left == Type::Int || right == Type::Int) =>
+        {
+            Err(format!(
+            "Error: The operands of `{}` are {} and {}; convert one of them with int() or float().",
+            operator_name,
+            describe_type(left),
+            describe_type(right)
+            ))
+        }
+        (Some(Type::List), Some(Type::Complex)) | (Some(Type::Complex), Some(Type::List)) => Err(format!(
+            "Error: The operands of `{}` are a list and a complex number, but lists hold floats.",
+            operator_name
+        )),
+        (Some(Type::Matrix), Some(Type::Complex)) | (Some(Type::Complex), Some(Type::Matrix)) => Err(format!(
+            "Error: The operands of `{}` are a matrix and a complex number, but matrices hold floats.",
+            operator_name
+        )),
+        (Some(Type::Matrix), Some(Type::List)) | (Some(Type::List), Some(Type::Matrix)) => Err(format!(
+            "Error: The operands of `{}` are a matrix and a list; multiply them with `@`.",
+            operator_name
+        )),
+        (Some(Type::Polynomial), Some(other)) | (Some(other), Some(Type::Polynomial))
+            if other != Type::Float && other != Type::Polynomial =>
+        {
+            Err(format!(
+                "Error: The operands of `{}` are a polynomial and {}, but polynomials only combine with floats.",
+                operator_name,
+                describe_type(other)
+            ))
+        }
+        (Some(Type::Polynomial), _) | (_, Some(Type::Polynomial)) => Ok(Some(Type::Polynomial)),
+        (Some(Type::Complex), _) | (_, Some(Type::Complex)) => Ok(Some(Type::Complex)),
+        (Some(Type::Matrix), _) | (_, Some(Type::Matrix)) => Ok(Some(Type::Matrix)),
+        (Some(Type::List), _) | (_, Some(Type::List)) => Ok(Some(Type::List)),
+        _ => Ok(left.or(right)),
+    }
+}
+
+fn combine_lengths(
+    operator_name: &str,
+    left: Option<usize>,
+    right: Option<usize>,
+) -> Result<Option<usize>, String> {
+    match (left, right) {
+        (Some(left), Some(right)) if left != right => Err(format!(
+            "Error: The operands of `{}` are lists of lengths {} and {}.",
+            operator_name, left, right
+        )),
+        _ => Ok(left.or(right)),
+    }
+}
+
+fn describe_shape(rows: Option<usize>, columns: Option<usize>) -> String {
+    let describe = |size: Option<usize>| size.map_or("?".to_string(), |size| size.to_string());
+    format!("{}x{}", describe(rows), describe(columns))
+}
+
+// The length, and the number of columns, of `left operator right`.
+fn combine_shapes(
+    operator_name: &str,
+    left: ValueInfo,
+    right: ValueInfo,
+) -> Result<(Option<usize>, Option<usize>), String> {
+    if left.value_type == Some(Type::Matrix) && right.value_type == Some(Type::Matrix) {
+        let differ = |left: Option<usize>, right: Option<usize>| matches!((left, right), (Some(left), Some(right)) if left != right);
+        if differ(left.length, right.length) || differ(left.columns, right.columns) {
+            return Err(format!(
+                "Error: The operands of `{}` are matrices of shapes {} and {}.",
+                operator_name,
+                describe_shape(left.length, left.columns),
+                describe_shape(right.length, right.columns)
+            ));
+        }
+        return Ok((left.length.or(right.length), left.columns.or(right.columns)));
+    }
+    Ok((
+        combine_lengths(operator_name, left.length, right.length)?,
+        left.columns.or(right.columns),
+    ))
+}
+
+// `@` multiplies matrices, where a list on the left is a row and a list on
+// the right is a column. The product of two lists is their dot product.
+fn analyze_matrix_product(left: ValueInfo, right: ValueInfo) -> Result<ValueInfo, String> {
+    let describe = |info: ValueInfo| describe_type(info.value_type.unwrap_or(Type::Int));
+    let (left_inner, right_inner) = match (left.value_type, right.value_type) {
+        (Some(Type::Matrix), Some(Type::Matrix)) | (Some(Type::Matrix), Some(Type::List)) => {
+            (left.columns, right.length)
+        }
+        (Some(Type::List), Some(Type::Matrix)) | (Some(Type::List), Some(Type::List)) => (left.length, right.length),
+        _ => {
+            return Err(format!(
+                "Error: The operands of `@` must be matrices or lists, not {} and {}.",
+                describe(left),
+                describe(right)
+            ))
+        }
+    };
+    if let (Some(left_inner), Some(right_inner)) = (left_inner, right_inner) {
+        if left_inner != right_inner {
+            return Err(match (left.value_type, right.value_type) {
+                (Some(Type::List), Some(Type::List)) => format!(
+                    "Error: Cannot multiply lists of lengths {} and {}.",
+                    left_inner, right_inner
+                ),
+                _ => format!(
+                    "Error: Cannot multiply matrices of shapes {} and {}.",
+                    match left.value_type {
+                        Some(Type::List) => describe_shape(Some(1), left.length),
+                        _ => describe_shape(left.length, left.columns),
+                    },
+                    match right.value_type {
+                        Some(Type::List) => describe_shape(right.length, Some(1)),
+                        _ => describe_shape(right.length, right.columns),
+                    }
+                ),
+            });
+        }
+    }
+    let (value_type, length, columns) = match (left.value_type, right.value_type) {
+        (Some(Type::Matrix), Some(Type::Matrix)) => (Type::Matrix, left.length, right.columns),
+        (Some(Type::Matrix), _) => (Type::List, left.length, None),
+        (_, Some(Type::Matrix)) => (Type::List, right.columns, None),
+        _ => (Type::Float, None, None),
+    };
+    Ok(ValueInfo {
+        dimension: left.dimension.mul(right.dimension),
+        value_type: Some(value_type),
+        length,
+        columns,
+    })
+}
+
+fn check_real(operator_name: &str, left: Option<Type>, right: Option<Type>) -> Result<(), String> {
+    if left == Some(Type::Complex) || right == Some(Type::Complex) {
+        Err(format!(
+            "Error: The operands of `{}` must be real, not complex numbers.",
+            operator_name
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+fn imaginary_literal(value: f64) -> (AnalyzedFactor, ValueInfo) {
+    (
+        AnalyzedFactor::Literal(Value::Complex(Complex::new(0., value))),
+        ValueInfo {
+            dimension: DIMENSIONLESS,
+            value_type: Some(Type::Complex),
+            length: None,
+            columns: None,
+        },
+    )
+}
+
 fn analyze_factor(
     variables: &mut SymbolTable,
     parsed_factor: &ParsedFactor,
-) -> Result<AnalyzedFactor, String> {
+) -> Result<(AnalyzedFactor, ValueInfo), String> {
     match parsed_factor {
-        ParsedFactor::Literal(value) => Ok(AnalyzedFactor::Literal(*value)),
-        ParsedFactor::Identifier(name) => {
-            Ok(AnalyzedFactor::Identifier(variables.find_symbol(name)?))
+        ParsedFactor::Literal(value) => Ok((
+            AnalyzedFactor::Literal(variables.backend().float(*value)),
+            ValueInfo {
+                dimension: DIMENSIONLESS,
+                value_type: Some(Type::Float),
+                length: None,
+                columns: None,
+            },
+        )),
+        ParsedFactor::DegreeLiteral(value) => {
+            let backend = variables.backend();
+            let scale = angle_scale(variables, AngleUnit::Degree, variables.angle_unit());
+            Ok((
+                AnalyzedFactor::Literal(
+                    executor::apply_term_operator(backend, TermOperator::Multiply, backend.float(*value), scale)?,
+                ),
+                ValueInfo {
+                    dimension: DIMENSIONLESS,
+                    value_type: Some(Type::Float),
+                    length: None,
+                    columns: None,
+                },
+            ))
         }
-        ParsedFactor::FunctionExpression(expr) => Ok(AnalyzedFactor::FunctionExpression(
-            Box::<AnalyzedFunctionExpr>::new(analyze_function_expr(variables, expr)?),
+        ParsedFactor::AngleConversion(unit, expr) => analyze_angle_conversion(variables, *unit, expr),
+        ParsedFactor::IntLiteral(value) => Ok((
+            AnalyzedFactor::Literal(Value::Int(*value)),
+            ValueInfo {
+                dimension: DIMENSIONLESS,
+                value_type: None,
+                length: None,
+                columns: None,
+            },
         )),
-        ParsedFactor::SubExpression(expr) => Ok(AnalyzedFactor::SubExpression(
-            Box::<AnalyzedExpr>::new(analyze_expr(variables, expr)?),
+        ParsedFactor::Imaginary(value) => Ok(imaginary_literal(*value)),
+        ParsedFactor::Quantity(value, unit) => {
+            let unit = resolve_unit(unit)?;
+            Ok((
+                AnalyzedFactor::Literal(
+                    variables
+                        .backend()
+                        .in_base_units(variables.backend().float(*value), &unit),
+                ),
+                ValueInfo {
+                    dimension: unit.dimension,
+                    value_type: Some(Type::Float),
+                    length: None,
+                    columns: None,
+                },
+            ))
+        }
+        // `i` is the imaginary unit, unless a variable has that name.
+        ParsedFactor::Identifier("i") if variables.find_symbol("i").is_err() => {
+            Ok(imaginary_literal(1.))
+        }
+        ParsedFactor::Identifier(name) => {
+            let handle = variables.find_symbol(name)?;
+            Ok((
+                AnalyzedFactor::Identifier(handle),
+                ValueInfo {
+                    dimension: variables.get_dimension(handle).unwrap_or(DIMENSIONLESS),
+                    value_type: Some(variables.get_type(handle).unwrap_or(Type::Float)),
+                    length: variables.get_length(handle),
+                    columns: variables.get_columns(handle),
+                },
+            ))
+        }
+        // In degrees or gradians, the arguments of `sin`, `cos` and `tan` are
+        // converted to radians, and the results of their inverses back.
+        ParsedFactor::FunctionExpression(expr) => {
+            let (mut function_expr, info) = analyze_function_expr(variables, expr)?;
+            let unit = variables.angle_unit();
+            if unit != AngleUnit::Radian {
+                match function_expr.0 {
+                    FunctionOperator::Sin | FunctionOperator::Cos | FunctionOperator::Tan => {
+                        let scale = angle_scale(variables, unit, AngleUnit::Radian);
+                        function_expr.1 = scale_expr(function_expr.1, scale);
+                    }
+                    FunctionOperator::ArcSin | FunctionOperator::ArcCos | FunctionOperator::ArcTan => {
+                        let scale = angle_scale(variables, AngleUnit::Radian, unit);
+                        let factor = AnalyzedFactor::FunctionExpression(Box::new(function_expr));
+                        let expr = scale_expr(((factor, vec![]), vec![]), scale);
+                        return Ok((AnalyzedFactor::SubExpression(Box::new(expr)), info));
+                    }
+                    _ => {}
+                }
+            }
+            Ok((
+                AnalyzedFactor::FunctionExpression(Box::<AnalyzedFunctionExpr>::new(function_expr)),
+                info,
+            ))
+        }
+        ParsedFactor::SubExpression(expr) => {
+            let (expr, info) = analyze_expr(variables, expr)?;
+            Ok((AnalyzedFactor::SubExpression(Box::<AnalyzedExpr>::new(expr)), info))
+        }
+        ParsedFactor::Derivative(expr, name) => {
+            let (expr, dimension) = analyze_differentiated_expr(variables, expr)?;
+            let handle = differentiation_variable(variables, name)?;
+            Ok((
+                AnalyzedFactor::Derivative(Box::new(expr), handle),
+                ValueInfo {
+                    dimension: dimension.div(variables.get_dimension(handle).unwrap_or(DIMENSIONLESS)),
+                    value_type: Some(Type::Float),
+                    length: None,
+                    columns: None,
+                },
+            ))
+        }
+        ParsedFactor::Integral(integral) => {
+            let (integral, info) = analyze_integral(variables, integral)?;
+            Ok((AnalyzedFactor::Integral(Box::new(integral)), info))
+        }
+        ParsedFactor::Series(series) => {
+            let (series, info) = analyze_series(variables, series)?;
+            Ok((AnalyzedFactor::Series(Box::new(series)), info))
+        }
+        ParsedFactor::Statistic(statistic) => {
+            let (statistic, info) = analyze_statistic(variables, statistic)?;
+            Ok((AnalyzedFactor::Statistic(Box::new(statistic)), info))
+        }
+        ParsedFactor::MatrixFunction(function) => {
+            let (function, info) = analyze_matrix_function(variables, function)?;
+            Ok((AnalyzedFactor::MatrixFunction(Box::new(function)), info))
+        }
+        ParsedFactor::Polynomial(name, expr) => {
+            let (expr, handle) = analyze_polynomial(variables, name, expr)?;
+            Ok((
+                AnalyzedFactor::Polynomial(Box::new(expr), handle),
+                ValueInfo {
+                    dimension: DIMENSIONLESS,
+                    value_type: Some(Type::Polynomial),
+                    length: None,
+                    columns: None,
+                },
+            ))
+        }
+        ParsedFactor::PolynomialFunction(function) => {
+            let (function, info) = analyze_polynomial_function(variables, function)?;
+            Ok((AnalyzedFactor::PolynomialFunction(Box::new(function)), info))
+        }
+        ParsedFactor::NumberTheoryFunction(function) => {
+            let (function, info) = analyze_number_theory_function(variables, function)?;
+            Ok((AnalyzedFactor::NumberTheoryFunction(Box::new(function)), info))
+        }
+        ParsedFactor::Random(random) => {
+            let (random, info) = analyze_random(variables, random)?;
+            Ok((AnalyzedFactor::Random(Box::new(random)), info))
+        }
+        ParsedFactor::List(elements) => analyze_list(variables, elements),
+        ParsedFactor::Range(lo, hi) => {
+            let lo = analyze_range_bound(variables, lo)?;
+            let hi = analyze_range_bound(variables, hi)?;
+            let length = match (constant_value(variables, &lo), constant_value(variables, &hi)) {
+                (Some(lo), Some(hi)) => Some(executor::range_length(lo, hi)?),
+                _ => None,
+            };
+            Ok((
+                AnalyzedFactor::Range(Box::new(lo), Box::new(hi)),
+                ValueInfo {
+                    dimension: DIMENSIONLESS,
+                    value_type: Some(Type::List),
+                    length,
+                    columns: None,
+                },
+            ))
+        }
+        ParsedFactor::Index(list, index) => analyze_index(variables, list, index),
+        ParsedFactor::Length(expr) => {
+            let (expr, info) = analyze_expr(variables, expr)?;
+            if !matches!(info.value_type, Some(Type::List) | Some(Type::Matrix)) {
+                return Err(format!(
+                    "Error: len takes a list or a matrix, not {}.",
+                    describe_type(info.value_type.unwrap_or(Type::Int))
+                ));
+            }
+            Ok((
+                AnalyzedFactor::Length(Box::new(expr)),
+                ValueInfo {
+                    dimension: DIMENSIONLESS,
+                    value_type: Some(Type::Int),
+                    length: None,
+                    columns: None,
+                },
+            ))
+        }
+    }
+}
+
+// The value of an expression known before running, if any.
+fn constant_value(variables: &mut SymbolTable, expr: &AnalyzedExpr) -> Option<f64> {
+    if optimizer::is_constant_expr(variables, expr) {
+        executor::evaluate_expr(variables, expr)
+            .ok()
+            .map(|value| value.as_float())
+    } else {
+        None
+    }
+}
+
+// The elements of a list are floats with the same unit. A list of lists is
+// a matrix, whose rows must have the same length.
+fn analyze_list(
+    variables: &mut SymbolTable,
+    parsed_elements: &[ParsedExpr],
+) -> Result<(AnalyzedFactor, ValueInfo), String> {
+    let mut elements = Vec::new();
+    let mut dimension: Option<Dimension> = None;
+    let mut matrix = false;
+    let mut columns: Option<usize> = None;
+    for parsed_element in parsed_elements {
+        let (mut element, info) = analyze_expr(variables, parsed_element)?;
+        if elements.is_empty() {
+            matrix = info.value_type == Some(Type::List);
+        }
+        match info.value_type {
+            Some(Type::List) if matrix => match (columns, info.length) {
+                (_, Some(0)) => {
+                    return Err("Error: A matrix needs at least one row and one column.".to_string())
+                }
+                (Some(columns), Some(length)) if columns != length => {
+                    return Err(format!(
+                        "Error: The rows of a matrix have lengths {} and {}.",
+                        columns, length
+                    ))
+                }
+                _ => columns = columns.or(info.length),
+            },
+            Some(value_type) if matrix => {
+                return Err(format!(
+                    "Error: The rows of a matrix must be lists, not {}.",
+                    describe_type(value_type)
+                ))
+            }
+            None if matrix => return Err("Error: The rows of a matrix must be lists, not numbers.".to_string()),
+            None => make_float_expr(variables, &mut element),
+            Some(Type::Float) => {}
+            Some(Type::List) | Some(Type::Matrix) => return Err("Error: Lists cannot be nested.".to_string()),
+            Some(value_type) => {
+                return Err(format!(
+                    "Error: The elements of a list must be floats, not {}.",
+                    describe_type(value_type)
+                ))
+            }
+        }
+        match dimension {
+            Some(dimension) if dimension != info.dimension => {
+                return Err(format!(
+                    "Error: The elements of a list have incompatible units: {} and {}.",
+                    dimension.describe(),
+                    info.dimension.describe()
+                ))
+            }
+            _ => dimension = Some(info.dimension),
+        }
+        elements.push(element);
+    }
+    if matrix {
+        return Ok((
+            AnalyzedFactor::Matrix(elements),
+            ValueInfo {
+                dimension: dimension.unwrap_or(DIMENSIONLESS),
+                value_type: Some(Type::Matrix),
+                length: Some(parsed_elements.len()),
+                columns,
+            },
+        ));
+    }
+    Ok((
+        AnalyzedFactor::List(elements),
+        ValueInfo {
+            dimension: dimension.unwrap_or(DIMENSIONLESS),
+            value_type: Some(Type::List),
+            length: Some(parsed_elements.len()),
+            columns: None,
+        },
+    ))
+}
+
+// `transpose`, `det`, `inv` and `solve` take matrices, whose shapes are
+// checked when they are known, and `identity` and `zeros` take sizes without
+// unit. The determinant of an n x n matrix is in the nth power of its unit,
+// so n must be known before running when the matrix has a unit.
+fn analyze_matrix_function(
+    variables: &mut SymbolTable,
+    parsed_function: &ParsedMatrixFunction,
+) -> Result<(AnalyzedMatrixFunction, ValueInfo), String> {
+    let (operator, parsed_arguments) = parsed_function;
+    let name = matrix_function_name(*operator);
+    let constructor = matches!(operator, MatrixOperator::Identity | MatrixOperator::Zeros);
+    let arity = match operator {
+        MatrixOperator::Solve | MatrixOperator::Zeros => 2,
+        _ => 1,
+    };
+    if parsed_arguments.len() != arity {
+        return Err(format!(
+            "Error: {} takes {}.",
+            name,
+            if arity == 1 { "one argument" } else { "two arguments" }
+        ));
+    }
+    let mut arguments = Vec::new();
+    let mut infos = Vec::new();
+    let mut sizes = Vec::new();
+    for parsed_argument in parsed_arguments {
+        let (mut argument, info) = analyze_expr(variables, parsed_argument)?;
+        if constructor {
+            if !info.dimension.is_dimensionless() {
+                return Err(format!(
+                    "Error: The size of a matrix must be without unit, not {}.",
+                    describe_quantity(info.dimension)
+                ));
+            }
+            match info.value_type {
+                None => make_float_expr(variables, &mut argument),
+                Some(Type::Int) | Some(Type::Float) => {}
+                Some(value_type) => {
+                    return Err(format!(
+                        "Error: The size of a matrix must be a real number, not {}.",
+                        describe_type(value_type)
+                    ))
+                }
+            }
+            sizes.push(match constant_value(variables, &argument) {
+                Some(size) => Some(linear_algebra::matrix_size(size)?),
+                None => None,
+            });
+        }
+        arguments.push(argument);
+        infos.push(info);
+    }
+    let matrix = infos[0];
+    if !constructor && matrix.value_type != Some(Type::Matrix) {
+        return Err(format!(
+            "Error: {} takes a matrix, not {}.",
+            name,
+            describe_type(matrix.value_type.unwrap_or(Type::Int))
+        ));
+    }
+    let size = matrix.length.or(matrix.columns);
+    if let (Some(rows), Some(columns)) = (matrix.length, matrix.columns) {
+        if rows != columns && matches!(operator, MatrixOperator::Determinant | MatrixOperator::Inverse | MatrixOperator::Solve) {
+            return Err(format!(
+                "Error: {} needs a square matrix, not a {}x{} one.",
+                name, rows, columns
+            ));
+        }
+    }
+    let info = |dimension, value_type, length, columns| ValueInfo {
+        dimension,
+        value_type: Some(value_type),
+        length,
+        columns,
+    };
+    let info = match operator {
+        MatrixOperator::Transpose => info(matrix.dimension, Type::Matrix, matrix.columns, matrix.length),
+        MatrixOperator::Determinant => {
+            let dimension = match size {
+                _ if matrix.dimension.is_dimensionless() => DIMENSIONLESS,
+                Some(size) => matrix.dimension.powi(size as i32),
+                None => {
+                    return Err(format!(
+                        "Error: The determinant of a matrix in {} needs its size to be known before running.",
+                        matrix.dimension
+                    ))
+                }
+            };
+            info(dimension, Type::Float, None, None)
+        }
+        MatrixOperator::Inverse => info(DIMENSIONLESS.div(matrix.dimension), Type::Matrix, size, size),
+        MatrixOperator::Solve => {
+            let right = infos[1];
+            if !matches!(right.value_type, Some(Type::List) | Some(Type::Matrix)) {
+                return Err(format!(
+                    "Error: The right-hand side of solve must be a list or a matrix, not {}.",
+                    describe_type(right.value_type.unwrap_or(Type::Int))
+                ));
+            }
+            if let (Some(size), Some(rows)) = (size, right.length) {
+                if size != rows {
+                    return Err(format!(
+                        "Error: solve needs a right-hand side of {} rows, not {}.",
+                        size, rows
+                    ));
+                }
+            }
+            info(
+                right.dimension.div(matrix.dimension),
+                right.value_type.unwrap(),
+                size,
+                right.columns,
+            )
+        }
+        MatrixOperator::Identity => info(DIMENSIONLESS, Type::Matrix, sizes[0], sizes[0]),
+        MatrixOperator::Zeros => info(DIMENSIONLESS, Type::Matrix, sizes[0], sizes[1]),
+    };
+    Ok(((*operator, arguments), info))
+}
+
+// The factor that turns an angle in `from` into one in `to`.
+fn angle_scale(variables: &SymbolTable, from: AngleUnit, to: AngleUnit) -> Value {
+    let backend = variables.backend();
+    match executor::apply_term_operator(backend, TermOperator::Divide, backend.half_turn(to), backend.half_turn(from)) {
+        Ok(scale) => scale,
+        Err(_) => unreachable!("half a turn is never zero"),
+    }
+}
+
+// `expr * scale`, with `expr` parenthesized unless it is a single factor.
+fn scale_expr(expr: AnalyzedExpr, scale: Value) -> AnalyzedExpr {
+    let factor = if expr.1.is_empty() && expr.0 .1.is_empty() {
+        expr.0 .0
+    } else {
+        AnalyzedFactor::SubExpression(Box::new(expr))
+    };
+    ((factor, vec![(TermOperator::Multiply, AnalyzedFactor::Literal(scale))]), vec![])
+}
+
+// `deg(x)` converts radians to degrees and `rad(x)` degrees to radians,
+// whatever the angle mode. Like functions, they apply to floats, complex
+// numbers and each element of lists and matrices, without unit.
+fn analyze_angle_conversion(
+    variables: &mut SymbolTable,
+    unit: AngleUnit,
+    parsed_expr: &ParsedExpr,
+) -> Result<(AnalyzedFactor, ValueInfo), String> {
+    let name = unit.name();
+    let (mut expr, mut info) = analyze_expr(variables, parsed_expr)?;
+    if !info.dimension.is_dimensionless() {
+        return Err(format!(
+            "Error: The argument of {} must be dimensionless, not in {}.",
+            name, info.dimension
+        ));
+    }
+    match info.value_type {
+        None => {
+            make_float_expr(variables, &mut expr);
+            info.value_type = Some(Type::Float);
+        }
+        Some(Type::Int) => {
+            return Err(format!(
+                "Error: The argument of {} must be a float, not an int; convert it with float().",
+                name
+            ))
+        }
+        Some(Type::Polynomial) => return Err(format!("Error: {} cannot be applied to a polynomial.", name)),
+        Some(_) => {}
+    }
+    let from = match unit {
+        AngleUnit::Degree => AngleUnit::Radian,
+        _ => AngleUnit::Degree,
+    };
+    let scale = angle_scale(variables, from, unit);
+    Ok((AnalyzedFactor::SubExpression(Box::new(scale_expr(expr, scale))), info))
+}
+
+// The bounds of a range are ints or floats without unit.
+fn analyze_range_bound(variables: &mut SymbolTable, parsed_expr: &ParsedExpr) -> Result<AnalyzedExpr, String> {
+    let (mut expr, info) = analyze_expr(variables, parsed_expr)?;
+    if !info.dimension.is_dimensionless() {
+        return Err(format!(
+            "Error: The bounds of a range must be without unit, not {}.",
+            describe_quantity(info.dimension)
+        ));
+    }
+    match info.value_type {
+        None => make_float_expr(variables, &mut expr),
+        Some(Type::Int) | Some(Type::Float) => {}
+        Some(value_type) => {
+            return Err(format!(
+                "Error: The bounds of a range must be real numbers, not {}.",
+                describe_type(value_type)
+            ))
+        }
+    }
+    Ok(expr)
+}
+
+fn analyze_index(
+    variables: &mut SymbolTable,
+    parsed_list: &ParsedFactor,
+    parsed_index: &ParsedExpr,
+) -> Result<(AnalyzedFactor, ValueInfo), String> {
+    let (list, list_info) = analyze_factor(variables, parsed_list)?;
+    let matrix = list_info.value_type == Some(Type::Matrix);
+    if list_info.value_type != Some(Type::List) && !matrix {
+        return Err(format!(
+            "Error: Only lists and matrices can be indexed, not {}.",
+            describe_type(list_info.value_type.unwrap_or(Type::Int))
+        ));
+    }
+    let (index, index_info) = analyze_expr(variables, parsed_index)?;
+    if !index_info.dimension.is_dimensionless() {
+        return Err(format!(
+            "Error: The index of a list must be without unit, not {}.",
+            describe_quantity(index_info.dimension)
+        ));
+    }
+    if let Some(value_type) = index_info.value_type.filter(|value_type| *value_type != Type::Int) {
+        return Err(format!(
+            "Error: The index of a list must be an int, not {}.",
+            describe_type(value_type)
+        ));
+    }
+    if let (Some(length), Some(position)) = (list_info.length, constant_value(variables, &index)) {
+        executor::list_index(position as i64, length)?;
+    }
+    Ok((
+        AnalyzedFactor::Index(Box::new(((list, vec![]), vec![])), Box::new(index)),
+        ValueInfo {
+            dimension: list_info.dimension,
+            value_type: Some(if matrix { Type::List } else { Type::Float }),
+            length: if matrix { list_info.columns } else { None },
+            columns: None,
+        },
+    ))
+}
+
+// The variable bound by `poly` holds the polynomial `x` and can only be
+// named in the body, whose value becomes a polynomial. Polynomials have no
+// unit.
+fn analyze_polynomial(
+    variables: &mut SymbolTable,
+    name: &str,
+    parsed_body: &ParsedExpr,
+) -> Result<(AnalyzedExpr, usize), String> {
+    let handle = variables.push_scope(name, Type::Polynomial, DIMENSIONLESS);
+    let analyzed_body = analyze_expr(variables, parsed_body);
+    variables.pop_scope(handle);
+    let (mut body, info) = analyzed_body?;
+    match info.value_type {
+        None => make_float_expr(variables, &mut body),
+        Some(Type::Float) | Some(Type::Polynomial) => {}
+        Some(value_type) => {
+            return Err(format!(
+                "Error: The body of poly must be a polynomial or a float, not {}.",
+                describe_type(value_type)
+            ))
+        }
+    }
+    if !info.dimension.is_dimensionless() {
+        return Err(format!(
+            "Error: Polynomials are without unit, not in {}.",
+            info.dimension
+        ));
+    }
+    Ok((body, handle))
+}
+
+// The coefficients of `poly` are floats or lists of floats, taken together,
+// and the point of `eval` is a float or a list of them, all without unit.
+fn analyze_polynomial_function(
+    variables: &mut SymbolTable,
+    parsed_function: &ParsedPolynomialFunction,
+) -> Result<(AnalyzedPolynomialFunction, ValueInfo), String> {
+    let (operator, parsed_arguments) = parsed_function;
+    let name = polynomial_function_name(*operator);
+    match (operator, parsed_arguments.len()) {
+        (PolynomialOperator::Coefficients, 0) => {
+            return Err("Error: poly takes at least one coefficient.".to_string())
+        }
+        (PolynomialOperator::Coefficients, _) => {}
+        (PolynomialOperator::Evaluate, 2) => {}
+        (PolynomialOperator::Evaluate, _) => return Err("Error: eval takes a polynomial and a point.".to_string()),
+        (_, 1) => {}
+        (_, _) => return Err(format!("Error: {} takes one polynomial.", name)),
+    }
+    let mut arguments = Vec::new();
+    let mut point = None;
+    for parsed_argument in parsed_arguments {
+        let (mut argument, info) = analyze_expr(variables, parsed_argument)?;
+        if *operator != PolynomialOperator::Coefficients && arguments.is_empty() {
+            if info.value_type != Some(Type::Polynomial) {
+                return Err(format!(
+                    "Error: {} takes a polynomial, not {}.",
+                    name,
+                    describe_type(info.value_type.unwrap_or(Type::Int))
+                ));
+            }
+            arguments.push(argument);
+            continue;
+        }
+        let role = match operator {
+            PolynomialOperator::Coefficients => "coefficients of poly",
+            _ => "point of eval",
+        };
+        if !info.dimension.is_dimensionless() {
+            return Err(format!(
+                "Error: The {} must be without unit, not {}.",
+                role,
+                describe_quantity(info.dimension)
+            ));
+        }
+        match info.value_type {
+            None => make_float_expr(variables, &mut argument),
+            Some(Type::Float) | Some(Type::List) => {}
+            Some(value_type) => {
+                return Err(format!(
+                    "Error: The {} must be floats or lists, not {}.",
+                    role,
+                    describe_type(value_type)
+                ))
+            }
+        }
+        point = Some(info);
+        arguments.push(argument);
+    }
+    let info = |value_type, length| ValueInfo {
+        dimension: DIMENSIONLESS,
+        value_type: Some(value_type),
+        length,
+        columns: None,
+    };
+    let info = match operator {
+        PolynomialOperator::Coefficients | PolynomialOperator::Derivative => info(Type::Polynomial, None),
+        PolynomialOperator::Evaluate => {
+            let point = point.unwrap();
+            info(point.value_type.unwrap_or(Type::Float), point.length)
+        }
+        PolynomialOperator::Roots => info(Type::List, None),
+    };
+    Ok(((*operator, arguments), info))
+}
+
+// The arguments are without unit. `gamma` and `lgamma` take a float and give
+// a float; the others take ints, or floats checked to be whole, and give an
+// int, or the list of the prime factors for `factor`.
+fn analyze_number_theory_function(
+    variables: &mut SymbolTable,
+    parsed_function: &ParsedNumberTheoryFunction,
+) -> Result<(AnalyzedNumberTheoryFunction, ValueInfo), String> {
+    let (operator, parsed_arguments) = parsed_function;
+    let name = number_theory_name(*operator);
+    let float_function = matches!(operator, NumberTheoryOperator::Gamma | NumberTheoryOperator::LogGamma);
+    let arity = match operator {
+        NumberTheoryOperator::Binomial | NumberTheoryOperator::Permutations | NumberTheoryOperator::ModInverse => 2,
+        NumberTheoryOperator::ModPow => 3,
+        _ => 1,
+    };
+    if parsed_arguments.len() != arity {
+        return Err(format!(
+            "Error: {} takes {} argument{}.",
+            name,
+            ["one", "two", "three"][arity - 1],
+            if arity == 1 { "" } else { "s" }
+        ));
+    }
+    let mut arguments = Vec::new();
+    for parsed_argument in parsed_arguments {
+        let (mut argument, info) = analyze_expr(variables, parsed_argument)?;
+        if !info.dimension.is_dimensionless() {
+            return Err(format!(
+                "Error: The arguments of {} must be without unit, not {}.",
+                name,
+                describe_quantity(info.dimension)
+            ));
+        }
+        match (float_function, info.value_type) {
+            (true, None) => make_float_expr(variables, &mut argument),
+            (true, Some(Type::Float)) | (false, None) | (false, Some(Type::Int)) => {}
+            // Floats are converted when they are whole.
+            (false, Some(Type::Float)) => {
+                if let Some(value) = constant_value(variables, &argument) {
+                    number_theory::whole_number(name, value)?;
+                }
+            }
+            (true, Some(Type::Int)) => {
+                return Err(format!(
+                    "Error: The argument of {} must be a float, not an int; convert it with float().",
+                    name
+                ))
+            }
+            (_, Some(value_type)) => {
+                return Err(format!(
+                    "Error: {} takes {}, not {}.",
+                    name,
+                    if float_function { "a float" } else { "ints" },
+                    describe_type(value_type)
+                ))
+            }
+        }
+        arguments.push(argument);
+    }
+    let value_type = match operator {
+        _ if float_function => Type::Float,
+        NumberTheoryOperator::Factor => Type::List,
+        _ => Type::Int,
+    };
+    Ok((
+        (*operator, arguments),
+        ValueInfo {
+            dimension: DIMENSIONLESS,
+            value_type: Some(value_type),
+            length: None,
+            columns: None,
+        },
+    ))
+}
+
+// `randint` takes ints without unit and gives an int. The other parameters
+// are floats: the bounds of `uniform`, and the mean and the standard
+// deviation of `normal`, have the unit of the result, and the rate of
+// `exponential` its inverse unit.
+fn analyze_random(
+    variables: &mut SymbolTable,
+    parsed_random: &ParsedRandom,
+) -> Result<(AnalyzedRandom, ValueInfo), String> {
+    let (operator, parsed_arguments) = parsed_random;
+    let name = random_name(*operator);
+    match (operator, parsed_arguments.len()) {
+        (RandomOperator::Rand, 0) | (RandomOperator::Exponential, 1) => {}
+        (RandomOperator::Rand, _) => return Err("Error: rand takes no argument.".to_string()),
+        (RandomOperator::Exponential, _) => return Err("Error: exponential takes one argument.".to_string()),
+        (_, 2) => {}
+        (_, _) => return Err(format!("Error: {} takes two arguments.", name)),
+    }
+    let mut arguments = Vec::new();
+    let mut dimension: Option<Dimension> = None;
+    for parsed_argument in parsed_arguments {
+        let (mut argument, info) = analyze_expr(variables, parsed_argument)?;
+        match (*operator == RandomOperator::RandInt, info.value_type) {
+            (false, None) => make_float_expr(variables, &mut argument),
+            (false, Some(Type::Float)) | (true, None) | (true, Some(Type::Int)) => {}
+            (false, Some(Type::Int)) => {
+                return Err(format!(
+                    "Error: The arguments of {} must be floats, not ints; convert them with float().",
+                    name
+                ))
+            }
+            (true, Some(Type::Float)) => {
+                return Err("Error: randint takes ints, not a float; convert it with int().".to_string())
+            }
+            (_, Some(value_type)) => {
+                return Err(format!(
+                    "Error: The arguments of {} must be real numbers, not {}.",
+                    name,
+                    describe_type(value_type)
+                ))
+            }
+        }
+        match dimension {
+            Some(first) if first != info.dimension => {
+                return Err(format!(
+                    "Error: The arguments of {} have incompatible units: {} and {}.",
+                    name,
+                    first.describe(),
+                    info.dimension.describe()
+                ))
+            }
+            _ => dimension = Some(info.dimension),
+        }
+        arguments.push(argument);
+    }
+    let dimension = dimension.unwrap_or(DIMENSIONLESS);
+    let (dimension, value_type) = match operator {
+        RandomOperator::RandInt if !dimension.is_dimensionless() => {
+            return Err(format!(
+                "Error: The arguments of randint must be without unit, not {}.",
+                describe_quantity(dimension)
+            ))
+        }
+        RandomOperator::RandInt => (DIMENSIONLESS, Type::Int),
+        RandomOperator::Exponential => (DIMENSIONLESS.div(dimension), Type::Float),
+        _ => (dimension, Type::Float),
+    };
+    Ok((
+        (*operator, arguments),
+        ValueInfo {
+            dimension,
+            value_type: Some(value_type),
+            length: None,
+            columns: None,
+        },
+    ))
+}
+
+// A bound of `integrate` or `solve`, named by `construct` in errors.
+fn analyze_bound(
+    variables: &mut SymbolTable,
+    parsed_expr: &ParsedExpr,
+    construct: &str,
+) -> Result<(AnalyzedExpr, ValueInfo), String> {
+    let (mut expr, info) = analyze_expr(variables, parsed_expr)?;
+    match info.value_type {
+        None => make_float_expr(variables, &mut expr),
+        Some(Type::Float) => {}
+        Some(value_type) => {
+            return Err(format!(
+                "Error: The bounds of {} must be floats, not {}.",
+                construct,
+                describe_type(value_type)
+            ))
+        }
+    }
+    Ok((expr, info))
+}
+
+// The variable bound by `integrate` is a float in the unit of the bounds,
+// and can only be named in the integrand.
+fn analyze_integral(
+    variables: &mut SymbolTable,
+    parsed_integral: &ParsedIntegral,
+) -> Result<(AnalyzedIntegral, ValueInfo), String> {
+    let (integrand, name, lo, hi) = parsed_integral;
+    let (lo, lo_info) = analyze_bound(variables, lo, "integrate")?;
+    let (hi, hi_info) = analyze_bound(variables, hi, "integrate")?;
+    if lo_info.dimension != hi_info.dimension {
+        return Err(format!(
+            "Error: The bounds of integrate have incompatible units: {} and {}.",
+            lo_info.dimension.describe(),
+            hi_info.dimension.describe()
+        ));
+    }
+    let handle = variables.push_scope(name, Type::Float, lo_info.dimension);
+    let analyzed_integrand = analyze_expr(variables, integrand);
+    variables.pop_scope(handle);
+    let (mut integrand, info) = analyzed_integrand?;
+    match info.value_type {
+        None => make_float_expr(variables, &mut integrand),
+        Some(Type::Float) => {}
+        Some(Type::Int) => {
+            return Err(
+                "Error: The integrand of integrate must be a float, not an int; convert it with float()."
+                    .to_string(),
+            )
+        }
+        Some(Type::Complex) => return Err("Error: Cannot integrate complex numbers.".to_string()),
+        Some(value_type @ Type::List) | Some(value_type @ Type::Matrix) | Some(value_type @ Type::Polynomial) => {
+            return Err(format!(
+                "Error: The integrand of integrate must be a float, not {}.",
+                describe_type(value_type)
+            ))
+        }
+    }
+    Ok((
+        (integrand, handle, lo, hi),
+        ValueInfo {
+            dimension: info.dimension.mul(lo_info.dimension),
+            value_type: Some(Type::Float),
+            length: None,
+            columns: None,
+        },
+    ))
+}
+
+fn analyze_index_bound(
+    variables: &mut SymbolTable,
+    parsed_expr: &ParsedExpr,
+    operator: SeriesOperator,
+) -> Result<(AnalyzedExpr, Option<Type>), String> {
+    let (expr, info) = analyze_expr(variables, parsed_expr)?;
+    if !info.dimension.is_dimensionless() {
+        return Err(format!(
+            "Error: The bounds of {} must be without unit, not {}.",
+            series_name(operator),
+            describe_quantity(info.dimension)
+        ));
+    }
+    if let Some(value_type @ Type::Complex)
+    | Some(value_type @ Type::List)
+    | Some(value_type @ Type::Matrix)
+    | Some(value_type @ Type::Polynomial) = info.value_type
+    {
+        return Err(format!(
+            "Error: The bounds of {} must be real numbers, not {}.",
+            series_name(operator),
+            describe_type(value_type)
+        ));
+    }
+    Ok((expr, info.value_type))
+}
+
+// The index of `sum` and `prod` takes the whole values from `lo` to `hi`,
+// and can only be named in the body. It is an int when a bound is an int,
+// and else a float, like literals. The number of factors of a product is
+// only known at run time, so they cannot have a unit.
+fn analyze_series(
+    variables: &mut SymbolTable,
+    parsed_series: &ParsedSeries,
+) -> Result<(AnalyzedSeries, ValueInfo), String> {
+    let (operator, name, lo, hi, body) = parsed_series;
+    let (lo, lo_type) = analyze_index_bound(variables, lo, *operator)?;
+    let (hi, hi_type) = analyze_index_bound(variables, hi, *operator)?;
+    let index_type = match (lo_type, hi_type) {
+        (Some(Type::Float), _) | (_, Some(Type::Float)) | (None, None) => Type::Float,
+        _ => Type::Int,
+    };
+    let handle = variables.push_scope(name, index_type, DIMENSIONLESS);
+    let analyzed_body = analyze_expr(variables, body);
+    variables.pop_scope(handle);
+    let (body, info) = analyzed_body?;
+    if let Some(value_type @ Type::List) | Some(value_type @ Type::Matrix) | Some(value_type @ Type::Polynomial) =
+        info.value_type
+    {
+        return Err(format!(
+            "Error: The body of {} must be a number, not {}.",
+            series_name(*operator),
+            describe_type(value_type)
+        ));
+    }
+    if *operator == SeriesOperator::Product && !info.dimension.is_dimensionless() {
+        return Err(format!(
+            "Error: The factors of prod must be without unit, not {}.",
+            describe_quantity(info.dimension)
+        ));
+    }
+    Ok(((*operator, handle, lo, hi, body), info))
+}
+
+// The arguments of a statistic are floats or lists of floats with the same
+// unit, whose values are taken together, except that `cov`, `pcov` and
+// `corr` pair the values of two lists, and that the last argument of
+// `percentile` is the percentage. Statistics are in the unit of the values,
+// or of their square for variances, and `count` is an int.
+fn analyze_statistic(
+    variables: &mut SymbolTable,
+    parsed_statistic: &ParsedStatistic,
+) -> Result<(AnalyzedStatistic, ValueInfo), String> {
+    let (operator, parsed_arguments) = parsed_statistic;
+    let name = statistic_name(*operator);
+    let paired = matches!(
+        operator,
+        StatisticOperator::Covariance | StatisticOperator::PopulationCovariance | StatisticOperator::Correlation
+    );
+    let value_count = match operator {
+        StatisticOperator::Percentile => parsed_arguments.len().saturating_sub(1),
+        _ => parsed_arguments.len(),
+    };
+    if paired && parsed_arguments.len() != 2 {
+        return Err(format!("Error: {} takes two lists.", name));
+    }
+    if value_count == 0 {
+        return Err(match operator {
+            StatisticOperator::Percentile => "Error: percentile takes values and a percentage.".to_string(),
+            _ => format!("Error: {} takes at least one value.", name),
+        });
+    }
+    let mut arguments = Vec::new();
+    let mut infos: Vec<ValueInfo> = Vec::new();
+    for parsed_argument in parsed_arguments {
+        let (mut argument, info) = analyze_expr(variables, parsed_argument)?;
+        if arguments.len() == value_count {
+            if !info.dimension.is_dimensionless() {
+                return Err(format!(
+                    "Error: The percentage of percentile must be without unit, not {}.",
+                    describe_quantity(info.dimension)
+                ));
+            }
+            match info.value_type {
+                None => make_float_expr(variables, &mut argument),
+                Some(Type::Int) | Some(Type::Float) => {}
+                Some(value_type) => {
+                    return Err(format!(
+                        "Error: The percentage of percentile must be a real number, not {}.",
+                        describe_type(value_type)
+                    ))
+                }
+            }
+        } else {
+            if paired && info.value_type != Some(Type::List) {
+                return Err(format!("Error: {} takes two lists.", name));
+            }
+            match info.value_type {
+                None => make_float_expr(variables, &mut argument),
+                Some(Type::Float) | Some(Type::List) => {}
+                Some(value_type) => {
+                    return Err(format!(
+                        "Error: The arguments of {} must be floats or lists, not {}.",
+                        name,
+                        describe_type(value_type)
+                    ))
+                }
+            }
+            match infos.first() {
+                Some(first) if !paired && first.dimension != info.dimension => {
+                    return Err(format!(
+                        "Error: The arguments of {} have incompatible units: {} and {}.",
+                        name,
+                        first.dimension.describe(),
+                        info.dimension.describe()
+                    ))
+                }
+                _ => infos.push(info),
+            }
+        }
+        arguments.push(argument);
+    }
+    if paired {
+        if let (Some(left), Some(right)) = (infos[0].length, infos[1].length) {
+            if left != right {
+                return Err(format!("Error: Cannot pair lists of lengths {} and {}.", left, right));
+            }
+        }
+    }
+    let dimension = infos[0].dimension;
+    let (dimension, value_type) = match operator {
+        StatisticOperator::Count => (DIMENSIONLESS, Type::Int),
+        StatisticOperator::Variance | StatisticOperator::PopulationVariance => {
+            (dimension.mul(dimension), Type::Float)
+        }
+        StatisticOperator::Covariance | StatisticOperator::PopulationCovariance => {
+            (dimension.mul(infos[1].dimension), Type::Float)
+        }
+        StatisticOperator::Correlation => (DIMENSIONLESS, Type::Float),
+        _ => (dimension, Type::Float),
+    };
+    Ok((
+        (*operator, arguments),
+        ValueInfo {
+            dimension,
+            value_type: Some(value_type),
+            length: None,
+            columns: None,
+        },
+    ))
+}
+
+// Derivatives are computed with real dual numbers, with respect to a float
+// variable, and cannot be nested.
+fn differentiation_variable(variables: &SymbolTable, name: &str) -> Result<usize, String> {
+    let handle = variables.find_symbol(name)?;
+    if variables.is_constant(handle) {
+        return Err(format!(
+            "Error: Cannot differentiate with respect to the constant '{}'.",
+            name
+        ));
+    }
+    match variables.get_type(handle) {
+        Some(value_type @ Type::Int)
+        | Some(value_type @ Type::Complex)
+        | Some(value_type @ Type::List)
+        | Some(value_type @ Type::Matrix)
+        | Some(value_type @ Type::Polynomial) => Err(format!(
+            "Error: Derivatives are taken with respect to float variables, but '{}' is {}.",
+            name,
+            describe_type(value_type)
         )),
-        
+        _ => Ok(handle),
     }
 }
 
+fn analyze_differentiated_expr(
+    variables: &mut SymbolTable,
+    parsed_expr: &ParsedExpr,
+) -> Result<(AnalyzedExpr, Dimension), String> {
+    let (mut expr, info) = analyze_expr(variables, parsed_expr)?;
+    if info.value_type.is_none() {
+        make_float_expr(variables, &mut expr);
+    }
+    if info.value_type == Some(Type::List) {
+        return Err("Error: Cannot differentiate lists.".to_string());
+    }
+    if info.value_type == Some(Type::Matrix) {
+        return Err("Error: Cannot differentiate matrices.".to_string());
+    }
+    if info.value_type == Some(Type::Polynomial) {
+        return Err("Error: Cannot differentiate polynomials with diff; use derive(p).".to_string());
+    }
+    check_differentiable_expr(variables, &expr)?;
+    Ok((expr, info.dimension))
+}
+
+// The root is stored in a float variable in the unit of the bounds. Newton's
+// method differentiates the equation, which must follow the rules of `diff`.
+fn analyze_solve(variables: &mut SymbolTable, parsed_solve: &ParsedSolve) -> Result<AnalyzedSolve, String> {
+    let (lhs, rhs, name, lo, hi) = parsed_solve;
+    let handle = find_mutable_symbol(variables, name)?;
+    if let Some(value_type @ Type::Int)
+    | Some(value_type @ Type::Complex)
+    | Some(value_type @ Type::List)
+    | Some(value_type @ Type::Matrix)
+    | Some(value_type @ Type::Polynomial) = variables.get_type(handle)
+    {
+        return Err(format!(
+            "Error: solve finds the value of float variables, but '{}' is {}.",
+            name,
+            describe_type(value_type)
+        ));
+    }
+    let (lo, lo_info) = analyze_bound(variables, lo, "solve")?;
+    let (hi, hi_info) = analyze_bound(variables, hi, "solve")?;
+    if lo_info.dimension != hi_info.dimension {
+        return Err(format!(
+            "Error: The bounds of solve have incompatible units: {} and {}.",
+            lo_info.dimension.describe(),
+            hi_info.dimension.describe()
+        ));
+    }
+    match variables.get_dimension(handle) {
+        Some(expected) if expected != lo_info.dimension => {
+            return Err(format!(
+                "Error: The bounds of solve are {}, but '{}' is {}.",
+                describe_quantity(lo_info.dimension),
+                name,
+                describe_quantity(expected)
+            ))
+        }
+        _ => variables.set_dimension(handle, lo_info.dimension),
+    }
+    variables.set_type(handle, Type::Float);
+    let (lhs, lhs_dimension) = analyze_differentiated_expr(variables, lhs)?;
+    let (rhs, rhs_dimension) = analyze_differentiated_expr(variables, rhs)?;
+    if lhs_dimension != rhs_dimension {
+        return Err(format!(
+            "Error: The sides of the equation have incompatible units: {} and {}.",
+            lhs_dimension.describe(),
+            rhs_dimension.describe()
+        ));
+    }
+    Ok((lhs, rhs, handle, lo, hi))
+}
+
+fn check_differentiable_factor(variables: &SymbolTable, factor: &AnalyzedFactor) -> Result<(), String> {
+    if factor_type(variables, factor) == Type::Complex {
+        return Err("Error: Cannot differentiate complex numbers.".to_string());
+    }
+    match factor {
+        AnalyzedFactor::Literal(_) | AnalyzedFactor::Identifier(_) | AnalyzedFactor::Length(_) => Ok(()),
+        AnalyzedFactor::FunctionExpression(function_expr) => {
+            check_differentiable_expr(variables, &function_expr.1)
+        }
+        AnalyzedFactor::SubExpression(expr) => check_differentiable_expr(variables, expr),
+        AnalyzedFactor::Derivative(..) => Err("Error: Derivatives cannot be nested.".to_string()),
+        AnalyzedFactor::Integral(_) => Err("Error: Cannot differentiate integrals.".to_string()),
+        AnalyzedFactor::Series(_) => Err("Error: Cannot differentiate sums and products.".to_string()),
+        AnalyzedFactor::Statistic(statistic) if statistic.0 == StatisticOperator::Count => Ok(()),
+        AnalyzedFactor::Statistic(_) => Err("Error: Cannot differentiate statistics.".to_string()),
+        AnalyzedFactor::List(_) | AnalyzedFactor::Range(..) | AnalyzedFactor::Index(..) => {
+            Err("Error: Cannot differentiate lists.".to_string())
+        }
+        AnalyzedFactor::Matrix(_) | AnalyzedFactor::MatrixFunction(_) => {
+            Err("Error: Cannot differentiate matrices.".to_string())
+        }
+        AnalyzedFactor::Polynomial(..) | AnalyzedFactor::PolynomialFunction(_) => {
+            Err("Error: Cannot differentiate polynomials.".to_string())
+        }
+        AnalyzedFactor::NumberTheoryFunction(function) => match function.0 {
+            NumberTheoryOperator::Gamma | NumberTheoryOperator::LogGamma => {
+                Err(format!("Error: Cannot differentiate {}.", number_theory_name(function.0)))
+            }
+            NumberTheoryOperator::Factor => Err("Error: Cannot differentiate lists.".to_string()),
+            _ => Ok(()),
+        },
+        AnalyzedFactor::Random(random) if random.0 == RandomOperator::RandInt => Ok(()),
+        AnalyzedFactor::Random(_) => Err("Error: Cannot differentiate random numbers.".to_string()),
+    }
+}
+
+fn check_differentiable_expr(variables: &SymbolTable, expr: &AnalyzedExpr) -> Result<(), String> {
+    for term in std::iter::once(&expr.0).chain(expr.1.iter().map(|term| &term.1)) {
+        check_differentiable_factor(variables, &term.0)?;
+        for factor in &term.1 {
+            check_differentiable_factor(variables, &factor.1)?;
+        }
+    }
+    Ok(())
+}
+
+// A quantity can only be raised to a constant integer power, which gives the
+// exponents of its dimension.
+fn power_dimension(
+    variables: &mut SymbolTable,
+    base: Dimension,
+    exponent: &AnalyzedFactor,
+    exponent_dimension: Dimension,
+) -> Result<Dimension, String> {
+    if !exponent_dimension.is_dimensionless() {
+        return Err(format!(
+            "Error: The exponent of `^` must be dimensionless, not in {}.",
+            exponent_dimension
+        ));
+    }
+    if base.is_dimensionless() {
+        return Ok(DIMENSIONLESS);
+    }
+    if optimizer::is_constant_factor(variables, exponent) {
+        if let Some(value) = executor::evaluate_factor(variables, exponent)
+            .ok()
+            .filter(|value| value.get_type() != Type::Complex)
+        {
+            let value = value.as_float();
+            if value.fract() == 0. && value.abs() <= i32::MAX as f64 {
+                return Ok(base.powi(value as i32));
+            }
+        }
+    }
+    Err(format!(
+        "Error: A quantity in {} can only be raised to a constant integer power.",
+        base
+    ))
+}
+
 fn analyze_term(
     variables: &mut SymbolTable,
     parsed_term: &ParsedTerm,
-) -> Result<AnalyzedTerm, String> {
-    let first_factor = analyze_factor(variables, &parsed_term.0)?;
+) -> Result<(AnalyzedTerm, ValueInfo), String> {
+    let (mut first_factor, mut info) = analyze_factor(variables, &parsed_term.0)?;
     let mut other_factors = Vec::<(TermOperator, AnalyzedFactor)>::new();
     for factor in &parsed_term.1 {
-        other_factors.push((factor.0, analyze_factor(variables, &factor.1)?));
+        let (mut analyzed_factor, factor_info) = analyze_factor(variables, &factor.1)?;
+        let operator_name = match factor.0 {
+            TermOperator::Multiply => "*",
+            TermOperator::Divide => "/",
+            TermOperator::Exponent => "^",
+            TermOperator::IntegerDivide => "div",
+            TermOperator::MatrixMultiply => "@",
+        };
+        if factor.0 == TermOperator::MatrixMultiply {
+            info = analyze_matrix_product(info, factor_info)?;
+            other_factors.push((factor.0, analyzed_factor));
+            continue;
+        }
+        let dimension = match factor.0 {
+            TermOperator::Multiply | TermOperator::MatrixMultiply => info.dimension.mul(factor_info.dimension),
+            TermOperator::Divide | TermOperator::IntegerDivide => {
+                info.dimension.div(factor_info.dimension)
+            }
+            TermOperator::Exponent => power_dimension(
+                variables,
+                info.dimension,
+                &analyzed_factor,
+                factor_info.dimension,
+            )?,
+        };
+        if factor_info.value_type == Some(Type::Polynomial) {
+            match factor.0 {
+                TermOperator::Divide => {
+                    return Err("Error: `/` cannot divide by a polynomial; use `div` and `mod`.".to_string())
+                }
+                TermOperator::Exponent => return Err("Error: The exponent of `^` cannot be a polynomial.".to_string()),
+                _ => {}
+            }
+        }
+        let value_type = match factor.0 {
+            TermOperator::Divide => {
+                if info.value_type == Some(Type::Int) || factor_info.value_type == Some(Type::Int) {
+                    return Err(
+                        "Error: `/` divides floats; use `div` for ints or convert them with float()."
+                            .to_string(),
+                    );
+                }
+                combine_types(operator_name, info.value_type, factor_info.value_type)?.or(Some(Type::Float))
+            }
+            TermOperator::Multiply | TermOperator::Exponent | TermOperator::MatrixMultiply => {
+                combine_types(operator_name, info.value_type, factor_info.value_type)?
+            }
+            TermOperator::IntegerDivide => {
+                check_real(operator_name, info.value_type, factor_info.value_type)?;
+                combine_types(operator_name, info.value_type, factor_info.value_type)?
+            }
+        };
+        let (length, columns) = combine_shapes(operator_name, info, factor_info)?;
+        if value_type == Some(Type::Polynomial) && !dimension.is_dimensionless() {
+            return Err(format!("Error: Polynomials are without unit, not in {}.", dimension));
+        }
+        if needs_float(value_type) {
+            if info.value_type.is_none() {
+                make_float_factor(variables, &mut first_factor);
+                for factor in &mut other_factors {
+                    make_float_factor(variables, &mut factor.1);
+                }
+            }
+            if factor_info.value_type.is_none() {
+                make_float_factor(variables, &mut analyzed_factor);
+            }
+        }
+        info = ValueInfo {
+            dimension,
+            value_type,
+            length,
+            columns,
+        };
+        other_factors.push((factor.0, analyzed_factor));
     }
-    Ok((first_factor, other_factors))
+    Ok(((first_factor, other_factors), info))
 }
 
 fn analyze_expr(
     variables: &mut SymbolTable,
     parsed_expr: &ParsedExpr,
-) -> Result<AnalyzedExpr, String> {
-    let first_term = analyze_term(variables, &parsed_expr.0)?;
+) -> Result<(AnalyzedExpr, ValueInfo), String> {
+    let (mut first_term, mut info) = analyze_term(variables, &parsed_expr.0)?;
     let mut other_terms = Vec::<(ExprOperator, AnalyzedTerm)>::new();
     for term in &parsed_expr.1 {
-        other_terms.push((term.0, analyze_term(variables, &term.1)?));
+        let (mut analyzed_term, term_info) = analyze_term(variables, &term.1)?;
+        let operator_name = match term.0 {
+            ExprOperator::Add => "+",
+            ExprOperator::Subtract => "-",
+            ExprOperator::Modulo => "mod",
+            ExprOperator::PlusMinus => "+-",
+        };
+        if term_info.dimension != info.dimension {
+            return Err(format!(
+                "Error: The operands of `{}` have incompatible units: {} and {}.",
+                operator_name,
+                info.dimension.describe(),
+                term_info.dimension.describe()
+            ));
+        }
+        if term.0 == ExprOperator::Modulo || term.0 == ExprOperator::PlusMinus {
+            check_real(operator_name, info.value_type, term_info.value_type)?;
+        }
+        let value_type = if term.0 == ExprOperator::PlusMinus {
+            if info.value_type == Some(Type::Polynomial) || term_info.value_type == Some(Type::Polynomial) {
+                return Err("Error: Polynomials cannot be measured with `+-`.".to_string());
+            }
+            if info.value_type == Some(Type::Int) || term_info.value_type == Some(Type::Int) {
+                return Err(
+                    "Error: Measured values are floats; convert ints with float() before `+-`."
+                        .to_string(),
+                );
+            }
+            combine_types(operator_name, info.value_type, term_info.value_type)?.or(Some(Type::Float))
+        } else {
+            combine_types(operator_name, info.value_type, term_info.value_type)?
+        };
+        if needs_float(value_type) {
+            if info.value_type.is_none() {
+                make_float_term(variables, &mut first_term);
+                for term in &mut other_terms {
+                    make_float_term(variables, &mut term.1);
+                }
+            }
+            if term_info.value_type.is_none() {
+                make_float_term(variables, &mut analyzed_term);
+            }
+        }
+        let (length, columns) = combine_shapes(operator_name, info, term_info)?;
+        info.value_type = value_type;
+        info.length = length;
+        info.columns = columns;
+        other_terms.push((term.0, analyzed_term));
     }
-    Ok((first_term, other_terms))
+    Ok(((first_term, other_terms), info))
 }
 
-
 fn analyze_function_expr(
     variables: &mut SymbolTable,
     parsed_function_expr: &ParsedFunctionExpr,
-) -> Result<AnalyzedFunctionExpr, String> {
+) -> Result<(AnalyzedFunctionExpr, ValueInfo), String> {
     let function = parsed_function_expr.0;
-    let expr = analyze_expr(variables, &parsed_function_expr.1)?;
-    Ok((function, expr))
+    let (mut expr, argument) = analyze_expr(variables, &parsed_function_expr.1)?;
+    if argument.value_type == Some(Type::Polynomial) {
+        return Err(format!(
+            "Error: {} cannot be applied to a polynomial.",
+            function_name(function)
+        ));
+    }
+    let dimension = match function {
+        FunctionOperator::Abs
+        | FunctionOperator::Ceil
+        | FunctionOperator::Floor
+        | FunctionOperator::ToInt
+        | FunctionOperator::ToFloat
+        | FunctionOperator::Re
+        | FunctionOperator::Im
+        | FunctionOperator::Conj => argument.dimension,
+        FunctionOperator::Signum | FunctionOperator::Arg => DIMENSIONLESS,
+        FunctionOperator::Sqrt => argument.dimension.sqrt().ok_or_else(|| {
+            format!(
+                "Error: The square root of a quantity in {} has no unit.",
+                argument.dimension
+            )
+        })?,
+        _ if argument.dimension.is_dimensionless() => DIMENSIONLESS,
+        _ => {
+            return Err(format!(
+                "Error: The argument of {} must be dimensionless, not in {}.",
+                function_name(function),
+                argument.dimension
+            ))
+        }
+    };
+    let complex_argument = argument.value_type == Some(Type::Complex);
+    let list_argument = matches!(argument.value_type, Some(Type::List) | Some(Type::Matrix));
+    let value_type = match function {
+        FunctionOperator::Ceil
+        | FunctionOperator::Floor
+        | FunctionOperator::ToInt
+        | FunctionOperator::ToFloat
+            if complex_argument =>
+        {
+            return Err(format!(
+                "Error: The argument of {} must be real, not complex; take its re() or im() first.",
+                function_name(function)
+            ))
+        }
+        FunctionOperator::ToInt if list_argument => {
+            return Err("Error: Lists and matrices hold floats, so int() cannot be applied to them.".to_string())
+        }
+        _ if list_argument => argument.value_type,
+        FunctionOperator::Abs if complex_argument => Some(Type::Float),
+        FunctionOperator::Abs | FunctionOperator::Signum => argument.value_type,
+        FunctionOperator::ToInt => Some(Type::Int),
+        FunctionOperator::ToFloat => Some(Type::Float),
+        _ if argument.value_type == Some(Type::Int) => {
+            return Err(format!(
+                "Error: The argument of {} must be a float, not an int; convert it with float().",
+                function_name(function)
+            ))
+        }
+        FunctionOperator::Re | FunctionOperator::Im | FunctionOperator::Arg if complex_argument => {
+            Some(Type::Float)
+        }
+        _ if complex_argument => Some(Type::Complex),
+        _ => {
+            make_float_expr(variables, &mut expr);
+            Some(Type::Float)
+        }
+    };
+    Ok((
+        (function, expr),
+        ValueInfo {
+            dimension,
+            value_type,
+            length: argument.length,
+            columns: argument.columns,
+        },
+    ))
 }
 
+fn find_mutable_symbol(variables: &SymbolTable, identifier: &str) -> Result<usize, String> {
+    let handle = variables.find_symbol(identifier)?;
+    if variables.is_constant(handle) {
+        Err(format!(
+            "Error: Constant '{}' cannot be changed.",
+            identifier
+        ))
+    } else {
+        Ok(handle)
+    }
+}
+
+fn describe_quantity(dimension: Dimension) -> String {
+    if dimension.is_dimensionless() {
+        "without unit".to_string()
+    } else {
+        format!("in {}", dimension)
+    }
+}
+
+// The dimension and the type of a variable are those of its declaration, or
+// else of the first value stored in it; later values must match them.
+fn store_value(
+    variables: &mut SymbolTable,
+    handle: usize,
+    expr: &mut AnalyzedExpr,
+    info: ValueInfo,
+) -> Result<(), String> {
+    match variables.get_dimension(handle) {
+        Some(expected) if expected != info.dimension => {
+            return Err(format!(
+                "Error: Cannot store a quantity {} in '{}', which is {}.",
+                describe_quantity(info.dimension),
+                variables.get_name(handle),
+                describe_quantity(expected)
+            ))
+        }
+        _ => variables.set_dimension(handle, info.dimension),
+    }
+    variables.set_length(handle, info.length);
+    variables.set_columns(handle, info.columns);
+    match (variables.get_type(handle), info.value_type) {
+        (Some(expected), Some(actual)) if expected != actual => Err(format!(
+            "Error: Cannot store {} in '{}', which is {}.",
+            describe_type(actual),
+            variables.get_name(handle),
+            describe_type(expected)
+        )),
+        (Some(Type::Float), None) => {
+            make_float_expr(variables, expr);
+            Ok(())
+        }
+        (Some(Type::Complex), None) => Err(format!(
+            "Error: Cannot store a float in '{}', which is a complex number; add 0i to it.",
+            variables.get_name(handle)
+        )),
+        (Some(value_type @ Type::List), None)
+        | (Some(value_type @ Type::Matrix), None)
+        | (Some(value_type @ Type::Polynomial), None) => Err(format!(
+            "Error: Cannot store a number in '{}', which is {}.",
+            variables.get_name(handle),
+            describe_type(value_type)
+        )),
+        (Some(_), _) => Ok(()),
+        (None, value_type) => {
+            if value_type.is_none() {
+                make_float_expr(variables, expr);
+            }
+            variables.set_type(handle, value_type.unwrap_or(Type::Float));
+            Ok(())
+        }
+    }
+}
+
+fn annotate(
+    variables: &mut SymbolTable,
+    handle: usize,
+    annotation: &Option<ParsedAnnotation>,
+) -> Result<(), String> {
+    match annotation {
+        Some(ParsedAnnotation::Type(value_type)) => variables.set_type(handle, *value_type),
+        Some(ParsedAnnotation::Unit(unit)) => {
+            variables.set_unit(handle, resolve_unit(unit)?);
+            variables.set_type(handle, Type::Float);
+        }
+        None => {}
+    }
+    Ok(())
+}
 
 fn analyze_statement(
     variables: &mut SymbolTable,
@@ -104,29 +1889,319 @@ fn analyze_statement(
 ) -> Result<AnalyzedStatement, String> {
     match parsed_statement {
         
-        ParsedStatement::DeclarationToAssignment(identifier, expr) => {
+        ParsedStatement::DeclarationToAssignment(identifier, annotation, expr) => {
             variables.insert_symbol(identifier)?;
             let handle = variables.find_symbol(identifier)?;
-            let analyzed_expr = analyze_expr(variables, expr)?;
+            annotate(variables, handle, annotation)?;
+            let (mut analyzed_expr, info) = analyze_expr(variables, expr)?;
+            store_value(variables, handle, &mut analyzed_expr, info)?;
             Ok(AnalyzedStatement::DeclarationToAssignment(handle, analyzed_expr)) 
         }
         ParsedStatement::Assignment(identifier, expr) => {
-            let handle = variables.find_symbol(identifier)?;
-            let analyzed_expr = analyze_expr(variables, expr)?;
+            let handle = find_mutable_symbol(variables, identifier)?;
+            let (mut analyzed_expr, info) = analyze_expr(variables, expr)?;
+            store_value(variables, handle, &mut analyzed_expr, info)?;
             Ok(AnalyzedStatement::Assignment(handle, analyzed_expr))
         }
-        ParsedStatement::Declaration(identifier) => {
+        ParsedStatement::Declaration(identifier, annotation) => {
             let handle = variables.insert_symbol(identifier)?;
+            annotate(variables, handle, annotation)?;
             Ok(AnalyzedStatement::Declaration(handle))
         }
        
-        ParsedStatement::InputOperation(identifier) => {
-            let handle = variables.find_symbol(identifier)?;
-            Ok(AnalyzedStatement::InputOperation(handle))
+        ParsedStatement::InputOperation(identifier, range) => {
+            let handle = find_mutable_symbol(variables, identifier)?;
+            if let Some((lo, hi)) = range {
+                if lo > hi {
+                    return Err(format!(
+                        "Error: Empty range [{}, {}] for the input of '{}'.",
+                        lo, hi, identifier
+                    ));
+                }
+            }
+            if variables.get_dimension(handle).is_none() {
+                variables.set_dimension(handle, DIMENSIONLESS);
+            }
+            if variables.get_type(handle).is_none() {
+                variables.set_type(handle, Type::Float);
+            }
+            if variables.get_type(handle) == Some(Type::List) {
+                return Err(format!("Error: The list '{}' cannot be input.", identifier));
+            }
+            if variables.get_type(handle) == Some(Type::Matrix) {
+                return Err(format!("Error: The matrix '{}' cannot be input.", identifier));
+            }
+            if variables.get_type(handle) == Some(Type::Polynomial) {
+                return Err(format!("Error: The polynomial '{}' cannot be input.", identifier));
+            }
+            if range.is_some() && variables.get_type(handle) == Some(Type::Complex) {
+                return Err(format!(
+                    "Error: The input of the complex variable '{}' cannot have a range.",
+                    identifier
+                ));
+            }
+            Ok(AnalyzedStatement::InputOperation(handle, *range))
+        }
+        ParsedStatement::OutputOperation(expr, unit, format, label) => {
+            let (mut analyzed_expr, info) = analyze_expr(variables, expr)?;
+            if info.value_type.is_none() {
+                make_float_expr(variables, &mut analyzed_expr);
+            }
+            let unit = match unit {
+                Some(unit) => {
+                    let unit = resolve_unit(unit)?;
+                    if unit.dimension != info.dimension {
+                        return Err(format!(
+                            "Error: Cannot convert a quantity {} to {}.",
+                            describe_quantity(info.dimension),
+                            unit.name
+                        ));
+                    }
+                    Some(unit)
+                }
+                None if info.dimension.is_dimensionless() => None,
+                None => Some(Unit::base(info.dimension)),
+            };
+            let format = match format {
+                Some(format) => Some(format.parse::<Format>()?),
+                None => variables.format(),
+            };
+            if format.is_some() && info.value_type == Some(Type::Polynomial) {
+                return Err("Error: A polynomial cannot be output with a format.".to_string());
+            }
+            Ok(AnalyzedStatement::OutputOperation(
+                analyzed_expr,
+                unit,
+                format,
+                label.map(|label| label.to_string()),
+            ))
+        }
+        ParsedStatement::GradientOutput(expr, names) => {
+            let (analyzed_expr, dimension) = analyze_differentiated_expr(variables, expr)?;
+            let mut partials = Vec::new();
+            for name in names {
+                let handle = differentiation_variable(variables, name)?;
+                let dimension =
+                    dimension.div(variables.get_dimension(handle).unwrap_or(DIMENSIONLESS));
+                partials.push((
+                    handle,
+                    if dimension.is_dimensionless() { None } else { Some(Unit::base(dimension)) },
+                ));
+            }
+            Ok(AnalyzedStatement::GradientOutput(analyzed_expr, partials))
+        }
+        ParsedStatement::SymbolicDerivative(expr, name) => {
+            let (analyzed_expr, _) = analyze_differentiated_expr(variables, expr)?;
+            let handle = differentiation_variable(variables, name)?;
+            Ok(AnalyzedStatement::SymbolicDerivative(analyzed_expr, handle))
+        }
+        ParsedStatement::Solve(solve) => {
+            Ok(AnalyzedStatement::Solve(Box::new(analyze_solve(variables, solve)?)))
         }
-        ParsedStatement::OutputOperation(expr) => {
-            let analyzed_expr = analyze_expr(variables, expr)?;
-            Ok(AnalyzedStatement::OutputOperation(analyzed_expr))
+        ParsedStatement::AngleMode(unit) => {
+            variables.set_angle_unit(*unit);
+            Ok(AnalyzedStatement::AngleMode(*unit))
+        }
+        ParsedStatement::Seed(expr) => {
+            let (analyzed_expr, info) = analyze_expr(variables, expr)?;
+            if !info.dimension.is_dimensionless() {
+                return Err(format!(
+                    "Error: The seed must be without unit, not {}.",
+                    describe_quantity(info.dimension)
+                ));
+            }
+            match info.value_type {
+                None | Some(Type::Int) => Ok(AnalyzedStatement::Seed(analyzed_expr)),
+                Some(Type::Float) => {
+                    Err("Error: The seed must be an int, not a float; convert it with int().".to_string())
+                }
+                Some(value_type) => Err(format!("Error: The seed must be an int, not {}.", describe_type(value_type))),
+            }
         }  
     }
-}
\ No newline at end of file
+}
+
+pub fn factor_type(variables: &SymbolTable, factor: &AnalyzedFactor) -> Type {
+    match factor {
+        AnalyzedFactor::Literal(value) => value.get_type(),
+        AnalyzedFactor::Identifier(handle) => variables.get_type(*handle).unwrap_or(Type::Float),
+        AnalyzedFactor::FunctionExpression(function_expr) => {
+            match (function_expr.0, expr_type(variables, &function_expr.1)) {
+                (FunctionOperator::ToInt, _) => Type::Int,
+                (_, argument_type @ Type::List) | (_, argument_type @ Type::Matrix) => argument_type,
+                (FunctionOperator::Abs, Type::Complex)
+                | (FunctionOperator::Re, _)
+                | (FunctionOperator::Im, _)
+                | (FunctionOperator::Arg, _)
+                | (FunctionOperator::ToFloat, _) => Type::Float,
+                (FunctionOperator::Abs, argument_type)
+                | (FunctionOperator::Signum, argument_type)
+                | (_, argument_type @ Type::Complex) => argument_type,
+                _ => Type::Float,
+            }
+        }
+        AnalyzedFactor::SubExpression(expr) => expr_type(variables, expr),
+        AnalyzedFactor::Derivative(..) | AnalyzedFactor::Integral(_) => Type::Float,
+        AnalyzedFactor::Series(series) => expr_type(variables, &series.4),
+        AnalyzedFactor::Statistic(statistic) if statistic.0 == StatisticOperator::Count => Type::Int,
+        AnalyzedFactor::Statistic(_) => Type::Float,
+        AnalyzedFactor::MatrixFunction(function) => match function.0 {
+            MatrixOperator::Determinant => Type::Float,
+            MatrixOperator::Solve => expr_type(variables, &function.1[1]),
+            _ => Type::Matrix,
+        },
+        AnalyzedFactor::Polynomial(..) => Type::Polynomial,
+        AnalyzedFactor::PolynomialFunction(function) => match function.0 {
+            PolynomialOperator::Evaluate => expr_type(variables, &function.1[1]),
+            PolynomialOperator::Roots => Type::List,
+            _ => Type::Polynomial,
+        },
+        AnalyzedFactor::NumberTheoryFunction(function) => match function.0 {
+            NumberTheoryOperator::Gamma | NumberTheoryOperator::LogGamma => Type::Float,
+            NumberTheoryOperator::Factor => Type::List,
+            _ => Type::Int,
+        },
+        AnalyzedFactor::Random(random) if random.0 == RandomOperator::RandInt => Type::Int,
+        AnalyzedFactor::Random(_) => Type::Float,
+        AnalyzedFactor::List(_) | AnalyzedFactor::Range(..) => Type::List,
+        AnalyzedFactor::Matrix(_) => Type::Matrix,
+        AnalyzedFactor::Index(list, _) if expr_type(variables, list) == Type::Matrix => Type::List,
+        AnalyzedFactor::Index(..) => Type::Float,
+        AnalyzedFactor::Length(_) => Type::Int,
+    }
+}
+
+/// Type of the value of a term, as checked by the analyzer: all the factors
+/// have the same type, except that `/` gives a float, that a complex factor
+/// makes the term complex, that a polynomial factor makes it a polynomial,
+/// that a list or matrix factor makes it a list or a matrix, and that `@`
+/// gives the type of the product.
+pub fn term_type(variables: &SymbolTable, term: &AnalyzedTerm) -> Type {
+    let first_type = factor_type(variables, &term.0);
+    if term.1.iter().any(|factor| factor.0 == TermOperator::MatrixMultiply) {
+        return term.1.iter().fold(first_type, |left, factor| {
+            match (factor.0, left, factor_type(variables, &factor.1)) {
+                (TermOperator::MatrixMultiply, Type::List, Type::List) => Type::Float,
+                (TermOperator::MatrixMultiply, Type::Matrix, Type::Matrix) => Type::Matrix,
+                (TermOperator::MatrixMultiply, _, _) => Type::List,
+                (_, Type::Matrix, _) | (_, _, Type::Matrix) => Type::Matrix,
+                (_, Type::List, _) | (_, _, Type::List) => Type::List,
+                _ => Type::Float,
+            }
+        });
+    }
+    let has_factor_of_type = |value_type: Type| {
+        first_type == value_type
+            || term.1.iter().any(|factor| factor_type(variables, &factor.1) == value_type)
+    };
+    if has_factor_of_type(Type::Complex) {
+        Type::Complex
+    } else if has_factor_of_type(Type::Polynomial) {
+        Type::Polynomial
+    } else if has_factor_of_type(Type::Matrix) {
+        Type::Matrix
+    } else if has_factor_of_type(Type::List) {
+        Type::List
+    } else if term.1.iter().any(|factor| factor.0 == TermOperator::Divide) {
+        Type::Float
+    } else {
+        first_type
+    }
+}
+
+pub fn expr_type(variables: &SymbolTable, expr: &AnalyzedExpr) -> Type {
+    let first_type = term_type(variables, &expr.0);
+    if expr.1.iter().any(|term| term_type(variables, &term.1) == Type::Complex) {
+        Type::Complex
+    } else if expr.1.iter().any(|term| term_type(variables, &term.1) == Type::Polynomial) {
+        Type::Polynomial
+    } else if expr.1.iter().any(|term| term_type(variables, &term.1) == Type::Matrix) {
+        Type::Matrix
+    } else if expr.1.iter().any(|term| term_type(variables, &term.1) == Type::List) {
+        Type::List
+    } else {
+        first_type
+    }
+}
+
+// Definite-assignment analysis over the statements in order, which is all
+// the control flow there is. `initialized[handle]` tells whether the variable
+// is assigned before the current statement, starting from the variables the
+// executor has assigned on earlier lines of the REPL.
+struct InitializationCheck<'a> {
+    variables: &'a SymbolTable,
+    initialized: Vec<bool>,
+    reported: Vec<usize>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> InitializationCheck<'a> {
+    fn check_reads(&mut self, statement_number: usize, expr: &AnalyzedExpr) {
+        let mut reads = Vec::new();
+        collect_expr_reads(expr, &mut reads);
+        self.check_handles(statement_number, reads);
+    }
+
+    fn check_handles(&mut self, statement_number: usize, reads: Vec<usize>) {
+        for handle in reads {
+            if !self.initialized[handle] && !self.reported.contains(&handle) {
+                self.reported.push(handle);
+                self.diagnostics.push(Diagnostic::new(
+                    Lint::UninitializedRead,
+                    format!(
+                        "variable '{}' is read in statement {} before being assigned a value",
+                        self.variables.get_name(handle),
+                        statement_number
+                    ),
+                ));
+            }
+        }
+    }
+
+    fn check_statement(&mut self, statement_number: usize, statement: &AnalyzedStatement) {
+        match statement {
+            AnalyzedStatement::Declaration(handle) => self.initialized[*handle] = false,
+            AnalyzedStatement::DeclarationToAssignment(handle, expr)
+            | AnalyzedStatement::Assignment(handle, expr) => {
+                self.check_reads(statement_number, expr);
+                self.initialized[*handle] = true;
+            }
+            AnalyzedStatement::InputOperation(handle, _) => self.initialized[*handle] = true,
+            AnalyzedStatement::OutputOperation(expr, ..) => self.check_reads(statement_number, expr),
+            AnalyzedStatement::GradientOutput(expr, _) | AnalyzedStatement::Seed(expr) => {
+                self.check_reads(statement_number, expr)
+            }
+            AnalyzedStatement::SymbolicDerivative(..) | AnalyzedStatement::AngleMode(_) => {}
+            // The equation reads the values tried by the solver.
+            AnalyzedStatement::Solve(solve) => {
+                let (lhs, rhs, handle, lo, hi) = &**solve;
+                self.check_reads(statement_number, lo);
+                self.check_reads(statement_number, hi);
+                let mut reads = Vec::new();
+                collect_expr_reads(lhs, &mut reads);
+                collect_expr_reads(rhs, &mut reads);
+                reads.retain(|read| read != handle);
+                self.check_handles(statement_number, reads);
+                self.initialized[*handle] = true;
+            }
+        }
+    }
+}
+
+/// Reports every read of a variable that is not definitely assigned. The
+/// symbol table is left as is: a variable only counts as assigned once the
+/// executor has run its assignment, which may fail.
+pub fn check_initialization(variables: &SymbolTable, program: &AnalyzedProgram) -> Vec<Diagnostic> {
+    let mut check = InitializationCheck {
+        variables,
+        initialized: (0..variables.len())
+            .map(|handle| variables.is_initialized(handle))
+            .collect(),
+        reported: Vec::new(),
+        diagnostics: Vec::new(),
+    };
+    for (index, statement) in program.iter().enumerate() {
+        check.check_statement(index + 1, statement);
+    }
+    check.diagnostics
+}
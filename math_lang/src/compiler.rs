@@ -1,26 +1,380 @@
 use crate::analyzer::{
-    AnalyzedFunctionExpr, AnalyzedExpr, AnalyzedFactor, AnalyzedProgram, AnalyzedStatement, AnalyzedTerm,
+    expr_type, factor_type, term_type, AnalyzedFunctionExpr, AnalyzedExpr, AnalyzedFactor, AnalyzedMatrixFunction,
+    AnalyzedNumberTheoryFunction, AnalyzedPolynomialFunction, AnalyzedProgram, AnalyzedRandom, AnalyzedSeries, AnalyzedStatement, AnalyzedStatistic, AnalyzedTerm,
+};
+use crate::executor::{MAX_RANGE_LENGTH, MAX_SERIES_TERMS};
+use crate::number_format::Format;
+use crate::parser::{
+    ExprOperator, TermOperator, FunctionOperator, MatrixOperator, NumberTheoryOperator, PolynomialOperator,
+    RandomOperator, SeriesOperator, StatisticOperator,
 };
-use crate::parser::{ExprOperator, TermOperator, FunctionOperator};
 use crate::symbol_table::SymbolTable;
+use crate::symbolic::derive_source;
+use crate::units::Unit;
+use crate::unparser::{number_theory_name, series_name};
+use crate::value::{Type, Value};
+
+// Ints are emitted as i64 with checked operations, through the `checked`,
+// `int_pow` and `float_to_int` helpers of the generated program. Programs
+// using complex numbers get a copy of the `Complex` struct of the executor,
+// whose operators also accept f64 operands, programs taking derivatives
+// get a copy of its `Dual` struct, and programs computing integrals,
+// solving equations or summing floats get a copy of `quadrature.rs`,
+// `roots.rs` or `summation.rs`. Lists are `Vec<f64>`, combined element-wise
+// by the `list_broadcast` helper, and statistics are computed by a copy of
+// `statistics.rs`, in a module of its own. Matrices are `Vec<Vec<f64>>` of
+// their rows, combined element-wise by `matrix_broadcast`, and multiplied,
+// inverted or solved by a copy of `linear_algebra.rs`, in a module too.
+// Polynomials are the `Polynomial` struct of a copy of `polynomial.rs`, whose
+// operators also accept f64 operands. Programs that can meet measured
+// values, because they input floats or use `+-`, get a copy of
+// `uncertain.rs` and keep an `Uncertain` twin of each float variable that
+// they can reach. The functions of ints and the gamma function come from a
+// copy of `number_theory.rs`, in a module as well, and random numbers from a
+// copy of `random.rs`, seeded first with `--seed`.
+// The copied files therefore only depend on std, and those copied outside of
+// a module, `complex.rs`, `dual.rs`, `uncertain.rs`, `quadrature.rs` and
+// `roots.rs`, write their paths in full. The generated program allows dead
+// code, since it only calls some of the functions of the copies.
 
 fn translate_to_rust_factor(variables: &SymbolTable, analyzed_factor: &AnalyzedFactor) -> String {
     match analyzed_factor {
         AnalyzedFactor::FunctionExpression(expr) => {
             "(".to_string() + &translate_to_rust_function_expr(variables, expr)
         }
-        AnalyzedFactor::Literal(value) => value.to_string() + "f64",
+        AnalyzedFactor::Literal(Value::Int(value)) => format!("{}i64", value),
+        AnalyzedFactor::Literal(Value::Complex(value)) => format!(
+            "Complex::new({}, {})",
+            translate_to_rust_literal(value.re),
+            translate_to_rust_literal(value.im)
+        ),
+        AnalyzedFactor::Literal(value) => translate_to_rust_literal(value.as_float()),
+        AnalyzedFactor::Identifier(handle)
+            if matches!(
+                variables.get_type(*handle),
+                Some(Type::List) | Some(Type::Matrix) | Some(Type::Polynomial)
+            ) =>
+        {
+            format!("{}.clone()", variables.get_name(*handle))
+        }
         AnalyzedFactor::Identifier(handle) => "".to_string() + &variables.get_name(*handle),
         AnalyzedFactor::SubExpression(expr) => {
             "(".to_string() + &translate_to_rust_expr(variables, expr) + ")"
         }
-        
+        AnalyzedFactor::Derivative(expr, handle) => {
+            format!("({}).derivative", translate_to_rust_dual_expr(variables, expr, *handle))
+        }
+        // The bound variable is the parameter of the closure.
+        AnalyzedFactor::Integral(integral) => format!(
+            "integrate(|{}: f64| Ok({}), {}, {}, {}).unwrap_or_else(|err| panic!(\"{{}}\", err))",
+            variables.get_name(integral.1),
+            translate_to_rust_expr(variables, &integral.0),
+            translate_to_rust_expr(variables, &integral.2),
+            translate_to_rust_expr(variables, &integral.3),
+            translate_to_rust_literal(variables.tolerance())
+        ),
+        AnalyzedFactor::Series(series) => translate_to_rust_series(variables, series),
+        AnalyzedFactor::Statistic(statistic) => translate_to_rust_statistic(variables, statistic),
+        AnalyzedFactor::MatrixFunction(function) => translate_to_rust_matrix_function(variables, function),
+        // The bound variable shadows any other of the same name in the block.
+        AnalyzedFactor::Polynomial(body, handle) => format!(
+            "{{ #[allow(unused_variables)] let {} = Polynomial::variable(); Polynomial::from({}) }}",
+            variables.get_name(*handle),
+            translate_to_rust_expr(variables, body)
+        ),
+        AnalyzedFactor::PolynomialFunction(function) => translate_to_rust_polynomial_function(variables, function),
+        AnalyzedFactor::NumberTheoryFunction(function) => translate_to_rust_number_theory_function(variables, function),
+        AnalyzedFactor::Random(random) => translate_to_rust_random(variables, random),
+        AnalyzedFactor::Matrix(rows) => format!(
+            "matrix_rows(vec![{}])",
+            rows.iter()
+                .map(|row| translate_to_rust_expr(variables, row))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        AnalyzedFactor::List(elements) if elements.is_empty() => "Vec::<f64>::new()".to_string(),
+        AnalyzedFactor::List(elements) => format!(
+            "vec![{}]",
+            elements
+                .iter()
+                .map(|element| translate_to_rust_expr(variables, element))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        AnalyzedFactor::Range(lo, hi) => {
+            let bound = |expr: &AnalyzedExpr| {
+                if expr_type(variables, expr) == Type::Int {
+                    format!("({}) as f64", translate_to_rust_expr(variables, expr))
+                } else {
+                    translate_to_rust_expr(variables, expr)
+                }
+            };
+            format!("list_range({}, {})", bound(lo), bound(hi))
+        }
+        AnalyzedFactor::Index(list, index) => format!(
+            "list_index(&{}, {})",
+            translate_to_rust_expr(variables, list),
+            translate_to_rust_expr(variables, index)
+        ),
+        AnalyzedFactor::Length(list) => {
+            format!("(({}).len() as i64)", translate_to_rust_expr(variables, list))
+        }
+    }
+}
+
+// A list is solved for as a column, and the solution is a list again.
+fn translate_to_rust_matrix_function(variables: &SymbolTable, function: &AnalyzedMatrixFunction) -> String {
+    let (operator, arguments) = function;
+    let argument = |index: usize| match expr_type(variables, &arguments[index]) {
+        Type::Int => format!("({}) as f64", translate_to_rust_expr(variables, &arguments[index])),
+        _ => translate_to_rust_expr(variables, &arguments[index]),
+    };
+    let call = match operator {
+        MatrixOperator::Transpose => return format!("linear_algebra::transpose(&{})", argument(0)),
+        MatrixOperator::Determinant => format!("linear_algebra::determinant(&{})", argument(0)),
+        MatrixOperator::Inverse => format!("linear_algebra::inverse(&{})", argument(0)),
+        MatrixOperator::Solve if expr_type(variables, &arguments[1]) == Type::List => {
+            return format!(
+                "linear_algebra::solve(&{}, &linear_algebra::transpose(&[{}])).unwrap_or_else(|err| panic!(\"{{}}\", err)).concat()",
+                argument(0),
+                argument(1)
+            )
+        }
+        MatrixOperator::Solve => format!("linear_algebra::solve(&{}, &{})", argument(0), argument(1)),
+        MatrixOperator::Identity => format!("linear_algebra::identity({})", argument(0)),
+        MatrixOperator::Zeros => format!("linear_algebra::zeros({}, {})", argument(0), argument(1)),
+    };
+    format!("{}.unwrap_or_else(|err| panic!(\"{{}}\", err))", call)
+}
+
+fn translate_to_rust_polynomial_function(variables: &SymbolTable, function: &AnalyzedPolynomialFunction) -> String {
+    let (operator, arguments) = function;
+    if *operator == PolynomialOperator::Coefficients {
+        return format!("Polynomial::from_highest(&{})", translate_to_rust_values(variables, arguments));
+    }
+    let polynomial = translate_to_rust_expr(variables, &arguments[0]);
+    match operator {
+        PolynomialOperator::Evaluate if expr_type(variables, &arguments[1]) == Type::List => format!(
+            "({}).eval_each({})",
+            polynomial,
+            translate_to_rust_expr(variables, &arguments[1])
+        ),
+        PolynomialOperator::Evaluate => {
+            format!("({}).eval({})", polynomial, translate_to_rust_expr(variables, &arguments[1]))
+        }
+        PolynomialOperator::Roots => format!("({}).roots().unwrap_or_else(|err| panic!(\"{{}}\", err))", polynomial),
+        PolynomialOperator::Derivative => format!("({}).derivative()", polynomial),
+        PolynomialOperator::Coefficients => unreachable!("the coefficients are translated above"),
+    }
+}
+
+fn translate_to_rust_number_theory_function(variables: &SymbolTable, function: &AnalyzedNumberTheoryFunction) -> String {
+    let (operator, arguments) = function;
+    let float_function = matches!(operator, NumberTheoryOperator::Gamma | NumberTheoryOperator::LogGamma);
+    let arguments = arguments
+        .iter()
+        .map(|argument| match expr_type(variables, argument) {
+            Type::Float if !float_function => format!(
+                "number_theory::whole_number(\"{}\", {}).unwrap_or_else(|err| panic!(\"{{}}\", err))",
+                number_theory_name(*operator),
+                translate_to_rust_expr(variables, argument)
+            ),
+            _ => translate_to_rust_expr(variables, argument),
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    let call = match operator {
+        NumberTheoryOperator::IsPrime => return format!("(number_theory::is_prime({}) as i64)", arguments),
+        NumberTheoryOperator::Factorial => "factorial",
+        NumberTheoryOperator::Gamma => "gamma",
+        NumberTheoryOperator::LogGamma => "ln_gamma",
+        NumberTheoryOperator::Binomial => "binomial",
+        NumberTheoryOperator::Permutations => "permutations",
+        NumberTheoryOperator::NextPrime => "next_prime",
+        NumberTheoryOperator::Factor => "factor",
+        NumberTheoryOperator::ModPow => "mod_pow",
+        NumberTheoryOperator::ModInverse => "mod_inverse",
+    };
+    let result = format!("number_theory::{}({}).unwrap_or_else(|err| panic!(\"{{}}\", err))", call, arguments);
+    match operator {
+        NumberTheoryOperator::Factor => {
+            format!("{}.into_iter().map(|factor| factor as f64).collect::<Vec<f64>>()", result)
+        }
+        _ => result,
+    }
+}
+
+fn translate_to_rust_random(variables: &SymbolTable, random: &AnalyzedRandom) -> String {
+    let (operator, arguments) = random;
+    let arguments = arguments
+        .iter()
+        .map(|argument| translate_to_rust_expr(variables, argument))
+        .collect::<Vec<_>>()
+        .join(", ");
+    match operator {
+        RandomOperator::Rand => "random::rand()".to_string(),
+        RandomOperator::Uniform => format!("random::uniform({})", arguments),
+        operator => format!(
+            "random::{}({}).unwrap_or_else(|err| panic!(\"{{}}\", err))",
+            match operator {
+                RandomOperator::RandInt => "randint",
+                RandomOperator::Normal => "normal",
+                _ => "exponential",
+            },
+            arguments
+        ),
+    }
+}
+
+// The values of the arguments of a statistic as a `Vec<f64>`, with lists
+// concatenated.
+fn translate_to_rust_values(variables: &SymbolTable, arguments: &[AnalyzedExpr]) -> String {
+    let parts: Vec<String> = arguments
+        .iter()
+        .map(|argument| match expr_type(variables, argument) {
+            Type::List => translate_to_rust_expr(variables, argument),
+            _ => format!("vec![{}]", translate_to_rust_expr(variables, argument)),
+        })
+        .collect();
+    match parts.as_slice() {
+        [part] => part.clone(),
+        _ => format!("[{}].concat()", parts.join(", ")),
+    }
+}
+
+fn translate_to_rust_statistic(variables: &SymbolTable, statistic: &AnalyzedStatistic) -> String {
+    let (operator, arguments) = statistic;
+    let values = |arguments: &[AnalyzedExpr]| translate_to_rust_values(variables, arguments);
+    let call = match operator {
+        StatisticOperator::Count => return format!("({}.len() as i64)", values(arguments)),
+        StatisticOperator::Sum => return format!("statistics::sum(&{})", values(arguments)),
+        StatisticOperator::Covariance | StatisticOperator::PopulationCovariance => format!(
+            "statistics::covariance(&{}, &{}, {})",
+            values(&arguments[..1]),
+            values(&arguments[1..]),
+            *operator == StatisticOperator::Covariance
+        ),
+        StatisticOperator::Correlation => format!(
+            "statistics::correlation(&{}, &{})",
+            values(&arguments[..1]),
+            values(&arguments[1..])
+        ),
+        StatisticOperator::Percentile => {
+            let (percent, data) = arguments.split_last().unwrap();
+            let percent = match expr_type(variables, percent) {
+                Type::Int => format!("({}) as f64", translate_to_rust_expr(variables, percent)),
+                _ => translate_to_rust_expr(variables, percent),
+            };
+            format!("statistics::percentile(&{}, {})", values(data), percent)
+        }
+        StatisticOperator::Median => format!("statistics::percentile(&{}, 50.)", values(arguments)),
+        StatisticOperator::Mean => format!("statistics::mean(&{})", values(arguments)),
+        StatisticOperator::StandardDeviation | StatisticOperator::PopulationStandardDeviation => format!(
+            "statistics::variance(&{}, {}).map(f64::sqrt)",
+            values(arguments),
+            *operator == StatisticOperator::StandardDeviation
+        ),
+        StatisticOperator::Variance | StatisticOperator::PopulationVariance => format!(
+            "statistics::variance(&{}, {})",
+            values(arguments),
+            *operator == StatisticOperator::Variance
+        ),
+        StatisticOperator::Min => format!("statistics::min(&{})", values(arguments)),
+        StatisticOperator::Max => format!("statistics::max(&{})", values(arguments)),
+    };
+    format!("{}.unwrap_or_else(|err| panic!(\"{{}}\", err))", call)
+}
+
+// The terms are mapped from the index range, then folded like the executor
+// does: with checked operations for ints, and with compensated summation for
+// floats.
+fn translate_to_rust_series(variables: &SymbolTable, series: &AnalyzedSeries) -> String {
+    let (operator, handle, lo, hi, body) = series;
+    let bound = |expr: &AnalyzedExpr, round: &str| {
+        if expr_type(variables, expr) == Type::Int {
+            translate_to_rust_expr(variables, expr)
+        } else {
+            format!("float_to_int(({}).{}())", translate_to_rust_expr(variables, expr), round)
+        }
+    };
+    let name = variables.get_name(*handle);
+    let index = if variables.get_type(*handle) == Some(Type::Int) {
+        name.clone()
+    } else {
+        format!("{} as f64", name)
+    };
+    let terms = format!(
+        "series_range(\"{}\", {}, {}).map(|{}: i64| {{ let {} = {}; {} }})",
+        series_name(*operator),
+        bound(lo, "ceil"),
+        bound(hi, "floor"),
+        name,
+        name,
+        index,
+        translate_to_rust_expr(variables, body)
+    );
+    match (operator, expr_type(variables, body)) {
+        (SeriesOperator::Sum, Type::Int) => {
+            format!("{}.fold(0i64, |total, term| checked(total.checked_add(term)))", terms)
+        }
+        (SeriesOperator::Product, Type::Int) => {
+            format!("{}.fold(1i64, |total, factor| checked(total.checked_mul(factor)))", terms)
+        }
+        (SeriesOperator::Sum, Type::Float) => {
+            format!("{}.fold(Neumaier::default(), Neumaier::add).total()", terms)
+        }
+        (SeriesOperator::Product, Type::Float) => format!("{}.product::<f64>()", terms),
+        (SeriesOperator::Sum, Type::Complex) => {
+            format!("{}.fold(Complex::new(0., 0.), |total, term| total + term)", terms)
+        }
+        (SeriesOperator::Product, Type::Complex) => {
+            format!("{}.fold(Complex::new(1., 0.), |total, factor| total * factor)", terms)
+        }
+        (_, Type::List) | (_, Type::Matrix) | (_, Type::Polynomial) => {
+            unreachable!("the body of a series is not a list or a polynomial")
+        }
+    }
+}
+
+fn translate_to_rust_literal(value: f64) -> String {
+    if value.is_nan() {
+        "f64::NAN".to_string()
+    } else if value.is_infinite() {
+        if value > 0. { "f64::INFINITY".to_string() } else { "f64::NEG_INFINITY".to_string() }
+    } else {
+        value.to_string() + "f64"
     }
 }
 
 fn translate_to_rust_term(variables: &SymbolTable, analyzed_term: &AnalyzedTerm) -> String {
     let mut result = translate_to_rust_factor(variables, &analyzed_term.0);
+    if term_type(variables, analyzed_term) == Type::Int {
+        for factor in &analyzed_term.1 {
+            let operand = translate_to_rust_factor(variables, &factor.1);
+            result = match factor.0 {
+                TermOperator::Multiply => format!("checked(({}).checked_mul({}))", result, operand),
+                TermOperator::IntegerDivide => {
+                    format!("checked(({}).checked_div_euclid({}))", result, operand)
+                }
+                TermOperator::Exponent => format!("int_pow({}, {})", result, operand),
+                TermOperator::Divide => unreachable!("`/` always gives a float"),
+                TermOperator::MatrixMultiply => unreachable!("`@` never gives an int"),
+            };
+        }
+        return result;
+    }
+    if matches!(term_type(variables, analyzed_term), Type::List | Type::Matrix) && !analyzed_term.1.is_empty()
+        || analyzed_term.1.iter().any(|factor| factor.0 == TermOperator::MatrixMultiply)
+    {
+        return translate_to_rust_list_term(variables, analyzed_term);
+    }
+    // Whether the part of the term on the left of the operator is complex or
+    // a polynomial.
+    let mut complex = factor_type(variables, &analyzed_term.0) == Type::Complex;
+    let mut polynomial = factor_type(variables, &analyzed_term.0) == Type::Polynomial;
     for factor in &analyzed_term.1 {
+        let operand_complex = factor_type(variables, &factor.1) == Type::Complex;
+        let operand_polynomial = factor_type(variables, &factor.1) == Type::Polynomial;
         match factor.0 {
             TermOperator::Multiply => {
                 result += " * ";
@@ -30,20 +384,173 @@ fn translate_to_rust_term(variables: &SymbolTable, analyzed_term: &AnalyzedTerm)
                 result += " / ";
                 result += &translate_to_rust_factor(variables, &factor.1);
             }
+            TermOperator::Exponent if complex || operand_complex => {
+                result = format!(
+                    "Complex::from({}).pow(Complex::from({}))",
+                    result,
+                    translate_to_rust_factor(variables, &factor.1)
+                );
+            }
+            TermOperator::Exponent if polynomial => {
+                result = format!(
+                    "({}).pow({}).unwrap_or_else(|err| panic!(\"{{}}\", err))",
+                    result,
+                    translate_to_rust_factor(variables, &factor.1)
+                );
+            }
             TermOperator::Exponent => {
-                result += ".pow";
-                result += &translate_to_rust_factor(variables, &factor.1);
-                result += ""
-
+                // `^` has the same precedence as `*` in MathLang, so it applies
+                // to everything on its left.
+                result = format!(
+                    "({}).powf({})",
+                    result,
+                    translate_to_rust_factor(variables, &factor.1)
+                );
             }
+            TermOperator::IntegerDivide if polynomial || operand_polynomial => {
+                result = format!(
+                    "Polynomial::from({}).div_rem(&Polynomial::from({})).unwrap_or_else(|err| panic!(\"{{}}\", err)).0",
+                    result,
+                    translate_to_rust_factor(variables, &factor.1)
+                );
+            }
+            TermOperator::IntegerDivide => {
+                result = format!(
+                    "({}).div_euclid({})",
+                    result,
+                    translate_to_rust_factor(variables, &factor.1)
+                );
+            }
+            TermOperator::MatrixMultiply => unreachable!("`@` is translated by translate_to_rust_list_term"),
         }
+        complex |= operand_complex;
+        polynomial |= operand_polynomial;
+    }
+    result
+}
+
+// The helper applying an element-wise operation to operands of these types,
+// and the type of the result.
+fn broadcast_helper(left: Type, right: Type) -> (&'static str, Type) {
+    match (left, right) {
+        (Type::Matrix, _) | (_, Type::Matrix) => ("matrix_broadcast", Type::Matrix),
+        (Type::List, _) | (_, Type::List) => ("list_broadcast", Type::List),
+        _ => ("scalar_apply", Type::Float),
+    }
+}
+
+// `@` takes a list on its left as a row, and on its right as a column.
+fn translate_to_rust_matrix_product(left: String, left_type: Type, right: String, right_type: Type) -> (String, Type) {
+    match (left_type, right_type) {
+        (Type::List, Type::List) => (format!("list_dot(&{}, &{})", left, right), Type::Float),
+        (Type::List, _) => (
+            format!(
+                "linear_algebra::multiply(&[{}], &{}).unwrap_or_else(|err| panic!(\"{{}}\", err)).concat()",
+                left, right
+            ),
+            Type::List,
+        ),
+        (_, Type::List) => (
+            format!(
+                "linear_algebra::multiply(&{}, &linear_algebra::transpose(&[{}])).unwrap_or_else(|err| panic!(\"{{}}\", err)).concat()",
+                left, right
+            ),
+            Type::List,
+        ),
+        _ => (
+            format!(
+                "linear_algebra::multiply(&{}, &{}).unwrap_or_else(|err| panic!(\"{{}}\", err))",
+                left, right
+            ),
+            Type::Matrix,
+        ),
+    }
+}
+
+// The factors before the first list or matrix are computed as a float term,
+// and the operations from the first list or matrix on are applied to each
+// element, except `@`.
+fn translate_to_rust_list_term(variables: &SymbolTable, analyzed_term: &AnalyzedTerm) -> String {
+    let first_list = std::iter::once(&analyzed_term.0)
+        .chain(analyzed_term.1.iter().map(|factor| &factor.1))
+        .position(|factor| matches!(factor_type(variables, factor), Type::List | Type::Matrix))
+        .unwrap_or(0);
+    let split = first_list.max(1) - 1;
+    let first_factors = (analyzed_term.0.clone(), analyzed_term.1[..split].to_vec());
+    let mut result = translate_to_rust_term(variables, &first_factors);
+    let mut result_type = term_type(variables, &first_factors);
+    for factor in &analyzed_term.1[split..] {
+        let operand = translate_to_rust_factor(variables, &factor.1);
+        let operand_type = factor_type(variables, &factor.1);
+        let operation = match factor.0 {
+            TermOperator::Multiply => "left * right",
+            TermOperator::Divide => "left / right",
+            TermOperator::Exponent => "left.powf(right)",
+            TermOperator::IntegerDivide => "left.div_euclid(right)",
+            TermOperator::MatrixMultiply => {
+                let product = translate_to_rust_matrix_product(result, result_type, operand, operand_type);
+                result = product.0;
+                result_type = product.1;
+                continue;
+            }
+        };
+        let (helper, helper_type) = broadcast_helper(result_type, operand_type);
+        result = format!("{}({}, {}, |left: f64, right: f64| {})", helper, result, operand, operation);
+        result_type = helper_type;
+    }
+    result
+}
+
+fn translate_to_rust_list_expr(variables: &SymbolTable, analyzed_expr: &AnalyzedExpr) -> String {
+    let first_list = std::iter::once(&analyzed_expr.0)
+        .chain(analyzed_expr.1.iter().map(|term| &term.1))
+        .position(|term| matches!(term_type(variables, term), Type::List | Type::Matrix))
+        .unwrap_or(0);
+    let split = first_list.max(1) - 1;
+    let first_terms = (analyzed_expr.0.clone(), analyzed_expr.1[..split].to_vec());
+    let mut result = translate_to_rust_expr(variables, &first_terms);
+    let mut result_type = expr_type(variables, &first_terms);
+    for term in &analyzed_expr.1[split..] {
+        let operation = match term.0 {
+            ExprOperator::Add => "left + right",
+            ExprOperator::Subtract => "left - right",
+            ExprOperator::Modulo => "left.rem_euclid(right)",
+            ExprOperator::PlusMinus => "{ let _ = right; left }",
+        };
+        let (helper, helper_type) = broadcast_helper(result_type, term_type(variables, &term.1));
+        result = format!(
+            "{}({}, {}, |left: f64, right: f64| {})",
+            helper,
+            result,
+            translate_to_rust_term(variables, &term.1),
+            operation
+        );
+        result_type = helper_type;
     }
     result
 }
 
 fn translate_to_rust_expr(variables: &SymbolTable, analyzed_expr: &AnalyzedExpr) -> String {
+    if matches!(expr_type(variables, analyzed_expr), Type::List | Type::Matrix) && !analyzed_expr.1.is_empty() {
+        return translate_to_rust_list_expr(variables, analyzed_expr);
+    }
     let mut result = translate_to_rust_term(variables, &analyzed_expr.0);
+    if expr_type(variables, analyzed_expr) == Type::Int {
+        for term in &analyzed_expr.1 {
+            let operand = translate_to_rust_term(variables, &term.1);
+            let method = match term.0 {
+                ExprOperator::Add => "checked_add",
+                ExprOperator::Subtract => "checked_sub",
+                ExprOperator::Modulo => "checked_rem_euclid",
+                ExprOperator::PlusMinus => unreachable!("`+-` always gives a float"),
+            };
+            result = format!("checked(({}).{}({}))", result, method, operand);
+        }
+        return result;
+    }
+    let mut polynomial = term_type(variables, &analyzed_expr.0) == Type::Polynomial;
     for term in &analyzed_expr.1 {
+        let operand_polynomial = term_type(variables, &term.1) == Type::Polynomial;
         match term.0 {
             ExprOperator::Add => {
                 result += " + ";
@@ -53,96 +560,984 @@ fn translate_to_rust_expr(variables: &SymbolTable, analyzed_expr: &AnalyzedExpr)
                 result += " - ";
                 result += &translate_to_rust_term(variables, &term.1);
             }
+            ExprOperator::Modulo if polynomial || operand_polynomial => {
+                result = format!(
+                    "Polynomial::from({}).div_rem(&Polynomial::from({})).unwrap_or_else(|err| panic!(\"{{}}\", err)).1",
+                    result,
+                    translate_to_rust_term(variables, &term.1)
+                );
+            }
             ExprOperator::Modulo => {
-                result += " % ";
-                result += &translate_to_rust_term(variables, &term.1);
+                // `mod` has the precedence of `+` in MathLang.
+                result = format!(
+                    "({}).rem_euclid({})",
+                    result,
+                    translate_to_rust_term(variables, &term.1)
+                );
             }
+            // Compiled programs only compute nominal values.
+            ExprOperator::PlusMinus => {}
         }
+        polynomial |= operand_polynomial;
     }
     result
 }
 
 fn translate_to_rust_function_expr(variables: &SymbolTable, analyzed_expr: &AnalyzedFunctionExpr) -> String {
-    let mut result = translate_to_rust_expr(variables, &analyzed_expr.1);
-    match &analyzed_expr.0 {
-        FunctionOperator::Sin => {result += ").sin()";}
-        FunctionOperator::Cos => {result += ").cos()";}
-        FunctionOperator::Tan => {result += ").tan()";}
-        FunctionOperator::ArcSin => {result += ").asin()";}
-        FunctionOperator::ArcCos => {result += ").acos()";}
-        FunctionOperator::ArcTan => {result += ").atan()";}
-        FunctionOperator::Sinh => {result += ").sinh()";}
-        FunctionOperator::Cosh => {result += ").cosh()";}
-        FunctionOperator::Tanh => {result += ").tanh()";}
-        FunctionOperator::ArcSinh => {result += ").asinh()";}
-        FunctionOperator::ArcCosh => {result += ").acosh()";}
-        FunctionOperator::ArcTanh => {result += ").atanh()";}
-        FunctionOperator::Exp => {result += ").exp()";}
-        FunctionOperator::Exp2 => {result += ").exp2()";}
-        FunctionOperator::Log => {result += ").ln()";}
-        FunctionOperator::Log10 => {result += ").log10()";}
-        FunctionOperator::Log2 => {result += ").log2()";}
-        FunctionOperator::Abs => {result += ").abs()";}
-        FunctionOperator::Ceil => {result += ").ceil()";}
-        FunctionOperator::Floor => {result += ").floor()";}
-        FunctionOperator::Signum => {result += ").signum()";}
-        FunctionOperator::Sqrt => {result += ").sqrt()";}
+    let result = translate_to_rust_expr(variables, &analyzed_expr.1);
+    let argument_type = expr_type(variables, &analyzed_expr.1);
+    match (&analyzed_expr.0, argument_type) {
+        (FunctionOperator::Abs, Type::Int) => return format!("checked(({}).checked_abs()))", result),
+        (FunctionOperator::ToInt, Type::Float) => return format!("float_to_int({}))", result),
+        (FunctionOperator::ToFloat, Type::Int) => return result + ") as f64",
+        (FunctionOperator::ToInt, _) | (FunctionOperator::ToFloat, _) => return result + ")",
+        (FunctionOperator::Re, Type::Float) | (FunctionOperator::Conj, Type::Float) => {
+            return result + ")"
+        }
+        (FunctionOperator::Im, Type::Float) => return format!("{{ let _ = {}; 0f64 }})", result),
+        (FunctionOperator::Arg, Type::Float) => return format!("0f64.atan2({}))", result),
+        _ => {}
+    }
+    if argument_type == Type::List {
+        let function = match analyzed_expr.0 {
+            FunctionOperator::Re | FunctionOperator::Conj => "|element: f64| element".to_string(),
+            FunctionOperator::Im => "|_: f64| 0f64".to_string(),
+            FunctionOperator::Arg => "|element: f64| 0f64.atan2(element)".to_string(),
+            function => format!("|element: f64| element.{}()", method_name(function)),
+        };
+        return format!("{}).into_iter().map({}).collect::<Vec<f64>>()", result, function);
+    }
+    if argument_type == Type::Matrix {
+        let function = match analyzed_expr.0 {
+            FunctionOperator::Re | FunctionOperator::Conj => "|element: f64| element".to_string(),
+            FunctionOperator::Im => "|_: f64| 0f64".to_string(),
+            FunctionOperator::Arg => "|element: f64| 0f64.atan2(element)".to_string(),
+            function => format!("|element: f64| element.{}()", method_name(function)),
+        };
+        return format!(
+            "{}).into_iter().map(|row| row.into_iter().map({}).collect::<Vec<f64>>()).collect::<Vec<Vec<f64>>>()",
+            result, function
+        );
+    }
+    format!("{}).{}()", result, method_name(analyzed_expr.0))
+}
+
+// The method of f64, `Complex` and `Dual` computing the function.
+fn method_name(function: FunctionOperator) -> &'static str {
+    match function {
+        FunctionOperator::Sin => "sin",
+        FunctionOperator::Cos => "cos",
+        FunctionOperator::Tan => "tan",
+        FunctionOperator::ArcSin => "asin",
+        FunctionOperator::ArcCos => "acos",
+        FunctionOperator::ArcTan => "atan",
+        FunctionOperator::Sinh => "sinh",
+        FunctionOperator::Cosh => "cosh",
+        FunctionOperator::Tanh => "tanh",
+        FunctionOperator::ArcSinh => "asinh",
+        FunctionOperator::ArcCosh => "acosh",
+        FunctionOperator::ArcTanh => "atanh",
+        FunctionOperator::Exp => "exp",
+        FunctionOperator::Exp2 => "exp2",
+        FunctionOperator::Log => "ln",
+        FunctionOperator::Log10 => "log10",
+        FunctionOperator::Log2 => "log2",
+        FunctionOperator::Abs => "abs",
+        FunctionOperator::Ceil => "ceil",
+        FunctionOperator::Floor => "floor",
+        FunctionOperator::Signum => "signum",
+        FunctionOperator::Sqrt => "sqrt",
+        FunctionOperator::Re => "re",
+        FunctionOperator::Im => "im",
+        FunctionOperator::Conj => "conj",
+        FunctionOperator::Arg => "arg",
+        FunctionOperator::ToInt | FunctionOperator::ToFloat => unreachable!("conversions are not methods"),
+    }
+}
+
+// A derivative is computed by evaluating the expression with `Dual` numbers,
+// where only the variable of differentiation has a derivative. The parts of
+// type int are evaluated as usual and converted.
+fn translate_to_rust_dual_factor(variables: &SymbolTable, factor: &AnalyzedFactor, variable: usize) -> String {
+    if factor_type(variables, factor) == Type::Int {
+        return format!("Dual::constant({} as f64)", translate_to_rust_factor(variables, factor));
+    }
+    match factor {
+        AnalyzedFactor::Literal(value) => {
+            format!("Dual::constant({})", translate_to_rust_literal(value.as_float()))
+        }
+        AnalyzedFactor::Identifier(handle) if *handle == variable => {
+            format!("Dual::variable({})", variables.get_name(*handle))
+        }
+        AnalyzedFactor::Identifier(handle) => format!("Dual::constant({})", variables.get_name(*handle)),
+        AnalyzedFactor::FunctionExpression(function_expr) => {
+            let argument = translate_to_rust_dual_expr(variables, &function_expr.1, variable);
+            match function_expr.0 {
+                FunctionOperator::ToFloat | FunctionOperator::Re | FunctionOperator::Conj => {
+                    format!("({})", argument)
+                }
+                FunctionOperator::Im => "Dual::constant(0.)".to_string(),
+                function => format!("({}).{}()", argument, method_name(function)),
+            }
+        }
+        AnalyzedFactor::SubExpression(expr) => {
+            format!("({})", translate_to_rust_dual_expr(variables, expr, variable))
+        }
+        AnalyzedFactor::Derivative(..) => unreachable!("derivatives are not nested"),
+        AnalyzedFactor::Integral(_) => unreachable!("integrals are not differentiated"),
+        AnalyzedFactor::Series(_) => unreachable!("sums and products are not differentiated"),
+        AnalyzedFactor::Statistic(_) => unreachable!("statistics are not differentiated"),
+        AnalyzedFactor::MatrixFunction(_) | AnalyzedFactor::Matrix(_) => {
+            unreachable!("matrices are not differentiated")
+        }
+        AnalyzedFactor::Polynomial(..) | AnalyzedFactor::PolynomialFunction(_) => {
+            unreachable!("polynomials are not differentiated")
+        }
+        AnalyzedFactor::NumberTheoryFunction(_) => unreachable!("only the functions of ints are differentiated"),
+        AnalyzedFactor::Random(_) => unreachable!("only random ints are differentiated"),
+        AnalyzedFactor::List(_)
+        | AnalyzedFactor::Range(..)
+        | AnalyzedFactor::Index(..)
+        | AnalyzedFactor::Length(_) => unreachable!("lists are not differentiated"),
+    }
+}
+
+fn translate_to_rust_dual_term(variables: &SymbolTable, term: &AnalyzedTerm, variable: usize) -> String {
+    if term_type(variables, term) == Type::Int {
+        return format!("Dual::constant(({}) as f64)", translate_to_rust_term(variables, term));
+    }
+    let mut result = translate_to_rust_dual_factor(variables, &term.0, variable);
+    for factor in &term.1 {
+        let operand = translate_to_rust_dual_factor(variables, &factor.1, variable);
+        result = match factor.0 {
+            TermOperator::Multiply => format!("{} * {}", result, operand),
+            TermOperator::Divide => format!("{} / {}", result, operand),
+            TermOperator::Exponent => format!("({}).powf({})", result, operand),
+            TermOperator::IntegerDivide => format!("({}).div_euclid({})", result, operand),
+            TermOperator::MatrixMultiply => unreachable!("matrices are not differentiated"),
+        };
+    }
+    result
+}
+
+fn translate_to_rust_dual_expr(variables: &SymbolTable, expr: &AnalyzedExpr, variable: usize) -> String {
+    if expr_type(variables, expr) == Type::Int {
+        return format!("Dual::constant(({}) as f64)", translate_to_rust_expr(variables, expr));
+    }
+    let mut result = translate_to_rust_dual_term(variables, &expr.0, variable);
+    for term in &expr.1 {
+        let operand = translate_to_rust_dual_term(variables, &term.1, variable);
+        result = match term.0 {
+            ExprOperator::Add => format!("{} + {}", result, operand),
+            ExprOperator::Subtract => format!("{} - {}", result, operand),
+            ExprOperator::Modulo => format!("({}).rem_euclid({})", result, operand),
+            ExprOperator::PlusMinus => result,
+        };
+    }
+    result
+}
+
+// Where measured values can reach, floats are computed as `Uncertain`
+// values too, from the `_uncertain` twins of the measured variables, the way
+// the executor computes them. The other variables, parts of other types, and
+// the factors that the executor computes from nominal values, are evaluated
+// as usual and taken as exact.
+fn translate_to_rust_uncertain_factor(variables: &SymbolTable, measured: &[bool], factor: &AnalyzedFactor) -> String {
+    if factor_type(variables, factor) != Type::Float {
+        return format!("Uncertain::exact(({}) as f64)", translate_to_rust_factor(variables, factor));
+    }
+    match factor {
+        AnalyzedFactor::Literal(value) => {
+            format!("Uncertain::exact({})", translate_to_rust_literal(value.as_float()))
+        }
+        AnalyzedFactor::Identifier(handle) if measured[*handle] => {
+            format!("{}_uncertain.clone()", variables.get_name(*handle))
+        }
+        AnalyzedFactor::Identifier(handle) => format!("Uncertain::exact({})", variables.get_name(*handle)),
+        AnalyzedFactor::FunctionExpression(function_expr) if expr_type(variables, &function_expr.1) == Type::Float => {
+            let argument = translate_to_rust_uncertain_expr(variables, measured, &function_expr.1);
+            match function_expr.0 {
+                FunctionOperator::ToFloat | FunctionOperator::Re | FunctionOperator::Conj => {
+                    format!("({})", argument)
+                }
+                FunctionOperator::Im => format!("({}).map(0., 0.)", argument),
+                function => format!("uncertain_apply({}, Dual::{})", argument, method_name(function)),
+            }
+        }
+        AnalyzedFactor::SubExpression(expr) => {
+            format!("({})", translate_to_rust_uncertain_expr(variables, measured, expr))
+        }
+        factor => format!("Uncertain::exact({})", translate_to_rust_factor(variables, factor)),
+    }
+}
+
+fn is_scalar(value_type: Type) -> bool {
+    matches!(value_type, Type::Int | Type::Float)
+}
+
+fn translate_to_rust_uncertain_term(variables: &SymbolTable, measured: &[bool], term: &AnalyzedTerm) -> String {
+    if term_type(variables, term) != Type::Float
+        || !std::iter::once(&term.0)
+            .chain(term.1.iter().map(|factor| &factor.1))
+            .all(|factor| is_scalar(factor_type(variables, factor)))
+    {
+        return format!("Uncertain::exact(({}) as f64)", translate_to_rust_term(variables, term));
+    }
+    let mut result = translate_to_rust_uncertain_factor(variables, measured, &term.0);
+    for factor in &term.1 {
+        let operand = translate_to_rust_uncertain_factor(variables, measured, &factor.1);
+        result = match factor.0 {
+            TermOperator::Multiply => format!("{} * {}", result, operand),
+            TermOperator::Divide => format!("{} / {}", result, operand),
+            TermOperator::Exponent => format!("({}).powf({})", result, operand),
+            TermOperator::IntegerDivide => format!("({}).div_euclid({})", result, operand),
+            TermOperator::MatrixMultiply => unreachable!("`@` only applies to lists and matrices"),
+        };
     }
     result
 }
 
+fn translate_to_rust_uncertain_expr(variables: &SymbolTable, measured: &[bool], expr: &AnalyzedExpr) -> String {
+    if expr_type(variables, expr) != Type::Float
+        || !std::iter::once(&expr.0)
+            .chain(expr.1.iter().map(|term| &term.1))
+            .all(|term| is_scalar(term_type(variables, term)))
+    {
+        return format!("Uncertain::exact(({}) as f64)", translate_to_rust_expr(variables, expr));
+    }
+    let mut result = translate_to_rust_uncertain_term(variables, measured, &expr.0);
+    for term in &expr.1 {
+        let operand = translate_to_rust_uncertain_term(variables, measured, &term.1);
+        result = match term.0 {
+            ExprOperator::Add => format!("{} + {}", result, operand),
+            ExprOperator::Subtract => format!("{} - {}", result, operand),
+            ExprOperator::Modulo => format!("({}).rem_euclid({})", result, operand),
+            ExprOperator::PlusMinus => format!("({}).plus_minus({})", result, operand),
+        };
+    }
+    result
+}
+
+// Units only exist at compile time: the value is converted and the name of
+// the unit becomes part of the format string.
+fn translate_to_rust_output(
+    variables: &SymbolTable,
+    expr: &AnalyzedExpr,
+    unit: Option<&Unit>,
+    format: Option<Format>,
+    label: Option<&str>,
+    measured_variables: &[bool],
+) -> String {
+    let mut value_type = expr_type(variables, expr);
+    let measured = value_type == Type::Float && is_measured(measured_variables, expr);
+    let mut value = if measured {
+        translate_to_rust_uncertain_expr(variables, measured_variables, expr)
+    } else {
+        translate_to_rust_expr(variables, expr)
+    };
+    if let Some(unit) = unit.filter(|unit| unit.scale != 1.) {
+        let scale = translate_to_rust_literal(unit.scale);
+        value = match value_type {
+            _ if measured => format!("({}) / Uncertain::exact({})", value, scale),
+            Type::List => format!("list_broadcast({}, {}, |left: f64, right: f64| left / right)", value, scale),
+            Type::Matrix => format!("matrix_broadcast({}, {}, |left: f64, right: f64| left / right)", value, scale),
+            Type::Int => {
+                value_type = Type::Float;
+                format!("({}) as f64 / {}", value, scale)
+            }
+            _ => format!("({}) / {}", value, scale),
+        };
+    }
+    // The formats are written as their `Debug` forms, which are Rust.
+    let value = match (value_type, format) {
+        (Type::List, None) => format!("list_format(&{}, |value| value.to_string())", value),
+        (Type::List, Some(format)) => format!(
+            "list_format(&{}, |value| number_format::float(value, number_format::Format::{:?}))",
+            value, format
+        ),
+        (Type::Matrix, None) => format!("linear_algebra::format(&{})", value),
+        (Type::Matrix, Some(format)) => format!(
+            "matrix_format(&{}, |value| number_format::float(value, number_format::Format::{:?}))",
+            value, format
+        ),
+        (Type::Int, Some(format)) => format!("number_format::int({}, number_format::Format::{:?})", value, format),
+        (Type::Complex, Some(format)) => format!(
+            "{{ let value = {}; number_format::complex(value.re, value.im, number_format::Format::{:?}) }}",
+            value, format
+        ),
+        (_, Some(format)) if measured => format!(
+            "{{ let value = {}; let format = number_format::Format::{:?}; if value.measured {{ format!(\"{{}} +- {{}}\", number_format::float(value.value, format), number_format::float(value.uncertainty(), format)) }} else {{ number_format::float(value.value, format) }} }}",
+            value, format
+        ),
+        (_, Some(format)) => format!("number_format::float({}, number_format::Format::{:?})", value, format),
+        (_, None) => value,
+    };
+    // Matrices start on a line of their own, so that their rows are aligned.
+    let separator = if value_type == Type::Matrix { "\\n" } else { " " };
+    let unit = unit.map_or(String::new(), |unit| format!(" {}", unit.name));
+    match label {
+        Some(label) => format!(
+            "println!(\"<output>: {{}} ={}{{}}{}\", {:?}, {})",
+            separator, unit, label, value
+        ),
+        None => format!("println!(\"<output>:{}{{}}{}\", {})", separator, unit, value),
+    }
+}
+
+fn is_float_variable(variables: &SymbolTable, handle: usize) -> bool {
+    variables.get_type(handle).unwrap_or(Type::Float) == Type::Float
+}
+
+// The twin of a measured variable is assigned first, and the variable takes
+// its nominal value.
 fn translate_to_rust_statement(
     variables: &SymbolTable,
     analyzed_statement: &AnalyzedStatement,
+    measured: &[bool],
 ) -> String {
+    let twin = |handle: usize| measured[handle];
     match analyzed_statement {
-        
+        AnalyzedStatement::DeclarationToAssignment(handle, expr) if twin(*handle) => {
+            let name = variables.get_name(*handle);
+            format!(
+                "let mut {}_uncertain = {};\n    let mut {} = {}_uncertain.value",
+                name,
+                translate_to_rust_uncertain_expr(variables, measured, expr),
+                name,
+                name
+            )
+        }
         AnalyzedStatement::DeclarationToAssignment(handle, expr) => {
             format!("let mut {} = {}", 
             variables.get_name(*handle), 
-            translate_to_rust_expr(&variables, expr))
+            translate_to_rust_expr(variables, expr))
+        }
+        AnalyzedStatement::Assignment(handle, expr) if twin(*handle) => {
+            let name = variables.get_name(*handle);
+            format!(
+                "{}_uncertain = {};\n    {} = {}_uncertain.value",
+                name,
+                translate_to_rust_uncertain_expr(variables, measured, expr),
+                name,
+                name
+            )
         }
         AnalyzedStatement::Assignment(handle, expr) => format!(
             "{} = {}",
             variables.get_name(*handle),
-            translate_to_rust_expr(&variables, expr)
+            translate_to_rust_expr(variables, expr)
         ),
-        AnalyzedStatement::Declaration(handle) => {
-            format!("let mut {} = 0.0", variables.get_name(*handle))
+        AnalyzedStatement::Declaration(handle) => match variables.get_type(*handle) {
+            Some(Type::Int) => format!("let mut {}: i64 = 0", variables.get_name(*handle)),
+            Some(Type::Complex) => {
+                format!("let mut {} = Complex::new(0., 0.)", variables.get_name(*handle))
+            }
+            Some(Type::List) => format!("let mut {}: Vec<f64> = Vec::new()", variables.get_name(*handle)),
+            Some(Type::Matrix) => {
+                format!("let mut {}: Vec<Vec<f64>> = Vec::new()", variables.get_name(*handle))
+            }
+            Some(Type::Polynomial) => {
+                format!("let mut {} = Polynomial::default()", variables.get_name(*handle))
+            }
+            _ if measured[*handle] => format!(
+                "let mut {} = 0.0;\n    let mut {}_uncertain = Uncertain::exact(0.)",
+                variables.get_name(*handle),
+                variables.get_name(*handle)
+            ),
+            _ => format!("let mut {} = 0.0", variables.get_name(*handle)),
+        },
+        AnalyzedStatement::InputOperation(handle, range) if variables.get_type(*handle) == Some(Type::Int) => {
+            let (lo, hi) = range.unwrap_or((f64::NEG_INFINITY, f64::INFINITY));
+            format!(
+                "{} = input_int_in_range({}, {})",
+                variables.get_name(*handle),
+                translate_to_rust_literal(lo),
+                translate_to_rust_literal(hi)
+            )
+        }
+        AnalyzedStatement::InputOperation(handle, _) if variables.get_type(*handle) == Some(Type::Complex) => {
+            format!("{} = input_complex()", variables.get_name(*handle))
         }
-        AnalyzedStatement::InputOperation(handle) => {
-            format!("{} = input()", variables.get_name(*handle))
+        // Floats are read as measured values.
+        AnalyzedStatement::InputOperation(handle, range) => {
+            let name = variables.get_name(*handle);
+            let mut input = match range {
+                None => "input()".to_string(),
+                Some((lo, hi)) => format!(
+                    "input_in_range({}, {})",
+                    translate_to_rust_literal(*lo),
+                    translate_to_rust_literal(*hi)
+                ),
+            };
+            if let Some(unit) = variables.get_unit(*handle).filter(|unit| unit.scale != 1.) {
+                input = format!("{} * Uncertain::exact({})", input, translate_to_rust_literal(unit.scale));
+            }
+            format!("{}_uncertain = {};\n    {} = {}_uncertain.value", name, input, name, name)
+        }
+        AnalyzedStatement::OutputOperation(expr, unit, format, label) => {
+            translate_to_rust_output(variables, expr, unit.as_ref(), *format, label.as_deref(), measured)
+        }
+        AnalyzedStatement::GradientOutput(expr, partials) => {
+            let mut format_string = String::new();
+            let mut arguments = String::new();
+            for (handle, unit) in partials {
+                let derivative =
+                    format!("({}).derivative", translate_to_rust_dual_expr(variables, expr, *handle));
+                if !format_string.is_empty() {
+                    format_string += ", ";
+                }
+                format_string += "{}";
+                match unit {
+                    Some(unit) => {
+                        format_string += " ";
+                        format_string += &unit.name;
+                        arguments += &format!(", {} / {}", derivative, translate_to_rust_literal(unit.scale));
+                    }
+                    None => arguments += &format!(", {}", derivative),
+                }
+            }
+            format!("println!(\"<output>: ({})\"{})", format_string, arguments)
         }
-        AnalyzedStatement::OutputOperation(expr) => format!(
-            "println!(\"<output>: {}\", {})",
-            "{}",
-            translate_to_rust_expr(&variables, expr)
+        AnalyzedStatement::SymbolicDerivative(expr, handle) => format!(
+            "println!(\"<output>: {{}}\", {:?})",
+            derive_source(variables, expr, *handle)
         ),
+        // The variable is the parameter of the closure, which gives the
+        // difference of the sides and its derivative.
+        AnalyzedStatement::Solve(solve) => {
+            let (lhs, rhs, handle, lo, hi) = &**solve;
+            let solved = format!(
+                "{} = solve(|{}: f64| {{ let difference = {} - ({}); Ok((difference.value, difference.derivative)) }}, {}, {}, {}).unwrap_or_else(|err| panic!(\"{{}}\", err))",
+                variables.get_name(*handle),
+                variables.get_name(*handle),
+                translate_to_rust_dual_expr(variables, lhs, *handle),
+                translate_to_rust_dual_expr(variables, rhs, *handle),
+                translate_to_rust_expr(variables, lo),
+                translate_to_rust_expr(variables, hi),
+                translate_to_rust_literal(variables.tolerance())
+            );
+            let name = variables.get_name(*handle);
+            if twin(*handle) {
+                format!("{};\n    {}_uncertain = Uncertain::exact({})", solved, name, name)
+            } else {
+                solved
+            }
+        }
+        AnalyzedStatement::Seed(expr) => format!("random::seed(({}) as u64)", translate_to_rust_expr(variables, expr)),
+        AnalyzedStatement::AngleMode(_) => String::new(),
+    }
+}
+
+// Whether a factor of the program, at any depth, satisfies `found`.
+fn factor_contains(factor: &AnalyzedFactor, found: &impl Fn(&AnalyzedFactor) -> bool) -> bool {
+    found(factor)
+        || match factor {
+            AnalyzedFactor::Literal(_) | AnalyzedFactor::Identifier(_) => false,
+            AnalyzedFactor::FunctionExpression(function_expr) => expr_contains(&function_expr.1, found),
+            AnalyzedFactor::SubExpression(expr)
+            | AnalyzedFactor::Derivative(expr, _)
+            | AnalyzedFactor::Polynomial(expr, _) => expr_contains(expr, found),
+            AnalyzedFactor::Integral(integral) => {
+                expr_contains(&integral.0, found)
+                    || expr_contains(&integral.2, found)
+                    || expr_contains(&integral.3, found)
+            }
+            AnalyzedFactor::Series(series) => {
+                expr_contains(&series.2, found)
+                    || expr_contains(&series.3, found)
+                    || expr_contains(&series.4, found)
+            }
+            AnalyzedFactor::Statistic(statistic) => {
+                statistic.1.iter().any(|argument| expr_contains(argument, found))
+            }
+            AnalyzedFactor::MatrixFunction(function) => {
+                function.1.iter().any(|argument| expr_contains(argument, found))
+            }
+            AnalyzedFactor::PolynomialFunction(function) => {
+                function.1.iter().any(|argument| expr_contains(argument, found))
+            }
+            AnalyzedFactor::NumberTheoryFunction(function) => {
+                function.1.iter().any(|argument| expr_contains(argument, found))
+            }
+            AnalyzedFactor::Random(random) => random.1.iter().any(|argument| expr_contains(argument, found)),
+            AnalyzedFactor::List(elements) | AnalyzedFactor::Matrix(elements) => {
+                elements.iter().any(|element| expr_contains(element, found))
+            }
+            AnalyzedFactor::Range(first, second) | AnalyzedFactor::Index(first, second) => {
+                expr_contains(first, found) || expr_contains(second, found)
+            }
+            AnalyzedFactor::Length(list) => expr_contains(list, found),
+        }
+}
+
+fn expr_contains(expr: &AnalyzedExpr, found: &impl Fn(&AnalyzedFactor) -> bool) -> bool {
+    std::iter::once(&expr.0)
+        .chain(expr.1.iter().map(|term| &term.1))
+        .any(|term| {
+            factor_contains(&term.0, found)
+                || term.1.iter().any(|factor| factor_contains(&factor.1, found))
+        })
+}
+
+fn program_contains(analyzed_program: &AnalyzedProgram, found: &impl Fn(&AnalyzedFactor) -> bool) -> bool {
+    analyzed_program.iter().any(|statement| match statement {
+        AnalyzedStatement::DeclarationToAssignment(_, expr)
+        | AnalyzedStatement::Assignment(_, expr)
+        | AnalyzedStatement::OutputOperation(expr, ..)
+        | AnalyzedStatement::GradientOutput(expr, _)
+        | AnalyzedStatement::Seed(expr) => expr_contains(expr, found),
+        AnalyzedStatement::Solve(solve) => {
+            expr_contains(&solve.0, found)
+                || expr_contains(&solve.1, found)
+                || expr_contains(&solve.3, found)
+                || expr_contains(&solve.4, found)
+        }
+        AnalyzedStatement::Declaration(_)
+        | AnalyzedStatement::InputOperation(..)
+        | AnalyzedStatement::SymbolicDerivative(..)
+        | AnalyzedStatement::AngleMode(_) => false,
+    })
+}
+
+fn uses_complex(variables: &SymbolTable, analyzed_program: &AnalyzedProgram) -> bool {
+    (0..variables.len()).any(|handle| variables.get_type(handle) == Some(Type::Complex))
+        || program_contains(analyzed_program, &|factor| match factor {
+            AnalyzedFactor::Literal(value) => value.get_type() == Type::Complex,
+            _ => false,
+        })
+}
+
+fn uses_list(variables: &SymbolTable, analyzed_program: &AnalyzedProgram) -> bool {
+    (0..variables.len()).any(|handle| variables.get_type(handle) == Some(Type::List))
+        || program_contains(analyzed_program, &|factor| {
+            match factor {
+                AnalyzedFactor::List(_) | AnalyzedFactor::Range(..) => true,
+                AnalyzedFactor::PolynomialFunction(function) => function.0 == PolynomialOperator::Roots,
+                AnalyzedFactor::NumberTheoryFunction(function) => function.0 == NumberTheoryOperator::Factor,
+                _ => false,
+            }
+        })
+}
+
+fn uses_matrix(variables: &SymbolTable, analyzed_program: &AnalyzedProgram) -> bool {
+    (0..variables.len()).any(|handle| variables.get_type(handle) == Some(Type::Matrix))
+        || program_contains(analyzed_program, &|factor| {
+            matches!(factor, AnalyzedFactor::Matrix(_) | AnalyzedFactor::MatrixFunction(_))
+        })
+}
+
+fn uses_polynomial(variables: &SymbolTable, analyzed_program: &AnalyzedProgram) -> bool {
+    (0..variables.len()).any(|handle| variables.get_type(handle) == Some(Type::Polynomial))
+        || program_contains(analyzed_program, &|factor| {
+            matches!(factor, AnalyzedFactor::Polynomial(..) | AnalyzedFactor::PolynomialFunction(_))
+        })
+}
+
+fn uses_derivative(analyzed_program: &AnalyzedProgram) -> bool {
+    analyzed_program
+        .iter()
+        .any(|statement| {
+            matches!(statement, AnalyzedStatement::GradientOutput(..) | AnalyzedStatement::Solve(_))
+        })
+        || program_contains(analyzed_program, &|factor| matches!(factor, AnalyzedFactor::Derivative(..)))
+}
+
+fn uses_integral(analyzed_program: &AnalyzedProgram) -> bool {
+    program_contains(analyzed_program, &|factor| matches!(factor, AnalyzedFactor::Integral(_)))
+}
+
+fn uses_series(analyzed_program: &AnalyzedProgram) -> bool {
+    program_contains(analyzed_program, &|factor| matches!(factor, AnalyzedFactor::Series(_)))
+}
+
+fn uses_sum(analyzed_program: &AnalyzedProgram) -> bool {
+    program_contains(analyzed_program, &|factor| {
+        matches!(factor, AnalyzedFactor::Series(series) if series.0 == SeriesOperator::Sum)
+    })
+}
+
+fn uses_statistics(analyzed_program: &AnalyzedProgram) -> bool {
+    program_contains(analyzed_program, &|factor| matches!(factor, AnalyzedFactor::Statistic(_)))
+}
+
+fn uses_number_theory(analyzed_program: &AnalyzedProgram) -> bool {
+    program_contains(analyzed_program, &|factor| matches!(factor, AnalyzedFactor::NumberTheoryFunction(_)))
+}
+
+fn uses_number_format(analyzed_program: &AnalyzedProgram) -> bool {
+    analyzed_program
+        .iter()
+        .any(|statement| matches!(statement, AnalyzedStatement::OutputOperation(_, _, Some(_), _)))
+}
+
+fn uses_random(analyzed_program: &AnalyzedProgram) -> bool {
+    analyzed_program
+        .iter()
+        .any(|statement| matches!(statement, AnalyzedStatement::Seed(_)))
+        || program_contains(analyzed_program, &|factor| matches!(factor, AnalyzedFactor::Random(_)))
+}
+
+fn has_plus_minus(expr: &AnalyzedExpr) -> bool {
+    expr.1.iter().any(|term| term.0 == ExprOperator::PlusMinus)
+}
+
+// Measured values can be input into any float variable, or written with
+// `+-` where the program computes them.
+fn uses_uncertainty(variables: &SymbolTable, analyzed_program: &AnalyzedProgram) -> bool {
+    analyzed_program.iter().any(|statement| match statement {
+        AnalyzedStatement::InputOperation(handle, _) => is_float_variable(variables, *handle),
+        AnalyzedStatement::DeclarationToAssignment(_, expr)
+        | AnalyzedStatement::Assignment(_, expr)
+        | AnalyzedStatement::OutputOperation(expr, ..) => has_plus_minus(expr),
+        _ => false,
+    }) || program_contains(analyzed_program, &|factor| match factor {
+        AnalyzedFactor::SubExpression(expr) => has_plus_minus(expr),
+        AnalyzedFactor::FunctionExpression(function_expr) => has_plus_minus(&function_expr.1),
+        _ => false,
+    })
+}
+
+// Whether `expr` writes `+-` or reads a measured variable.
+fn is_measured(measured: &[bool], expr: &AnalyzedExpr) -> bool {
+    has_plus_minus(expr)
+        || expr_contains(expr, &|factor| match factor {
+            AnalyzedFactor::Identifier(handle) => measured[*handle],
+            AnalyzedFactor::SubExpression(expr) => has_plus_minus(expr),
+            AnalyzedFactor::FunctionExpression(function_expr) => has_plus_minus(&function_expr.1),
+            _ => false,
+        })
+}
+
+// The float variables that can hold measured values: those input, and those
+// assigned a measured expression, until no more are found. The others stay
+// plain `f64` values.
+fn measured_variables(variables: &SymbolTable, analyzed_program: &AnalyzedProgram) -> Vec<bool> {
+    let mut measured = vec![false; variables.len()];
+    loop {
+        let mut changed = false;
+        for statement in analyzed_program {
+            let handle = match statement {
+                AnalyzedStatement::InputOperation(handle, _) => *handle,
+                AnalyzedStatement::DeclarationToAssignment(handle, expr)
+                | AnalyzedStatement::Assignment(handle, expr)
+                    if is_measured(&measured, expr) =>
+                {
+                    *handle
+                }
+                _ => continue,
+            };
+            if is_float_variable(variables, handle) && !measured[handle] {
+                measured[handle] = true;
+                changed = true;
+            }
+        }
+        if !changed {
+            return measured;
+        }
     }
 }
 
+fn uses_solve(analyzed_program: &AnalyzedProgram) -> bool {
+    analyzed_program
+        .iter()
+        .any(|statement| matches!(statement, AnalyzedStatement::Solve(_)))
+}
+
 pub fn translate_to_rust_program(
     variables: &SymbolTable,
     analyzed_program: &AnalyzedProgram,
 ) -> String {
+    let uncertain = uses_uncertainty(variables, analyzed_program);
+    let measured = measured_variables(variables, analyzed_program);
     let mut rust_program = String::new();
+    rust_program += "#![allow(dead_code)]\n";
+    rust_program += "\n";
     rust_program += "use std::io::Write;\n";
     rust_program += "\n";
-    rust_program += "#[allow(dead_code)]\n";
-    rust_program += "fn input() -> f64 {\n";
+    rust_program += "fn read_line() -> String {\n";
     rust_program += "    let mut text = String::new();\n";
     rust_program += "    eprint!(\"<input>: \");\n";
     rust_program += "    std::io::stderr().flush().unwrap();\n";
-    rust_program += "    std::io::stdin()\n";
-    rust_program += "        .read_line(&mut text)\n";
-    rust_program += "        .expect(\"Cannot read line.\");\n";
-    rust_program += "    text.trim().parse::<f64>().unwrap_or(0.)\n";
+    rust_program += "    if std::io::stdin().read_line(&mut text).expect(\"Cannot read line.\") == 0 {\n";
+    rust_program += "        eprintln!(\"Error: The input ended before a value was read.\");\n";
+    rust_program += "        std::process::exit(1);\n";
+    rust_program += "    }\n";
+    rust_program += "    text\n";
+    rust_program += "}\n";
+    rust_program += "\n";
+    rust_program += "fn input_int_in_range(lo: f64, hi: f64) -> i64 {\n";
+    rust_program += "    loop {\n";
+    rust_program += "        let text = read_line();\n";
+    rust_program += "        match text.trim().parse::<i64>() {\n";
+    rust_program += "            Ok(value) if lo <= value as f64 && value as f64 <= hi => return value,\n";
+    rust_program += "            Ok(_) => eprintln!(\"The value must be in [{}, {}].\", lo, hi),\n";
+    rust_program += "            Err(_) => eprintln!(\"Cannot read '{}' as an int.\", text.trim()),\n";
+    rust_program += "        }\n";
+    rust_program += "    }\n";
     rust_program += "}\n";
     rust_program += "\n";
+    rust_program += "fn checked(value: Option<i64>) -> i64 {\n";
+    rust_program += "    value.expect(\"Integer overflow or division by zero.\")\n";
+    rust_program += "}\n";
+    rust_program += "\n";
+    rust_program += "fn int_pow(base: i64, exponent: i64) -> i64 {\n";
+    rust_program += "    if exponent < 0 {\n";
+    rust_program += "        panic!(\"Negative exponent {} for an int.\", exponent);\n";
+    rust_program += "    }\n";
+    rust_program += "    checked(base.checked_pow(exponent.min(u32::MAX as i64) as u32))\n";
+    rust_program += "}\n";
+    rust_program += "\n";
+    rust_program += "fn float_to_int(value: f64) -> i64 {\n";
+    rust_program += "    let truncated = value.trunc();\n";
+    rust_program += "    if truncated >= i64::MIN as f64 && truncated < -(i64::MIN as f64) {\n";
+    rust_program += "        truncated as i64\n";
+    rust_program += "    } else {\n";
+    rust_program += "        panic!(\"Cannot convert {} to an int.\", value)\n";
+    rust_program += "    }\n";
+    rust_program += "}\n";
+    rust_program += "\n";
+    if uses_series(analyzed_program) {
+        rust_program += "fn series_range(name: &str, lo: i64, hi: i64) -> std::ops::RangeInclusive<i64> {\n";
+        rust_program += "    let terms = hi as i128 - lo as i128 + 1;\n";
+        rust_program += &format!("    if terms > {} {{\n", MAX_SERIES_TERMS);
+        rust_program += &format!(
+            "        panic!(\"{{}} takes at most {} terms, not {{}}.\", name, terms);\n",
+            MAX_SERIES_TERMS
+        );
+        rust_program += "    }\n";
+        rust_program += "    lo..=hi\n";
+        rust_program += "}\n";
+        rust_program += "\n";
+    }
+    if uses_complex(variables, analyzed_program) {
+        rust_program += include_str!("complex.rs");
+        rust_program += "\n";
+        rust_program += "fn input_complex() -> Complex {\n";
+        rust_program += "    loop {\n";
+        rust_program += "        let text = read_line();\n";
+        rust_program += "        match Complex::parse(text.trim()) {\n";
+        rust_program += "            Some(value) => return value,\n";
+        rust_program += "            None => eprintln!(\"Cannot read '{}' as a complex number.\", text.trim()),\n";
+        rust_program += "        }\n";
+        rust_program += "    }\n";
+        rust_program += "}\n";
+        rust_program += "\n";
+    }
+    // Matrices are indexed like lists of their rows.
+    if uses_list(variables, analyzed_program) || uses_matrix(variables, analyzed_program) {
+        rust_program += "trait Elements {\n";
+        rust_program += "    fn length(&self) -> Option<usize>;\n";
+        rust_program += "    fn element(&self, index: usize) -> f64;\n";
+        rust_program += "}\n";
+        rust_program += "\n";
+        rust_program += "impl Elements for f64 {\n";
+        rust_program += "    fn length(&self) -> Option<usize> {\n";
+        rust_program += "        None\n";
+        rust_program += "    }\n";
+        rust_program += "    fn element(&self, _: usize) -> f64 {\n";
+        rust_program += "        *self\n";
+        rust_program += "    }\n";
+        rust_program += "}\n";
+        rust_program += "\n";
+        rust_program += "impl Elements for Vec<f64> {\n";
+        rust_program += "    fn length(&self) -> Option<usize> {\n";
+        rust_program += "        Some(self.len())\n";
+        rust_program += "    }\n";
+        rust_program += "    fn element(&self, index: usize) -> f64 {\n";
+        rust_program += "        self[index]\n";
+        rust_program += "    }\n";
+        rust_program += "}\n";
+        rust_program += "\n";
+        rust_program += "fn list_broadcast(left: impl Elements, right: impl Elements, apply: impl Fn(f64, f64) -> f64) -> Vec<f64> {\n";
+        rust_program += "    let length = match (left.length(), right.length()) {\n";
+        rust_program += "        (Some(left), Some(right)) if left != right => {\n";
+        rust_program += "            panic!(\"Cannot combine lists of lengths {} and {}.\", left, right)\n";
+        rust_program += "        }\n";
+        rust_program += "        (left, right) => left.or(right).unwrap_or(1),\n";
+        rust_program += "    };\n";
+        rust_program += "    (0..length).map(|index| apply(left.element(index), right.element(index))).collect()\n";
+        rust_program += "}\n";
+        rust_program += "\n";
+        rust_program += "fn list_range(lo: f64, hi: f64) -> Vec<f64> {\n";
+        rust_program += "    if !lo.is_finite() || !hi.is_finite() {\n";
+        rust_program += "        panic!(\"The bounds of a range must be finite, not {} and {}.\", lo, hi);\n";
+        rust_program += "    }\n";
+        rust_program += "    if hi < lo {\n";
+        rust_program += "        return Vec::new();\n";
+        rust_program += "    }\n";
+        rust_program += &format!("    if hi - lo >= {}. {{\n", MAX_RANGE_LENGTH);
+        rust_program += &format!(
+            "        panic!(\"The range {{}}..{{}} has more than {} elements.\", lo, hi);\n",
+            MAX_RANGE_LENGTH
+        );
+        rust_program += "    }\n";
+        rust_program += "    (0..=float_to_int((hi - lo).floor())).map(|step| lo + step as f64).collect()\n";
+        rust_program += "}\n";
+        rust_program += "\n";
+        rust_program += "fn list_index<T: Clone>(list: &[T], index: i64) -> T {\n";
+        rust_program += "    if index < 1 || index as u64 > list.len() as u64 {\n";
+        rust_program += "        panic!(\"Index {} is out of range for a list of length {}.\", index, list.len());\n";
+        rust_program += "    }\n";
+        rust_program += "    list[index as usize - 1].clone()\n";
+        rust_program += "}\n";
+        rust_program += "\n";
+        rust_program += "fn list_dot(left: &[f64], right: &[f64]) -> f64 {\n";
+        rust_program += "    if left.len() != right.len() {\n";
+        rust_program += "        panic!(\"Cannot multiply lists of lengths {} and {}.\", left.len(), right.len());\n";
+        rust_program += "    }\n";
+        rust_program += "    left.iter().zip(right).map(|(left, right)| left * right).sum()\n";
+        rust_program += "}\n";
+        rust_program += "\n";
+        rust_program += "fn scalar_apply(left: f64, right: f64, apply: impl Fn(f64, f64) -> f64) -> f64 {\n";
+        rust_program += "    apply(left, right)\n";
+        rust_program += "}\n";
+        rust_program += "\n";
+        rust_program += "fn list_format(list: &[f64], format: impl Fn(f64) -> String) -> String {\n";
+        rust_program += "    let elements: Vec<String> = list.iter().map(|&element| format(element)).collect();\n";
+        rust_program += "    format!(\"[{}]\", elements.join(\", \"))\n";
+        rust_program += "}\n";
+        rust_program += "\n";
+    }
+    if uses_matrix(variables, analyzed_program) {
+        rust_program += "mod linear_algebra {\n";
+        rust_program += include_str!("linear_algebra.rs");
+        rust_program += "}\n";
+        rust_program += "\n";
+        rust_program += "trait Cells {\n";
+        rust_program += "    fn shape(&self) -> Option<(usize, usize)>;\n";
+        rust_program += "    fn cell(&self, row: usize, column: usize) -> f64;\n";
+        rust_program += "}\n";
+        rust_program += "\n";
+        rust_program += "impl Cells for f64 {\n";
+        rust_program += "    fn shape(&self) -> Option<(usize, usize)> {\n";
+        rust_program += "        None\n";
+        rust_program += "    }\n";
+        rust_program += "    fn cell(&self, _: usize, _: usize) -> f64 {\n";
+        rust_program += "        *self\n";
+        rust_program += "    }\n";
+        rust_program += "}\n";
+        rust_program += "\n";
+        rust_program += "impl Cells for Vec<Vec<f64>> {\n";
+        rust_program += "    fn shape(&self) -> Option<(usize, usize)> {\n";
+        rust_program += "        Some((self.len(), self[0].len()))\n";
+        rust_program += "    }\n";
+        rust_program += "    fn cell(&self, row: usize, column: usize) -> f64 {\n";
+        rust_program += "        self[row][column]\n";
+        rust_program += "    }\n";
+        rust_program += "}\n";
+        rust_program += "\n";
+        rust_program += "fn matrix_broadcast(left: impl Cells, right: impl Cells, apply: impl Fn(f64, f64) -> f64) -> Vec<Vec<f64>> {\n";
+        rust_program += "    let (rows, columns) = match (left.shape(), right.shape()) {\n";
+        rust_program += "        (Some(left), Some(right)) if left != right => panic!(\n";
+        rust_program += "            \"Cannot combine matrices of shapes {}x{} and {}x{}.\",\n";
+        rust_program += "            left.0, left.1, right.0, right.1\n";
+        rust_program += "        ),\n";
+        rust_program += "        (left, right) => left.or(right).unwrap_or((1, 1)),\n";
+        rust_program += "    };\n";
+        rust_program += "    (0..rows)\n";
+        rust_program += "        .map(|row| (0..columns).map(|column| apply(left.cell(row, column), right.cell(row, column))).collect())\n";
+        rust_program += "        .collect()\n";
+        rust_program += "}\n";
+        rust_program += "\n";
+        rust_program += "fn matrix_rows(rows: Vec<Vec<f64>>) -> Vec<Vec<f64>> {\n";
+        rust_program += "    linear_algebra::check_rows(&rows).unwrap_or_else(|err| panic!(\"{}\", err));\n";
+        rust_program += "    rows\n";
+        rust_program += "}\n";
+        rust_program += "\n";
+        rust_program += "fn matrix_format(rows: &[Vec<f64>], format: impl Fn(f64) -> String) -> String {\n";
+        rust_program += "    let cells: Vec<Vec<String>> = rows.iter().map(|row| row.iter().map(|&cell| format(cell)).collect()).collect();\n";
+        rust_program += "    linear_algebra::format(&cells)\n";
+        rust_program += "}\n";
+        rust_program += "\n";
+    }
+    if uses_polynomial(variables, analyzed_program) {
+        rust_program += "mod polynomial {\n";
+        rust_program += include_str!("polynomial.rs");
+        rust_program += "}\n";
+        rust_program += "#[allow(unused_imports)]\n";
+        rust_program += "use polynomial::Polynomial;\n";
+        rust_program += "\n";
+    }
+    if uses_derivative(analyzed_program) || uncertain {
+        rust_program += include_str!("dual.rs");
+        rust_program += "\n";
+    }
+    if uncertain {
+        rust_program += include_str!("uncertain.rs");
+        rust_program += "\n";
+        rust_program += "fn uncertain_apply(argument: Uncertain, function: fn(Dual) -> Dual) -> Uncertain {\n";
+        rust_program += "    let result = function(Dual::variable(argument.value));\n";
+        rust_program += "    argument.map(result.value, result.derivative)\n";
+        rust_program += "}\n";
+        rust_program += "\n";
+        rust_program += "fn input_in_range(lo: f64, hi: f64) -> Uncertain {\n";
+        rust_program += "    loop {\n";
+        rust_program += "        let text = read_line();\n";
+        rust_program += "        let value = Uncertain::parse(text.trim()).or_else(|| text.trim().parse().ok().map(Uncertain::exact));\n";
+        rust_program += "        match value {\n";
+        rust_program += "            Some(value) if lo <= value.value && value.value <= hi => return value,\n";
+        rust_program += "            Some(_) => eprintln!(\"The value must be in [{}, {}].\", lo, hi),\n";
+        rust_program += "            None => eprintln!(\"Cannot read '{}' as a number.\", text.trim()),\n";
+        rust_program += "        }\n";
+        rust_program += "    }\n";
+        rust_program += "}\n";
+        rust_program += "\n";
+        rust_program += "fn input() -> Uncertain {\n";
+        rust_program += "    input_in_range(f64::NEG_INFINITY, f64::INFINITY)\n";
+        rust_program += "}\n";
+        rust_program += "\n";
+    }
+    if uses_integral(analyzed_program) {
+        rust_program += include_str!("quadrature.rs");
+        rust_program += "\n";
+    }
+    if uses_solve(analyzed_program) {
+        rust_program += include_str!("roots.rs");
+        rust_program += "\n";
+    }
+    if uses_sum(analyzed_program) || uses_statistics(analyzed_program) {
+        rust_program += "mod summation {\n";
+        rust_program += include_str!("summation.rs");
+        rust_program += "}\n";
+        rust_program += "#[allow(unused_imports)]\n";
+        rust_program += "use summation::Neumaier;\n";
+        rust_program += "\n";
+    }
+    if uses_statistics(analyzed_program) {
+        rust_program += "mod statistics {\n";
+        rust_program += include_str!("statistics.rs");
+        rust_program += "}\n";
+        rust_program += "\n";
+    }
+    if uses_number_theory(analyzed_program) {
+        rust_program += "mod number_theory {\n";
+        rust_program += include_str!("number_theory.rs");
+        rust_program += "}\n";
+        rust_program += "\n";
+    }
+    if uses_number_format(analyzed_program) {
+        rust_program += "mod number_format {\n";
+        rust_program += include_str!("number_format.rs");
+        rust_program += "}\n";
+        rust_program += "\n";
+    }
+    if uses_random(analyzed_program) {
+        rust_program += "mod random {\n";
+        rust_program += include_str!("random.rs");
+        rust_program += "}\n";
+        rust_program += "\n";
+    }
     rust_program += "fn main() {\n"; 
+    if let (Some(seed), true) = (variables.seed(), uses_random(analyzed_program)) {
+        rust_program += &format!("    random::seed({});\n", seed);
+    }
+    // The angles have been converted to radians, so `mode` leaves no code.
     for statement in analyzed_program {
+        if let AnalyzedStatement::AngleMode(_) = statement {
+            continue;
+        }
         rust_program += "    ";
-        rust_program += &translate_to_rust_statement(&variables, statement);
+        rust_program += &translate_to_rust_statement(variables, statement, &measured);
         rust_program += ";\n";
     }
     rust_program += "}\n";
@@ -0,0 +1,337 @@
+// Complex numbers with f64 parts, whatever the numeric backend, with the
+// arithmetic, the elementary functions and the parsing of `a+bi`.
+
+use std::fmt;
+use std::ops::{Add, Div, Mul, Sub};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Complex {
+    pub re: f64,
+    pub im: f64,
+}
+
+const ONE: Complex = Complex { re: 1., im: 0. };
+
+impl From<f64> for Complex {
+    fn from(re: f64) -> Complex {
+        Complex::new(re, 0.)
+    }
+}
+
+impl Add for Complex {
+    type Output = Complex;
+    fn add(self, other: Complex) -> Complex {
+        Complex::new(self.re + other.re, self.im + other.im)
+    }
+}
+
+impl Sub for Complex {
+    type Output = Complex;
+    fn sub(self, other: Complex) -> Complex {
+        Complex::new(self.re - other.re, self.im - other.im)
+    }
+}
+
+impl Mul for Complex {
+    type Output = Complex;
+    fn mul(self, other: Complex) -> Complex {
+        Complex::new(
+            self.re * other.re - self.im * other.im,
+            self.re * other.im + self.im * other.re,
+        )
+    }
+}
+
+impl Div for Complex {
+    type Output = Complex;
+    // Smith's algorithm, which avoids squaring the parts of the divisor.
+    fn div(self, other: Complex) -> Complex {
+        if other.im.abs() <= other.re.abs() {
+            let ratio = other.im / other.re;
+            let denominator = other.re + other.im * ratio;
+            Complex::new(
+                (self.re + self.im * ratio) / denominator,
+                (self.im - self.re * ratio) / denominator,
+            )
+        } else {
+            let ratio = other.re / other.im;
+            let denominator = other.re * ratio + other.im;
+            Complex::new(
+                (self.re * ratio + self.im) / denominator,
+                (self.im * ratio - self.re) / denominator,
+            )
+        }
+    }
+}
+
+// A float meeting a complex number is first turned into one.
+macro_rules! mixed_operators {
+    ($($operator:ident $method:ident),*) => {$(
+        impl $operator<f64> for Complex {
+            type Output = Complex;
+            fn $method(self, other: f64) -> Complex {
+                self.$method(Complex::from(other))
+            }
+        }
+        impl $operator<Complex> for f64 {
+            type Output = Complex;
+            fn $method(self, other: Complex) -> Complex {
+                Complex::from(self).$method(other)
+            }
+        }
+    )*};
+}
+
+mixed_operators!(Add add, Sub sub, Mul mul, Div div);
+
+// Principal values throughout, with the branch cuts of the usual real
+// functions. The methods have the names of their f64 counterparts, and
+// programs emitted by `compiler` may only use some of them.
+impl Complex {
+    pub fn new(re: f64, im: f64) -> Complex {
+        Complex { re, im }
+    }
+
+    pub fn re(self) -> f64 {
+        self.re
+    }
+
+    pub fn im(self) -> f64 {
+        self.im
+    }
+
+    pub fn conj(self) -> Complex {
+        Complex::new(self.re, -self.im)
+    }
+
+    pub fn abs(self) -> f64 {
+        self.re.hypot(self.im)
+    }
+
+    pub fn arg(self) -> f64 {
+        self.im.atan2(self.re)
+    }
+
+    pub fn signum(self) -> Complex {
+        if self.re == 0. && self.im == 0. {
+            self
+        } else {
+            self / self.abs()
+        }
+    }
+
+    pub fn sqrt(self) -> Complex {
+        if self.re == 0. && self.im == 0. {
+            return self;
+        }
+        let root = ((self.re.abs() + self.abs()) / 2.).sqrt();
+        if self.re >= 0. {
+            Complex::new(root, self.im / (2. * root))
+        } else {
+            Complex::new(self.im.abs() / (2. * root), root.copysign(self.im))
+        }
+    }
+
+    pub fn exp(self) -> Complex {
+        let magnitude = self.re.exp();
+        Complex::new(magnitude * self.im.cos(), magnitude * self.im.sin())
+    }
+
+    pub fn exp2(self) -> Complex {
+        (self * std::f64::consts::LN_2).exp()
+    }
+
+    pub fn ln(self) -> Complex {
+        Complex::new(self.abs().ln(), self.arg())
+    }
+
+    pub fn log10(self) -> Complex {
+        self.ln() / std::f64::consts::LN_10
+    }
+
+    pub fn log2(self) -> Complex {
+        self.ln() / std::f64::consts::LN_2
+    }
+
+    // Binary exponentiation, so that small integer powers are exact.
+    fn powi(self, exponent: i64) -> Complex {
+        let mut base = if exponent < 0 { ONE / self } else { self };
+        let mut remaining = exponent.unsigned_abs();
+        let mut result: Option<Complex> = None;
+        while remaining > 0 {
+            if remaining & 1 == 1 {
+                result = Some(result.map_or(base, |result| result * base));
+            }
+            remaining >>= 1;
+            if remaining > 0 {
+                base = base * base;
+            }
+        }
+        result.unwrap_or(ONE)
+    }
+
+    pub fn pow(self, exponent: Complex) -> Complex {
+        if exponent.im == 0. && exponent.re.fract() == 0. && exponent.re.abs() <= i32::MAX as f64 {
+            return self.powi(exponent.re as i64);
+        }
+        if self.re == 0. && self.im == 0. {
+            return if exponent.re > 0. {
+                Complex::new(0., 0.)
+            } else {
+                Complex::new(f64::NAN, f64::NAN)
+            };
+        }
+        (exponent * self.ln()).exp()
+    }
+
+    pub fn sin(self) -> Complex {
+        Complex::new(self.re.sin() * self.im.cosh(), self.re.cos() * self.im.sinh())
+    }
+
+    pub fn cos(self) -> Complex {
+        Complex::new(self.re.cos() * self.im.cosh(), -(self.re.sin() * self.im.sinh()))
+    }
+
+    pub fn tan(self) -> Complex {
+        self.sin() / self.cos()
+    }
+
+    pub fn sinh(self) -> Complex {
+        Complex::new(self.re.sinh() * self.im.cos(), self.re.cosh() * self.im.sin())
+    }
+
+    pub fn cosh(self) -> Complex {
+        Complex::new(self.re.cosh() * self.im.cos(), self.re.sinh() * self.im.sin())
+    }
+
+    pub fn tanh(self) -> Complex {
+        self.sinh() / self.cosh()
+    }
+
+    // 1 + z and 1 - z, keeping the sign of a zero imaginary part, which
+    // selects the side of the branch cuts.
+    fn one_plus(self) -> Complex {
+        Complex::new(1. + self.re, self.im)
+    }
+
+    fn one_minus(self) -> Complex {
+        Complex::new(1. - self.re, -self.im)
+    }
+
+    // i z
+    fn rotate(self) -> Complex {
+        Complex::new(-self.im, self.re)
+    }
+
+    // Kahan's formulas for asin and acos.
+    pub fn asin(self) -> Complex {
+        let (a, b) = (self.one_minus().sqrt(), self.one_plus().sqrt());
+        Complex::new(
+            self.re.atan2(a.re * b.re - a.im * b.im),
+            (a.re * b.im - a.im * b.re).asinh(),
+        )
+    }
+
+    pub fn acos(self) -> Complex {
+        let (a, b) = (self.one_minus().sqrt(), self.one_plus().sqrt());
+        Complex::new(2. * a.re.atan2(b.re), (b.re * a.im - b.im * a.re).asinh())
+    }
+
+    // asinh(z) = -i asin(iz)
+    pub fn asinh(self) -> Complex {
+        let w = self.rotate().asin();
+        Complex::new(w.im, -w.re)
+    }
+
+    pub fn acosh(self) -> Complex {
+        let minus_one = Complex::new(self.re - 1., self.im);
+        (self + self.one_plus().sqrt() * minus_one.sqrt()).ln()
+    }
+
+    pub fn atanh(self) -> Complex {
+        (self.one_plus().ln() - self.one_minus().ln()) / 2.
+    }
+
+    // atan(z) = -i atanh(iz)
+    pub fn atan(self) -> Complex {
+        let w = self.rotate().atanh();
+        Complex::new(w.im, -w.re)
+    }
+
+    /// Reads `a`, `bi`, `a+bi` or `a-bi`, where `j` may replace `i`.
+    pub fn parse(text: &str) -> Option<Complex> {
+        let text: String = text.chars().filter(|ch| !ch.is_whitespace()).collect();
+        let body = match text.strip_suffix(['i', 'j']) {
+            Some(body) => body,
+            None => return Some(Complex::from(text.parse::<f64>().ok()?)),
+        };
+        // The imaginary part starts at the last sign outside of an exponent.
+        let split = body
+            .char_indices()
+            .rev()
+            .find(|&(index, ch)| {
+                (ch == '+' || ch == '-') && !body[..index].ends_with(['e', 'E'])
+            })
+            .map_or(0, |(index, _)| index);
+        let (re, im) = body.split_at(split);
+        let im = match im {
+            "" | "+" => 1.,
+            "-" => -1.,
+            _ => im.parse().ok()?,
+        };
+        let re = if re.is_empty() { 0. } else { re.parse().ok()? };
+        Some(Complex::new(re, im))
+    }
+}
+
+impl fmt::Display for Complex {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.im.is_sign_negative() {
+            write!(f, "{}-{}i", self.re, -self.im)
+        } else {
+            write!(f, "{}+{}i", self.re, self.im)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(value: Complex, expected: Complex) {
+        assert!((value - expected).abs() < 1e-12, "{} is not {}", value, expected);
+    }
+
+    #[test]
+    fn arithmetic() {
+        let (a, b) = (Complex::new(3., -4.), Complex::new(1., 2.));
+        assert_eq!(a + b, Complex::new(4., -2.));
+        assert_eq!(a * b, Complex::new(11., 2.));
+        assert_eq!(a / b, Complex::new(-1., -2.));
+        assert_eq!(a.abs(), 5.);
+        assert_eq!(2. * b - 1., Complex::new(1., 4.));
+        // Smith's algorithm does not overflow on large divisors.
+        assert_eq!(Complex::new(1e300, 1e300) / Complex::new(1e300, 1e300), ONE);
+    }
+
+    #[test]
+    fn principal_values() {
+        assert_eq!(Complex::from(-4.).sqrt(), Complex::new(0., 2.));
+        assert_eq!(Complex::new(-4., -0.).sqrt(), Complex::new(0., -2.));
+        assert_close(Complex::new(0., std::f64::consts::PI).exp(), Complex::from(-1.));
+        assert_close(Complex::from(-1.).ln(), Complex::new(0., std::f64::consts::PI));
+        assert_close(Complex::new(0., 1.).pow(Complex::from(2.)), Complex::from(-1.));
+        assert_close(Complex::from(2.).asin().sin(), Complex::from(2.));
+    }
+
+    #[test]
+    fn parsing_and_printing() {
+        assert_eq!(Complex::parse("3-4i"), Some(Complex::new(3., -4.)));
+        assert_eq!(Complex::parse("-2j"), Some(Complex::new(0., -2.)));
+        assert_eq!(Complex::parse("1e-3+i"), Some(Complex::new(1e-3, 1.)));
+        assert_eq!(Complex::parse("2.5"), Some(Complex::from(2.5)));
+        assert_eq!(Complex::parse("3-4k"), None);
+        assert_eq!(Complex::new(3., -4.).to_string(), "3-4i");
+        assert_eq!(Complex::new(0., 2.).to_string(), "0+2i");
+    }
+}
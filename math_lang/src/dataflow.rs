@@ -0,0 +1,255 @@
+use crate::analyzer::{
+    AnalyzedExpr, AnalyzedFactor, AnalyzedProgram, AnalyzedStatement, AnalyzedTerm,
+};
+use crate::diagnostics::{Diagnostic, Lint};
+use crate::optimizer;
+use crate::symbol_table::SymbolTable;
+use std::collections::HashMap;
+
+fn collect_factor_reads(factor: &AnalyzedFactor, reads: &mut Vec<usize>) {
+    match factor {
+        AnalyzedFactor::Literal(_) => {}
+        AnalyzedFactor::Identifier(handle) => reads.push(*handle),
+        AnalyzedFactor::FunctionExpression(function_expr) => {
+            collect_expr_reads(&function_expr.1, reads)
+        }
+        AnalyzedFactor::SubExpression(expr)
+        | AnalyzedFactor::Derivative(expr, _)
+        | AnalyzedFactor::Polynomial(expr, _) => {
+            collect_expr_reads(expr, reads)
+        }
+        AnalyzedFactor::Integral(integral) => {
+            collect_expr_reads(&integral.0, reads);
+            collect_expr_reads(&integral.2, reads);
+            collect_expr_reads(&integral.3, reads);
+        }
+        AnalyzedFactor::Series(series) => {
+            collect_expr_reads(&series.2, reads);
+            collect_expr_reads(&series.3, reads);
+            collect_expr_reads(&series.4, reads);
+        }
+        AnalyzedFactor::Statistic(statistic) => {
+            for argument in &statistic.1 {
+                collect_expr_reads(argument, reads);
+            }
+        }
+        AnalyzedFactor::MatrixFunction(function) => {
+            for argument in &function.1 {
+                collect_expr_reads(argument, reads);
+            }
+        }
+        AnalyzedFactor::PolynomialFunction(function) => {
+            for argument in &function.1 {
+                collect_expr_reads(argument, reads);
+            }
+        }
+        AnalyzedFactor::NumberTheoryFunction(function) => {
+            for argument in &function.1 {
+                collect_expr_reads(argument, reads);
+            }
+        }
+        AnalyzedFactor::Random(random) => {
+            for argument in &random.1 {
+                collect_expr_reads(argument, reads);
+            }
+        }
+        AnalyzedFactor::List(elements) | AnalyzedFactor::Matrix(elements) => {
+            for element in elements {
+                collect_expr_reads(element, reads);
+            }
+        }
+        AnalyzedFactor::Range(first, second) | AnalyzedFactor::Index(first, second) => {
+            collect_expr_reads(first, reads);
+            collect_expr_reads(second, reads);
+        }
+        AnalyzedFactor::Length(list) => collect_expr_reads(list, reads),
+    }
+}
+
+fn collect_term_reads(term: &AnalyzedTerm, reads: &mut Vec<usize>) {
+    collect_factor_reads(&term.0, reads);
+    for factor in &term.1 {
+        collect_factor_reads(&factor.1, reads);
+    }
+}
+
+pub fn collect_expr_reads(expr: &AnalyzedExpr, reads: &mut Vec<usize>) {
+    collect_term_reads(&expr.0, reads);
+    for term in &expr.1 {
+        collect_term_reads(&term.1, reads);
+    }
+}
+
+// State of the walk over a straight-line program. Statements are numbered
+// from 1 in messages.
+struct Dataflow<'a> {
+    variables: &'a SymbolTable,
+    diagnostics: Vec<Diagnostic>,
+    declared: Vec<usize>,
+    read: Vec<usize>,
+    unread_stores: HashMap<usize, usize>,
+}
+
+impl<'a> Dataflow<'a> {
+    fn read_expr(&mut self, expr: &AnalyzedExpr) {
+        let mut reads = Vec::new();
+        collect_expr_reads(expr, &mut reads);
+        self.read_handles(reads);
+    }
+
+    fn read_handles(&mut self, reads: Vec<usize>) {
+        for handle in reads {
+            self.unread_stores.remove(&handle);
+            if !self.read.contains(&handle) {
+                self.read.push(handle);
+            }
+        }
+    }
+
+    fn store(&mut self, statement_number: usize, handle: usize) {
+        if let Some(previous) = self.unread_stores.insert(handle, statement_number) {
+            self.diagnostics.push(Diagnostic::new(
+                Lint::DeadStore,
+                format!(
+                    "value stored to '{}' in statement {} is overwritten in statement {} before being read",
+                    self.variables.get_name(handle),
+                    previous,
+                    statement_number
+                ),
+            ));
+        }
+    }
+
+    fn check_statement(&mut self, statement_number: usize, statement: &AnalyzedStatement) {
+        match statement {
+            AnalyzedStatement::Declaration(handle) => self.declared.push(*handle),
+            AnalyzedStatement::DeclarationToAssignment(handle, expr) => {
+                self.declared.push(*handle);
+                self.read_expr(expr);
+                self.store(statement_number, *handle);
+            }
+            AnalyzedStatement::Assignment(handle, expr) => {
+                self.read_expr(expr);
+                self.store(statement_number, *handle);
+            }
+            AnalyzedStatement::InputOperation(handle, _) => self.store(statement_number, *handle),
+            AnalyzedStatement::AngleMode(_) => {}
+            AnalyzedStatement::OutputOperation(expr, ..) => {
+                if optimizer::is_constant_expr(self.variables, expr) {
+                    self.diagnostics.push(Diagnostic::new(
+                        Lint::ConstantOutput,
+                        format!(
+                            "statement {} outputs a value that is known at compile time",
+                            statement_number
+                        ),
+                    ));
+                }
+                self.read_expr(expr);
+            }
+            AnalyzedStatement::GradientOutput(expr, _) | AnalyzedStatement::Seed(expr) => self.read_expr(expr),
+            // Only the names of the variables are used, not their values.
+            AnalyzedStatement::SymbolicDerivative(expr, _) => {
+                let mut mentions = Vec::new();
+                collect_expr_reads(expr, &mut mentions);
+                for handle in mentions {
+                    if !self.read.contains(&handle) {
+                        self.read.push(handle);
+                    }
+                }
+            }
+            // The equation only reads the values tried by the solver, so the
+            // previous value of the variable is not read.
+            AnalyzedStatement::Solve(solve) => {
+                let (lhs, rhs, handle, lo, hi) = &**solve;
+                self.read_expr(lo);
+                self.read_expr(hi);
+                let mut reads = Vec::new();
+                collect_expr_reads(lhs, &mut reads);
+                collect_expr_reads(rhs, &mut reads);
+                reads.retain(|read| read != handle);
+                self.read_handles(reads);
+                if !self.read.contains(handle) {
+                    self.read.push(*handle);
+                }
+                self.store(statement_number, *handle);
+            }
+        }
+    }
+}
+
+/// Looks for unused variables, dead stores and constant outputs. Reads of
+/// unassigned variables are found by `analyzer::check_initialization`.
+pub fn check_program(variables: &SymbolTable, program: &AnalyzedProgram) -> Vec<Diagnostic> {
+    let mut dataflow = Dataflow {
+        variables,
+        diagnostics: Vec::new(),
+        declared: Vec::new(),
+        read: Vec::new(),
+        unread_stores: HashMap::new(),
+    };
+    for (index, statement) in program.iter().enumerate() {
+        dataflow.check_statement(index + 1, statement);
+    }
+    for handle in &dataflow.declared {
+        if !dataflow.read.contains(handle) {
+            dataflow.diagnostics.push(Diagnostic::new(
+                Lint::UnusedVariable,
+                format!("variable '{}' is declared but never read", variables.get_name(*handle)),
+            ));
+        }
+    }
+    dataflow.diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzer::analyze_program;
+    use crate::numeric::Backend;
+    use crate::parser::parse_program;
+
+    fn messages(source: &str) -> Vec<(Lint, String)> {
+        let mut variables = SymbolTable::with_backend(Backend::Float);
+        let (_, parsed_program) = parse_program(source).unwrap();
+        let analyzed_program = analyze_program(&mut variables, &parsed_program).unwrap();
+        check_program(&variables, &analyzed_program)
+            .into_iter()
+            .map(|diagnostic| (diagnostic.lint, diagnostic.message))
+            .collect()
+    }
+
+    #[test]
+    fn unused_variables() {
+        assert_eq!(
+            messages("var a = 1\nvar b\nin b\nout b"),
+            vec![(Lint::UnusedVariable, "variable 'a' is declared but never read".to_string())]
+        );
+        // The variable of `derive` is only named, and counts as read.
+        assert!(messages("var x\nderive x^2 wrt x").is_empty());
+    }
+
+    #[test]
+    fn dead_stores() {
+        assert_eq!(
+            messages("var a\nin a\na = 2\nout a"),
+            vec![(
+                Lint::DeadStore,
+                "value stored to 'a' in statement 2 is overwritten in statement 3 before being read".to_string()
+            )]
+        );
+        assert!(messages("var a\nin a\na = a + 1\nout a").is_empty());
+        // `solve` does not read the previous value of its variable.
+        let diagnostics = messages("var x = 1\nsolve x^2 = 2 for x in [0, 2]\nout x");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].0, Lint::DeadStore);
+    }
+
+    #[test]
+    fn constant_outputs() {
+        assert_eq!(
+            messages("out 1 + 2"),
+            vec![(Lint::ConstantOutput, "statement 1 outputs a value that is known at compile time".to_string())]
+        );
+        assert!(messages("var a\nin a\nout a + 2").is_empty());
+    }
+}
@@ -0,0 +1,834 @@
+// Decimal floating point with a precision chosen per run, used by
+// `--precision`. A value is `mantissa * 10^exponent`, and every operation
+// rounds its exact result to the precision. The functions are evaluated with
+// guard digits before being rounded, so their last digit may be off by one.
+
+use crate::parser::{ExprOperator, FunctionOperator, TermOperator};
+use crate::unparser::function_name;
+use crate::value::Value;
+use num_bigint::BigInt;
+use num_traits::{One, Pow, Signed, ToPrimitive, Zero};
+use std::cmp::Ordering;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Rounding {
+    HalfEven,
+    HalfUp,
+    // Toward zero.
+    Down,
+    // Away from zero.
+    Up,
+    Floor,
+    Ceiling,
+}
+
+impl Rounding {
+    pub fn from_name(name: &str) -> Result<Rounding, String> {
+        match name {
+            "half-even" => Ok(Rounding::HalfEven),
+            "half-up" => Ok(Rounding::HalfUp),
+            "down" => Ok(Rounding::Down),
+            "up" => Ok(Rounding::Up),
+            "floor" => Ok(Rounding::Floor),
+            "ceiling" => Ok(Rounding::Ceiling),
+            _ => Err(format!(
+                "Unknown rounding mode '{}'. Modes are half-even, half-up, down, up, floor and ceiling.",
+                name
+            )),
+        }
+    }
+}
+
+/// Number of significant digits kept, and how the others are dropped.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Context {
+    pub precision: usize,
+    pub rounding: Rounding,
+}
+
+/// Always normalized: the mantissa has no trailing zeros, and zero has a
+/// zero exponent, so equal numbers are equal values.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Decimal {
+    mantissa: BigInt,
+    exponent: i64,
+}
+
+fn power_of_ten(exponent: usize) -> BigInt {
+    Pow::pow(BigInt::from(10), exponent)
+}
+
+fn digit_count(integer: &BigInt) -> usize {
+    integer.magnitude().to_string().len()
+}
+
+impl Decimal {
+    fn new(mut mantissa: BigInt, mut exponent: i64) -> Decimal {
+        if mantissa.is_zero() {
+            return Decimal::zero();
+        }
+        let ten = BigInt::from(10);
+        while (&mantissa % &ten).is_zero() {
+            mantissa /= &ten;
+            exponent += 1;
+        }
+        Decimal { mantissa, exponent }
+    }
+    fn zero() -> Decimal {
+        Decimal {
+            mantissa: BigInt::zero(),
+            exponent: 0,
+        }
+    }
+    pub fn from_integer(value: i64) -> Decimal {
+        Decimal::new(BigInt::from(value), 0)
+    }
+    pub fn from_integer_digits(value: BigInt) -> Decimal {
+        Decimal::new(value, 0)
+    }
+
+    /// Reads a decimal number such as `-1.25` or `3e-4`, without rounding it.
+    pub fn parse(text: &str) -> Option<Decimal> {
+        let (mantissa, exponent) = match text.find(['e', 'E']) {
+            Some(position) => (&text[..position], text[position + 1..].parse::<i64>().ok()?),
+            None => (text, 0),
+        };
+        let (integer_part, fraction_part) = match mantissa.find('.') {
+            Some(position) => (&mantissa[..position], &mantissa[position + 1..]),
+            None => (mantissa, ""),
+        };
+        let digits: BigInt = format!("{}{}", integer_part, fraction_part).parse().ok()?;
+        let exponent = exponent.checked_sub(fraction_part.len() as i64)?;
+        Some(Decimal::new(digits, exponent))
+    }
+
+    /// The shortest decimal giving back `value`, so that the literal `0.1`
+    /// is exactly 1/10. Infinities and NaN have no decimal.
+    pub fn from_f64(value: f64) -> Option<Decimal> {
+        if value.is_finite() {
+            Decimal::parse(&value.to_string())
+        } else {
+            None
+        }
+    }
+
+    pub fn into_parts(self) -> (BigInt, i64) {
+        (self.mantissa, self.exponent)
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        format!("{}e{}", self.mantissa, self.exponent)
+            .parse()
+            .unwrap_or(f64::NAN)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.mantissa.is_zero()
+    }
+    pub fn is_negative(&self) -> bool {
+        self.mantissa.is_negative()
+    }
+    fn is_integer(&self) -> bool {
+        self.exponent >= 0
+    }
+    fn neg(&self) -> Decimal {
+        Decimal {
+            mantissa: -&self.mantissa,
+            exponent: self.exponent,
+        }
+    }
+    fn abs(&self) -> Decimal {
+        Decimal {
+            mantissa: self.mantissa.abs(),
+            exponent: self.exponent,
+        }
+    }
+    // Exponent of the leading digit; `10^adjusted <= |self| < 10^(adjusted + 1)`.
+    fn adjusted(&self) -> i64 {
+        self.exponent + digit_count(&self.mantissa) as i64 - 1
+    }
+
+    fn compare(&self, other: &Decimal) -> Ordering {
+        let sign = |value: &Decimal| value.mantissa.sign();
+        if sign(self) != sign(other) || self.is_zero() {
+            return sign(self).cmp(&sign(other));
+        }
+        let magnitude_order = match self.adjusted().cmp(&other.adjusted()) {
+            Ordering::Equal => {
+                // Same leading digit position, so the exponents differ by
+                // less than the number of digits.
+                let exponent = self.exponent.min(other.exponent);
+                let align = |value: &Decimal| {
+                    value.mantissa.abs() * power_of_ten((value.exponent - exponent) as usize)
+                };
+                align(self).cmp(&align(other))
+            }
+            order => order,
+        };
+        if self.is_negative() {
+            magnitude_order.reverse()
+        } else {
+            magnitude_order
+        }
+    }
+
+    // The integer rounded toward zero, or else toward minus or plus infinity.
+    fn to_integer(&self, rounding: Rounding) -> Decimal {
+        if self.is_integer() {
+            return self.clone();
+        }
+        let truncated = if self.adjusted() < 0 {
+            BigInt::zero()
+        } else {
+            &self.mantissa / power_of_ten((-self.exponent) as usize)
+        };
+        let adjustment = match rounding {
+            Rounding::Floor if self.is_negative() => -1,
+            Rounding::Ceiling if !self.is_negative() => 1,
+            _ => 0,
+        };
+        Decimal::new(truncated + adjustment, 0)
+    }
+}
+
+impl std::fmt::Display for Decimal {
+    /// Positional notation for moderate exponents, scientific otherwise.
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let digits = self.mantissa.magnitude().to_string();
+        let sign = if self.is_negative() { "-" } else { "" };
+        let adjusted = self.adjusted();
+        if !(-7..=20).contains(&adjusted) {
+            let (first, rest) = digits.split_at(1);
+            return if rest.is_empty() {
+                write!(f, "{}{}e{}", sign, first, adjusted)
+            } else {
+                write!(f, "{}{}.{}e{}", sign, first, rest, adjusted)
+            };
+        }
+        if self.exponent >= 0 {
+            write!(f, "{}{}{}", sign, digits, "0".repeat(self.exponent as usize))
+        } else if adjusted >= 0 {
+            let (integer_part, fraction_part) = digits.split_at(adjusted as usize + 1);
+            write!(f, "{}{}.{}", sign, integer_part, fraction_part)
+        } else {
+            write!(f, "{}0.{}{}", sign, "0".repeat((-adjusted - 1) as usize), digits)
+        }
+    }
+}
+
+impl Context {
+    /// Rounds `mantissa * 10^exponent` to the precision. `sticky` tells that
+    /// the exact value is slightly larger in magnitude, as for a division
+    /// with a remainder.
+    fn round(self, mantissa: BigInt, exponent: i64, sticky: bool) -> Decimal {
+        let negative = mantissa.is_negative();
+        let magnitude = mantissa.abs();
+        let excess = digit_count(&magnitude).saturating_sub(self.precision);
+        let divisor = power_of_ten(excess);
+        let mut quotient = &magnitude / &divisor;
+        let remainder = &magnitude % &divisor;
+        let inexact = sticky || !remainder.is_zero();
+        let half = match (&remainder * 2u32).cmp(&divisor) {
+            Ordering::Equal if sticky => Ordering::Greater,
+            order => order,
+        };
+        let round_away = match self.rounding {
+            Rounding::HalfEven => {
+                half == Ordering::Greater || half == Ordering::Equal && (&quotient % 2u32).is_one()
+            }
+            Rounding::HalfUp => half != Ordering::Less,
+            Rounding::Down => false,
+            Rounding::Up => inexact,
+            Rounding::Floor => inexact && negative,
+            Rounding::Ceiling => inexact && !negative,
+        };
+        if round_away {
+            quotient += 1;
+        }
+        let quotient = if negative { -quotient } else { quotient };
+        Decimal::new(quotient, exponent + excess as i64)
+    }
+
+    pub fn round_decimal(self, value: &Decimal) -> Decimal {
+        self.round(value.mantissa.clone(), value.exponent, false)
+    }
+
+    // Context for intermediate results, with `extra` more guard digits.
+    fn guard(self, extra: usize) -> Context {
+        Context {
+            precision: self.precision + 10 + extra,
+            rounding: Rounding::HalfEven,
+        }
+    }
+
+    fn integer(self, value: i64) -> Decimal {
+        self.round(BigInt::from(value), 0, false)
+    }
+
+    fn add(self, left: &Decimal, right: &Decimal) -> Decimal {
+        if left.is_zero() || right.is_zero() {
+            let other = if left.is_zero() { right } else { left };
+            return self.round_decimal(other);
+        }
+        let (high, low) = if left.adjusted() >= right.adjusted() {
+            (left, right)
+        } else {
+            (right, left)
+        };
+        // A term far below the last digit kept only decides the rounding, so
+        // it is replaced by a smaller one of the same sign.
+        let extension = (self.precision + 3).saturating_sub(digit_count(&high.mantissa));
+        let last_exponent = high.exponent - extension as i64;
+        if low.adjusted() < last_exponent - 1 {
+            let mantissa = &high.mantissa * power_of_ten(extension + 1) + low.mantissa.signum();
+            return self.round(mantissa, last_exponent - 1, false);
+        }
+        let exponent = left.exponent.min(right.exponent);
+        let align = |value: &Decimal| &value.mantissa * power_of_ten((value.exponent - exponent) as usize);
+        self.round(align(left) + align(right), exponent, false)
+    }
+
+    fn sub(self, left: &Decimal, right: &Decimal) -> Decimal {
+        self.add(left, &right.neg())
+    }
+
+    pub fn mul(self, left: &Decimal, right: &Decimal) -> Decimal {
+        self.round(&left.mantissa * &right.mantissa, left.exponent + right.exponent, false)
+    }
+
+    // The divisor must not be zero.
+    pub fn div(self, left: &Decimal, right: &Decimal) -> Decimal {
+        let shift = (self.precision + 2 + digit_count(&right.mantissa))
+            .saturating_sub(digit_count(&left.mantissa));
+        let numerator = &left.mantissa * power_of_ten(shift);
+        let remainder = &numerator % &right.mantissa;
+        self.round(
+            numerator / &right.mantissa,
+            left.exponent - right.exponent - shift as i64,
+            !remainder.is_zero(),
+        )
+    }
+
+    // The argument must not be negative.
+    fn sqrt(self, value: &Decimal) -> Decimal {
+        if value.is_zero() {
+            return Decimal::zero();
+        }
+        let mut shift = (2 * (self.precision + 2)).saturating_sub(digit_count(&value.mantissa)) as i64;
+        if (value.exponent - shift) % 2 != 0 {
+            shift += 1;
+        }
+        let radicand = &value.mantissa * power_of_ten(shift as usize);
+        let root = radicand.sqrt();
+        let sticky = &root * &root != radicand;
+        self.round(root, (value.exponent - shift) / 2, sticky)
+    }
+
+    fn is_negligible(self, term: &Decimal, sum: &Decimal) -> bool {
+        term.is_zero() || term.adjusted() < sum.adjusted() - self.precision as i64 - 1
+    }
+
+    // x - x^3/3 + x^5/5 - ... for |x| well below 1, or the same series
+    // without alternating signs, which is atanh(x).
+    fn arctangent_series(self, x: &Decimal, alternating: bool) -> Decimal {
+        let square = self.mul(x, x);
+        let square = if alternating { square.neg() } else { square };
+        let mut power = x.clone();
+        let mut sum = x.clone();
+        let mut n = 1;
+        loop {
+            power = self.mul(&power, &square);
+            n += 2;
+            let term = self.div(&power, &self.integer(n));
+            if self.is_negligible(&term, &sum) {
+                return sum;
+            }
+            sum = self.add(&sum, &term);
+        }
+    }
+
+    pub fn pi(self) -> Decimal {
+        let w = self.guard(0);
+        let machin = w.sub(
+            &w.mul(&w.integer(16), &w.arctangent_series(&w.div(&w.integer(1), &w.integer(5)), true)),
+            &w.mul(&w.integer(4), &w.arctangent_series(&w.div(&w.integer(1), &w.integer(239)), true)),
+        );
+        self.round_decimal(&machin)
+    }
+
+    fn ln2(self) -> Decimal {
+        let w = self.guard(0);
+        let third = w.div(&w.integer(1), &w.integer(3));
+        self.round_decimal(&w.mul(&w.integer(2), &w.arctangent_series(&third, false)))
+    }
+
+    fn ln10(self) -> Decimal {
+        // ln 10 = 3 ln 2 + ln 1.25, with ln 1.25 = 2 atanh(1/9).
+        let w = self.guard(0);
+        let ninth = w.div(&w.integer(1), &w.integer(9));
+        let ln_five_fourths = w.mul(&w.integer(2), &w.arctangent_series(&ninth, false));
+        self.round_decimal(&w.add(&w.mul(&w.integer(3), &w.ln2()), &ln_five_fourths))
+    }
+
+    pub fn e(self) -> Decimal {
+        self.exp(&Decimal::from_integer(1)).unwrap_or_else(|_| Decimal::zero())
+    }
+
+    fn exp(self, x: &Decimal) -> Result<Decimal, String> {
+        if x.is_zero() {
+            return Ok(self.integer(1));
+        }
+        if x.adjusted() > 17 {
+            // Beyond the exponents a decimal can have.
+            return if x.is_negative() {
+                Ok(Decimal::zero())
+            } else {
+                Err(format!("Error: exp({}) is out of range.", x))
+            };
+        }
+        // exp(x) = exp(x / 2^k)^(2^k), with |x / 2^k| below 0.01.
+        let halvings = ((x.adjusted() + 3) * 10 / 3 + 1).max(0) as usize;
+        let w = self.guard(halvings / 3);
+        let reduced = w.div(x, &w.round(Pow::pow(BigInt::from(2), halvings), 0, false));
+        let mut term = w.integer(1);
+        let mut sum = w.integer(1);
+        let mut n = 0;
+        loop {
+            n += 1;
+            term = w.div(&w.mul(&term, &reduced), &w.integer(n));
+            if w.is_negligible(&term, &sum) {
+                break;
+            }
+            sum = w.add(&sum, &term);
+        }
+        for _ in 0..halvings {
+            sum = w.mul(&sum, &sum);
+        }
+        Ok(self.round_decimal(&sum))
+    }
+
+    // The argument must be positive.
+    fn ln(self, x: &Decimal) -> Decimal {
+        // x = m * 2^j * 10^k with m in [0.75, 1.5), and
+        // ln m = 2 atanh((m - 1) / (m + 1)).
+        let decimal_exponent = x.adjusted();
+        let w = self.guard(digit_count(&BigInt::from(decimal_exponent)));
+        let mut m = Decimal::new(x.mantissa.clone(), x.exponent - decimal_exponent);
+        let three_halves = Decimal::new(BigInt::from(15), -1);
+        let half = Decimal::new(BigInt::from(5), -1);
+        let mut halvings = 0;
+        while m.compare(&three_halves) != Ordering::Less {
+            m = w.mul(&m, &half);
+            halvings += 1;
+        }
+        let one = w.integer(1);
+        let ratio = w.div(&w.sub(&m, &one), &w.add(&m, &one));
+        let ln_m = w.mul(&w.integer(2), &w.arctangent_series(&ratio, false));
+        let result = w.add(
+            &w.add(&ln_m, &w.mul(&w.integer(halvings), &w.ln2())),
+            &w.mul(&w.integer(decimal_exponent), &w.ln10()),
+        );
+        self.round_decimal(&result)
+    }
+
+    // Both sine and cosine reduce the argument to [-pi, pi] first.
+    fn reduce_angle(self, x: &Decimal) -> (Context, Decimal) {
+        let w = self.guard(x.adjusted().max(0) as usize);
+        let two_pi = w.mul(&w.integer(2), &w.pi());
+        let turns = w.add(&w.div(x, &two_pi), &Decimal::new(BigInt::from(5), -1));
+        let turns = turns.to_integer(Rounding::Floor);
+        (w, w.sub(x, &w.mul(&turns, &two_pi)))
+    }
+
+    // sin when `odd`, else cos, by their Taylor series.
+    fn trigonometric_series(self, x: &Decimal, odd: bool) -> Decimal {
+        let negative_square = self.mul(x, x).neg();
+        let mut term = if odd { x.clone() } else { self.integer(1) };
+        let mut sum = term.clone();
+        let mut n = if odd { 1 } else { 0 };
+        loop {
+            term = self.div(&self.mul(&term, &negative_square), &self.integer((n + 1) * (n + 2)));
+            n += 2;
+            if self.is_negligible(&term, &sum) {
+                return sum;
+            }
+            sum = self.add(&sum, &term);
+        }
+    }
+
+    fn sin(self, x: &Decimal) -> Decimal {
+        let (w, reduced) = self.reduce_angle(x);
+        self.round_decimal(&w.trigonometric_series(&reduced, true))
+    }
+
+    fn cos(self, x: &Decimal) -> Decimal {
+        let (w, reduced) = self.reduce_angle(x);
+        self.round_decimal(&w.trigonometric_series(&reduced, false))
+    }
+
+    fn atan(self, x: &Decimal) -> Decimal {
+        if x.is_negative() {
+            return self.atan(&x.neg()).neg();
+        }
+        let w = self.guard(0);
+        let one = w.integer(1);
+        if x.compare(&one) == Ordering::Greater {
+            let half_pi = w.div(&w.pi(), &w.integer(2));
+            return self.round_decimal(&w.sub(&half_pi, &w.atan(&w.div(&one, x))));
+        }
+        // atan(x) = 2 atan(x / (1 + sqrt(1 + x^2))) until x is below 0.1.
+        let tenth = Decimal::new(BigInt::one(), -1);
+        let mut reduced = x.clone();
+        let mut doublings = 0;
+        while reduced.compare(&tenth) == Ordering::Greater {
+            let root = w.sqrt(&w.add(&one, &w.mul(&reduced, &reduced)));
+            reduced = w.div(&reduced, &w.add(&one, &root));
+            doublings += 1;
+        }
+        let series = w.arctangent_series(&reduced, true);
+        self.round_decimal(&w.mul(&series, &w.integer(1 << doublings)))
+    }
+
+    // Number of digits lost by subtracting nearly equal values for an
+    // argument close to zero, as in sinh(x) = (e^x - e^-x) / 2.
+    fn cancellation(x: &Decimal) -> usize {
+        (-x.adjusted()).max(0) as usize
+    }
+
+    /// Rounds a value just below 1, which gives 1 except when rounding
+    /// toward zero: the value of tanh for large arguments.
+    fn below_one(self) -> Decimal {
+        let digits = self.precision + 15;
+        self.round(power_of_ten(digits) - 1, -(digits as i64), false)
+    }
+
+    fn evaluate_function(self, function: FunctionOperator, x: &Decimal) -> Result<Decimal, String> {
+        let undefined = || format!("Error: {}({}) is not defined.", function_name(function), x);
+        let one = Decimal::from_integer(1);
+        let w = self.guard(Context::cancellation(x));
+        Ok(match function {
+            FunctionOperator::Sin => self.sin(x),
+            FunctionOperator::Cos => self.cos(x),
+            FunctionOperator::Tan => {
+                let cos = w.cos(x);
+                if cos.is_zero() {
+                    return Err(undefined());
+                }
+                self.round_decimal(&w.div(&w.sin(x), &cos))
+            }
+            FunctionOperator::ArcSin | FunctionOperator::ArcCos => {
+                if x.abs().compare(&one) == Ordering::Greater {
+                    return Err(undefined());
+                }
+                let one = w.integer(1);
+                if function == FunctionOperator::ArcSin {
+                    // asin(x) = atan(x / sqrt((1 - x)(1 + x))).
+                    let cosine = w.sqrt(&w.mul(&w.sub(&one, x), &w.add(&one, x)));
+                    if cosine.is_zero() {
+                        let half_pi = self.round_decimal(&w.div(&w.pi(), &w.integer(2)));
+                        if x.is_negative() { half_pi.neg() } else { half_pi }
+                    } else {
+                        self.round_decimal(&w.atan(&w.div(x, &cosine)))
+                    }
+                } else if x.compare(&one.neg()) == Ordering::Equal {
+                    self.pi()
+                } else {
+                    // acos(x) = 2 atan(sqrt((1 - x) / (1 + x))).
+                    let ratio = w.div(&w.sub(&one, x), &w.add(&one, x));
+                    self.round_decimal(&w.mul(&w.integer(2), &w.atan(&w.sqrt(&ratio))))
+                }
+            }
+            FunctionOperator::ArcTan => self.atan(x),
+            FunctionOperator::Sinh | FunctionOperator::Cosh => {
+                let exp = w.exp(x)?;
+                let inverse = w.div(&w.integer(1), &exp);
+                let sum = if function == FunctionOperator::Sinh {
+                    w.sub(&exp, &inverse)
+                } else {
+                    w.add(&exp, &inverse)
+                };
+                self.round_decimal(&w.div(&sum, &w.integer(2)))
+            }
+            FunctionOperator::Tanh => {
+                // Beyond 2 * precision, 1 - tanh(x) ~ 2e^-2x is below the last digit.
+                if x.abs().compare(&w.integer(2 * w.precision as i64)) == Ordering::Greater {
+                    let limit = self.below_one();
+                    if x.is_negative() { limit.neg() } else { limit }
+                } else {
+                    // tanh(x) = (e^2x - 1) / (e^2x + 1).
+                    let exp = w.exp(&w.mul(&w.integer(2), x))?;
+                    let one = w.integer(1);
+                    self.round_decimal(&w.div(&w.sub(&exp, &one), &w.add(&exp, &one)))
+                }
+            }
+            FunctionOperator::ArcSinh => {
+                // asinh(x) = ln(|x| + sqrt(x^2 + 1)), an odd function.
+                let magnitude = x.abs();
+                let root = w.sqrt(&w.add(&w.mul(x, x), &w.integer(1)));
+                let result = self.round_decimal(&w.ln(&w.add(&magnitude, &root)));
+                if x.is_negative() { result.neg() } else { result }
+            }
+            FunctionOperator::ArcCosh => {
+                if x.compare(&one) == Ordering::Less {
+                    return Err(undefined());
+                }
+                let root = w.sqrt(&w.sub(&w.mul(x, x), &w.integer(1)));
+                self.round_decimal(&w.ln(&w.add(x, &root)))
+            }
+            FunctionOperator::ArcTanh => {
+                if x.abs().compare(&one) != Ordering::Less {
+                    return Err(undefined());
+                }
+                let one = w.integer(1);
+                let ratio = w.div(&w.add(&one, x), &w.sub(&one, x));
+                self.round_decimal(&w.div(&w.ln(&ratio), &w.integer(2)))
+            }
+            FunctionOperator::Exp => self.exp(x)?,
+            FunctionOperator::Exp2 => self.power(&Decimal::from_integer(2), x)?,
+            FunctionOperator::Log | FunctionOperator::Log10 | FunctionOperator::Log2 => {
+                if x.is_zero() || x.is_negative() {
+                    return Err(undefined());
+                }
+                match function {
+                    FunctionOperator::Log => self.ln(x),
+                    // Powers of ten have an exact logarithm.
+                    FunctionOperator::Log10 if x.mantissa.is_one() => self.integer(x.exponent),
+                    FunctionOperator::Log10 => self.round_decimal(&w.div(&w.ln(x), &w.ln10())),
+                    _ => self.round_decimal(&w.div(&w.ln(x), &w.ln2())),
+                }
+            }
+            FunctionOperator::Abs => self.round_decimal(&x.abs()),
+            FunctionOperator::Ceil => self.round_decimal(&x.to_integer(Rounding::Ceiling)),
+            FunctionOperator::Floor => self.round_decimal(&x.to_integer(Rounding::Floor)),
+            FunctionOperator::Signum => self.integer(x.mantissa.signum().to_i64().unwrap_or(0)),
+            FunctionOperator::Sqrt => {
+                if x.is_negative() {
+                    return Err(undefined());
+                }
+                self.sqrt(x)
+            }
+            FunctionOperator::ToInt
+            | FunctionOperator::ToFloat
+            | FunctionOperator::Re
+            | FunctionOperator::Conj => self.round_decimal(x),
+            FunctionOperator::Im => Decimal::zero(),
+            FunctionOperator::Arg if x.is_negative() => self.pi(),
+            FunctionOperator::Arg => Decimal::zero(),
+        })
+    }
+
+    fn power(self, base: &Decimal, exponent: &Decimal) -> Result<Decimal, String> {
+        let not_real = || format!("Error: ({}) ^ ({}) is not a real number.", base, exponent);
+        if exponent.is_integer() && exponent.adjusted() < 6 {
+            let n = (&exponent.mantissa * power_of_ten(exponent.exponent as usize))
+                .to_i64()
+                .unwrap_or(0);
+            if base.is_zero() && n < 0 {
+                return Err("Error: Division by zero in `^`.".to_string());
+            }
+            // Binary exponentiation, losing a little accuracy at each squaring.
+            let w = self.guard(digit_count(&BigInt::from(n)));
+            let mut result = w.integer(1);
+            let mut square = base.clone();
+            let mut remaining = n.unsigned_abs();
+            while remaining > 0 {
+                if remaining % 2 == 1 {
+                    result = w.mul(&result, &square);
+                }
+                square = w.mul(&square, &square);
+                remaining /= 2;
+            }
+            if n < 0 {
+                result = w.div(&w.integer(1), &result);
+            }
+            return Ok(self.round_decimal(&result));
+        }
+        if base.is_negative() {
+            return Err(not_real());
+        }
+        if base.is_zero() {
+            return if exponent.is_negative() {
+                Err("Error: Division by zero in `^`.".to_string())
+            } else {
+                Ok(Decimal::zero())
+            };
+        }
+        // x^y = exp(y ln x), where the error of ln x grows with |y ln x|.
+        let w = self.guard(
+            exponent.adjusted().max(0) as usize + digit_count(&BigInt::from(base.adjusted())) + 1,
+        );
+        let product = w.mul(exponent, &w.ln(base));
+        self.exp(&product)
+    }
+
+    fn check_divisor(divisor: &Decimal, operator_name: &str) -> Result<(), String> {
+        if divisor.is_zero() {
+            Err(format!("Error: Division by zero in `{}`.", operator_name))
+        } else {
+            Ok(())
+        }
+    }
+
+    // Exact quotient and remainder, the remainder lying in `[0, |right|)`.
+    fn div_euclid(left: &Decimal, right: &Decimal) -> (Decimal, Decimal) {
+        let exponent = left.exponent.min(right.exponent);
+        let align = |value: &Decimal| &value.mantissa * power_of_ten((value.exponent - exponent) as usize);
+        let (numerator, denominator) = (align(left), align(right));
+        let mut quotient = &numerator / &denominator;
+        let mut remainder = &numerator % &denominator;
+        if remainder.is_negative() {
+            if denominator.is_negative() {
+                quotient += 1;
+                remainder -= &denominator;
+            } else {
+                quotient -= 1;
+                remainder += &denominator;
+            }
+        }
+        (Decimal::new(quotient, 0), Decimal::new(remainder, exponent))
+    }
+
+    pub fn apply_term_operator(
+        self,
+        operator: TermOperator,
+        left: &Decimal,
+        right: &Decimal,
+    ) -> Result<Decimal, String> {
+        match operator {
+            TermOperator::Multiply => Ok(self.mul(left, right)),
+            TermOperator::Divide => {
+                Context::check_divisor(right, "/")?;
+                Ok(self.div(left, right))
+            }
+            TermOperator::IntegerDivide => {
+                Context::check_divisor(right, "div")?;
+                Ok(self.round_decimal(&Context::div_euclid(left, right).0))
+            }
+            TermOperator::Exponent => self.power(left, right),
+            TermOperator::MatrixMultiply => unreachable!("`@` only applies to lists and matrices"),
+        }
+    }
+
+    pub fn apply_expr_operator(
+        self,
+        operator: ExprOperator,
+        left: &Decimal,
+        right: &Decimal,
+    ) -> Result<Decimal, String> {
+        match operator {
+            ExprOperator::Add => Ok(self.add(left, right)),
+            ExprOperator::Subtract => Ok(self.sub(left, right)),
+            ExprOperator::Modulo => {
+                Context::check_divisor(right, "mod")?;
+                Ok(self.round_decimal(&Context::div_euclid(left, right).1))
+            }
+            // The executor makes a measured value of `x +- u`, whose nominal
+            // value is `x`.
+            ExprOperator::PlusMinus => Ok(left.clone()),
+        }
+    }
+
+    pub fn apply_function(self, function: FunctionOperator, argument: &Decimal) -> Result<Value, String> {
+        if function != FunctionOperator::ToInt {
+            return self.evaluate_function(function, argument).map(Value::Decimal);
+        }
+        let truncated = argument.to_integer(Rounding::Down);
+        let integer = if truncated.adjusted() < 19 {
+            (truncated.mantissa * power_of_ten(truncated.exponent as usize)).to_i64()
+        } else {
+            None
+        };
+        integer
+            .map(Value::Int)
+            .ok_or_else(|| format!("Error: Cannot convert {} to an int.", argument))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context(precision: usize, rounding: Rounding) -> Context {
+        Context { precision, rounding }
+    }
+
+    fn decimal(text: &str) -> Decimal {
+        Decimal::parse(text).unwrap()
+    }
+
+    fn rounded(text: &str, precision: usize, rounding: Rounding) -> String {
+        context(precision, rounding).round_decimal(&decimal(text)).to_string()
+    }
+
+    fn function(function: FunctionOperator, argument: &str, precision: usize) -> String {
+        match context(precision, Rounding::HalfEven).apply_function(function, &decimal(argument)) {
+            Ok(Value::Decimal(value)) => value.to_string(),
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn rounding_modes() {
+        let cases = [
+            (Rounding::HalfEven, ["1.2", "1.2", "-1.2", "1.3"]),
+            (Rounding::HalfUp, ["1.3", "1.2", "-1.3", "1.3"]),
+            (Rounding::Down, ["1.2", "1.2", "-1.2", "1.2"]),
+            (Rounding::Up, ["1.3", "1.3", "-1.3", "1.3"]),
+            (Rounding::Floor, ["1.2", "1.2", "-1.3", "1.2"]),
+            (Rounding::Ceiling, ["1.3", "1.3", "-1.2", "1.3"]),
+        ];
+        for (rounding, expected) in cases.iter() {
+            let actual = ["1.25", "1.21", "-1.25", "1.251"]
+                .iter()
+                .map(|text| rounded(text, 2, *rounding))
+                .collect::<Vec<_>>();
+            assert_eq!(actual, expected, "{:?}", rounding);
+        }
+        assert_eq!(rounded("1.35", 2, Rounding::HalfEven), "1.4");
+        assert_eq!(rounded("999.5", 3, Rounding::HalfUp), "1000");
+    }
+
+    #[test]
+    fn division_rounds_the_exact_quotient() {
+        let half_even = context(10, Rounding::HalfEven);
+        assert_eq!(half_even.div(&decimal("1"), &decimal("3")).to_string(), "0.3333333333");
+        assert_eq!(half_even.div(&decimal("2"), &decimal("3")).to_string(), "0.6666666667");
+        assert_eq!(half_even.div(&decimal("1"), &decimal("8")).to_string(), "0.125");
+        // The remainder makes the quotient more than half way.
+        let half_even = context(1, Rounding::HalfEven);
+        assert_eq!(half_even.div(&decimal("2.5000001"), &decimal("1")).to_string(), "3");
+        let down = context(10, Rounding::Down);
+        assert_eq!(down.div(&decimal("2"), &decimal("3")).to_string(), "0.6666666666");
+        let floor = context(3, Rounding::Floor);
+        assert_eq!(floor.div(&decimal("-1"), &decimal("3")).to_string(), "-0.334");
+    }
+
+    #[test]
+    fn functions_match_known_digits() {
+        assert_eq!(
+            context(50, Rounding::HalfEven).pi().to_string(),
+            "3.1415926535897932384626433832795028841971693993751"
+        );
+        assert_eq!(
+            context(50, Rounding::HalfEven).e().to_string(),
+            "2.7182818284590452353602874713526624977572470937"
+        );
+        assert_eq!(
+            function(FunctionOperator::Sqrt, "2", 40),
+            "1.41421356237309504880168872420969807857"
+        );
+        assert_eq!(function(FunctionOperator::Sqrt, "1.44", 40), "1.2");
+        assert_eq!(
+            function(FunctionOperator::Log, "2", 40),
+            "0.6931471805599453094172321214581765680755"
+        );
+        assert_eq!(
+            function(FunctionOperator::Exp, "1", 40),
+            "2.718281828459045235360287471352662497757"
+        );
+        assert_eq!(
+            function(FunctionOperator::Sin, "1", 30),
+            "0.84147098480789650665250232163"
+        );
+    }
+}
@@ -0,0 +1,136 @@
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Lint {
+    UnusedVariable,
+    DeadStore,
+    UninitializedRead,
+    ConstantOutput,
+    DomainError,
+    DivisionByZero,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum LintLevel {
+    Allow,
+    Warn,
+    Deny,
+}
+
+const ALL_LINTS: [Lint; 6] = [
+    Lint::UnusedVariable,
+    Lint::DeadStore,
+    Lint::UninitializedRead,
+    Lint::ConstantOutput,
+    Lint::DomainError,
+    Lint::DivisionByZero,
+];
+
+impl Lint {
+    pub fn name(self) -> &'static str {
+        match self {
+            Lint::UnusedVariable => "unused-variable",
+            Lint::DeadStore => "dead-store",
+            Lint::UninitializedRead => "uninitialized-read",
+            Lint::ConstantOutput => "constant-output",
+            Lint::DomainError => "domain-error",
+            Lint::DivisionByZero => "division-by-zero",
+        }
+    }
+    /// Reading a variable before assigning it is an error unless relaxed
+    /// with `--warn uninitialized-read` for older programs relying on the
+    /// implicit 0.
+    pub fn default_level(self) -> LintLevel {
+        match self {
+            Lint::UninitializedRead => LintLevel::Deny,
+            _ => LintLevel::Warn,
+        }
+    }
+    pub fn from_name(name: &str) -> Result<Lint, String> {
+        ALL_LINTS
+            .iter()
+            .find(|lint| lint.name() == name)
+            .copied()
+            .ok_or_else(|| {
+                let names: Vec<&str> = ALL_LINTS.iter().map(|lint| lint.name()).collect();
+                format!("Error: Unknown lint '{}'. Lints are: {}.", name, names.join(", "))
+            })
+    }
+}
+
+#[derive(Debug)]
+pub struct Diagnostic {
+    pub lint: Lint,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn new(lint: Lint, message: String) -> Diagnostic {
+        Diagnostic { lint, message }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LintLevels {
+    levels: Vec<(Lint, LintLevel)>,
+}
+
+impl LintLevels {
+    pub fn new() -> LintLevels {
+        LintLevels {
+            levels: ALL_LINTS.iter().map(|lint| (*lint, lint.default_level())).collect(),
+        }
+    }
+    pub fn get(&self, lint: Lint) -> LintLevel {
+        self.levels
+            .iter()
+            .find(|item| item.0 == lint)
+            .map(|item| item.1)
+            .unwrap_or_else(|| lint.default_level())
+    }
+    pub fn set(&mut self, lint: Lint, level: LintLevel) {
+        for item in self.levels.iter_mut() {
+            if item.0 == lint {
+                item.1 = level;
+            }
+        }
+    }
+    /// Applies a command line flag such as `--deny dead-store`. The name
+    /// `all` sets every lint at once.
+    pub fn set_from_flag(&mut self, flag: &str, name: &str) -> Result<(), String> {
+        let level = match flag {
+            "--allow" => LintLevel::Allow,
+            "--warn" => LintLevel::Warn,
+            "--deny" => LintLevel::Deny,
+            _ => return Err(format!("Error: Unknown lint flag '{}'.", flag)),
+        };
+        if name == "all" {
+            for lint in ALL_LINTS.iter() {
+                self.set(*lint, level);
+            }
+        } else {
+            self.set(Lint::from_name(name)?, level);
+        }
+        Ok(())
+    }
+}
+
+/// Prints the diagnostics that are not allowed, and fails if any of them is denied.
+pub fn report(levels: &LintLevels, diagnostics: &[Diagnostic]) -> Result<(), String> {
+    let mut denied = 0;
+    for diagnostic in diagnostics {
+        match levels.get(diagnostic.lint) {
+            LintLevel::Allow => {}
+            LintLevel::Warn => {
+                eprintln!("warning: {} [{}]", diagnostic.message, diagnostic.lint.name())
+            }
+            LintLevel::Deny => {
+                eprintln!("error: {} [{}]", diagnostic.message, diagnostic.lint.name());
+                denied += 1;
+            }
+        }
+    }
+    if denied > 0 {
+        Err(format!("Error: {} denied diagnostic(s).", denied))
+    } else {
+        Ok(())
+    }
+}
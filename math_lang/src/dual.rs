@@ -0,0 +1,207 @@
+// Dual numbers for forward-mode automatic differentiation: a value along
+// with its derivative with respect to one variable, for `diff` and `grad`,
+// and for the uncertainty of functions of measured values.
+//
+// Where a function has no derivative, a subgradient is used: `abs` has the
+// derivative 0 at 0, and `ceil`, `floor` and `sgn` have the derivative 0
+// everywhere, their jumps included. A derivative multiplied by a zero
+// derivative is 0, even if it is infinite, so that constants such as
+// `sqrt(0)` never make a derivative NaN.
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Dual {
+    pub value: f64,
+    pub derivative: f64,
+}
+
+fn scaled(derivative: f64, factor: f64) -> f64 {
+    if derivative == 0. {
+        0.
+    } else {
+        derivative * factor
+    }
+}
+
+impl std::ops::Add for Dual {
+    type Output = Dual;
+    fn add(self, other: Dual) -> Dual {
+        Dual {
+            value: self.value + other.value,
+            derivative: self.derivative + other.derivative,
+        }
+    }
+}
+
+impl std::ops::Sub for Dual {
+    type Output = Dual;
+    fn sub(self, other: Dual) -> Dual {
+        Dual {
+            value: self.value - other.value,
+            derivative: self.derivative - other.derivative,
+        }
+    }
+}
+
+impl std::ops::Mul for Dual {
+    type Output = Dual;
+    fn mul(self, other: Dual) -> Dual {
+        Dual {
+            value: self.value * other.value,
+            derivative: scaled(self.derivative, other.value) + scaled(other.derivative, self.value),
+        }
+    }
+}
+
+impl std::ops::Div for Dual {
+    type Output = Dual;
+    fn div(self, other: Dual) -> Dual {
+        let value = self.value / other.value;
+        Dual {
+            value,
+            derivative: scaled(self.derivative, 1. / other.value)
+                - scaled(other.derivative, value / other.value),
+        }
+    }
+}
+
+// The methods have the names of their f64 counterparts, and programs
+// emitted by `compiler` may only use some of them.
+impl Dual {
+    pub fn constant(value: f64) -> Dual {
+        Dual { value, derivative: 0. }
+    }
+
+    /// The variable of differentiation, whose derivative is 1.
+    pub fn variable(value: f64) -> Dual {
+        Dual { value, derivative: 1. }
+    }
+
+    // f(self), given f and its derivative at the value of `self`.
+    fn chain(self, value: f64, derivative: f64) -> Dual {
+        Dual {
+            value,
+            derivative: scaled(self.derivative, derivative),
+        }
+    }
+
+    pub fn powf(self, exponent: Dual) -> Dual {
+        let (a, b) = (self.value, exponent.value);
+        let value = a.powf(b);
+        Dual {
+            value,
+            derivative: scaled(self.derivative, b * a.powf(b - 1.))
+                + scaled(exponent.derivative, value * a.ln()),
+        }
+    }
+
+    pub fn div_euclid(self, other: Dual) -> Dual {
+        Dual::constant(self.value.div_euclid(other.value))
+    }
+
+    pub fn rem_euclid(self, other: Dual) -> Dual {
+        Dual {
+            value: self.value.rem_euclid(other.value),
+            derivative: self.derivative
+                - scaled(other.derivative, self.value.div_euclid(other.value)),
+        }
+    }
+
+    pub fn sin(self) -> Dual {
+        self.chain(self.value.sin(), self.value.cos())
+    }
+
+    pub fn cos(self) -> Dual {
+        self.chain(self.value.cos(), -self.value.sin())
+    }
+
+    pub fn tan(self) -> Dual {
+        let value = self.value.tan();
+        self.chain(value, 1. + value * value)
+    }
+
+    pub fn asin(self) -> Dual {
+        self.chain(self.value.asin(), 1. / (1. - self.value * self.value).sqrt())
+    }
+
+    pub fn acos(self) -> Dual {
+        self.chain(self.value.acos(), -1. / (1. - self.value * self.value).sqrt())
+    }
+
+    pub fn atan(self) -> Dual {
+        self.chain(self.value.atan(), 1. / (1. + self.value * self.value))
+    }
+
+    pub fn sinh(self) -> Dual {
+        self.chain(self.value.sinh(), self.value.cosh())
+    }
+
+    pub fn cosh(self) -> Dual {
+        self.chain(self.value.cosh(), self.value.sinh())
+    }
+
+    pub fn tanh(self) -> Dual {
+        let value = self.value.tanh();
+        self.chain(value, 1. - value * value)
+    }
+
+    pub fn asinh(self) -> Dual {
+        self.chain(self.value.asinh(), 1. / (self.value * self.value + 1.).sqrt())
+    }
+
+    pub fn acosh(self) -> Dual {
+        self.chain(self.value.acosh(), 1. / (self.value * self.value - 1.).sqrt())
+    }
+
+    pub fn atanh(self) -> Dual {
+        self.chain(self.value.atanh(), 1. / (1. - self.value * self.value))
+    }
+
+    pub fn exp(self) -> Dual {
+        let value = self.value.exp();
+        self.chain(value, value)
+    }
+
+    pub fn exp2(self) -> Dual {
+        let value = self.value.exp2();
+        self.chain(value, value * std::f64::consts::LN_2)
+    }
+
+    pub fn ln(self) -> Dual {
+        self.chain(self.value.ln(), 1. / self.value)
+    }
+
+    pub fn log10(self) -> Dual {
+        self.chain(self.value.log10(), 1. / (self.value * std::f64::consts::LN_10))
+    }
+
+    pub fn log2(self) -> Dual {
+        self.chain(self.value.log2(), 1. / (self.value * std::f64::consts::LN_2))
+    }
+
+    pub fn sqrt(self) -> Dual {
+        let value = self.value.sqrt();
+        self.chain(value, 0.5 / value)
+    }
+
+    pub fn abs(self) -> Dual {
+        let slope = if self.value == 0. { 0. } else { self.value.signum() };
+        self.chain(self.value.abs(), slope)
+    }
+
+    pub fn ceil(self) -> Dual {
+        Dual::constant(self.value.ceil())
+    }
+
+    pub fn floor(self) -> Dual {
+        Dual::constant(self.value.floor())
+    }
+
+    pub fn signum(self) -> Dual {
+        Dual::constant(self.value.signum())
+    }
+
+    // The argument of a real number, 0 or pi.
+    pub fn arg(self) -> Dual {
+        Dual::constant(0f64.atan2(self.value))
+    }
+}
@@ -1,99 +1,1170 @@
 use crate::analyzer::{
-    AnalyzedFunctionExpr, AnalyzedExpr, AnalyzedFactor, AnalyzedProgram, AnalyzedStatement, AnalyzedTerm,
+    expr_type, factor_type, term_type, AnalyzedFunctionExpr, AnalyzedExpr, AnalyzedFactor,
+    AnalyzedIntegral, AnalyzedMatrixFunction, AnalyzedNumberTheoryFunction, AnalyzedPolynomialFunction, AnalyzedRandom, AnalyzedProgram, AnalyzedSeries,
+    AnalyzedSolve, AnalyzedStatement, AnalyzedStatistic, AnalyzedTerm,
 };
-use crate::parser::{ExprOperator, TermOperator, FunctionOperator};
+use crate::complex::Complex;
+use crate::decimal::Rounding;
+use crate::dual::Dual;
+use crate::interval;
+use crate::linear_algebra;
+use crate::number_format::{self, Format};
+use crate::number_theory;
+use crate::numeric::Backend;
+use crate::parser::{
+    ExprOperator, TermOperator, FunctionOperator, MatrixOperator, NumberTheoryOperator, PolynomialOperator, RandomOperator,
+    SeriesOperator, StatisticOperator,
+};
+use crate::polynomial::Polynomial;
+use crate::quadrature;
+use crate::random;
+use crate::rational;
+use crate::roots;
+use crate::statistics;
+use crate::summation::Neumaier;
 use crate::symbol_table::SymbolTable;
+use crate::symbolic::derive_source;
+use crate::uncertain::Uncertain;
+use crate::units::{Unit, DIMENSIONLESS};
+use crate::unparser::{function_name, number_theory_name, series_name};
+use crate::value::{float_to_int, Type, Value};
 
 
-fn evaluate_factor(variables: &SymbolTable, factor: &AnalyzedFactor) -> f64 {
+pub fn evaluate_factor(variables: &mut SymbolTable, factor: &AnalyzedFactor) -> Result<Value, String> {
     match factor {
-        AnalyzedFactor::Literal(value) => *value,
-        AnalyzedFactor::Identifier(handle) => variables.get_value(*handle),
+        AnalyzedFactor::Literal(value) => Ok(value.clone()),
+        AnalyzedFactor::Identifier(handle) => Ok(variables.get_value(*handle)),
         AnalyzedFactor::FunctionExpression(f_expr) => evaluate_function_expr(variables,f_expr),
         AnalyzedFactor::SubExpression(expr) => evaluate_expr(variables, expr),
-        
+        AnalyzedFactor::Derivative(expr, handle) => {
+            Ok(Value::Float(evaluate_dual_expr(variables, expr, *handle)?.derivative))
+        }
+        AnalyzedFactor::Integral(integral) => evaluate_integral(variables, integral),
+        AnalyzedFactor::Series(series) => evaluate_series(variables, series),
+        AnalyzedFactor::Statistic(statistic) => evaluate_statistic(variables, statistic),
+        AnalyzedFactor::MatrixFunction(function) => evaluate_matrix_function(variables, function),
+        AnalyzedFactor::Polynomial(body, handle) => {
+            variables.set_value(*handle, Value::Polynomial(Polynomial::variable()));
+            Ok(Value::Polynomial(to_polynomial(evaluate_expr(variables, body)?)))
+        }
+        AnalyzedFactor::PolynomialFunction(function) => evaluate_polynomial_function(variables, function),
+        AnalyzedFactor::NumberTheoryFunction(function) => evaluate_number_theory_function(variables, function),
+        AnalyzedFactor::Random(random) => evaluate_random(variables, random),
+        AnalyzedFactor::Matrix(rows) => {
+            let rows = rows
+                .iter()
+                .map(|row| evaluate_list(variables, row))
+                .collect::<Result<Vec<_>, _>>()?;
+            linear_algebra::check_rows(&rows)?;
+            Ok(Value::Matrix(rows))
+        }
+        AnalyzedFactor::List(elements) => elements
+            .iter()
+            .map(|element| evaluate_expr(variables, element))
+            .collect::<Result<_, _>>()
+            .map(Value::List),
+        AnalyzedFactor::Range(lo, hi) => evaluate_range(variables, lo, hi),
+        AnalyzedFactor::Index(list, index) => {
+            let elements = evaluate_list(variables, list)?;
+            match evaluate_expr(variables, index)? {
+                Value::Int(index) => Ok(elements[list_index(index, elements.len())?].clone()),
+                _ => unreachable!("the analyzer checks that lists are indexed by ints"),
+            }
+        }
+        AnalyzedFactor::Length(list) => Ok(Value::Int(evaluate_list(variables, list)?.len() as i64)),
     }
 }
 
-fn evaluate_term(variables: &SymbolTable, term: &AnalyzedTerm) -> f64 {
-    let mut result = evaluate_factor(variables, &term.0);
+fn evaluate_term(variables: &mut SymbolTable, term: &AnalyzedTerm) -> Result<Value, String> {
+    let mut result = evaluate_factor(variables, &term.0)?;
     for factor in &term.1 {
-        match factor.0 {
-            TermOperator::Multiply => result *= evaluate_factor(variables, &factor.1),
-            TermOperator::Divide => result /= evaluate_factor(variables, &factor.1),
-            TermOperator::Exponent => result = result.powf(evaluate_factor(variables, &factor.1)),
-        }
+        result = apply_term_operator(
+            variables.backend(),
+            factor.0,
+            result,
+            evaluate_factor(variables, &factor.1)?,
+        )?;
     }
-    result
+    Ok(result)
 }
 
-fn evaluate_expr(variables: &SymbolTable, expr: &AnalyzedExpr) -> f64 {
-    let mut result = evaluate_term(variables, &expr.0);
+pub fn evaluate_expr(variables: &mut SymbolTable, expr: &AnalyzedExpr) -> Result<Value, String> {
+    let mut result = evaluate_term(variables, &expr.0)?;
     for term in &expr.1 {
-        match term.0 {
-            ExprOperator::Add => result += evaluate_term(variables, &term.1),
-            ExprOperator::Subtract => result -= evaluate_term(variables, &term.1),
-            ExprOperator::Modulo => result %= evaluate_term(variables, &term.1),
+        result = apply_expr_operator(
+            variables.backend(),
+            term.0,
+            result,
+            evaluate_term(variables, &term.1)?,
+        )?;
+    }
+    Ok(result)
+}
+
+fn evaluate_function_expr(variables: &mut SymbolTable, function_expr: &AnalyzedFunctionExpr) -> Result<Value, String> {
+    apply_function(
+        variables.backend(),
+        function_expr.0,
+        evaluate_expr(variables, &function_expr.1)?,
+    )
+}
+
+// The elements of a list, or the rows of a matrix.
+fn evaluate_list(variables: &mut SymbolTable, expr: &AnalyzedExpr) -> Result<Vec<Value>, String> {
+    match evaluate_expr(variables, expr)? {
+        Value::List(elements) => Ok(elements),
+        Value::Matrix(rows) => Ok(rows.into_iter().map(Value::List).collect()),
+        _ => unreachable!("the analyzer checks that the expression is a list or a matrix"),
+    }
+}
+
+// The elements are computed by the backend from the first one, so that they
+// are exact with `--rational`.
+fn evaluate_range(variables: &mut SymbolTable, lo: &AnalyzedExpr, hi: &AnalyzedExpr) -> Result<Value, String> {
+    let backend = variables.backend();
+    let lo = backend.to_float(evaluate_expr(variables, lo)?);
+    let hi = evaluate_expr(variables, hi)?.as_float();
+    (0..range_length(lo.as_float(), hi)?)
+        .map(|step| apply_expr_operator(backend, ExprOperator::Add, lo.clone(), backend.float(step as f64)))
+        .collect::<Result<_, _>>()
+        .map(Value::List)
+}
+
+/// The most elements of `lo..hi`, so that a mistyped bound is an error
+/// rather than a list filling the memory.
+pub const MAX_RANGE_LENGTH: usize = 1_000_000;
+
+/// The number of elements of `lo..hi`.
+pub fn range_length(lo: f64, hi: f64) -> Result<usize, String> {
+    if !lo.is_finite() || !hi.is_finite() {
+        Err(format!("Error: The bounds of a range must be finite, not {} and {}.", lo, hi))
+    } else if hi < lo {
+        Ok(0)
+    } else if hi - lo >= MAX_RANGE_LENGTH as f64 {
+        Err(format!(
+            "Error: The range {}..{} has more than {} elements.",
+            lo, hi, MAX_RANGE_LENGTH
+        ))
+    } else {
+        Ok(float_to_int((hi - lo).floor())? as usize + 1)
+    }
+}
+
+/// The position in a list of `length` elements of the element at `index`,
+/// counted from 1.
+pub fn list_index(index: i64, length: usize) -> Result<usize, String> {
+    if index >= 1 && index as u64 <= length as u64 {
+        Ok(index as usize - 1)
+    } else {
+        Err(format!(
+            "Error: Index {} is out of range for a list of length {}.",
+            index, length
+        ))
+    }
+}
+
+// The values of the arguments, with lists expanded.
+fn evaluate_values(variables: &mut SymbolTable, arguments: &[AnalyzedExpr]) -> Result<Vec<f64>, String> {
+    let mut values = Vec::new();
+    for argument in arguments {
+        match evaluate_expr(variables, argument)? {
+            Value::List(elements) => values.extend(elements.iter().map(Value::as_float)),
+            value => values.push(value.as_float()),
         }
     }
-    result
+    Ok(values)
+}
+
+// Statistics are computed in f64 whatever the backend, and are f64 floats
+// rather than exact values or enclosures.
+fn evaluate_statistic(variables: &mut SymbolTable, statistic: &AnalyzedStatistic) -> Result<Value, String> {
+    let (operator, arguments) = statistic;
+    let result = match operator {
+        StatisticOperator::Count => return Ok(Value::Int(evaluate_values(variables, arguments)?.len() as i64)),
+        StatisticOperator::Covariance | StatisticOperator::PopulationCovariance | StatisticOperator::Correlation => {
+            let x = evaluate_values(variables, &arguments[..1])?;
+            let y = evaluate_values(variables, &arguments[1..])?;
+            match operator {
+                StatisticOperator::Covariance => statistics::covariance(&x, &y, true)?,
+                StatisticOperator::PopulationCovariance => statistics::covariance(&x, &y, false)?,
+                _ => statistics::correlation(&x, &y)?,
+            }
+        }
+        StatisticOperator::Percentile => {
+            let (percent, data) = arguments.split_last().unwrap();
+            let percent = evaluate_expr(variables, percent)?.as_float();
+            statistics::percentile(&evaluate_values(variables, data)?, percent)?
+        }
+        _ => {
+            let data = evaluate_values(variables, arguments)?;
+            match operator {
+                StatisticOperator::Mean => statistics::mean(&data)?,
+                StatisticOperator::Median => statistics::percentile(&data, 50.)?,
+                StatisticOperator::StandardDeviation => statistics::variance(&data, true)?.sqrt(),
+                StatisticOperator::PopulationStandardDeviation => statistics::variance(&data, false)?.sqrt(),
+                StatisticOperator::Variance => statistics::variance(&data, true)?,
+                StatisticOperator::PopulationVariance => statistics::variance(&data, false)?,
+                StatisticOperator::Min => statistics::min(&data)?,
+                StatisticOperator::Max => statistics::max(&data)?,
+                _ => statistics::sum(&data),
+            }
+        }
+    };
+    Ok(Value::Float(result))
+}
+
+fn to_floats(values: &[Value]) -> Vec<f64> {
+    values.iter().map(Value::as_float).collect()
+}
+
+fn to_float_matrix(value: &Value) -> Vec<Vec<f64>> {
+    match value {
+        Value::Matrix(rows) => rows.iter().map(|row| to_floats(row)).collect(),
+        Value::List(elements) => elements.iter().map(|element| vec![element.as_float()]).collect(),
+        _ => unreachable!("the analyzer checks that the value is a matrix or a list"),
+    }
+}
+
+// Results computed in f64 stay f64 floats, since under the other backends
+// they would pass for exact values or enclosures.
+fn from_float_matrix(rows: Vec<Vec<f64>>) -> Value {
+    Value::Matrix(rows.into_iter().map(|row| row.into_iter().map(Value::Float).collect()).collect())
+}
+
+fn from_floats(values: Vec<f64>) -> Value {
+    Value::List(values.into_iter().map(Value::Float).collect())
 }
 
-fn evaluate_function_expr(variables: &SymbolTable, function_expr: &AnalyzedFunctionExpr) -> f64 {
-    let mut result = evaluate_expr(variables, &function_expr.1);
-    match function_expr.0 {
-            FunctionOperator::Sin => {result = result.sin()},
-            FunctionOperator::Cos => {result = result.cos()},
-            FunctionOperator::Tan => {result = result.tan()},
-            FunctionOperator::ArcSin => {result = result.asin()},
-            FunctionOperator::ArcCos => {result = result.acos()},
-            FunctionOperator::ArcTan => {result = result.atan()},
-            FunctionOperator::Sinh => {result = result.sinh()},
-            FunctionOperator::Cosh => {result = result.cosh()},
-            FunctionOperator::Tanh => {result = result.tanh()},
-            FunctionOperator::ArcSinh => {result = result.asinh()},
-            FunctionOperator::ArcCosh => {result = result.acosh()},
-            FunctionOperator::ArcTanh => {result = result.atanh()},
-            FunctionOperator::Exp2 => {result = result.exp2()},
-            FunctionOperator::Exp => {result = result.exp()},
-            FunctionOperator::Log => {result = result.ln()},
-            FunctionOperator::Log10 => {result = result.log10()},
-            FunctionOperator::Log2 => {result = result.log2()},
-            FunctionOperator::Abs => {result = result.abs()},
-            FunctionOperator::Ceil => {result = result.ceil()},
-            FunctionOperator::Floor => {result = result.floor()},
-            FunctionOperator::Signum => {result = result.signum()},
-            FunctionOperator::Sqrt => {result = result.sqrt()},
+// Linear algebra is computed in f64 whatever the backend, except that
+// transposing keeps the elements. A list is solved for as a column.
+fn evaluate_matrix_function(variables: &mut SymbolTable, function: &AnalyzedMatrixFunction) -> Result<Value, String> {
+    let (operator, arguments) = function;
+    let arguments = arguments
+        .iter()
+        .map(|argument| evaluate_expr(variables, argument))
+        .collect::<Result<Vec<_>, _>>()?;
+    match operator {
+        MatrixOperator::Transpose => match &arguments[0] {
+            Value::Matrix(rows) => Ok(Value::Matrix(linear_algebra::transpose(rows))),
+            _ => unreachable!("the analyzer checks that transpose takes a matrix"),
+        },
+        MatrixOperator::Determinant => Ok(Value::Float(linear_algebra::determinant(&to_float_matrix(&arguments[0]))?)),
+        MatrixOperator::Inverse => Ok(from_float_matrix(linear_algebra::inverse(&to_float_matrix(&arguments[0]))?)),
+        MatrixOperator::Solve => {
+            let solution = linear_algebra::solve(&to_float_matrix(&arguments[0]), &to_float_matrix(&arguments[1]))?;
+            match arguments[1] {
+                Value::List(_) => Ok(from_floats(solution.concat())),
+                _ => Ok(from_float_matrix(solution)),
+            }
+        }
+        MatrixOperator::Identity => Ok(from_float_matrix(linear_algebra::identity(arguments[0].as_float())?)),
+        MatrixOperator::Zeros => Ok(from_float_matrix(linear_algebra::zeros(
+            arguments[0].as_float(),
+            arguments[1].as_float(),
+        )?)),
+    }
+}
+
+// `@` is computed in f64 whatever the backend. A list is a row on the left
+// and a column on the right, and two lists give their dot product.
+fn multiply_matrices(left: Value, right: Value) -> Result<Value, String> {
+    match (&left, &right) {
+        (Value::List(left), Value::List(right)) => {
+            Ok(Value::Float(linear_algebra::inner_product(&to_floats(left), &to_floats(right))?))
+        }
+        (Value::List(row), _) => {
+            let product = linear_algebra::multiply(&[to_floats(row)], &to_float_matrix(&right))?;
+            Ok(from_floats(product.concat()))
+        }
+        (_, Value::List(_)) => {
+            let product = linear_algebra::multiply(&to_float_matrix(&left), &to_float_matrix(&right))?;
+            Ok(from_floats(product.concat()))
+        }
+        _ => Ok(from_float_matrix(linear_algebra::multiply(&to_float_matrix(&left), &to_float_matrix(&right))?)),
     }
+}
+
+// Polynomials have f64 coefficients whatever the backend, and their values
+// and roots are computed in f64 and stay f64 floats.
+fn evaluate_polynomial_function(
+    variables: &mut SymbolTable,
+    function: &AnalyzedPolynomialFunction,
+) -> Result<Value, String> {
+    let (operator, arguments) = function;
+    if *operator == PolynomialOperator::Coefficients {
+        return Ok(Value::Polynomial(Polynomial::from_highest(&evaluate_values(variables, arguments)?)));
+    }
+    let polynomial = to_polynomial(evaluate_expr(variables, &arguments[0])?);
+    match operator {
+        PolynomialOperator::Evaluate => match evaluate_expr(variables, &arguments[1])? {
+            Value::List(points) => Ok(from_floats(polynomial.eval_each(to_floats(&points)))),
+            point => Ok(Value::Float(polynomial.eval(point.as_float()))),
+        },
+        PolynomialOperator::Roots => Ok(from_floats(polynomial.roots()?)),
+        PolynomialOperator::Derivative => Ok(Value::Polynomial(polynomial.derivative())),
+        PolynomialOperator::Coefficients => unreachable!("the coefficients are read above"),
+    }
+}
+
+// A float meeting a polynomial is a constant polynomial.
+fn to_polynomial(value: Value) -> Polynomial {
+    match value {
+        Value::Polynomial(polynomial) => polynomial,
+        value => Polynomial::from(value.as_float()),
+    }
+}
+
+// The functions of ints are exact. The gamma function is computed in f64
+// whatever the backend and stays an f64 float, and the prime factors are
+// listed as floats.
+fn evaluate_number_theory_function(
+    variables: &mut SymbolTable,
+    function: &AnalyzedNumberTheoryFunction,
+) -> Result<Value, String> {
+    let (operator, arguments) = function;
+    if let NumberTheoryOperator::Gamma | NumberTheoryOperator::LogGamma = operator {
+        let x = evaluate_expr(variables, &arguments[0])?.as_float();
+        return Ok(Value::Float(match operator {
+            NumberTheoryOperator::Gamma => number_theory::gamma(x)?,
+            _ => number_theory::ln_gamma(x)?,
+        }));
+    }
+    let mut values = Vec::new();
+    for argument in arguments {
+        values.push(match evaluate_expr(variables, argument)? {
+            Value::Int(value) => value,
+            // An interval is taken as the one whole number it holds, if any.
+            Value::Interval(value) if value.lo.ceil() == value.hi.floor() => {
+                number_theory::whole_number(number_theory_name(*operator), value.lo.ceil())?
+            }
+            value => number_theory::whole_number(number_theory_name(*operator), value.as_float())?,
+        });
+    }
+    Ok(Value::Int(match operator {
+        NumberTheoryOperator::Factorial => number_theory::factorial(values[0])?,
+        NumberTheoryOperator::Binomial => number_theory::binomial(values[0], values[1])?,
+        NumberTheoryOperator::Permutations => number_theory::permutations(values[0], values[1])?,
+        NumberTheoryOperator::IsPrime => number_theory::is_prime(values[0]) as i64,
+        NumberTheoryOperator::NextPrime => number_theory::next_prime(values[0])?,
+        NumberTheoryOperator::Factor => {
+            let factors = number_theory::factor(values[0])?;
+            return Ok(from_floats(factors.into_iter().map(|factor| factor as f64).collect()));
+        }
+        NumberTheoryOperator::ModPow => number_theory::mod_pow(values[0], values[1], values[2])?,
+        NumberTheoryOperator::ModInverse => number_theory::mod_inverse(values[0], values[1])?,
+        NumberTheoryOperator::Gamma | NumberTheoryOperator::LogGamma => {
+            unreachable!("the gamma function is computed above")
+        }
+    }))
+}
+
+// The parameters are evaluated before the number is drawn, as in the
+// emitted program. Floats are drawn in f64 whatever the backend.
+fn evaluate_random(variables: &mut SymbolTable, random: &AnalyzedRandom) -> Result<Value, String> {
+    let (operator, arguments) = random;
+    let backend = variables.backend();
+    let mut values = Vec::new();
+    for argument in arguments {
+        values.push(evaluate_expr(variables, argument)?);
+    }
+    let floats = to_floats(&values);
+    Ok(match operator {
+        RandomOperator::Rand => backend.float(random::rand()),
+        RandomOperator::RandInt => match (&values[0], &values[1]) {
+            (Value::Int(lo), Value::Int(hi)) => Value::Int(random::randint(*lo, *hi)?),
+            _ => Value::Int(random::randint(floats[0] as i64, floats[1] as i64)?),
+        },
+        RandomOperator::Uniform => backend.float(random::uniform(floats[0], floats[1])),
+        RandomOperator::Normal => backend.float(random::normal(floats[0], floats[1])?),
+        RandomOperator::Exponential => backend.float(random::exponential(floats[0])?),
+    })
+}
+
+// Integrals are f64 whatever the backend, like derivatives.
+fn evaluate_integral(variables: &mut SymbolTable, integral: &AnalyzedIntegral) -> Result<Value, String> {
+    let (integrand, handle, lo, hi) = integral;
+    let lo = evaluate_expr(variables, lo)?.as_float();
+    let hi = evaluate_expr(variables, hi)?.as_float();
+    let tolerance = variables.tolerance();
+    quadrature::integrate(
+        |x| {
+            variables.set_value(*handle, Value::Float(x));
+            Ok(evaluate_expr(variables, integrand)?.as_float())
+        },
+        lo,
+        hi,
+        tolerance,
+    )
+    .map(Value::Float)
+}
+
+// Sums of f64 are compensated; the other floats are exact or enclosures, and
+// are added as usual. An empty sum is 0 and an empty product is 1, of the
+// type of the body.
+/// The most terms of a sum or a product, so that a mistyped bound is an
+/// error rather than a program running for days.
+pub const MAX_SERIES_TERMS: i64 = 10_000_000;
+
+fn evaluate_series(variables: &mut SymbolTable, series: &AnalyzedSeries) -> Result<Value, String> {
+    let (operator, handle, lo, hi, body) = series;
+    let lo = evaluate_index_bound(variables, lo, f64::ceil)?;
+    let hi = evaluate_index_bound(variables, hi, f64::floor)?;
+    let terms = hi as i128 - lo as i128 + 1;
+    if terms > MAX_SERIES_TERMS as i128 {
+        return Err(format!(
+            "Error: {} takes at most {} terms, not {}.",
+            series_name(*operator),
+            MAX_SERIES_TERMS,
+            terms
+        ));
+    }
+    let index_type = variables.get_type(*handle);
+    let identity = match operator {
+        SeriesOperator::Sum => 0,
+        SeriesOperator::Product => 1,
+    };
+    let mut result = match expr_type(variables, body) {
+        Type::Int => Value::Int(identity),
+        Type::Float => variables.backend().to_float(Value::Int(identity)),
+        Type::Complex => Value::Complex(Complex::from(identity as f64)),
+        Type::List | Type::Matrix | Type::Polynomial => {
+            unreachable!("the analyzer does not let the body of a series be a list or a polynomial")
+        }
+    };
+    let mut compensated = Neumaier::default();
+    for index in lo..=hi {
+        let index = match index_type {
+            Some(Type::Int) => Value::Int(index),
+            _ => variables.backend().float(index as f64),
+        };
+        variables.set_value(*handle, index);
+        let value = evaluate_expr(variables, body)?;
+        result = match (operator, result, value) {
+            (SeriesOperator::Sum, Value::Float(_), Value::Float(term)) => {
+                compensated = compensated.add(term);
+                Value::Float(compensated.total())
+            }
+            (SeriesOperator::Sum, total, term) => {
+                apply_expr_operator(variables.backend(), ExprOperator::Add, total, term)?
+            }
+            (SeriesOperator::Product, total, factor) => {
+                apply_term_operator(variables.backend(), TermOperator::Multiply, total, factor)?
+            }
+        };
+    }
+    Ok(result)
+}
+
+// The first or last whole value of the index, rounded by `round`.
+fn evaluate_index_bound(
+    variables: &mut SymbolTable,
+    expr: &AnalyzedExpr,
+    round: fn(f64) -> f64,
+) -> Result<i64, String> {
+    match evaluate_expr(variables, expr)? {
+        Value::Int(value) => Ok(value),
+        value => float_to_int(round(value.as_float())),
+    }
+}
+
+// The root is found in f64 and stored as an f64 float, which is neither
+// exact nor an enclosure. The variable gets back its previous value if no
+// root is found.
+fn evaluate_solve(variables: &mut SymbolTable, solve: &AnalyzedSolve) -> Result<Value, String> {
+    let (lhs, rhs, handle, lo, hi) = solve;
+    let lo = evaluate_expr(variables, lo)?.as_float();
+    let hi = evaluate_expr(variables, hi)?.as_float();
+    let tolerance = variables.tolerance();
+    let previous = variables.get_value(*handle);
+    let root = roots::solve(
+        |x| {
+            variables.set_value(*handle, Value::Float(x));
+            let difference =
+                evaluate_dual_expr(variables, lhs, *handle)? - evaluate_dual_expr(variables, rhs, *handle)?;
+            Ok((difference.value, difference.derivative))
+        },
+        lo,
+        hi,
+        tolerance,
+    );
+    variables.set_value(*handle, previous);
+    Ok(Value::Float(root?))
+}
+
+// Forward-mode differentiation with respect to `variable`, in f64 whatever
+// the backend. The parts of type int can only depend on a float variable
+// through int(), so they are evaluated as usual and have no derivative.
+fn evaluate_dual_factor(variables: &mut SymbolTable, factor: &AnalyzedFactor, variable: usize) -> Result<Dual, String> {
+    if factor_type(variables, factor) == Type::Int {
+        return Ok(Dual::constant(evaluate_factor(variables, factor)?.as_float()));
+    }
+    Ok(match factor {
+        AnalyzedFactor::Literal(value) => Dual::constant(value.as_float()),
+        AnalyzedFactor::Identifier(handle) if *handle == variable => {
+            Dual::variable(variables.get_value(*handle).as_float())
+        }
+        AnalyzedFactor::Identifier(handle) => Dual::constant(variables.get_value(*handle).as_float()),
+        AnalyzedFactor::FunctionExpression(function_expr) => apply_dual_function(
+            function_expr.0,
+            evaluate_dual_expr(variables, &function_expr.1, variable)?,
+        ),
+        AnalyzedFactor::SubExpression(expr) => evaluate_dual_expr(variables, expr, variable)?,
+        // The analyzer does not let derivatives be nested, nor integrals,
+        // sums and products be differentiated.
+        AnalyzedFactor::Derivative(..) | AnalyzedFactor::Integral(_) | AnalyzedFactor::Series(_) => {
+            Dual::constant(evaluate_factor(variables, factor)?.as_float())
+        }
+        AnalyzedFactor::Statistic(_) => unreachable!("the analyzer does not let statistics be differentiated"),
+        AnalyzedFactor::Matrix(_) | AnalyzedFactor::MatrixFunction(_) => {
+            unreachable!("the analyzer does not let matrices be differentiated")
+        }
+        AnalyzedFactor::Polynomial(..) | AnalyzedFactor::PolynomialFunction(_) => {
+            unreachable!("the analyzer does not let polynomials be differentiated")
+        }
+        AnalyzedFactor::NumberTheoryFunction(_) => {
+            unreachable!("the analyzer only lets the functions of ints be differentiated")
+        }
+        AnalyzedFactor::Random(_) => unreachable!("the analyzer only lets random ints be differentiated"),
+        AnalyzedFactor::List(_)
+        | AnalyzedFactor::Range(..)
+        | AnalyzedFactor::Index(..)
+        | AnalyzedFactor::Length(_) => unreachable!("the analyzer does not let lists be differentiated"),
+    })
+}
+
+fn evaluate_dual_term(variables: &mut SymbolTable, term: &AnalyzedTerm, variable: usize) -> Result<Dual, String> {
+    if term_type(variables, term) == Type::Int {
+        return Ok(Dual::constant(evaluate_term(variables, term)?.as_float()));
+    }
+    let mut result = evaluate_dual_factor(variables, &term.0, variable)?;
+    for factor in &term.1 {
+        let operand = evaluate_dual_factor(variables, &factor.1, variable)?;
+        result = match factor.0 {
+            TermOperator::Multiply => result * operand,
+            TermOperator::Divide => result / operand,
+            TermOperator::Exponent => result.powf(operand),
+            TermOperator::IntegerDivide => result.div_euclid(operand),
+            TermOperator::MatrixMultiply => unreachable!("the analyzer does not let lists be differentiated"),
+        };
+    }
+    Ok(result)
+}
+
+fn evaluate_dual_expr(variables: &mut SymbolTable, expr: &AnalyzedExpr, variable: usize) -> Result<Dual, String> {
+    if expr_type(variables, expr) == Type::Int {
+        return Ok(Dual::constant(evaluate_expr(variables, expr)?.as_float()));
+    }
+    let mut result = evaluate_dual_term(variables, &expr.0, variable)?;
+    for term in &expr.1 {
+        let operand = evaluate_dual_term(variables, &term.1, variable)?;
+        result = match term.0 {
+            ExprOperator::Add => result + operand,
+            ExprOperator::Subtract => result - operand,
+            ExprOperator::Modulo => result.rem_euclid(operand),
+            // The nominal value of a measured value.
+            ExprOperator::PlusMinus => result,
+        };
+    }
+    Ok(result)
+}
+
+pub fn apply_dual_function(function: FunctionOperator, argument: Dual) -> Dual {
+    match function {
+        FunctionOperator::Sin => argument.sin(),
+        FunctionOperator::Cos => argument.cos(),
+        FunctionOperator::Tan => argument.tan(),
+        FunctionOperator::ArcSin => argument.asin(),
+        FunctionOperator::ArcCos => argument.acos(),
+        FunctionOperator::ArcTan => argument.atan(),
+        FunctionOperator::Sinh => argument.sinh(),
+        FunctionOperator::Cosh => argument.cosh(),
+        FunctionOperator::Tanh => argument.tanh(),
+        FunctionOperator::ArcSinh => argument.asinh(),
+        FunctionOperator::ArcCosh => argument.acosh(),
+        FunctionOperator::ArcTanh => argument.atanh(),
+        FunctionOperator::Exp => argument.exp(),
+        FunctionOperator::Exp2 => argument.exp2(),
+        FunctionOperator::Log => argument.ln(),
+        FunctionOperator::Log10 => argument.log10(),
+        FunctionOperator::Log2 => argument.log2(),
+        FunctionOperator::Abs => argument.abs(),
+        FunctionOperator::Ceil => argument.ceil(),
+        FunctionOperator::Floor => argument.floor(),
+        FunctionOperator::Signum => argument.signum(),
+        FunctionOperator::Sqrt => argument.sqrt(),
+        FunctionOperator::ToInt => Dual::constant(argument.value.trunc()),
+        FunctionOperator::ToFloat | FunctionOperator::Re | FunctionOperator::Conj => argument,
+        FunctionOperator::Im => Dual::constant(0.),
+        FunctionOperator::Arg => argument.arg(),
+    }
+}
+
+fn checked(result: Option<i64>, operator_name: &str) -> Result<Value, String> {
     result
+        .map(Value::Int)
+        .ok_or_else(|| format!("Error: Integer overflow in `{}`.", operator_name))
+}
+
+fn check_int_divisor(divisor: i64, operator_name: &str) -> Result<(), String> {
+    if divisor == 0 {
+        Err(format!("Error: Integer division by zero in `{}`.", operator_name))
+    } else {
+        Ok(())
+    }
+}
+
+fn is_complex(value: &Value) -> bool {
+    matches!(value, Value::Complex(_))
 }
 
-fn execute_statement(variables: &mut SymbolTable, statement: &AnalyzedStatement) {
+fn is_uncertain(value: &Value) -> bool {
+    matches!(value, Value::Uncertain(_))
+}
+
+fn is_polynomial(value: &Value) -> bool {
+    matches!(value, Value::Polynomial(_))
+}
+
+fn is_list(value: &Value) -> bool {
+    matches!(value, Value::List(_) | Value::Matrix(_))
+}
+
+// Applies `apply` to each element of the lists, and to the other operand if
+// it is not a list. Matrices are combined row by row.
+fn broadcast(
+    left: Value,
+    right: Value,
+    apply: impl Fn(Value, Value) -> Result<Value, String>,
+) -> Result<Value, String> {
+    let elements: Result<Vec<Value>, String> = match (left, right) {
+        (Value::Matrix(left), Value::Matrix(right)) => {
+            if linear_algebra::describe_shape(&left) != linear_algebra::describe_shape(&right) {
+                return Err(format!(
+                    "Error: Cannot combine matrices of shapes {} and {}.",
+                    linear_algebra::describe_shape(&left),
+                    linear_algebra::describe_shape(&right)
+                ));
+            }
+            let rows = left.into_iter().zip(right).map(|(left, right)| apply(Value::List(left), Value::List(right)));
+            return rows.map(|row| row.map(into_row)).collect::<Result<_, _>>().map(Value::Matrix);
+        }
+        (Value::Matrix(left), right) => {
+            let rows = left.into_iter().map(|left| apply(Value::List(left), right.clone()));
+            return rows.map(|row| row.map(into_row)).collect::<Result<_, _>>().map(Value::Matrix);
+        }
+        (left, Value::Matrix(right)) => {
+            let rows = right.into_iter().map(|right| apply(left.clone(), Value::List(right)));
+            return rows.map(|row| row.map(into_row)).collect::<Result<_, _>>().map(Value::Matrix);
+        }
+        (Value::List(left), Value::List(right)) => {
+            if left.len() != right.len() {
+                return Err(format!(
+                    "Error: Cannot combine lists of lengths {} and {}.",
+                    left.len(),
+                    right.len()
+                ));
+            }
+            left.into_iter().zip(right).map(|(left, right)| apply(left, right)).collect()
+        }
+        (Value::List(left), right) => left.into_iter().map(|left| apply(left, right.clone())).collect(),
+        (left, Value::List(right)) => right.into_iter().map(|right| apply(left.clone(), right)).collect(),
+        (left, right) => return apply(left, right),
+    };
+    elements.map(Value::List)
+}
+
+fn into_row(value: Value) -> Vec<Value> {
+    match value {
+        Value::List(elements) => elements,
+        _ => unreachable!("a row combined element-wise is a list"),
+    }
+}
+
+fn apply_complex_term_operator(operator: TermOperator, left: Complex, right: Complex) -> Result<Value, String> {
+    Ok(Value::Complex(match operator {
+        TermOperator::Multiply => left * right,
+        TermOperator::Divide => left / right,
+        TermOperator::Exponent => left.pow(right),
+        TermOperator::IntegerDivide => {
+            return Err("Error: The operands of `div` must be real, not complex numbers.".to_string())
+        }
+        TermOperator::MatrixMultiply => unreachable!("`@` only applies to lists and matrices"),
+    }))
+}
+
+// `/` only divides by a float, and `div` and `mod` give the quotient and the
+// remainder of the long division.
+fn apply_polynomial_term_operator(operator: TermOperator, left: Value, right: Value) -> Result<Value, String> {
+    Ok(Value::Polynomial(match operator {
+        TermOperator::Multiply => to_polynomial(left) * to_polynomial(right),
+        TermOperator::Divide => to_polynomial(left) / right.as_float(),
+        TermOperator::Exponent => to_polynomial(left).pow(right.as_float())?,
+        TermOperator::IntegerDivide => to_polynomial(left).div_rem(&to_polynomial(right))?.0,
+        TermOperator::MatrixMultiply => unreachable!("`@` only applies to lists and matrices"),
+    }))
+}
+
+fn apply_polynomial_expr_operator(operator: ExprOperator, left: Value, right: Value) -> Result<Value, String> {
+    Ok(Value::Polynomial(match operator {
+        ExprOperator::Add => to_polynomial(left) + to_polynomial(right),
+        ExprOperator::Subtract => to_polynomial(left) - to_polynomial(right),
+        ExprOperator::Modulo => to_polynomial(left).div_rem(&to_polynomial(right))?.1,
+        ExprOperator::PlusMinus => unreachable!("the analyzer does not let polynomials be measured"),
+    }))
+}
+
+fn apply_complex_expr_operator(operator: ExprOperator, left: Complex, right: Complex) -> Result<Value, String> {
+    Ok(Value::Complex(match operator {
+        ExprOperator::Add => left + right,
+        ExprOperator::Subtract => left - right,
+        ExprOperator::Modulo => {
+            return Err("Error: The operands of `mod` must be real, not complex numbers.".to_string())
+        }
+        ExprOperator::PlusMinus => {
+            return Err("Error: The operands of `+-` must be real, not complex numbers.".to_string())
+        }
+    }))
+}
+
+fn apply_uncertain_term_operator(operator: TermOperator, left: Uncertain, right: Uncertain) -> Uncertain {
+    match operator {
+        TermOperator::Multiply => left * right,
+        TermOperator::Divide => left / right,
+        TermOperator::Exponent => left.powf(right),
+        TermOperator::IntegerDivide => left.div_euclid(right),
+        TermOperator::MatrixMultiply => unreachable!("`@` only applies to lists and matrices"),
+    }
+}
+
+fn apply_uncertain_expr_operator(operator: ExprOperator, left: Uncertain, right: Uncertain) -> Uncertain {
+    match operator {
+        ExprOperator::Add => left + right,
+        ExprOperator::Subtract => left - right,
+        ExprOperator::Modulo => left.rem_euclid(right),
+        ExprOperator::PlusMinus => left.plus_minus(right),
+    }
+}
+
+fn apply_uncertain_function(function: FunctionOperator, argument: Uncertain) -> Result<Value, String> {
+    if function == FunctionOperator::ToInt {
+        return float_to_int(argument.value).map(Value::Int);
+    }
+    let result = apply_dual_function(function, Dual::variable(argument.value));
+    Ok(Value::Uncertain(argument.map(result.value, result.derivative)))
+}
+
+// The analyzer only lets operands of the same type meet, except for `/`
+// which always divides floats, and floats which become complex when they
+// meet a complex number or a polynomial. Measured values meeting complex
+// numbers or polynomials only count with their nominal value. Lists and
+// matrices are combined element by element, except by `@`.
+pub fn apply_term_operator(
+    backend: Backend,
+    operator: TermOperator,
+    left: Value,
+    right: Value,
+) -> Result<Value, String> {
+    match (left, right, operator) {
+        (left, right, TermOperator::MatrixMultiply) => multiply_matrices(left, right),
+        (left, right, operator) if is_list(&left) || is_list(&right) => {
+            broadcast(left, right, |left, right| apply_term_operator(backend, operator, left, right))
+        }
+        (left, right, operator) if is_polynomial(&left) || is_polynomial(&right) => {
+            apply_polynomial_term_operator(operator, left, right)
+        }
+        (Value::Int(left), Value::Int(right), TermOperator::Multiply) => {
+            checked(left.checked_mul(right), "*")
+        }
+        (Value::Int(left), Value::Int(right), TermOperator::IntegerDivide) => {
+            check_int_divisor(right, "div")?;
+            checked(left.checked_div_euclid(right), "div")
+        }
+        (Value::Int(left), Value::Int(right), TermOperator::Exponent) => {
+            if right < 0 {
+                return Err(format!("Error: Negative exponent {} for an int.", right));
+            }
+            checked(left.checked_pow(right.min(u32::MAX as i64) as u32), "^")
+        }
+        (left, right, operator) if is_complex(&left) || is_complex(&right) => {
+            apply_complex_term_operator(operator, left.as_complex(), right.as_complex())
+        }
+        (left, right, operator) if is_uncertain(&left) || is_uncertain(&right) => Ok(Value::Uncertain(
+            apply_uncertain_term_operator(operator, left.as_uncertain(), right.as_uncertain()),
+        )),
+        (Value::Rational(left), Value::Rational(right), operator) => {
+            rational::apply_term_operator(operator, &left, &right).map(Value::Rational)
+        }
+        (Value::Decimal(left), Value::Decimal(right), operator) => backend
+            .decimal_context()
+            .apply_term_operator(operator, &left, &right)
+            .map(Value::Decimal),
+        (Value::Interval(left), Value::Interval(right), operator) => {
+            interval::apply_term_operator(operator, left, right).map(Value::Interval)
+        }
+        (left, right, operator) => {
+            let (left, right) = (left.as_float(), right.as_float());
+            Ok(Value::Float(match operator {
+                TermOperator::Multiply => left * right,
+                TermOperator::Divide => left / right,
+                TermOperator::Exponent => left.powf(right),
+                TermOperator::IntegerDivide => left.div_euclid(right),
+                TermOperator::MatrixMultiply => unreachable!("`@` is applied by multiply_matrices"),
+            }))
+        }
+    }
+}
+
+pub fn apply_expr_operator(
+    backend: Backend,
+    operator: ExprOperator,
+    left: Value,
+    right: Value,
+) -> Result<Value, String> {
+    match (left, right, operator) {
+        (left, right, operator) if is_list(&left) || is_list(&right) => {
+            broadcast(left, right, |left, right| apply_expr_operator(backend, operator, left, right))
+        }
+        (left, right, operator) if is_polynomial(&left) || is_polynomial(&right) => {
+            apply_polynomial_expr_operator(operator, left, right)
+        }
+        (Value::Int(left), Value::Int(right), ExprOperator::Add) => checked(left.checked_add(right), "+"),
+        (Value::Int(left), Value::Int(right), ExprOperator::Subtract) => {
+            checked(left.checked_sub(right), "-")
+        }
+        (Value::Int(left), Value::Int(right), ExprOperator::Modulo) => {
+            check_int_divisor(right, "mod")?;
+            checked(left.checked_rem_euclid(right), "mod")
+        }
+        (left, right, operator) if is_complex(&left) || is_complex(&right) => {
+            apply_complex_expr_operator(operator, left.as_complex(), right.as_complex())
+        }
+        (Value::Interval(left), Value::Interval(right), operator) => {
+            interval::apply_expr_operator(operator, left, right).map(Value::Interval)
+        }
+        (left, right, operator)
+            if operator == ExprOperator::PlusMinus || is_uncertain(&left) || is_uncertain(&right) =>
+        {
+            Ok(Value::Uncertain(apply_uncertain_expr_operator(
+                operator,
+                left.as_uncertain(),
+                right.as_uncertain(),
+            )))
+        }
+        (Value::Rational(left), Value::Rational(right), operator) => {
+            rational::apply_expr_operator(operator, &left, &right).map(Value::Rational)
+        }
+        (Value::Decimal(left), Value::Decimal(right), operator) => backend
+            .decimal_context()
+            .apply_expr_operator(operator, &left, &right)
+            .map(Value::Decimal),
+        (left, right, operator) => {
+            let (left, right) = (left.as_float(), right.as_float());
+            Ok(Value::Float(match operator {
+                ExprOperator::Add => left + right,
+                ExprOperator::Subtract => left - right,
+                ExprOperator::Modulo => left.rem_euclid(right),
+                ExprOperator::PlusMinus => left,
+            }))
+        }
+    }
+}
+
+pub fn apply_function(backend: Backend, function: FunctionOperator, argument: Value) -> Result<Value, String> {
+    match (function, argument) {
+        (function, Value::List(elements)) => elements
+            .into_iter()
+            .map(|element| apply_function(backend, function, element))
+            .collect::<Result<_, _>>()
+            .map(Value::List),
+        (function, Value::Matrix(rows)) => rows
+            .into_iter()
+            .map(|row| apply_function(backend, function, Value::List(row)).map(into_row))
+            .collect::<Result<_, _>>()
+            .map(Value::Matrix),
+        (function, Value::Complex(argument)) => apply_complex_function(function, argument),
+        (FunctionOperator::ToFloat, argument) => Ok(backend.to_float(argument)),
+        (FunctionOperator::Abs, Value::Int(argument)) => checked(argument.checked_abs(), "abs"),
+        (FunctionOperator::Signum, Value::Int(argument)) => Ok(Value::Int(argument.signum())),
+        (FunctionOperator::ToInt, Value::Int(argument)) => Ok(Value::Int(argument)),
+        (FunctionOperator::ToInt, Value::Float(argument)) => Ok(Value::Int(float_to_int(argument)?)),
+        (function, Value::Rational(argument)) => rational::apply_function(function, &argument),
+        (function, Value::Decimal(argument)) => {
+            backend.decimal_context().apply_function(function, &argument)
+        }
+        (function, Value::Interval(argument)) => interval::apply_function(function, argument),
+        (function, Value::Uncertain(argument)) => apply_uncertain_function(function, argument),
+        (function, argument) => Ok(Value::Float(apply_float_function(function, argument.as_float()))),
+    }
+}
+
+fn apply_complex_function(function: FunctionOperator, argument: Complex) -> Result<Value, String> {
+    Ok(Value::Complex(match function {
+        FunctionOperator::Sin => argument.sin(),
+        FunctionOperator::Cos => argument.cos(),
+        FunctionOperator::Tan => argument.tan(),
+        FunctionOperator::ArcSin => argument.asin(),
+        FunctionOperator::ArcCos => argument.acos(),
+        FunctionOperator::ArcTan => argument.atan(),
+        FunctionOperator::Sinh => argument.sinh(),
+        FunctionOperator::Cosh => argument.cosh(),
+        FunctionOperator::Tanh => argument.tanh(),
+        FunctionOperator::ArcSinh => argument.asinh(),
+        FunctionOperator::ArcCosh => argument.acosh(),
+        FunctionOperator::ArcTanh => argument.atanh(),
+        FunctionOperator::Exp2 => argument.exp2(),
+        FunctionOperator::Exp => argument.exp(),
+        FunctionOperator::Log => argument.ln(),
+        FunctionOperator::Log10 => argument.log10(),
+        FunctionOperator::Log2 => argument.log2(),
+        FunctionOperator::Signum => argument.signum(),
+        FunctionOperator::Sqrt => argument.sqrt(),
+        FunctionOperator::Conj => argument.conj(),
+        FunctionOperator::Abs => return Ok(Value::Float(argument.abs())),
+        FunctionOperator::Re => return Ok(Value::Float(argument.re)),
+        FunctionOperator::Im => return Ok(Value::Float(argument.im)),
+        FunctionOperator::Arg => return Ok(Value::Float(argument.arg())),
+        FunctionOperator::Ceil
+        | FunctionOperator::Floor
+        | FunctionOperator::ToInt
+        | FunctionOperator::ToFloat => {
+            return Err(format!(
+                "Error: The argument of {} must be real, not {}.",
+                function_name(function),
+                argument
+            ))
+        }
+    }))
+}
+
+pub fn apply_float_function(function: FunctionOperator, argument: f64) -> f64 {
+    match function {
+        FunctionOperator::Sin => argument.sin(),
+        FunctionOperator::Cos => argument.cos(),
+        FunctionOperator::Tan => argument.tan(),
+        FunctionOperator::ArcSin => argument.asin(),
+        FunctionOperator::ArcCos => argument.acos(),
+        FunctionOperator::ArcTan => argument.atan(),
+        FunctionOperator::Sinh => argument.sinh(),
+        FunctionOperator::Cosh => argument.cosh(),
+        FunctionOperator::Tanh => argument.tanh(),
+        FunctionOperator::ArcSinh => argument.asinh(),
+        FunctionOperator::ArcCosh => argument.acosh(),
+        FunctionOperator::ArcTanh => argument.atanh(),
+        FunctionOperator::Exp2 => argument.exp2(),
+        FunctionOperator::Exp => argument.exp(),
+        FunctionOperator::Log => argument.ln(),
+        FunctionOperator::Log10 => argument.log10(),
+        FunctionOperator::Log2 => argument.log2(),
+        FunctionOperator::Abs => argument.abs(),
+        FunctionOperator::Ceil => argument.ceil(),
+        FunctionOperator::Floor => argument.floor(),
+        FunctionOperator::Signum => argument.signum(),
+        FunctionOperator::Sqrt => argument.sqrt(),
+        FunctionOperator::ToInt => argument.trunc(),
+        FunctionOperator::ToFloat => argument,
+        FunctionOperator::Re | FunctionOperator::Conj => argument,
+        FunctionOperator::Im => 0.,
+        FunctionOperator::Arg => 0f64.atan2(argument),
+    }
+}
+
+fn execute_statement(variables: &mut SymbolTable, statement: &AnalyzedStatement) -> Result<(), String> {
     match statement {
-        AnalyzedStatement::Assignment(handle, expr) => {
-            variables.set_value(*handle, evaluate_expr(variables, expr));
-        }
-        AnalyzedStatement::DeclarationToAssignment(handle, expr) => {
-            variables.set_value(*handle, evaluate_expr(variables, expr));
-        }
-        AnalyzedStatement::Declaration(_) => {}
-        AnalyzedStatement::InputOperation(handle) => {
-            let mut text = String::new();
-            eprint!("<input>: ");
-            std::io::stdin()
-                .read_line(&mut text)
-                .expect("Cannot read line.");
-            let value = text.trim().parse::<f64>().unwrap_or(0.);
+        AnalyzedStatement::Assignment(handle, expr)
+        | AnalyzedStatement::DeclarationToAssignment(handle, expr) => {
+            let value = evaluate_expr(variables, expr)?;
             variables.set_value(*handle, value);
+            variables.set_initialized(*handle, true);
+        }
+        AnalyzedStatement::Declaration(handle) => variables.set_initialized(*handle, false),
+        AnalyzedStatement::AngleMode(_) => {}
+        AnalyzedStatement::InputOperation(handle, range) => {
+            let (lo, hi) = range.unwrap_or((f64::NEG_INFINITY, f64::INFINITY));
+            let backend = variables.backend();
+            let unit = variables.get_unit(*handle).cloned().unwrap_or_else(|| Unit::base(DIMENSIONLESS));
+            let value = match variables.get_type(*handle) {
+                Some(Type::Int) => read_value(lo, hi, "an int", |text| text.parse().ok().map(Value::Int))?,
+                Some(Type::Complex) => backend.in_base_units(
+                    read_value(lo, hi, "a complex number", |text| Complex::parse(text).map(Value::Complex))?,
+                    &unit,
+                ),
+                _ => backend.in_base_units(read_value(lo, hi, "a number", |text| backend.parse(text))?, &unit),
+            };
+            variables.set_value(*handle, value);
+            variables.set_initialized(*handle, true);
+        }
+        AnalyzedStatement::OutputOperation(expr, unit, format, label) => {
+            write_output(
+                variables.backend(),
+                evaluate_expr(variables, expr)?,
+                unit.as_ref(),
+                *format,
+                label.as_deref(),
+            );
         }
-        AnalyzedStatement::OutputOperation(expr) => {
-            println!("<output>: {}", evaluate_expr(variables, expr));
+        AnalyzedStatement::GradientOutput(expr, partials) => {
+            let mut components = Vec::new();
+            for (handle, unit) in partials {
+                let derivative = evaluate_dual_expr(variables, expr, *handle)?.derivative;
+                components.push(format_output(variables.backend(), Value::Float(derivative), unit.as_ref(), None));
+            }
+            println!("<output>: ({})", components.join(", "));
         }
+        AnalyzedStatement::SymbolicDerivative(expr, handle) => {
+            println!("<output>: {}", derive_source(variables, expr, *handle));
+        }
+        AnalyzedStatement::Solve(solve) => {
+            let value = evaluate_solve(variables, solve)?;
+            variables.set_value(solve.2, value);
+            variables.set_initialized(solve.2, true);
+        }
+        AnalyzedStatement::Seed(expr) => match evaluate_expr(variables, expr)? {
+            Value::Int(seed) => random::seed(seed as u64),
+            seed => random::seed(seed.as_float() as u64),
+        },
+    }
+    Ok(())
+}
+
+fn read_line() -> Result<String, String> {
+    let mut text = String::new();
+    eprint!("<input>: ");
+    match std::io::stdin().read_line(&mut text) {
+        Ok(0) => Err("Error: The input ended before a value was read.".to_string()),
+        Ok(_) => Ok(text),
+        Err(err) => Err(format!("Error: Cannot read the input: {}.", err)),
     }
 }
 
-pub fn execute_program(variables: &mut SymbolTable, program: &AnalyzedProgram) {
+/// Asks again until the text reads as `expected` and the value is within
+/// `[lo, hi]`, as declared by `in x range [lo, hi]`, failing when the input
+/// ends.
+pub fn read_value(
+    lo: f64,
+    hi: f64,
+    expected: &str,
+    parse: impl Fn(&str) -> Option<Value>,
+) -> Result<Value, String> {
+    loop {
+        let text = read_line()?;
+        match parse(text.trim()) {
+            Some(value) if lo <= value.as_float() && value.as_float() <= hi => return Ok(value),
+            Some(_) => eprintln!("The value must be in [{}, {}].", lo, hi),
+            None => eprintln!("Cannot read '{}' as {}.", text.trim(), expected),
+        }
+    }
+}
+
+/// Prints a value given in SI base units, converted to `unit` if any, after
+/// its label if any.
+// Matrices start on a line of their own, so that their rows are aligned.
+pub fn write_output(backend: Backend, value: Value, unit: Option<&Unit>, format: Option<Format>, label: Option<&str>) {
+    let label = label.map_or(String::new(), |label| format!(" {} =", label));
+    if value.get_type() == Type::Matrix {
+        println!("<output>:{}\n{}", label, format_output(backend, value, unit, format));
+    } else {
+        println!("<output>:{} {}", label, format_output(backend, value, unit, format));
+    }
+}
+
+fn format_output(backend: Backend, value: Value, unit: Option<&Unit>, format: Option<Format>) -> String {
+    let value = match unit {
+        Some(unit) => backend.in_unit(value, unit),
+        None => value,
+    };
+    let text = match format {
+        Some(format) => format_value(&value, format),
+        None => backend.format(&value),
+    };
+    match unit {
+        Some(unit) => format!("{} {}", text, unit.name),
+        None => text,
+    }
+}
+
+// The numbers of other backends are formatted as floats, except exact
+// fractions with `frac`, and both bounds of intervals.
+fn format_value(value: &Value, format: Format) -> String {
+    match value {
+        Value::Int(value) => number_format::int(*value, format),
+        Value::Rational(value) if format == Format::Fraction => value.to_string(),
+        // Exact values are written from all their digits.
+        Value::Rational(exact) => rational::format_exact(exact, format, Rounding::HalfEven)
+            .unwrap_or_else(|| number_format::float(value.as_float(), format)),
+        Value::Decimal(decimal) => rational::from_decimal(decimal.clone())
+            .and_then(|exact| rational::format_exact(&exact, format, Rounding::HalfEven))
+            .unwrap_or_else(|| number_format::float(value.as_float(), format)),
+        Value::Complex(value) => number_format::complex(value.re, value.im, format),
+        Value::Interval(value) => value.format(format),
+        Value::Uncertain(value) if value.measured => format!(
+            "{} +- {}",
+            number_format::float(value.value, format),
+            number_format::float(value.uncertainty(), format)
+        ),
+        Value::Uncertain(value) => number_format::float(value.value, format),
+        Value::List(values) => {
+            let values: Vec<String> = values.iter().map(|value| format_value(value, format)).collect();
+            format!("[{}]", values.join(", "))
+        }
+        Value::Matrix(rows) => {
+            let rows: Vec<Vec<String>> = rows
+                .iter()
+                .map(|row| row.iter().map(|value| format_value(value, format)).collect())
+                .collect();
+            linear_algebra::format(&rows)
+        }
+        value => number_format::float(value.as_float(), format),
+    }
+}
+
+/// Runs the statements in order, stopping at the first run-time error such
+/// as an integer overflow.
+pub fn execute_program(variables: &mut SymbolTable, program: &AnalyzedProgram) -> Result<(), String> {
     for statement in program {
-        execute_statement(variables, statement);
+        execute_statement(variables, statement)?;
+    }
+    Ok(())
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzer::analyze_program;
+    use crate::parser::parse_program;
+
+    fn term(operator: TermOperator, left: i64, right: i64) -> Result<Value, String> {
+        apply_term_operator(Backend::Float, operator, Value::Int(left), Value::Int(right))
+    }
+
+    fn expr(operator: ExprOperator, left: i64, right: i64) -> Result<Value, String> {
+        apply_expr_operator(Backend::Float, operator, Value::Int(left), Value::Int(right))
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn ints_round_towards_negative_infinity_and_report_overflows() {
+        assert_eq!(term(TermOperator::IntegerDivide, -7, 2), Ok(Value::Int(-4)));
+        assert_eq!(expr(ExprOperator::Modulo, -7, 3), Ok(Value::Int(2)));
+        assert_eq!(term(TermOperator::Exponent, 3, 4), Ok(Value::Int(81)));
+        assert!(term(TermOperator::Exponent, 2, -1).is_err());
+        assert!(term(TermOperator::Multiply, i64::MAX, 2).is_err());
+        assert!(expr(ExprOperator::Add, i64::MAX, 1).is_err());
+        assert!(term(TermOperator::IntegerDivide, 1, 0).is_err());
+        assert!(expr(ExprOperator::Modulo, 1, 0).is_err());
+    }
+
+    #[test]
+    fn ints_and_floats_are_converted_explicitly() {
+        let int = |argument| apply_function(Backend::Float, FunctionOperator::ToInt, Value::Float(argument));
+        assert_eq!(int(-2.5), Ok(Value::Int(-2)));
+        assert!(int(f64::NAN).is_err());
+        assert!(int(1e19).is_err());
+        assert_eq!(
+            apply_function(Backend::Float, FunctionOperator::ToFloat, Value::Int(3)),
+            Ok(Value::Float(3.))
+        );
+        for source in &["var n: int = 7\nout n + 1.5", "var n: int = 7\nout n / 2"] {
+            let mut variables = SymbolTable::with_backend(Backend::Float);
+            let (_, parsed_program) = parse_program(source).unwrap();
+            assert!(analyze_program(&mut variables, &parsed_program).is_err(), "{}", source);
+        }
+    }
+}
@@ -0,0 +1,752 @@
+use crate::decimal::Rounding;
+use crate::number_format::{self, Format};
+use crate::parser::{ExprOperator, FunctionOperator, TermOperator};
+use crate::rational;
+use crate::unparser::function_name;
+use crate::value::{float_to_int, Value};
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::ToPrimitive;
+use std::f64::consts::{FRAC_PI_2, PI};
+
+/// A closed set of reals `[lo, hi]`, possibly unbounded. `Interval::entire()`
+/// stands for "any value", which is also what operations fall back to when
+/// they cannot bound their result.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Interval {
+    pub lo: f64,
+    pub hi: f64,
+}
+
+// The bounds are printed with the shortest decimals that keep enclosing the
+// interval, which may be one ulp wider than the shortest writing of the f64.
+impl std::fmt::Display for Interval {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "[{}, {}]", format_bound(self.lo, false), format_bound(self.hi, true))
+    }
+}
+
+fn format_bound(value: f64, upper: bool) -> String {
+    match BigRational::from_float(value) {
+        Some(exact) => outward(&exact, upper).to_string(),
+        None => value.to_string(),
+    }
+}
+
+// The f64 nearest to `exact` whose shortest writing is on the outer side of
+// it, for a lower bound or an upper one.
+fn outward(exact: &BigRational, upper: bool) -> f64 {
+    let mut value = exact.to_f64().unwrap_or(f64::NAN);
+    while let Some(decimal) = rational::from_f64(value) {
+        if upper && decimal < *exact {
+            value = value.next_up();
+        } else if !upper && decimal > *exact {
+            value = value.next_down();
+        } else {
+            break;
+        }
+    }
+    value
+}
+
+// With a format, the digits of the bound are rounded outward from its exact
+// value.
+fn format_bound_with(value: f64, upper: bool, format: Format) -> String {
+    let rounding = if upper { Rounding::Ceiling } else { Rounding::Floor };
+    let exact = match BigRational::from_float(value) {
+        Some(exact) => exact,
+        None => return number_format::float(value, format),
+    };
+    match format {
+        Format::Scientific(None) => number_format::float(outward(&exact, upper), format),
+        Format::Percent(None) => format!("{}%", outward(&(exact * BigRational::from_integer(BigInt::from(100))), upper)),
+        _ => rational::format_exact(&exact, format, rounding).unwrap_or_else(|| number_format::float(value, format)),
+    }
+}
+
+// Product of two bounds, taking 0 * inf as 0 as usual in interval arithmetic.
+fn bound_product(a: f64, b: f64) -> f64 {
+    if a == 0. || b == 0. { 0. } else { a * b }
+}
+
+impl Interval {
+    pub fn new(lo: f64, hi: f64) -> Interval {
+        if lo.is_nan() || hi.is_nan() || lo > hi {
+            Interval::entire()
+        } else {
+            Interval { lo, hi }
+        }
+    }
+    /// The bounds written with `format`, rounded outward.
+    pub fn format(&self, format: Format) -> String {
+        format!(
+            "[{}, {}]",
+            format_bound_with(self.lo, false, format),
+            format_bound_with(self.hi, true, format)
+        )
+    }
+
+    pub fn point(value: f64) -> Interval {
+        Interval::new(value, value)
+    }
+    pub fn entire() -> Interval {
+        Interval {
+            lo: f64::NEG_INFINITY,
+            hi: f64::INFINITY,
+        }
+    }
+    pub fn contains(&self, value: f64) -> bool {
+        self.lo <= value && value <= self.hi
+    }
+    pub fn is_point(&self) -> bool {
+        self.lo == self.hi
+    }
+    fn from_bounds(bounds: &[f64]) -> Interval {
+        if bounds.iter().any(|bound| bound.is_nan()) {
+            return Interval::entire();
+        }
+        let lo = bounds.iter().copied().fold(f64::INFINITY, f64::min);
+        let hi = bounds.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        Interval::new(lo, hi)
+    }
+
+    pub fn add(self, other: Interval) -> Interval {
+        Interval::new(self.lo + other.lo, self.hi + other.hi)
+    }
+    pub fn sub(self, other: Interval) -> Interval {
+        Interval::new(self.lo - other.hi, self.hi - other.lo)
+    }
+    pub fn mul(self, other: Interval) -> Interval {
+        Interval::from_bounds(&[
+            bound_product(self.lo, other.lo),
+            bound_product(self.lo, other.hi),
+            bound_product(self.hi, other.lo),
+            bound_product(self.hi, other.hi),
+        ])
+    }
+    /// Division by an interval containing zero is unbounded.
+    pub fn div(self, other: Interval) -> Interval {
+        if other.contains(0.) {
+            Interval::entire()
+        } else {
+            self.mul(Interval::new(1. / other.hi, 1. / other.lo))
+        }
+    }
+    /// Euclidean remainder, which lies in `[0, |divisor|)`.
+    pub fn rem(self, other: Interval) -> Interval {
+        if other.contains(0.) {
+            return Interval::entire();
+        }
+        let min_divisor = other.lo.abs().min(other.hi.abs());
+        let max_divisor = other.lo.abs().max(other.hi.abs());
+        if self.lo >= 0. && self.hi < min_divisor {
+            self
+        } else if self.lo >= 0. {
+            Interval::new(0., max_divisor.min(self.hi))
+        } else {
+            Interval::new(0., max_divisor)
+        }
+    }
+    /// Euclidean quotient: the real quotient rounded down for a positive
+    /// divisor and up for a negative one.
+    pub fn div_euclid(self, other: Interval) -> Interval {
+        let quotient = self.div(other);
+        Interval::new(quotient.lo.floor(), quotient.hi.ceil())
+    }
+    pub fn pow(self, exponent: Interval) -> Interval {
+        if exponent.is_point() && exponent.lo.fract() == 0. {
+            let n = exponent.lo;
+            if n == 0. {
+                return Interval::point(1.);
+            }
+            if n < 0. && self.contains(0.) {
+                return Interval::entire();
+            }
+            let at_lo = self.lo.powf(n);
+            let at_hi = self.hi.powf(n);
+            if n % 2. == 0. && self.contains(0.) {
+                // Even positive powers reach their minimum at zero.
+                return Interval::from_bounds(&[0., at_lo, at_hi]);
+            }
+            return Interval::from_bounds(&[at_lo, at_hi]);
+        }
+        if self.lo < 0. {
+            return Interval::entire();
+        }
+        // For a non-negative base x^y is monotonic in each argument.
+        Interval::from_bounds(&[
+            self.lo.powf(exponent.lo),
+            self.lo.powf(exponent.hi),
+            self.hi.powf(exponent.lo),
+            self.hi.powf(exponent.hi),
+        ])
+    }
+
+    /// Values of `function` over the interval. Parts of the interval outside
+    /// the domain of `function` are ignored; use `domain` to detect them.
+    pub fn apply_function(self, function: FunctionOperator) -> Interval {
+        let domain = Interval::domain(function);
+        let (lo, hi) = (self.lo.max(domain.lo), self.hi.min(domain.hi));
+        if lo > hi {
+            return Interval::entire();
+        }
+        let argument = Interval { lo, hi };
+        let increasing = |f: fn(f64) -> f64| Interval::from_bounds(&[f(argument.lo), f(argument.hi)]);
+        match function {
+            FunctionOperator::Sin => argument.sin(),
+            FunctionOperator::Cos => argument.cos(),
+            FunctionOperator::Tan => {
+                if argument.hi - argument.lo >= PI || argument.contains_tan_pole() {
+                    Interval::entire()
+                } else {
+                    increasing(f64::tan)
+                }
+            }
+            FunctionOperator::ArcSin => increasing(f64::asin),
+            FunctionOperator::ArcCos => Interval::from_bounds(&[argument.hi.acos(), argument.lo.acos()]),
+            FunctionOperator::ArcTan => increasing(f64::atan),
+            FunctionOperator::Sinh => increasing(f64::sinh),
+            FunctionOperator::Cosh => {
+                let magnitude = argument.abs();
+                Interval::from_bounds(&[magnitude.lo.cosh(), magnitude.hi.cosh()])
+            }
+            FunctionOperator::Tanh => increasing(f64::tanh),
+            FunctionOperator::ArcSinh => increasing(f64::asinh),
+            FunctionOperator::ArcCosh => increasing(f64::acosh),
+            FunctionOperator::ArcTanh => increasing(f64::atanh),
+            FunctionOperator::Exp => increasing(f64::exp),
+            FunctionOperator::Exp2 => increasing(f64::exp2),
+            FunctionOperator::Log => increasing(f64::ln),
+            FunctionOperator::Log10 => increasing(f64::log10),
+            FunctionOperator::Log2 => increasing(f64::log2),
+            FunctionOperator::Abs => argument.abs(),
+            FunctionOperator::Ceil => increasing(f64::ceil),
+            FunctionOperator::Floor => increasing(f64::floor),
+            FunctionOperator::Signum => increasing(f64::signum),
+            FunctionOperator::Sqrt => increasing(f64::sqrt),
+            FunctionOperator::ToInt => increasing(f64::trunc),
+            FunctionOperator::ToFloat | FunctionOperator::Re | FunctionOperator::Conj => argument,
+            FunctionOperator::Im => Interval::point(0.),
+            FunctionOperator::Arg if argument.lo > 0. => Interval::point(0.),
+            FunctionOperator::Arg if argument.hi < 0. => Interval::point(PI),
+            FunctionOperator::Arg => Interval::new(0., PI),
+        }
+    }
+
+    /// Closure of the arguments for which `function` is defined.
+    pub fn domain(function: FunctionOperator) -> Interval {
+        match function {
+            FunctionOperator::ArcSin | FunctionOperator::ArcCos => Interval::new(-1., 1.),
+            FunctionOperator::ArcCosh => Interval::new(1., f64::INFINITY),
+            FunctionOperator::ArcTanh => Interval::new(-1., 1.),
+            FunctionOperator::Log | FunctionOperator::Log10 | FunctionOperator::Log2 => {
+                Interval::new(0., f64::INFINITY)
+            }
+            FunctionOperator::Sqrt => Interval::new(0., f64::INFINITY),
+            FunctionOperator::ToInt => Interval::new(i64::MIN as f64, i64::MAX as f64),
+            _ => Interval::entire(),
+        }
+    }
+
+    pub fn domain_description(function: FunctionOperator) -> &'static str {
+        match function {
+            FunctionOperator::Tan => "away from pi/2 + k*pi",
+            FunctionOperator::ArcSin | FunctionOperator::ArcCos => "within [-1, 1]",
+            FunctionOperator::ArcCosh => "at least 1",
+            FunctionOperator::ArcTanh => "strictly between -1 and 1",
+            FunctionOperator::Log | FunctionOperator::Log10 | FunctionOperator::Log2 => "positive",
+            FunctionOperator::Sqrt => "non-negative",
+            FunctionOperator::ToInt => "within the range of int",
+            _ => "a number",
+        }
+    }
+
+    fn abs(self) -> Interval {
+        if self.lo >= 0. {
+            self
+        } else if self.hi <= 0. {
+            Interval::new(-self.hi, -self.lo)
+        } else {
+            Interval::new(0., self.hi.max(-self.lo))
+        }
+    }
+
+    // First point of offset + k * period (k integer) at or after lo; used to
+    // find the extrema and poles of periodic functions.
+    fn first_at_or_after(lo: f64, offset: f64, period: f64) -> f64 {
+        offset + ((lo - offset) / period).ceil() * period
+    }
+
+    fn sin(self) -> Interval {
+        if !self.lo.is_finite() || !self.hi.is_finite() || self.hi - self.lo >= 2. * PI {
+            return Interval::new(-1., 1.);
+        }
+        let mut bounds = vec![self.lo.sin(), self.hi.sin()];
+        if Interval::first_at_or_after(self.lo, FRAC_PI_2, 2. * PI) <= self.hi {
+            bounds.push(1.);
+        }
+        if Interval::first_at_or_after(self.lo, -FRAC_PI_2, 2. * PI) <= self.hi {
+            bounds.push(-1.);
+        }
+        Interval::from_bounds(&bounds)
+    }
+
+    fn cos(self) -> Interval {
+        if !self.lo.is_finite() || !self.hi.is_finite() || self.hi - self.lo >= 2. * PI {
+            return Interval::new(-1., 1.);
+        }
+        let mut bounds = vec![self.lo.cos(), self.hi.cos()];
+        if Interval::first_at_or_after(self.lo, 0., 2. * PI) <= self.hi {
+            bounds.push(1.);
+        }
+        if Interval::first_at_or_after(self.lo, PI, 2. * PI) <= self.hi {
+            bounds.push(-1.);
+        }
+        Interval::from_bounds(&bounds)
+    }
+
+    fn contains_tan_pole(self) -> bool {
+        !self.lo.is_finite()
+            || !self.hi.is_finite()
+            || Interval::first_at_or_after(self.lo, FRAC_PI_2, PI) <= self.hi
+    }
+
+    /// Tells whether `function` may be evaluated outside of its domain, which
+    /// for `tan` means at one of its poles.
+    pub fn may_leave_domain(self, function: FunctionOperator) -> bool {
+        match function {
+            FunctionOperator::Tan => self.contains_tan_pole(),
+            FunctionOperator::Log | FunctionOperator::Log10 | FunctionOperator::Log2 => self.lo <= 0.,
+            FunctionOperator::ArcTanh => self.lo <= -1. || self.hi >= 1.,
+            _ => {
+                let domain = Interval::domain(function);
+                self.lo < domain.lo || self.hi > domain.hi
+            }
+        }
+    }
+}
+
+// Outward-rounded arithmetic, used by `--intervals` to evaluate programs.
+// Every bound is rounded away from the interval, so that the result encloses
+// the values of the operation over its operands. Sums, products, quotients
+// and square roots compute their exact rounding error to round only when
+// needed, so exact results stay points; the other functions are widened by
+// a few ulps, more than the error of the math library.
+
+const LIBRARY_ULPS: usize = 2;
+
+// Below this magnitude, products and quotients may underflow, and their
+// rounding error is not exact any more.
+const TINY: f64 = 1e-290;
+
+// A bound `value` rounded to nearest, where the exact result was
+// `value + error`.
+fn lower(value: f64, error: f64) -> f64 {
+    if value == f64::INFINITY {
+        f64::MAX
+    } else if error < 0. {
+        value.next_down()
+    } else {
+        value
+    }
+}
+
+fn upper(value: f64, error: f64) -> f64 {
+    if value == f64::NEG_INFINITY {
+        f64::MIN
+    } else if error > 0. {
+        value.next_up()
+    } else {
+        value
+    }
+}
+
+// Knuth's TwoSum.
+fn sum_error(a: f64, b: f64, sum: f64) -> f64 {
+    let b_part = sum - a;
+    (a - (sum - b_part)) + (b - b_part)
+}
+
+fn product_error(a: f64, b: f64, product: f64) -> Option<f64> {
+    if product.abs() < TINY {
+        None
+    } else {
+        Some(a.mul_add(b, -product))
+    }
+}
+
+fn mul_down(a: f64, b: f64) -> f64 {
+    if a == 0. || b == 0. {
+        return 0.;
+    }
+    let product = a * b;
+    product_error(a, b, product).map_or(product.next_down(), |error| lower(product, error))
+}
+
+fn mul_up(a: f64, b: f64) -> f64 {
+    if a == 0. || b == 0. {
+        return 0.;
+    }
+    let product = a * b;
+    product_error(a, b, product).map_or(product.next_up(), |error| upper(product, error))
+}
+
+// The sign of the exact quotient minus the rounded one.
+fn quotient_error(a: f64, b: f64, quotient: f64) -> Option<f64> {
+    if quotient.abs() < TINY {
+        return None;
+    }
+    let remainder = -quotient.mul_add(b, -a);
+    Some(if b > 0. { remainder } else { -remainder })
+}
+
+fn div_down(a: f64, b: f64) -> f64 {
+    if a == 0. {
+        return 0.;
+    }
+    let quotient = a / b;
+    quotient_error(a, b, quotient).map_or(quotient.next_down(), |error| lower(quotient, error))
+}
+
+fn div_up(a: f64, b: f64) -> f64 {
+    if a == 0. {
+        return 0.;
+    }
+    let quotient = a / b;
+    quotient_error(a, b, quotient).map_or(quotient.next_up(), |error| upper(quotient, error))
+}
+
+fn sqrt_error(value: f64, root: f64) -> Option<f64> {
+    if value < TINY {
+        None
+    } else {
+        Some(-root.mul_add(root, -value))
+    }
+}
+
+// `base` and the result are non-negative, so rounding every step in the same
+// direction rounds the result in that direction.
+fn power_bound(base: f64, exponent: u64, multiply: fn(f64, f64) -> f64) -> f64 {
+    let (mut result, mut base, mut exponent) = (1., base, exponent);
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = multiply(result, base);
+        }
+        base = multiply(base, base);
+        exponent >>= 1;
+    }
+    result
+}
+
+fn enclose_exact(nearest: f64, exact: &BigRational) -> Interval {
+    match BigRational::from_float(nearest) {
+        Some(value) if value < *exact => Interval::new(nearest, nearest.next_up()),
+        Some(value) if value > *exact => Interval::new(nearest.next_down(), nearest),
+        _ => Interval::point(nearest),
+    }
+}
+
+/// Encloses a literal, which only has its f64 left: the value itself when
+/// its shortest decimal writing is exact, such as `0.5`, and the f64 on
+/// either side otherwise, such as for `0.1`.
+pub fn enclose(value: f64) -> Interval {
+    match (rational::from_f64(value), BigRational::from_float(value)) {
+        (Some(decimal), Some(exact)) if decimal != exact => {
+            Interval::new(value.next_down(), value.next_up())
+        }
+        _ => Interval::point(value),
+    }
+}
+
+pub fn enclose_rational(value: &BigRational) -> Interval {
+    enclose_exact(value.to_f64().unwrap_or(f64::NAN), value)
+}
+
+pub fn enclose_integer(value: i64) -> Interval {
+    enclose_exact(value as f64, &BigRational::from_integer(BigInt::from(value)))
+}
+
+fn parse_number(text: &str) -> Option<Interval> {
+    let exact = rational::parse_rational(text)?;
+    Some(enclose_exact(exact.to_f64()?, &exact))
+}
+
+/// Reads `1.2`, `1/3`, `1.2±0.01`, `1.2+-0.01` or `[1.1, 1.3]`.
+pub fn parse(text: &str) -> Option<Interval> {
+    if let Some(bounds) = text.strip_prefix('[').and_then(|text| text.strip_suffix(']')) {
+        let (lo, hi) = bounds.split_once(',')?;
+        let (lo, hi) = (parse_number(lo.trim())?, parse_number(hi.trim())?);
+        return if lo.lo <= hi.hi { Some(Interval::new(lo.lo, hi.hi)) } else { None };
+    }
+    match text.split_once('±').or_else(|| text.split_once("+-")) {
+        Some((center, radius)) => {
+            let (center, radius) = (parse_number(center.trim())?, parse_number(radius.trim())?);
+            if radius.lo < 0. {
+                None
+            } else {
+                Some(center.add_outward(Interval::new(-radius.hi, radius.hi)))
+            }
+        }
+        None => parse_number(text),
+    }
+}
+
+impl Interval {
+    fn widen(self, ulps: usize) -> Interval {
+        let (mut lo, mut hi) = (self.lo, self.hi);
+        for _ in 0..ulps {
+            lo = lo.next_down();
+            hi = hi.next_up();
+        }
+        Interval::new(lo, hi)
+    }
+
+    /// The middle of the interval, standing for it where a single f64 is
+    /// needed.
+    pub fn midpoint(self) -> f64 {
+        if self.lo == self.hi {
+            self.lo
+        } else {
+            self.lo / 2. + self.hi / 2.
+        }
+    }
+
+    pub fn add_outward(self, other: Interval) -> Interval {
+        let (lo, hi) = (self.lo + other.lo, self.hi + other.hi);
+        Interval::new(
+            lower(lo, sum_error(self.lo, other.lo, lo)),
+            upper(hi, sum_error(self.hi, other.hi, hi)),
+        )
+    }
+
+    pub fn sub_outward(self, other: Interval) -> Interval {
+        self.add_outward(Interval::new(-other.hi, -other.lo))
+    }
+
+    pub fn mul_outward(self, other: Interval) -> Interval {
+        let corners = [(self.lo, other.lo), (self.lo, other.hi), (self.hi, other.lo), (self.hi, other.hi)];
+        Interval::new(
+            corners.iter().map(|&(a, b)| mul_down(a, b)).fold(f64::INFINITY, f64::min),
+            corners.iter().map(|&(a, b)| mul_up(a, b)).fold(f64::NEG_INFINITY, f64::max),
+        )
+    }
+
+    /// Division by an interval containing zero is unbounded, and division by
+    /// zero itself is an error.
+    pub fn div_outward(self, other: Interval, operator_name: &str) -> Result<Interval, String> {
+        if other.lo == 0. && other.hi == 0. {
+            return Err(format!("Error: Division by zero in `{}`.", operator_name));
+        }
+        if other.contains(0.) {
+            return Ok(Interval::entire());
+        }
+        let corners = [(self.lo, other.lo), (self.lo, other.hi), (self.hi, other.lo), (self.hi, other.hi)];
+        Ok(Interval::new(
+            corners.iter().map(|&(a, b)| div_down(a, b)).fold(f64::INFINITY, f64::min),
+            corners.iter().map(|&(a, b)| div_up(a, b)).fold(f64::NEG_INFINITY, f64::max),
+        ))
+    }
+
+    fn div_euclid_outward(self, other: Interval) -> Result<Interval, String> {
+        let quotient = self.div_outward(other, "div")?;
+        Ok(if other.contains(0.) {
+            quotient
+        } else if other.lo > 0. {
+            Interval::new(quotient.lo.floor(), quotient.hi.floor())
+        } else {
+            Interval::new(quotient.lo.ceil(), quotient.hi.ceil())
+        })
+    }
+
+    // When the quotient is a single integer k, the remainder is exactly
+    // x - k * y; otherwise only its range is known.
+    fn rem_outward(self, other: Interval) -> Result<Interval, String> {
+        let quotient = self.div_euclid_outward(other)?;
+        Ok(if quotient.is_point() && quotient.lo.is_finite() {
+            self.sub_outward(other.mul_outward(quotient))
+        } else {
+            self.rem(other)
+        })
+    }
+
+    fn sqrt_outward(self) -> Interval {
+        let (lo, hi) = (self.lo.max(0.).sqrt(), self.hi.sqrt());
+        Interval::new(
+            sqrt_error(self.lo.max(0.), lo).map_or(lo.next_down().max(0.), |error| lower(lo, error)),
+            sqrt_error(self.hi, hi).map_or(hi.next_up(), |error| upper(hi, error)),
+        )
+    }
+
+    fn pow_outward(self, exponent: Interval) -> Result<Interval, String> {
+        if !(exponent.is_point() && exponent.lo.fract() == 0. && exponent.lo.abs() <= i32::MAX as f64) {
+            // x^y with a real exponent is only defined for x >= 0.
+            if self.hi < 0. {
+                return Err(format!("Error: {} ^ {} is not a real number.", self, exponent));
+            }
+            let power = Interval::new(self.lo.max(0.), self.hi).pow(exponent).widen(LIBRARY_ULPS);
+            return Ok(Interval::new(power.lo.max(0.), power.hi));
+        }
+        let n = exponent.lo as i64;
+        if n < 0 {
+            let power = self.pow_outward(Interval::point(-n as f64))?;
+            return Interval::point(1.).div_outward(power, "^");
+        }
+        let n = n as u64;
+        let magnitude_lo = if self.contains(0.) { 0. } else { self.lo.abs().min(self.hi.abs()) };
+        let magnitude_hi = self.lo.abs().max(self.hi.abs());
+        Ok(if n.is_multiple_of(2) {
+            Interval::new(power_bound(magnitude_lo, n, mul_down), power_bound(magnitude_hi, n, mul_up))
+        } else {
+            // Odd powers are increasing.
+            let signed_power = |value: f64, down: bool| {
+                let magnitude = if (value >= 0.) == down { mul_down } else { mul_up };
+                power_bound(value.abs(), n, magnitude).copysign(value)
+            };
+            Interval::new(signed_power(self.lo, true), signed_power(self.hi, false))
+        })
+    }
+}
+
+pub fn apply_term_operator(
+    operator: TermOperator,
+    left: Interval,
+    right: Interval,
+) -> Result<Interval, String> {
+    match operator {
+        TermOperator::Multiply => Ok(left.mul_outward(right)),
+        TermOperator::Divide => left.div_outward(right, "/"),
+        TermOperator::Exponent => left.pow_outward(right),
+        TermOperator::IntegerDivide => left.div_euclid_outward(right),
+        TermOperator::MatrixMultiply => unreachable!("`@` only applies to lists and matrices"),
+    }
+}
+
+pub fn apply_expr_operator(
+    operator: ExprOperator,
+    left: Interval,
+    right: Interval,
+) -> Result<Interval, String> {
+    match operator {
+        ExprOperator::Add => Ok(left.add_outward(right)),
+        ExprOperator::Subtract => Ok(left.sub_outward(right)),
+        ExprOperator::Modulo => left.rem_outward(right),
+        // `x +- u` encloses every value within `u` of `x`.
+        ExprOperator::PlusMinus => {
+            let radius = right.lo.abs().max(right.hi.abs());
+            Ok(left.add_outward(Interval::new(-radius, radius)))
+        }
+    }
+}
+
+/// Parts of the argument outside of the domain of `function` are ignored,
+/// and an argument entirely outside of it is an error.
+pub fn apply_function(function: FunctionOperator, argument: Interval) -> Result<Value, String> {
+    let domain = Interval::domain(function);
+    if argument.hi < domain.lo || argument.lo > domain.hi {
+        return Err(format!("Error: {}({}) is not defined.", function_name(function), argument));
+    }
+    Ok(Value::Interval(match function {
+        FunctionOperator::ToInt => {
+            let (lo, hi) = (argument.lo.trunc(), argument.hi.trunc());
+            if lo != hi {
+                return Err(format!("Error: int({}) is not a single int.", argument));
+            }
+            return float_to_int(lo).map(Value::Int);
+        }
+        FunctionOperator::Sqrt => argument.sqrt_outward(),
+        FunctionOperator::Arg if argument.lo >= 0. => Interval::point(0.),
+        FunctionOperator::Arg if argument.hi < 0. => Interval::new(PI, PI.next_up()),
+        FunctionOperator::Arg => Interval::new(0., PI.next_up()),
+        FunctionOperator::Abs
+        | FunctionOperator::Ceil
+        | FunctionOperator::Floor
+        | FunctionOperator::Signum
+        | FunctionOperator::ToFloat
+        | FunctionOperator::Re
+        | FunctionOperator::Im
+        | FunctionOperator::Conj => argument.apply_function(function),
+        _ => argument.apply_function(function).widen(LIBRARY_ULPS),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exact(value: f64) -> BigRational {
+        BigRational::from_float(value).unwrap()
+    }
+
+    fn ratio(numerator: i64, denominator: i64) -> BigRational {
+        BigRational::new(BigInt::from(numerator), BigInt::from(denominator))
+    }
+
+    // The interval holds `value`, and is at most one ulp wide on each side
+    // of the nearest f64.
+    fn assert_tight(interval: Interval, value: &BigRational) {
+        assert!(exact(interval.lo) <= *value && *value <= exact(interval.hi), "{} misses {}", interval, value);
+        assert!(interval.lo.next_up().next_up() >= interval.hi, "{} is too wide", interval);
+    }
+
+    #[test]
+    fn literals_are_enclosed() {
+        assert_eq!(enclose(0.5), Interval::point(0.5));
+        assert_tight(enclose(0.1), &ratio(1, 10));
+        assert!(!enclose(0.1).is_point());
+        assert_tight(enclose_rational(&ratio(5, 18)), &ratio(5, 18));
+        assert_eq!(enclose_integer(1 << 53), Interval::point(9007199254740992.));
+        assert_tight(enclose_integer((1 << 53) + 1), &ratio((1 << 53) + 1, 1));
+        assert_tight(parse("1/3").unwrap(), &ratio(1, 3));
+    }
+
+    #[test]
+    fn arithmetic_rounds_outward() {
+        let sum = enclose(0.1).add_outward(enclose(0.2));
+        assert!(exact(sum.lo) <= ratio(3, 10) && ratio(3, 10) <= exact(sum.hi));
+        assert!(exact(sum.hi) < ratio(3, 10) + ratio(1, 1_000_000_000_000));
+        let third = Interval::point(1.).div_outward(Interval::point(3.), "/").unwrap();
+        assert_tight(third, &ratio(1, 3));
+        let product = Interval::point(1. / 3.).mul_outward(Interval::point(3.));
+        assert_tight(product, &(exact(1. / 3.) * ratio(3, 1)));
+        let difference = Interval::point(1.).sub_outward(Interval::point(1e-20));
+        assert_tight(difference, &(ratio(1, 1) - exact(1e-20)));
+        let root = Interval::point(2.).sqrt_outward();
+        assert!(exact(root.lo) * exact(root.lo) < ratio(2, 1));
+        assert!(exact(root.hi) * exact(root.hi) > ratio(2, 1));
+    }
+
+    #[test]
+    fn exact_results_stay_points() {
+        assert_eq!(Interval::point(0.5).add_outward(Interval::point(0.25)), Interval::point(0.75));
+        assert_eq!(Interval::point(1.5).mul_outward(Interval::point(4.)), Interval::point(6.));
+        assert_eq!(Interval::point(1.).div_outward(Interval::point(8.), "/"), Ok(Interval::point(0.125)));
+        assert_eq!(Interval::point(9.).sqrt_outward(), Interval::point(3.));
+    }
+
+    #[test]
+    fn tiny_results_are_widened() {
+        let product = Interval::point(1e-200).mul_outward(Interval::point(1e-200));
+        assert!(product.lo <= 0. && product.hi > 0.);
+        let quotient = Interval::point(1e-300).div_outward(Interval::point(1e100), "/").unwrap();
+        assert!(quotient.lo <= 0. && quotient.hi > 0.);
+    }
+
+    #[test]
+    fn bounds_print_outward() {
+        let printed = Interval::new(0.1, 0.1).to_string();
+        let (lo, hi) = printed[1..printed.len() - 1].split_once(", ").unwrap();
+        assert!(rational::parse_rational(lo).unwrap() <= exact(0.1));
+        assert!(rational::parse_rational(hi).unwrap() >= exact(0.1));
+    }
+
+    #[test]
+    fn formatted_bounds_round_outward() {
+        let third = enclose_rational(&ratio(1, 3));
+        assert_eq!(third.format(Format::Fixed(3)), "[0.333, 0.334]");
+        assert_eq!(third.format(Format::Significant(2)), "[0.33, 0.34]");
+        assert_eq!(third.format(Format::Scientific(Some(1))), "[3.3e-1, 3.4e-1]");
+        assert_eq!(Interval::point(-2.5).format(Format::Fixed(0)), "[-3, -2]");
+        assert_eq!(Interval::point(0.5).format(Format::Fixed(2)), "[0.50, 0.50]");
+        assert_eq!(Interval::entire().format(Format::Fixed(2)), "[-inf, inf]");
+    }
+}
@@ -0,0 +1,418 @@
+use crate::analyzer::{
+    AnalyzedExpr, AnalyzedFactor, AnalyzedFunctionExpr, AnalyzedProgram, AnalyzedStatement,
+    AnalyzedTerm,
+};
+use crate::executor;
+use crate::numeric::Backend;
+use crate::parser::{ExprOperator, FunctionOperator, TermOperator};
+use crate::symbol_table::SymbolTable;
+use crate::units::Unit;
+use crate::unparser::{series_name, statistic_name};
+use crate::value::{Type, Value as MathValue};
+
+use cranelift_codegen::ir::condcodes::FloatCC;
+use cranelift_codegen::ir::{types, AbiParam, FuncRef, InstBuilder, MemFlags, UserFuncName, Value};
+use cranelift_codegen::settings::{self, Configurable};
+use cranelift_frontend::{FunctionBuilder, FunctionBuilderContext};
+use cranelift_jit::{JITBuilder, JITModule};
+use cranelift_module::{default_libcall_names, Linkage, Module};
+use std::collections::HashMap;
+
+// Every call out of the generated code goes through these wrappers, so the
+// JIT uses exactly the same libm routines as `executor`.
+extern "C" fn jit_output(value: f64) { executor::write_output(Backend::Float, MathValue::Float(value), None, None, None) }
+// `unit` points into the program being run, which outlives the call.
+extern "C" fn jit_output_in_unit(value: f64, unit: *const Unit) {
+    executor::write_output(Backend::Float, MathValue::Float(value), Some(unsafe { &*unit }), None, None)
+}
+extern "C" fn jit_pow(base: f64, exponent: f64) -> f64 { base.powf(exponent) }
+extern "C" fn jit_div_euclid(dividend: f64, divisor: f64) -> f64 { dividend.div_euclid(divisor) }
+extern "C" fn jit_rem_euclid(dividend: f64, divisor: f64) -> f64 { dividend.rem_euclid(divisor) }
+extern "C" fn jit_sin(x: f64) -> f64 { x.sin() }
+extern "C" fn jit_cos(x: f64) -> f64 { x.cos() }
+extern "C" fn jit_tan(x: f64) -> f64 { x.tan() }
+extern "C" fn jit_asin(x: f64) -> f64 { x.asin() }
+extern "C" fn jit_acos(x: f64) -> f64 { x.acos() }
+extern "C" fn jit_atan(x: f64) -> f64 { x.atan() }
+extern "C" fn jit_sinh(x: f64) -> f64 { x.sinh() }
+extern "C" fn jit_cosh(x: f64) -> f64 { x.cosh() }
+extern "C" fn jit_tanh(x: f64) -> f64 { x.tanh() }
+extern "C" fn jit_asinh(x: f64) -> f64 { x.asinh() }
+extern "C" fn jit_acosh(x: f64) -> f64 { x.acosh() }
+extern "C" fn jit_atanh(x: f64) -> f64 { x.atanh() }
+extern "C" fn jit_exp(x: f64) -> f64 { x.exp() }
+extern "C" fn jit_exp2(x: f64) -> f64 { x.exp2() }
+extern "C" fn jit_ln(x: f64) -> f64 { x.ln() }
+extern "C" fn jit_log10(x: f64) -> f64 { x.log10() }
+extern "C" fn jit_log2(x: f64) -> f64 { x.log2() }
+extern "C" fn jit_atan2(y: f64, x: f64) -> f64 { y.atan2(x) }
+
+// (symbol, address, number of f64 parameters, returns an f64)
+fn host_functions() -> Vec<(&'static str, *const u8, usize, bool)> {
+    vec![
+        ("mathlang_output", jit_output as *const u8, 1, false),
+        ("pow", jit_pow as *const u8, 2, true),
+        ("mathlang_div_euclid", jit_div_euclid as *const u8, 2, true),
+        ("mathlang_rem_euclid", jit_rem_euclid as *const u8, 2, true),
+        ("sin", jit_sin as *const u8, 1, true),
+        ("cos", jit_cos as *const u8, 1, true),
+        ("tan", jit_tan as *const u8, 1, true),
+        ("asin", jit_asin as *const u8, 1, true),
+        ("acos", jit_acos as *const u8, 1, true),
+        ("atan", jit_atan as *const u8, 1, true),
+        ("sinh", jit_sinh as *const u8, 1, true),
+        ("cosh", jit_cosh as *const u8, 1, true),
+        ("tanh", jit_tanh as *const u8, 1, true),
+        ("asinh", jit_asinh as *const u8, 1, true),
+        ("acosh", jit_acosh as *const u8, 1, true),
+        ("atanh", jit_atanh as *const u8, 1, true),
+        ("exp", jit_exp as *const u8, 1, true),
+        ("exp2", jit_exp2 as *const u8, 1, true),
+        ("log", jit_ln as *const u8, 1, true),
+        ("log10", jit_log10 as *const u8, 1, true),
+        ("log2", jit_log2 as *const u8, 1, true),
+        ("atan2", jit_atan2 as *const u8, 2, true),
+    ]
+}
+
+struct FunctionLowering<'a> {
+    builder: FunctionBuilder<'a>,
+    functions: &'a HashMap<&'static str, FuncRef>,
+    variables_base: Value,
+    pointer_type: cranelift_codegen::ir::Type,
+    variables: &'a SymbolTable,
+}
+
+impl<'a> FunctionLowering<'a> {
+    fn call(&mut self, name: &str, args: &[Value]) -> Option<Value> {
+        let call = self.builder.ins().call(self.functions[name], args);
+        self.builder.inst_results(call).first().copied()
+    }
+
+    fn slot_offset(handle: usize) -> i32 {
+        (handle * std::mem::size_of::<f64>()) as i32
+    }
+
+    fn load_variable(&mut self, handle: usize) -> Value {
+        self.builder.ins().load(
+            types::F64,
+            MemFlags::trusted(),
+            self.variables_base,
+            Self::slot_offset(handle),
+        )
+    }
+
+    fn store_variable(&mut self, handle: usize, value: Value) {
+        self.builder.ins().store(
+            MemFlags::trusted(),
+            value,
+            self.variables_base,
+            Self::slot_offset(handle),
+        );
+    }
+
+    fn check_float(&self, handle: usize) -> Result<(), String> {
+        match self.variables.get_type(handle) {
+            Some(Type::Int) => Err(format!(
+                "JIT: '{}' is an int, and ints are not supported.",
+                self.variables.get_name(handle)
+            )),
+            Some(Type::Complex) => Err(format!(
+                "JIT: '{}' is complex, and complex numbers are not supported.",
+                self.variables.get_name(handle)
+            )),
+            Some(Type::List) => Err(format!(
+                "JIT: '{}' is a list, and lists are not supported.",
+                self.variables.get_name(handle)
+            )),
+            Some(Type::Matrix) => Err(format!(
+                "JIT: '{}' is a matrix, and matrices are not supported.",
+                self.variables.get_name(handle)
+            )),
+            Some(Type::Polynomial) => Err(format!(
+                "JIT: '{}' is a polynomial, and polynomials are not supported.",
+                self.variables.get_name(handle)
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    fn lower_factor(&mut self, factor: &AnalyzedFactor) -> Result<Value, String> {
+        match factor {
+            AnalyzedFactor::Literal(MathValue::Float(value)) => Ok(self.builder.ins().f64const(*value)),
+            AnalyzedFactor::Literal(MathValue::Int(_)) => {
+                Err("JIT: ints are not supported.".to_string())
+            }
+            AnalyzedFactor::Literal(MathValue::Rational(_))
+            | AnalyzedFactor::Literal(MathValue::Decimal(_))
+            | AnalyzedFactor::Literal(MathValue::Interval(_)) => {
+                Err("JIT: only f64 values are supported.".to_string())
+            }
+            AnalyzedFactor::Literal(MathValue::Uncertain(_)) => {
+                Err("JIT: measured values are not supported.".to_string())
+            }
+            AnalyzedFactor::Literal(MathValue::Complex(_)) => {
+                Err("JIT: complex numbers are not supported.".to_string())
+            }
+            AnalyzedFactor::Literal(MathValue::List(_))
+            | AnalyzedFactor::List(_)
+            | AnalyzedFactor::Range(..)
+            | AnalyzedFactor::Index(..)
+            | AnalyzedFactor::Length(_) => Err("JIT: lists are not supported.".to_string()),
+            AnalyzedFactor::Literal(MathValue::Matrix(_))
+            | AnalyzedFactor::Matrix(_)
+            | AnalyzedFactor::MatrixFunction(_) => Err("JIT: matrices are not supported.".to_string()),
+            AnalyzedFactor::Literal(MathValue::Polynomial(_))
+            | AnalyzedFactor::Polynomial(..)
+            | AnalyzedFactor::PolynomialFunction(_) => Err("JIT: polynomials are not supported.".to_string()),
+            AnalyzedFactor::NumberTheoryFunction(_) => {
+                Err("JIT: number theory functions are not supported.".to_string())
+            }
+            AnalyzedFactor::Random(_) => Err("JIT: random numbers are not supported.".to_string()),
+            AnalyzedFactor::Identifier(handle) => {
+                self.check_float(*handle)?;
+                Ok(self.load_variable(*handle))
+            }
+            AnalyzedFactor::FunctionExpression(f_expr) => self.lower_function_expr(f_expr),
+            AnalyzedFactor::SubExpression(expr) => self.lower_expr(expr),
+            AnalyzedFactor::Derivative(..) => Err("JIT: diff is not supported.".to_string()),
+            AnalyzedFactor::Integral(_) => Err("JIT: integrate is not supported.".to_string()),
+            AnalyzedFactor::Series(series) => {
+                Err(format!("JIT: {} is not supported.", series_name(series.0)))
+            }
+            AnalyzedFactor::Statistic(statistic) => {
+                Err(format!("JIT: {} is not supported.", statistic_name(statistic.0)))
+            }
+        }
+    }
+
+    fn lower_term(&mut self, term: &AnalyzedTerm) -> Result<Value, String> {
+        let mut result = self.lower_factor(&term.0)?;
+        for factor in &term.1 {
+            let operand = self.lower_factor(&factor.1)?;
+            result = match factor.0 {
+                TermOperator::Multiply => self.builder.ins().fmul(result, operand),
+                TermOperator::Divide => self.builder.ins().fdiv(result, operand),
+                TermOperator::Exponent => self.call("pow", &[result, operand]).unwrap(),
+                TermOperator::IntegerDivide => {
+                    self.call("mathlang_div_euclid", &[result, operand]).unwrap()
+                }
+                TermOperator::MatrixMultiply => return Err("JIT: matrices are not supported.".to_string()),
+            };
+        }
+        Ok(result)
+    }
+
+    fn lower_expr(&mut self, expr: &AnalyzedExpr) -> Result<Value, String> {
+        let mut result = self.lower_term(&expr.0)?;
+        for term in &expr.1 {
+            let operand = self.lower_term(&term.1)?;
+            result = match term.0 {
+                ExprOperator::Add => self.builder.ins().fadd(result, operand),
+                ExprOperator::Subtract => self.builder.ins().fsub(result, operand),
+                ExprOperator::Modulo => self.call("mathlang_rem_euclid", &[result, operand]).unwrap(),
+                ExprOperator::PlusMinus => {
+                    return Err("JIT: measured values are not supported.".to_string())
+                }
+            };
+        }
+        Ok(result)
+    }
+
+    fn lower_function_expr(&mut self, function_expr: &AnalyzedFunctionExpr) -> Result<Value, String> {
+        let argument = self.lower_expr(&function_expr.1)?;
+        let libm_name = match function_expr.0 {
+            FunctionOperator::Sin => "sin",
+            FunctionOperator::Cos => "cos",
+            FunctionOperator::Tan => "tan",
+            FunctionOperator::ArcSin => "asin",
+            FunctionOperator::ArcCos => "acos",
+            FunctionOperator::ArcTan => "atan",
+            FunctionOperator::Sinh => "sinh",
+            FunctionOperator::Cosh => "cosh",
+            FunctionOperator::Tanh => "tanh",
+            FunctionOperator::ArcSinh => "asinh",
+            FunctionOperator::ArcCosh => "acosh",
+            FunctionOperator::ArcTanh => "atanh",
+            FunctionOperator::Exp2 => "exp2",
+            FunctionOperator::Exp => "exp",
+            FunctionOperator::Log => "log",
+            FunctionOperator::Log10 => "log10",
+            FunctionOperator::Log2 => "log2",
+            FunctionOperator::Abs => return Ok(self.builder.ins().fabs(argument)),
+            FunctionOperator::Ceil => return Ok(self.builder.ins().ceil(argument)),
+            FunctionOperator::Floor => return Ok(self.builder.ins().floor(argument)),
+            FunctionOperator::Sqrt => return Ok(self.builder.ins().sqrt(argument)),
+            // The argument of float() is a float here, since ints are rejected.
+            // Arguments are real here, since complex numbers are rejected.
+            FunctionOperator::ToFloat | FunctionOperator::Re | FunctionOperator::Conj => {
+                return Ok(argument)
+            }
+            FunctionOperator::Im => return Ok(self.builder.ins().f64const(0.0)),
+            FunctionOperator::Arg => {
+                let zero = self.builder.ins().f64const(0.0);
+                return Ok(self.call("atan2", &[zero, argument]).unwrap());
+            }
+            FunctionOperator::ToInt => return Err("JIT: ints are not supported.".to_string()),
+            FunctionOperator::Signum => {
+                // Same as f64::signum: NaN stays NaN, otherwise +-1 with the sign of the argument.
+                let one = self.builder.ins().f64const(1.0);
+                let signed_one = self.builder.ins().fcopysign(one, argument);
+                let is_nan = self.builder.ins().fcmp(FloatCC::Unordered, argument, argument);
+                return Ok(self.builder.ins().select(is_nan, argument, signed_one));
+            }
+        };
+        Ok(self.call(libm_name, &[argument]).unwrap())
+    }
+
+    fn lower_statement(&mut self, statement: &AnalyzedStatement) -> Result<(), String> {
+        match statement {
+            AnalyzedStatement::Assignment(handle, expr)
+            | AnalyzedStatement::DeclarationToAssignment(handle, expr) => {
+                self.check_float(*handle)?;
+                let value = self.lower_expr(expr)?;
+                self.store_variable(*handle, value);
+            }
+            AnalyzedStatement::Declaration(handle) => self.check_float(*handle)?,
+            // A float read at run time may be a measured value, which the
+            // JIT could only keep the nominal value of.
+            AnalyzedStatement::InputOperation(..) => {
+                return Err("JIT: inputs are not supported, since they may be measured values.".to_string());
+            }
+            AnalyzedStatement::OutputOperation(_, _, Some(_), _) | AnalyzedStatement::OutputOperation(_, _, _, Some(_)) => {
+                return Err("JIT: output formats and labels are not supported.".to_string());
+            }
+            AnalyzedStatement::OutputOperation(expr, None, None, None) => {
+                let value = self.lower_expr(expr)?;
+                self.call("mathlang_output", &[value]);
+            }
+            AnalyzedStatement::OutputOperation(expr, Some(unit), None, None) => {
+                let value = self.lower_expr(expr)?;
+                let unit = self
+                    .builder
+                    .ins()
+                    .iconst(self.pointer_type, unit as *const Unit as i64);
+                self.call("mathlang_output_in_unit", &[value, unit]);
+            }
+            AnalyzedStatement::GradientOutput(..) => {
+                return Err("JIT: grad is not supported.".to_string());
+            }
+            AnalyzedStatement::SymbolicDerivative(..) => {
+                return Err("JIT: derive is not supported.".to_string());
+            }
+            AnalyzedStatement::Solve(_) => {
+                return Err("JIT: solve is not supported.".to_string());
+            }
+            AnalyzedStatement::Seed(_) => {
+                return Err("JIT: seed is not supported.".to_string());
+            }
+            AnalyzedStatement::AngleMode(_) => {}
+        }
+        Ok(())
+    }
+}
+
+fn new_module() -> Result<JITModule, String> {
+    let mut flag_builder = settings::builder();
+    flag_builder
+        .set("use_colocated_libcalls", "false")
+        .map_err(|err| err.to_string())?;
+    flag_builder.set("is_pic", "false").map_err(|err| err.to_string())?;
+    flag_builder.set("opt_level", "speed").map_err(|err| err.to_string())?;
+    let isa = cranelift_native::builder()
+        .map_err(|msg| format!("JIT: host machine is not supported: {}", msg))?
+        .finish(settings::Flags::new(flag_builder))
+        .map_err(|err| err.to_string())?;
+
+    let mut jit_builder = JITBuilder::with_isa(isa, default_libcall_names());
+    for (name, address, _, _) in host_functions() {
+        jit_builder.symbol(name, address);
+    }
+    jit_builder.symbol("mathlang_output_in_unit", jit_output_in_unit as *const u8);
+    Ok(JITModule::new(jit_builder))
+}
+
+/// Lowers the whole program to a single native function operating on the
+/// values of the symbol table, runs it, and stores the final values back.
+pub fn execute_program(variables: &mut SymbolTable, program: &AnalyzedProgram) -> Result<(), String> {
+    let mut module = new_module()?;
+    let pointer_type = module.target_config().pointer_type();
+
+    let mut signature = module.make_signature();
+    signature.params.push(AbiParam::new(pointer_type));
+    let main_id = module
+        .declare_function("mathlang_main", Linkage::Local, &signature)
+        .map_err(|err| err.to_string())?;
+
+    let mut imports = Vec::new();
+    for (name, _, arity, returns_value) in host_functions() {
+        let mut host_signature = module.make_signature();
+        for _ in 0..arity {
+            host_signature.params.push(AbiParam::new(types::F64));
+        }
+        if returns_value {
+            host_signature.returns.push(AbiParam::new(types::F64));
+        }
+        let id = module
+            .declare_function(name, Linkage::Import, &host_signature)
+            .map_err(|err| err.to_string())?;
+        imports.push((name, id));
+    }
+    let mut output_in_unit_signature = module.make_signature();
+    output_in_unit_signature.params.push(AbiParam::new(types::F64));
+    output_in_unit_signature.params.push(AbiParam::new(pointer_type));
+    let output_in_unit_id = module
+        .declare_function("mathlang_output_in_unit", Linkage::Import, &output_in_unit_signature)
+        .map_err(|err| err.to_string())?;
+    imports.push(("mathlang_output_in_unit", output_in_unit_id));
+
+    let mut context = module.make_context();
+    context.func.signature = signature;
+    context.func.name = UserFuncName::user(0, main_id.as_u32());
+    let mut builder_context = FunctionBuilderContext::new();
+    {
+        let mut builder = FunctionBuilder::new(&mut context.func, &mut builder_context);
+        let mut functions = HashMap::new();
+        for (name, id) in &imports {
+            functions.insert(*name, module.declare_func_in_func(*id, builder.func));
+        }
+
+        let entry_block = builder.create_block();
+        builder.append_block_params_for_function_params(entry_block);
+        builder.switch_to_block(entry_block);
+        builder.seal_block(entry_block);
+        let variables_base = builder.block_params(entry_block)[0];
+
+        let mut lowering = FunctionLowering {
+            builder,
+            functions: &functions,
+            variables_base,
+            pointer_type,
+            variables,
+        };
+        for statement in program {
+            lowering.lower_statement(statement)?;
+        }
+        lowering.builder.ins().return_(&[]);
+        lowering.builder.finalize();
+    }
+
+    module
+        .define_function(main_id, &mut context)
+        .map_err(|err| format!("JIT: {:?}", err))?;
+    module.clear_context(&mut context);
+    module.finalize_definitions().map_err(|err| err.to_string())?;
+
+    let mut values: Vec<f64> = variables.iter().map(|entry| entry.1.as_float()).collect();
+    let code = module.get_finalized_function(main_id);
+    // The generated function only touches slots of handles produced by the
+    // analyzer, which are all within `values`.
+    let compiled_main = unsafe { std::mem::transmute::<*const u8, extern "C" fn(*mut f64)>(code) };
+    compiled_main(values.as_mut_ptr());
+
+    for (handle, value) in values.into_iter().enumerate() {
+        variables.set_value(handle, MathValue::Float(value));
+    }
+    unsafe { module.free_memory() };
+    Ok(())
+}
@@ -0,0 +1,192 @@
+// Linear algebra over matrices stored as their rows, and their printing in
+// aligned columns.
+//
+// Determinants, inverses and solutions of linear systems come from the LU
+// decomposition of the matrix with partial pivoting: the largest remaining
+// element of each column is taken as the pivot, which keeps the rounding
+// errors small. A zero pivot means that the matrix is singular.
+
+pub fn describe_shape<T>(matrix: &[Vec<T>]) -> String {
+    format!("{}x{}", matrix.len(), matrix.first().map_or(0, Vec::len))
+}
+
+/// Checks that the rows are not empty and have the same length.
+pub fn check_rows<T>(rows: &[Vec<T>]) -> Result<(), String> {
+    match rows.first().map(Vec::len) {
+        None | Some(0) => Err("Error: A matrix needs at least one row and one column.".to_string()),
+        Some(columns) => match rows.iter().find(|row| row.len() != columns) {
+            Some(row) => Err(format!(
+                "Error: The rows of a matrix have lengths {} and {}.",
+                columns,
+                row.len()
+            )),
+            None => Ok(()),
+        },
+    }
+}
+
+/// The number of rows or columns given by `value`.
+pub fn matrix_size(value: f64) -> Result<usize, String> {
+    if value >= 1. && value.fract() == 0. && value <= u32::MAX as f64 {
+        Ok(value as usize)
+    } else {
+        Err(format!(
+            "Error: The size of a matrix must be a positive whole number, not {}.",
+            value
+        ))
+    }
+}
+
+pub fn identity(size: f64) -> Result<Vec<Vec<f64>>, String> {
+    let size = matrix_size(size)?;
+    Ok((0..size)
+        .map(|row| (0..size).map(|column| if row == column { 1. } else { 0. }).collect())
+        .collect())
+}
+
+pub fn zeros(rows: f64, columns: f64) -> Result<Vec<Vec<f64>>, String> {
+    Ok(vec![vec![0.; matrix_size(columns)?]; matrix_size(rows)?])
+}
+
+pub fn transpose<T: Clone>(matrix: &[Vec<T>]) -> Vec<Vec<T>> {
+    (0..matrix.first().map_or(0, Vec::len))
+        .map(|column| matrix.iter().map(|row| row[column].clone()).collect())
+        .collect()
+}
+
+pub fn multiply(left: &[Vec<f64>], right: &[Vec<f64>]) -> Result<Vec<Vec<f64>>, String> {
+    if left.first().map_or(0, Vec::len) != right.len() {
+        return Err(format!(
+            "Error: Cannot multiply matrices of shapes {} and {}.",
+            describe_shape(left),
+            describe_shape(right)
+        ));
+    }
+    let columns = transpose(right);
+    Ok(left
+        .iter()
+        .map(|row| columns.iter().map(|column| dot(row, column)).collect())
+        .collect())
+}
+
+fn dot(left: &[f64], right: &[f64]) -> f64 {
+    left.iter().zip(right).map(|(left, right)| left * right).sum()
+}
+
+/// The dot product of two lists of the same length.
+pub fn inner_product(left: &[f64], right: &[f64]) -> Result<f64, String> {
+    if left.len() != right.len() {
+        return Err(format!(
+            "Error: Cannot multiply lists of lengths {} and {}.",
+            left.len(),
+            right.len()
+        ));
+    }
+    Ok(dot(left, right))
+}
+
+fn check_square(name: &str, matrix: &[Vec<f64>]) -> Result<(), String> {
+    if matrix.len() != matrix.first().map_or(0, Vec::len) {
+        return Err(format!(
+            "Error: {} needs a square matrix, not a {} one.",
+            name,
+            describe_shape(matrix)
+        ));
+    }
+    Ok(())
+}
+
+// The LU decomposition of a square matrix, with the order of the rows after
+// the swaps and the sign of their permutation, or None if it is singular.
+fn decompose(matrix: &[Vec<f64>]) -> Option<(Vec<Vec<f64>>, Vec<usize>, f64)> {
+    let size = matrix.len();
+    let mut lu = matrix.to_vec();
+    let mut order: Vec<usize> = (0..size).collect();
+    let mut sign = 1.;
+    for column in 0..size {
+        let pivot = (column..size)
+            .max_by(|a, b| lu[*a][column].abs().total_cmp(&lu[*b][column].abs()))
+            .unwrap();
+        if lu[pivot][column] == 0. {
+            return None;
+        }
+        if pivot != column {
+            lu.swap(pivot, column);
+            order.swap(pivot, column);
+            sign = -sign;
+        }
+        let pivot_row = lu[column].clone();
+        for row in &mut lu[column + 1..] {
+            let factor = row[column] / pivot_row[column];
+            row[column] = factor;
+            for (element, pivot_element) in row.iter_mut().zip(&pivot_row).skip(column + 1) {
+                *element -= factor * pivot_element;
+            }
+        }
+    }
+    Some((lu, order, sign))
+}
+
+pub fn determinant(matrix: &[Vec<f64>]) -> Result<f64, String> {
+    check_square("det", matrix)?;
+    Ok(match decompose(matrix) {
+        Some((lu, _, sign)) => sign * (0..lu.len()).map(|index| lu[index][index]).product::<f64>(),
+        None => 0.,
+    })
+}
+
+/// The matrix `x` such that `matrix @ x` is `right`.
+pub fn solve(matrix: &[Vec<f64>], right: &[Vec<f64>]) -> Result<Vec<Vec<f64>>, String> {
+    check_square("solve", matrix)?;
+    if right.len() != matrix.len() {
+        return Err(format!(
+            "Error: solve needs a right-hand side of {} rows, not {}.",
+            matrix.len(),
+            right.len()
+        ));
+    }
+    let (lu, order, _) = decompose(matrix).ok_or_else(|| "Error: The matrix is singular.".to_string())?;
+    let solution: Vec<Vec<f64>> = transpose(right)
+        .iter()
+        .map(|column| {
+            let mut x: Vec<f64> = order.iter().map(|row| column[*row]).collect();
+            for row in 0..x.len() {
+                x[row] -= dot(&lu[row][..row], &x[..row]);
+            }
+            for row in (0..x.len()).rev() {
+                x[row] = (x[row] - dot(&lu[row][row + 1..], &x[row + 1..])) / lu[row][row];
+            }
+            x
+        })
+        .collect();
+    Ok(transpose(&solution))
+}
+
+pub fn inverse(matrix: &[Vec<f64>]) -> Result<Vec<Vec<f64>>, String> {
+    check_square("inv", matrix)?;
+    solve(matrix, &identity(matrix.len() as f64)?)
+}
+
+/// One row per line, with the elements of each column aligned on the right.
+pub fn format<T: std::fmt::Display>(matrix: &[Vec<T>]) -> String {
+    let cells: Vec<Vec<String>> = matrix
+        .iter()
+        .map(|row| row.iter().map(|element| element.to_string()).collect())
+        .collect();
+    let widths: Vec<usize> = transpose(&cells)
+        .iter()
+        .map(|column| column.iter().map(|cell| cell.chars().count()).max().unwrap_or(0))
+        .collect();
+    let rows: Vec<String> = cells
+        .iter()
+        .map(|row| {
+            let row: Vec<String> = row
+                .iter()
+                .zip(&widths)
+                .map(|(cell, width)| format!("{:>width$}", cell, width = width))
+                .collect();
+            format!("[{}]", row.join(", "))
+        })
+        .collect();
+    rows.join("\n")
+}
@@ -1,141 +1,273 @@
 mod analyzer;
 mod compiler;
+mod complex;
+mod dataflow;
+mod decimal;
+mod diagnostics;
+mod dual;
 mod executor;
+mod interval;
+#[cfg(feature = "jit")]
+mod jit;
+mod linear_algebra;
+mod number_format;
+mod number_theory;
+mod numeric;
+mod optimizer;
 mod parser;
+mod polynomial;
+mod quadrature;
+mod random;
+mod range_analysis;
+mod rational;
+mod roots;
+mod statistics;
+mod summation;
 mod symbol_table;
+mod symbolic;
+mod uncertain;
+mod units;
+mod unparser;
+mod value;
 
+use crate::analyzer::AnalyzedProgram;
+use crate::decimal::{Context, Rounding};
+use crate::diagnostics::{Lint, LintLevel, LintLevels};
+use crate::number_format::Format;
+use crate::numeric::Backend;
 use crate::symbol_table::SymbolTable;
 
 const MATH_SUFFIX: &str = ".math";
-const PI: f64 = 3.1415926535897931;
-const EXP: f64 = 2.7182818284590451;
+
+// Settings given by flags that may appear anywhere on the command line.
+struct Options {
+    lint_levels: LintLevels,
+    backend: Backend,
+    // Relative tolerance of `integrate`.
+    tolerance: f64,
+    // Seed of `rand()` and the other random numbers, from the clock if none.
+    seed: Option<u64>,
+    // Format of the outputs without one, Rust's default if none.
+    format: Option<Format>,
+}
+
+fn parse_options(args: Vec<String>) -> Result<(Options, Vec<String>), String> {
+    let mut options = Options {
+        lint_levels: LintLevels::new(),
+        backend: Backend::Float,
+        tolerance: symbol_table::DEFAULT_TOLERANCE,
+        seed: None,
+        format: None,
+    };
+    let mut rational = false;
+    let mut intervals = false;
+    let mut digits = None;
+    let mut precision = None;
+    let mut rounding = None;
+    let mut positional_args = Vec::new();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--allow" | "--warn" | "--deny" => match args.next() {
+                Some(lint) => options.lint_levels.set_from_flag(&arg, &lint)?,
+                None => return Err(format!("Additional argument needed: {} <lint>", arg)),
+            },
+            "--rational" => rational = true,
+            "--intervals" => intervals = true,
+            "--digits" => match args.next().map(|count| count.parse::<usize>()) {
+                Some(Ok(count)) => digits = Some(count),
+                _ => return Err("Additional argument needed: --digits <number of decimals>".to_string()),
+            },
+            "--precision" => match args.next().map(|count| count.parse::<usize>()) {
+                Some(Ok(count)) if count > 0 => precision = Some(count),
+                _ => return Err("Additional argument needed: --precision <number of digits>".to_string()),
+            },
+            "--tolerance" => match args.next().map(|tolerance| tolerance.parse::<f64>()) {
+                Some(Ok(tolerance)) if tolerance > 0. && tolerance.is_finite() => options.tolerance = tolerance,
+                _ => return Err("Additional argument needed: --tolerance <relative tolerance>".to_string()),
+            },
+            "--seed" => match args.next().map(|seed| seed.parse::<u64>()) {
+                Some(Ok(seed)) => options.seed = Some(seed),
+                _ => return Err("Additional argument needed: --seed <natural number>".to_string()),
+            },
+            "--format" => match args.next() {
+                Some(format) => options.format = Some(format.parse()?),
+                None => return Err("Additional argument needed: --format <format>".to_string()),
+            },
+            "--rounding" => match args.next() {
+                Some(mode) => rounding = Some(Rounding::from_name(&mode)?),
+                None => return Err("Additional argument needed: --rounding <mode>".to_string()),
+            },
+            _ => positional_args.push(arg),
+        }
+    }
+    if [rational, precision.is_some(), intervals].iter().filter(|&&given| given).count() > 1 {
+        return Err("Only one of --rational, --precision and --intervals can be given.".to_string());
+    } else if intervals {
+        options.backend = Backend::Interval;
+    } else if rational {
+        options.backend = Backend::Rational(digits);
+    } else if digits.is_some() {
+        return Err("--digits only applies with --rational.".to_string());
+    }
+    if let Some(precision) = precision {
+        options.backend = Backend::Decimal(Context {
+            precision,
+            rounding: rounding.unwrap_or(Rounding::HalfEven),
+        });
+    } else if rounding.is_some() {
+        return Err("--rounding only applies with --precision.".to_string());
+    }
+    Ok((options, positional_args))
+}
 
 fn main() {
 
     let mut args = std::env::args();
     let current_program_path = args.next().unwrap();
+    let (mut options, args) = match parse_options(args.collect()) {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            eprintln!("{}", err);
+            return;
+        }
+    };
+    let mut args = args.into_iter();
     let option = args.next();
+    if options.backend != Backend::Float
+        && !matches!(option.as_deref(), Some("--repl") | Some("--interpret") | Some("--run"))
+    {
+        eprintln!("--rational, --precision and --intervals only apply to --run, --interpret and --repl.");
+        return;
+    }
 
     match option.as_deref() {
-        Some("--repl") => run_interpreter(),
+        Some("--repl") => run_interpreter(&options),
         Some("--compile") => {
-            let source_path = args.next();
-            
-            match source_path.as_deref() {
-                Some(file) => { 
-                    if file.ends_with(MATH_SUFFIX) {
-                        process_file(&current_program_path, &source_path.unwrap());    
-                    } else {
-                        eprintln!(
-                            "{}: Invalid argument '{}': It must end with {}",
-                            current_program_path, file, MATH_SUFFIX
-                        );
-                        return;
-                    }
-                }  
-                None => {
-                    eprintln!("Additional argumeng needed: <file.math>");
-                    return;
-                }
-            }        
+            match args.next().as_deref() {
+                Some(file) => process_file(&current_program_path, file, &options),
+                None => eprintln!("Additional argumeng needed: <file.math>"),
+            }
         }
         Some("--interpret") => {
-            let program = args.next();
-            match program.as_deref() {
-                Some(program) => { interpret(program)},
-                None => {
-                    eprintln!("Additional argumeng needed: <string>");
-                    return;
-                }
-
+            // As in the REPL, the outputs are mostly calculations on
+            // constants, which are not worth a warning.
+            options.lint_levels.set(Lint::ConstantOutput, LintLevel::Allow);
+            match args.next().as_deref() {
+                Some(program) => interpret(program, &options),
+                None => eprintln!("Additional argumeng needed: <string>"),
             }
         }
         Some("--run") => {
-            let source_path = args.next();
-            
-            match source_path.as_deref() {
-                Some(file) => { 
-                    if file.ends_with(MATH_SUFFIX) {
-                        run_file(&current_program_path, &source_path.unwrap());    
-                    } else {
-                        eprintln!(
-                            "{}: Invalid argument '{}': It must end with {}",
-                            current_program_path, file, MATH_SUFFIX
-                        );
-                        return;
-                    }
-                }  
-                None => {
-                    eprintln!("Additional argumeng needed: <file.math>");
-                    return;
-                }
+            match args.next().as_deref() {
+                Some(file) => run_file(&current_program_path, file, &options),
+                None => eprintln!("Additional argumeng needed: <file.math>"),
+            }
+        }
+        Some("--emit") => {
+            match (args.next().as_deref(), args.next().as_deref()) {
+                (Some(stage), Some(file)) => emit_file(&current_program_path, stage, file, &options),
+                _ => eprintln!("Additional arguments needed: <analyzed|optimized|rust> <file.math>"),
+            }
+        }
+        #[cfg(feature = "jit")]
+        Some("--jit") => {
+            match args.next().as_deref() {
+                Some(file) => jit_file(&current_program_path, file, &options),
+                None => eprintln!("Additional argumeng needed: <file.math>"),
             }
         }
         Some(_) => {
-            eprintln!("Command not recognized. Supported commands are --run <file.math>, --compile <file.math>, --intepret <string> , and --repl");
-            return;
+            eprintln!("Command not recognized. Supported commands are --run <file.math>, --compile <file.math>, --emit <stage> <file.math>, --intepret <string> , and --repl");
         }
         None => {
-            eprintln!("Additional Command needed. Options are --run <file.math>, --compile <file.math>, --intepret <string> , and --repl");
-            return;
+            eprintln!("Additional Command needed. Options are --run <file.math>, --compile <file.math>, --emit <stage> <file.math>, --intepret <string> , and --repl");
         }
-    } 
+    }
 
 }
 
-fn process_file(current_program_path: &str, source_path: &str) {
-    
+fn read_source_file(current_program_path: &str, source_path: &str) -> Option<String> {
     if !source_path.ends_with(MATH_SUFFIX) {
         eprintln!(
             "{}: Invalid argument '{}': It must end with {}",
             current_program_path, source_path, MATH_SUFFIX
         );
-        return;
+        return None;
     }
-    let target_path = source_path[0..source_path.len() - MATH_SUFFIX.len()].to_string() + ".rs";
-    let source_code = std::fs::read_to_string(&source_path);
-    if source_code.is_err() {
-        eprintln!(
-            "Failed to read from file {}: ({})",
-            source_path,
-            source_code.unwrap_err()
-        );
-        return;
+    match std::fs::read_to_string(source_path) {
+        Ok(source_code) => Some(source_code),
+        Err(err) => {
+            eprintln!("Failed to read from file {}: ({})", source_path, err);
+            None
+        }
     }
-    let source_code = source_code.unwrap();
+}
 
-    let parsed_program;
-    match parser::parse_program(&source_code) {
+fn check_program(variables: &SymbolTable, program: &AnalyzedProgram, options: &Options) -> bool {
+    let mut diagnostics = analyzer::check_initialization(variables, program);
+    diagnostics.extend(dataflow::check_program(variables, program));
+    diagnostics.extend(range_analysis::check_program(variables, program));
+    match diagnostics::report(&options.lint_levels, &diagnostics) {
+        Ok(()) => true,
+        Err(err) => {
+            eprintln!("{}", err);
+            false
+        }
+    }
+}
+
+fn analyze_source(
+    variables: &mut SymbolTable,
+    source_path: &str,
+    source_code: &str,
+    options: &Options,
+) -> Option<AnalyzedProgram> {
+    let parsed_program = match parser::parse_program(source_code) {
         Ok((rest, syntax_tree)) => {
             let trimmed_rest = rest.trim();
-            if trimmed_rest.len() > 0 {
+            if !trimmed_rest.is_empty() {
                 eprintln!(
                     "Invalid remaining code in '{}': {}",
                     source_path, trimmed_rest
                 );
-                return;
+                return None;
             }
-            parsed_program = syntax_tree;
+            syntax_tree
         }
         Err(err) => {
             eprintln!("Invalid code in '{}': {:?}", source_path, err);
-            return;
+            return None;
         }
-    }
+    };
 
-    let analyzed_program;
-    let mut variables = symbol_table::SymbolTable::new();
-    initialize_math_constants(&mut variables);
-    
-    match analyzer::analyze_program(&mut variables, &parsed_program) {
+    match analyzer::analyze_program(variables, &parsed_program) {
         Ok(analyzed_tree) => {
-            analyzed_program = analyzed_tree;
+            if check_program(variables, &analyzed_tree, options) {
+                Some(analyzed_tree)
+            } else {
+                None
+            }
         }
         Err(err) => {
             eprintln!("Invalid code in '{}': {}", source_path, err);
-            return;
+            None
         }
-    } 
+    }
+}
+
+fn process_file(current_program_path: &str, source_path: &str, options: &Options) {
+    let source_code = match read_source_file(current_program_path, source_path) {
+        Some(source_code) => source_code,
+        None => return,
+    };
+    let target_path = source_path[0..source_path.len() - MATH_SUFFIX.len()].to_string() + ".rs";
+
+    let mut variables = new_symbol_table(options);
+    let analyzed_program = match analyze_source(&mut variables, source_path, &source_code, options) {
+        Some(analyzed_program) => optimizer::optimize_program(&variables, analyzed_program),
+        None => return,
+    };
 
     match std::fs::write(
         &target_path,
@@ -146,97 +278,140 @@ fn process_file(current_program_path: &str, source_path: &str) {
     }
 }
 
-fn interpret(program:&str) {
+fn emit_file(current_program_path: &str, stage: &str, source_path: &str, options: &Options) {
+    if !["analyzed", "optimized", "rust"].contains(&stage) {
+        eprintln!("Unknown stage '{}'. Stages are analyzed, optimized and rust.", stage);
+        return;
+    }
+    let source_code = match read_source_file(current_program_path, source_path) {
+        Some(source_code) => source_code,
+        None => return,
+    };
+
+    let mut variables = new_symbol_table(options);
+    let analyzed_program = match analyze_source(&mut variables, source_path, &source_code, options) {
+        Some(analyzed_program) => analyzed_program,
+        None => return,
+    };
+    if stage == "analyzed" {
+        print!("{}", unparser::unparse_program(&variables, &analyzed_program));
+        return;
+    }
+    let optimized_program = optimizer::optimize_program(&variables, analyzed_program);
+    if stage == "optimized" {
+        print!("{}", unparser::unparse_program(&variables, &optimized_program));
+    } else {
+        print!("{}", compiler::translate_to_rust_program(&variables, &optimized_program));
+    }
+}
+
+fn interpret(program:&str, options: &Options) {
     eprintln!("\n* Interpreting *\n");
-    let mut variables = symbol_table::SymbolTable::new();
-    initialize_math_constants(&mut variables);
+    let mut variables = new_symbol_table(options);
     let program = program.trim();
-    
-    loop  {
-        let pattern = parser::parse_program(&program);
-        match pattern {
-            Ok((rest, parsed_program)) => { 
-                if rest.len() > 0 {
-                    //eprintln!("Symbol table: {:#?}", &mut variables); 
-                    eprintln!("Unparsed input: `{}`.", rest)
-                } else {
-                    match analyzer::analyze_program(&mut variables, &parsed_program) {
-                        Ok(analyzed_program) => {
-                            //eprintln!("Symbol table: {:#?}", &mut variables); 
-                            //eprint!("\n");
-                            //eprintln!("Analyzed program: {:#?}", &analyzed_program);
-                            executor::execute_program(&mut variables, &analyzed_program);   
-                            break;
+
+    match parser::parse_program(program) {
+        Ok((rest, parsed_program)) => {
+            if !rest.is_empty() {
+                eprintln!("Unparsed input: `{}`.", rest)
+            } else {
+                match analyzer::analyze_program(&mut variables, &parsed_program) {
+                    Ok(analyzed_program) if check_program(&variables, &analyzed_program, options) => {
+                        //eprintln!("Analyzed program: {:#?}", &analyzed_program);
+                        let analyzed_program = optimizer::optimize_program(&variables, analyzed_program);
+                        if let Err(err) = executor::execute_program(&mut variables, &analyzed_program) {
+                            eprintln!("{}", err);
                         }
-                        Err(err) => {eprintln!("Error: {}", err); break;},
                     }
+                    Ok(_) => {}
+                    Err(err) => eprintln!("Error: {}", err),
                 }
             }
-            Err(err) =>  {eprintln!("Error: {:?}", err); break;},
-        } 
-        break; 
+        }
+        Err(err) => eprintln!("Error: {:?}", err),
     }
 }
 
-fn run_file(current_program_path: &str, source_path: &str) {
-    
-    if !source_path.ends_with(MATH_SUFFIX) {
-        eprintln!(
-            "{}: Invalid argument '{}': It must end with {}",
-            current_program_path, source_path, MATH_SUFFIX
-        );
-        return;
+fn run_file(current_program_path: &str, source_path: &str, options: &Options) {
+    if let Some(source_code) = read_source_file(current_program_path, source_path) {
+        interpret(source_code.trim(), options)
     }
-    let source_code = std::fs::read_to_string(&source_path);
-    if source_code.is_err() {
-        eprintln!(
-            "Failed to read from file {}: ({})",
-            source_path,
-            source_code.unwrap_err()
-        );
-        return;
+}
+
+#[cfg(feature = "jit")]
+fn jit_file(current_program_path: &str, source_path: &str, options: &Options) {
+    let source_code = match read_source_file(current_program_path, source_path) {
+        Some(source_code) => source_code,
+        None => return,
+    };
+
+    let mut variables = new_symbol_table(options);
+    if let Some(analyzed_program) = analyze_source(&mut variables, source_path, &source_code, options) {
+        let analyzed_program = optimizer::optimize_program(&variables, analyzed_program);
+        // The program is only run once it is fully lowered, so a program the
+        // JIT does not support is run by the interpreter from the start.
+        if let Err(err) = jit::execute_program(&mut variables, &analyzed_program) {
+            eprintln!("{} Falling back to the interpreter.", err);
+            if let Err(err) = executor::execute_program(&mut variables, &analyzed_program) {
+                eprintln!("{}", err);
+            }
+        }
     }
-    let source_code = source_code.unwrap();
-    let source_code = source_code.trim();
-    interpret(&source_code)
 }
 
-fn run_interpreter() {
+fn run_interpreter(options: &Options) {
     eprintln!("\n* Math Interactive Interpreter *\n");
-    let mut variables = symbol_table::SymbolTable::new();
-    initialize_math_constants(&mut variables);
-    
+    // Each line is checked on its own, so a variable declared on one line
+    // and read on a later one would always look unused, and the outputs are
+    // mostly calculations on constants, which are not worth a warning.
+    let mut lint_levels = options.lint_levels.clone();
+    lint_levels.set(Lint::UnusedVariable, LintLevel::Allow);
+    lint_levels.set(Lint::ConstantOutput, LintLevel::Allow);
+    let options = &Options {
+        lint_levels,
+        backend: options.backend,
+        tolerance: options.tolerance,
+        seed: options.seed,
+        format: options.format,
+    };
+    let mut variables = new_symbol_table(options);
+
     loop {
         let command = input_command();
-        if command.len() == 0 {
+        if command.is_empty() {
             break;
         }
         match command.trim() {
             "quit" => {eprintln!("Goodbye"); break},
             "clear" => {
-                variables = symbol_table::SymbolTable::new();
-                initialize_math_constants(&mut variables);
+                // The angle mode is a setting rather than a variable.
+                let angle_unit = variables.angle_unit();
+                variables = new_symbol_table(options);
+                variables.set_angle_unit(angle_unit);
                 eprintln!("Cleared variables.");
             }
             "variables" => {
                 eprintln!("Variables:");
                 for v in variables.iter() {
-                    eprintln!("  {}: {}", v.0, v.1);
+                    eprintln!("  {}: {}", v.0, variables.backend().format(&v.1));
                 }
-                
+
             }
-            trimmed_command => match parser::parse_program(&trimmed_command) {
+            trimmed_command => match parser::parse_program(trimmed_command) {
                 Ok((rest, parsed_program)) => {
-                    if rest.len() > 0 {
-                        //eprintln!("Symbol table: {:#?}", &mut variables); 
+                    if !rest.is_empty() {
                         eprintln!("Unparsed input: `{}`.", rest)
                     } else {
                         match analyzer::analyze_program(&mut variables, &parsed_program) {
-                            Ok(analyzed_program) => {
+                            Ok(analyzed_program) if check_program(&variables, &analyzed_program, options) => {
                                 //println!("Analyzed program: {:#?}", &analyzed_program);
-                                executor::execute_program(&mut variables, &analyzed_program)        
-                                
+                                let analyzed_program = optimizer::optimize_program(&variables, analyzed_program);
+                                if let Err(err) = executor::execute_program(&mut variables, &analyzed_program) {
+                                    eprintln!("{}", err);
+                                }
+
                             }
+                            Ok(_) => {}
                             Err(err) => {eprintln!("Error: {}", err)},
                         }
                     }
@@ -246,7 +421,7 @@ fn run_interpreter() {
         }
 
     }
-    
+
 }
 
 fn input_command() -> String {
@@ -258,18 +433,22 @@ fn input_command() -> String {
     text
 }
 
-fn initialize_math_constants(variables: &mut SymbolTable) {
-    let pi = String::from("pi");
-    let e = String::from("e"); 
-    
-    if let Ok(_) = variables.insert_symbol(&pi){}
-    if let Ok(_) = variables.insert_symbol(&e){}
-    
-    if let Ok(v) = variables.find_symbol(&pi) {
-        variables.set_value(v, PI);
-    }
-    if let Ok(v) = variables.find_symbol(&e) {
-        variables.set_value(v,EXP);
+fn new_symbol_table(options: &Options) -> SymbolTable {
+    let mut variables = SymbolTable::with_backend(options.backend);
+    variables.set_tolerance(options.tolerance);
+    variables.set_seed(options.seed);
+    variables.set_format(options.format);
+    if let Some(seed) = options.seed {
+        random::seed(seed);
     }
+    initialize_math_constants(&mut variables);
+    variables
+}
 
-}
\ No newline at end of file
+// With `--precision`, the constants are computed to the precision, and with
+// `--intervals`, enclosed.
+fn initialize_math_constants(variables: &mut SymbolTable) {
+    let backend = variables.backend();
+    let _ = variables.insert_constant("pi", backend.pi());
+    let _ = variables.insert_constant("e", backend.e());
+}
@@ -0,0 +1,204 @@
+// The formats of `out x : spec` and `--format`, shared by the interpreter and
+// the compiled programs so that a value is printed the same by both.
+//
+// `hex` prints ints in base 16 and floats as hexadecimal significands with a
+// binary exponent, as `%a` in C. `frac` prints the first convergent of the
+// continued fraction of the float within a few units in the last place of
+// it, so that the rounding of `0.1 + 0.2` gives 3/10, and whose terms stay
+// exact.
+
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Format {
+    // `.3f`, with the number of decimals.
+    Fixed(usize),
+    // `e`, or `.3e` with the number of decimals of the significand.
+    Scientific(Option<usize>),
+    // `sig4`, with the number of significant digits.
+    Significant(usize),
+    Hex,
+    Fraction,
+    // `%`, or `.1%` with the number of decimals of the percentage.
+    Percent(Option<usize>),
+}
+
+// The most digits a format prints, so that a mistyped count is an error
+// rather than a string of millions of zeros.
+const MAX_DIGITS: usize = 1000;
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Format, String> {
+        let decimals = |digits: &str| digits.parse::<usize>().ok();
+        let digits = text.trim_start_matches(|c: char| !c.is_ascii_digit());
+        if let Some(count) = decimals(digits.trim_end_matches(|c: char| !c.is_ascii_digit())) {
+            if count > MAX_DIGITS {
+                return Err(format!(
+                    "Error: The format '{}' has more than {} digits.",
+                    text, MAX_DIGITS
+                ));
+            }
+        }
+        let format = match text {
+            "e" => Some(Format::Scientific(None)),
+            "hex" => Some(Format::Hex),
+            "frac" => Some(Format::Fraction),
+            "%" => Some(Format::Percent(None)),
+            _ if text.starts_with("sig") => decimals(&text[3..])
+                .filter(|&digits| digits > 0)
+                .map(Format::Significant),
+            _ if text.starts_with('.') && text.len() > 2 => {
+                let kind = text.chars().last().unwrap_or('.');
+                match (decimals(&text[1..text.len() - kind.len_utf8()]), kind) {
+                    (Some(digits), 'f') => Some(Format::Fixed(digits)),
+                    (Some(digits), 'e') => Some(Format::Scientific(Some(digits))),
+                    (Some(digits), '%') => Some(Format::Percent(Some(digits))),
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+        format.ok_or_else(|| {
+            format!(
+                "Error: Unknown format '{}'; use .<n>f, e, .<n>e, sig<n>, hex, frac, % or .<n>%.",
+                text
+            )
+        })
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Format::Fixed(decimals) => write!(f, ".{}f", decimals),
+            Format::Scientific(None) => write!(f, "e"),
+            Format::Scientific(Some(decimals)) => write!(f, ".{}e", decimals),
+            Format::Significant(digits) => write!(f, "sig{}", digits),
+            Format::Hex => write!(f, "hex"),
+            Format::Fraction => write!(f, "frac"),
+            Format::Percent(None) => write!(f, "%"),
+            Format::Percent(Some(decimals)) => write!(f, ".{}%", decimals),
+        }
+    }
+}
+
+pub fn int(value: i64, format: Format) -> String {
+    match format {
+        Format::Hex if value < 0 => format!("-0x{:x}", value.unsigned_abs()),
+        Format::Hex => format!("0x{:x}", value),
+        Format::Fraction => value.to_string(),
+        _ => float(value as f64, format),
+    }
+}
+
+pub fn float(value: f64, format: Format) -> String {
+    match format {
+        Format::Fixed(decimals) => format!("{:.*}", decimals, value),
+        Format::Scientific(None) => format!("{:e}", value),
+        Format::Scientific(Some(decimals)) => format!("{:.*e}", decimals, value),
+        Format::Significant(digits) => significant(value, digits),
+        Format::Hex => hex(value),
+        Format::Fraction => fraction(value),
+        Format::Percent(None) => format!("{}%", value * 100.),
+        Format::Percent(Some(decimals)) => format!("{:.*}%", decimals, value * 100.),
+    }
+}
+
+pub fn complex(re: f64, im: f64, format: Format) -> String {
+    if im.is_sign_negative() {
+        format!("{}-{}i", float(re, format), float(-im, format))
+    } else {
+        format!("{}+{}i", float(re, format), float(im, format))
+    }
+}
+
+// Positional notation while the exponent is small, scientific otherwise, and
+// the trailing zeros kept since they are significant.
+fn significant(value: f64, digits: usize) -> String {
+    if !value.is_finite() {
+        return value.to_string();
+    }
+    let scientific = format!("{:.*e}", digits - 1, value);
+    let exponent: i64 = match scientific.split_once('e') {
+        Some((_, exponent)) => exponent.parse().unwrap_or(0),
+        None => 0,
+    };
+    if (-5..digits as i64).contains(&exponent) {
+        format!("{:.*}", (digits as i64 - 1 - exponent) as usize, value)
+    } else {
+        scientific
+    }
+}
+
+fn hex(value: f64) -> String {
+    if !value.is_finite() {
+        return value.to_string();
+    }
+    let sign = if value.is_sign_negative() { "-" } else { "" };
+    let bits = value.to_bits();
+    let exponent = ((bits >> 52) & 0x7ff) as i64;
+    let significand = bits & ((1 << 52) - 1);
+    let (leading, exponent) = match exponent {
+        0 if significand == 0 => (0, 0),
+        0 => (0, -1022),
+        _ => (1, exponent - 1023),
+    };
+    let digits = format!("{:013x}", significand);
+    let digits = digits.trim_end_matches('0');
+    if digits.is_empty() {
+        format!("{}0x{}p{:+}", sign, leading, exponent)
+    } else {
+        format!("{}0x{}.{}p{:+}", sign, leading, digits, exponent)
+    }
+}
+
+fn fraction(value: f64) -> String {
+    if !value.is_finite() || value.fract() == 0. {
+        return value.to_string();
+    }
+    // Numerators and denominators above 2^53 would not be exact.
+    const LIMIT: f64 = 9007199254740992.;
+    let target = value.abs();
+    let (mut numerator, mut previous_numerator) = (1., 0.);
+    let (mut denominator, mut previous_denominator) = (0., 1.);
+    let mut rest = target;
+    loop {
+        let term = rest.floor();
+        let next_numerator = term * numerator + previous_numerator;
+        let next_denominator = term * denominator + previous_denominator;
+        if next_numerator > LIMIT || next_denominator > LIMIT {
+            break;
+        }
+        previous_numerator = numerator;
+        previous_denominator = denominator;
+        numerator = next_numerator;
+        denominator = next_denominator;
+        if (numerator / denominator - target).abs() <= 4. * f64::EPSILON * target || rest == term {
+            break;
+        }
+        rest = 1. / (rest - term);
+    }
+    let sign = if value < 0. { "-" } else { "" };
+    if denominator == 1. {
+        format!("{}{}", sign, numerator)
+    } else {
+        format!("{}{}/{}", sign, numerator, denominator)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn digit_counts_are_limited() {
+        assert_eq!(".1000f".parse::<Format>(), Ok(Format::Fixed(1000)));
+        assert_eq!("sig1000".parse::<Format>(), Ok(Format::Significant(1000)));
+        for spec in &[".1001f", ".1000000000f", ".1001e", ".99999999999999999999%", "sig1001"] {
+            assert!(spec.parse::<Format>().is_err(), "{}", spec);
+        }
+    }
+}
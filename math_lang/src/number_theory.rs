@@ -0,0 +1,318 @@
+// Number theory and combinatorics on i64, and the gamma function on f64.
+//
+// Products are computed exactly and checked against the range of i64.
+// Primes are tested by the Miller–Rabin test with the first twelve primes as
+// witnesses, which never fails below 2^64, and composite numbers are split by
+// Pollard's rho method. The gamma function comes from the Lanczos
+// approximation, and from the reflection formula below 1/2.
+
+use std::f64::consts::PI;
+
+const WITNESSES: [u64; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+
+const LANCZOS_G: f64 = 7.;
+
+const LANCZOS: [f64; 9] = [
+    0.999_999_999_999_809_9,
+    676.520_368_121_885_1,
+    -1_259.139_216_722_402_8,
+    771.323_428_777_653_1,
+    -176.615_029_162_140_6,
+    12.507_343_278_686_905,
+    -0.138_571_095_265_720_12,
+    9.984_369_578_019_572e-6,
+    1.505_632_735_149_311_6e-7,
+];
+
+fn overflow(name: &str) -> String {
+    format!("Error: Integer overflow in {}.", name)
+}
+
+fn check_natural(name: &str, values: &[i64]) -> Result<(), String> {
+    match values.iter().find(|value| **value < 0) {
+        Some(value) => Err(format!("Error: {} takes natural numbers, not {}.", name, value)),
+        None => Ok(()),
+    }
+}
+
+/// The int equal to a float argument of `name`, which must be whole.
+pub fn whole_number(name: &str, value: f64) -> Result<i64, String> {
+    if value.fract() == 0. && value >= i64::MIN as f64 && value < -(i64::MIN as f64) {
+        Ok(value as i64)
+    } else {
+        Err(format!("Error: {} takes whole numbers, not {}.", name, value))
+    }
+}
+
+pub fn factorial(n: i64) -> Result<i64, String> {
+    check_natural("factorial", &[n])?;
+    (1..=n)
+        .try_fold(1i64, |product, factor| product.checked_mul(factor))
+        .ok_or_else(|| overflow("factorial"))
+}
+
+/// The number of ways to choose `k` of `n` things, in order.
+pub fn permutations(n: i64, k: i64) -> Result<i64, String> {
+    check_natural("perm", &[n, k])?;
+    if k > n {
+        return Ok(0);
+    }
+    (n - k + 1..=n)
+        .try_fold(1i64, |product, factor| product.checked_mul(factor))
+        .ok_or_else(|| overflow("perm"))
+}
+
+/// The number of ways to choose `k` of `n` things. The partial products
+/// are binomial coefficients themselves, so each division is exact.
+pub fn binomial(n: i64, k: i64) -> Result<i64, String> {
+    check_natural("binom", &[n, k])?;
+    if k > n {
+        return Ok(0);
+    }
+    let k = k.min(n - k);
+    let mut result: i128 = 1;
+    for index in 0..k {
+        result = result * (n - index) as i128 / (index + 1) as i128;
+        if result > i64::MAX as i128 {
+            return Err(overflow("binom"));
+        }
+    }
+    Ok(result as i64)
+}
+
+fn mul_mod(left: u64, right: u64, modulus: u64) -> u64 {
+    (left as u128 * right as u128 % modulus as u128) as u64
+}
+
+fn pow_mod(mut base: u64, mut exponent: u64, modulus: u64) -> u64 {
+    let mut result = 1 % modulus;
+    base %= modulus;
+    while exponent > 0 {
+        if exponent % 2 == 1 {
+            result = mul_mod(result, base, modulus);
+        }
+        base = mul_mod(base, base, modulus);
+        exponent /= 2;
+    }
+    result
+}
+
+fn is_prime_u64(n: u64) -> bool {
+    if n < 2 {
+        return false;
+    }
+    for prime in WITNESSES.iter() {
+        if n.is_multiple_of(*prime) {
+            return n == *prime;
+        }
+    }
+    let zeros = (n - 1).trailing_zeros();
+    let odd = (n - 1) >> zeros;
+    WITNESSES.iter().all(|witness| {
+        let mut x = pow_mod(*witness, odd, n);
+        if x == 1 || x == n - 1 {
+            return true;
+        }
+        for _ in 1..zeros {
+            x = mul_mod(x, x, n);
+            if x == n - 1 {
+                return true;
+            }
+        }
+        false
+    })
+}
+
+pub fn is_prime(n: i64) -> bool {
+    n >= 2 && is_prime_u64(n as u64)
+}
+
+/// The smallest prime greater than `n`.
+pub fn next_prime(n: i64) -> Result<i64, String> {
+    let mut candidate = n.max(1);
+    loop {
+        candidate = candidate.checked_add(1).ok_or_else(|| overflow("nextprime"))?;
+        if is_prime(candidate) {
+            return Ok(candidate);
+        }
+    }
+}
+
+fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        let remainder = a % b;
+        a = b;
+        b = remainder;
+    }
+    a
+}
+
+// A proper divisor of the odd composite `n`, from the cycle of
+// x -> x^2 + c modulo one of its prime factors.
+fn pollard_rho(n: u64) -> u64 {
+    let mut c = 1;
+    loop {
+        let step = |x: u64| (mul_mod(x, x, n) + c) % n;
+        let (mut slow, mut fast, mut divisor) = (2, 2, 1);
+        while divisor == 1 {
+            slow = step(slow);
+            fast = step(step(fast));
+            divisor = gcd(slow.abs_diff(fast), n);
+        }
+        if divisor != n {
+            return divisor;
+        }
+        c += 1;
+    }
+}
+
+fn split(n: u64, factors: &mut Vec<u64>) {
+    if n == 1 {
+        return;
+    }
+    if is_prime_u64(n) {
+        factors.push(n);
+        return;
+    }
+    let divisor = pollard_rho(n);
+    split(divisor, factors);
+    split(n / divisor, factors);
+}
+
+/// The prime factors in increasing order, repeated by their multiplicity.
+pub fn factor(n: i64) -> Result<Vec<i64>, String> {
+    if n < 1 {
+        return Err(format!("Error: factor takes a positive int, not {}.", n));
+    }
+    let mut n = n as u64;
+    let mut factors = Vec::new();
+    for prime in WITNESSES.iter() {
+        while n.is_multiple_of(*prime) {
+            factors.push(*prime);
+            n /= prime;
+        }
+    }
+    split(n, &mut factors);
+    factors.sort_unstable();
+    Ok(factors.into_iter().map(|factor| factor as i64).collect())
+}
+
+fn check_modulus(modulus: i64) -> Result<u64, String> {
+    if modulus < 1 {
+        return Err(format!("Error: The modulus must be a positive int, not {}.", modulus));
+    }
+    Ok(modulus as u64)
+}
+
+/// The `x` in [0, modulus) such that `a * x` is 1 modulo `modulus`.
+pub fn mod_inverse(a: i64, modulus: i64) -> Result<i64, String> {
+    check_modulus(modulus)?;
+    let (mut r, mut next_r) = (modulus as i128, (a as i128).rem_euclid(modulus as i128));
+    let (mut t, mut next_t) = (0i128, 1i128);
+    while next_r != 0 {
+        let quotient = r / next_r;
+        let remainder = r - quotient * next_r;
+        r = next_r;
+        next_r = remainder;
+        let coefficient = t - quotient * next_t;
+        t = next_t;
+        next_t = coefficient;
+    }
+    if r > 1 {
+        return Err(format!("Error: {} has no inverse modulo {}.", a, modulus));
+    }
+    Ok(t.rem_euclid(modulus as i128) as i64)
+}
+
+/// `base ^ exponent` modulo `modulus`, in [0, modulus). A negative exponent
+/// raises the inverse of the base.
+pub fn mod_pow(base: i64, exponent: i64, modulus: i64) -> Result<i64, String> {
+    let unsigned_modulus = check_modulus(modulus)?;
+    let base = if exponent < 0 {
+        mod_inverse(base, modulus)?
+    } else {
+        base.rem_euclid(modulus)
+    };
+    Ok(pow_mod(base as u64, exponent.unsigned_abs(), unsigned_modulus) as i64)
+}
+
+fn check_pole(name: &str, x: f64) -> Result<(), String> {
+    if x <= 0. && x.fract() == 0. {
+        return Err(format!("Error: {}({}) is not defined.", name, x));
+    }
+    Ok(())
+}
+
+// The point `t` and the sum of the series of the Lanczos approximation of
+// the gamma function at x + 1, for x >= -1/2.
+fn lanczos(x: f64) -> (f64, f64) {
+    let series = LANCZOS[1..]
+        .iter()
+        .enumerate()
+        .fold(LANCZOS[0], |sum, (index, coefficient)| sum + coefficient / (x + index as f64 + 1.));
+    (x + LANCZOS_G + 0.5, series)
+}
+
+// Whole arguments give exact products as long as they are representable.
+pub fn gamma(x: f64) -> Result<f64, String> {
+    check_pole("gamma", x)?;
+    if x.fract() == 0. && x <= 171. {
+        return Ok((1..x as i64).fold(1., |product, factor| product * factor as f64));
+    }
+    if x == f64::INFINITY {
+        return Ok(x);
+    }
+    if x < 0.5 {
+        return Ok(PI / ((PI * x).sin() * gamma(1. - x)?));
+    }
+    // `t^(x - 1/2)` is split in two so that no factor overflows before the
+    // result does.
+    let (t, series) = lanczos(x - 1.);
+    let power = t.powf((x - 0.5) / 2.);
+    Ok((2. * PI).sqrt() * series * (power * (-t).exp()) * power)
+}
+
+/// The logarithm of the absolute value of the gamma function.
+pub fn ln_gamma(x: f64) -> Result<f64, String> {
+    check_pole("lgamma", x)?;
+    if x < 0.5 {
+        return Ok((PI / (PI * x).sin().abs()).ln() - ln_gamma(1. - x)?);
+    }
+    if x <= 171. {
+        return Ok(gamma(x)?.ln());
+    }
+    let (t, series) = lanczos(x - 1.);
+    Ok(0.5 * (2. * PI).ln() + (x - 0.5) * t.ln() - t + series.ln())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn whole_numbers() {
+        assert_eq!(whole_number("binom", 5.), Ok(5));
+        assert_eq!(whole_number("binom", -0.), Ok(0));
+        assert!(whole_number("binom", 2.5).is_err());
+        assert!(whole_number("binom", f64::NAN).is_err());
+        assert!(whole_number("binom", 9.3e18).is_err());
+    }
+
+    #[test]
+    fn factorial_limits() {
+        assert_eq!(factorial(0), Ok(1));
+        assert_eq!(factorial(20), Ok(2432902008176640000));
+        assert_eq!(factorial(21), Err(overflow("factorial")));
+        assert!(factorial(-1).is_err());
+    }
+
+    #[test]
+    fn binomial_limits() {
+        assert_eq!(binomial(5, 7), Ok(0));
+        assert_eq!(binomial(66, 33), Ok(7219428434016265740));
+        assert_eq!(binomial(67, 33), Err(overflow("binom")));
+        assert_eq!(binomial(i64::MAX, 1), Ok(i64::MAX));
+        assert_eq!(binomial(i64::MAX, i64::MAX - 1), Ok(i64::MAX));
+        assert_eq!(binomial(i64::MAX, 2), Err(overflow("binom")));
+    }
+}
@@ -0,0 +1,226 @@
+// Numeric backends, chosen once per run. The analyzed program is the same in
+// every backend: ints are always i64, and the backend decides how the values
+// of type float are represented. The executor dispatches on the values, so
+// the backend is only needed where floats are created or printed, and for
+// the precision of decimals. Complex numbers always have f64 parts, and with
+// intervals, are computed from their midpoints. Measured values have an f64
+// value and uncertainty.
+
+use crate::decimal::{Context, Decimal, Rounding};
+use crate::interval::{self, Interval};
+use crate::linear_algebra;
+use crate::rational;
+use crate::uncertain::Uncertain;
+use crate::units::{AngleUnit, Unit};
+use crate::value::Value;
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use std::f64::consts::{E, PI};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Backend {
+    Float,
+    // Exact fractions, printed as such or rounded to the given number of
+    // decimals.
+    Rational(Option<usize>),
+    Decimal(Context),
+    // Outward-rounded enclosures of the exact values.
+    Interval,
+}
+
+// Decimals only appear with the decimal backend; elsewhere they would be
+// rounded like f64.
+const F64_CONTEXT: Context = Context {
+    precision: 17,
+    rounding: Rounding::HalfEven,
+};
+
+impl Backend {
+    /// The float `value`, such as a literal.
+    pub fn float(self, value: f64) -> Value {
+        match self {
+            Backend::Rational(_) => match rational::from_f64(value) {
+                Some(value) => Value::Rational(value),
+                None => Value::Float(value),
+            },
+            Backend::Decimal(context) => match Decimal::from_f64(value) {
+                Some(value) => Value::Decimal(context.round_decimal(&value)),
+                None => Value::Float(value),
+            },
+            Backend::Interval if value.is_nan() => Value::Float(value),
+            Backend::Interval => Value::Interval(interval::enclose(value)),
+            Backend::Float => Value::Float(value),
+        }
+    }
+
+    /// Converts an int to a float, as done by `float()`.
+    pub fn to_float(self, value: Value) -> Value {
+        match (self, value) {
+            (_, Value::List(values)) => {
+                Value::List(values.into_iter().map(|value| self.to_float(value)).collect())
+            }
+            (_, Value::Matrix(rows)) => Value::Matrix(
+                rows.into_iter()
+                    .map(|row| row.into_iter().map(|value| self.to_float(value)).collect())
+                    .collect(),
+            ),
+            (_, Value::Complex(value)) => Value::Complex(value),
+            (_, Value::Polynomial(value)) => Value::Polynomial(value),
+            (_, Value::Rational(value)) => Value::Rational(value),
+            (_, Value::Decimal(value)) => Value::Decimal(value),
+            (_, Value::Interval(value)) => Value::Interval(value),
+            (_, Value::Uncertain(value)) => Value::Uncertain(value),
+            (Backend::Rational(_), Value::Int(value)) => {
+                Value::Rational(BigRational::from_integer(BigInt::from(value)))
+            }
+            (Backend::Decimal(context), Value::Int(value)) => {
+                Value::Decimal(context.round_decimal(&Decimal::from_integer(value)))
+            }
+            (Backend::Interval, Value::Int(value)) => {
+                Value::Interval(interval::enclose_integer(value))
+            }
+            (Backend::Float, value) => Value::Float(value.as_float()),
+            (_, Value::Float(value)) => self.float(value),
+        }
+    }
+
+    pub fn pi(self) -> Value {
+        match self {
+            Backend::Decimal(context) => Value::Decimal(context.pi()),
+            // The f64 constants are just below pi and e.
+            Backend::Interval => Value::Interval(Interval::new(PI, PI.next_up())),
+            _ => self.float(PI),
+        }
+    }
+
+    pub fn e(self) -> Value {
+        match self {
+            Backend::Decimal(context) => Value::Decimal(context.e()),
+            Backend::Interval => Value::Interval(Interval::new(E, E.next_up())),
+            _ => self.float(E),
+        }
+    }
+
+    /// Half a turn in the unit, pi radians.
+    pub fn half_turn(self, unit: AngleUnit) -> Value {
+        match unit {
+            AngleUnit::Radian => self.pi(),
+            AngleUnit::Degree => self.float(180.),
+            AngleUnit::Gradian => self.float(200.),
+        }
+    }
+
+    pub fn decimal_context(self) -> Context {
+        match self {
+            Backend::Decimal(context) => context,
+            _ => F64_CONTEXT,
+        }
+    }
+
+    /// Reads a float as typed by the user, or a measured value `x +- u`.
+    pub fn parse(self, text: &str) -> Option<Value> {
+        if self != Backend::Interval {
+            if let Some(value) = Uncertain::parse(text) {
+                return Some(Value::Uncertain(value));
+            }
+        }
+        match self {
+            Backend::Float => text.parse().ok().map(Value::Float),
+            Backend::Rational(_) => rational::parse_rational(text).map(Value::Rational),
+            Backend::Decimal(context) => {
+                Decimal::parse(text).map(|value| Value::Decimal(context.round_decimal(&value)))
+            }
+            Backend::Interval => interval::parse(text).map(Value::Interval),
+        }
+    }
+
+    pub fn format(self, value: &Value) -> String {
+        match (self, value) {
+            (Backend::Rational(Some(digits)), Value::Rational(value)) => {
+                rational::format_decimal(value, digits)
+            }
+            (_, Value::List(values)) => {
+                let values: Vec<String> = values.iter().map(|value| self.format(value)).collect();
+                format!("[{}]", values.join(", "))
+            }
+            (_, Value::Matrix(rows)) => {
+                let rows: Vec<Vec<String>> = rows
+                    .iter()
+                    .map(|row| row.iter().map(|value| self.format(value)).collect())
+                    .collect();
+                linear_algebra::format(&rows)
+            }
+            (_, value) => value.to_string(),
+        }
+    }
+
+    /// The value of `unit` in SI base units: exact with `--rational`,
+    /// divided out to the precision with `--precision`, and enclosed with
+    /// `--intervals`.
+    pub fn unit_scale(self, unit: &Unit) -> Value {
+        match self {
+            Backend::Rational(_) => Value::Rational(unit.ratio.clone()),
+            Backend::Decimal(context) => Value::Decimal(context.div(
+                &Decimal::from_integer_digits(unit.ratio.numer().clone()),
+                &Decimal::from_integer_digits(unit.ratio.denom().clone()),
+            )),
+            Backend::Interval => Value::Interval(interval::enclose_rational(&unit.ratio)),
+            Backend::Float => Value::Float(unit.scale),
+        }
+    }
+
+    /// Converts a value given in `unit` to SI base units.
+    pub fn in_base_units(self, value: Value, unit: &Unit) -> Value {
+        let scale = unit.scale;
+        match (value, self.unit_scale(unit)) {
+            (Value::List(values), _) => Value::List(
+                values
+                    .into_iter()
+                    .map(|value| self.in_base_units(value, unit))
+                    .collect(),
+            ),
+            (Value::Matrix(rows), _) => Value::Matrix(
+                rows.into_iter()
+                    .map(|row| row.into_iter().map(|value| self.in_base_units(value, unit)).collect())
+                    .collect(),
+            ),
+            (Value::Complex(value), _) => Value::Complex(value * scale),
+            (Value::Polynomial(value), _) => Value::Polynomial(value * scale),
+            (Value::Rational(value), Value::Rational(scale)) => Value::Rational(value * scale),
+            (Value::Decimal(value), Value::Decimal(scale)) => {
+                Value::Decimal(self.decimal_context().mul(&value, &scale))
+            }
+            (Value::Interval(value), Value::Interval(scale)) => Value::Interval(value.mul_outward(scale)),
+            (Value::Uncertain(value), _) => Value::Uncertain(value * Uncertain::exact(scale)),
+            (value, _) => Value::Float(value.as_float() * scale),
+        }
+    }
+
+    /// Converts a value in SI base units to `unit`.
+    pub fn in_unit(self, value: Value, unit: &Unit) -> Value {
+        let scale = unit.scale;
+        match (value, self.unit_scale(unit)) {
+            (Value::List(values), _) => {
+                Value::List(values.into_iter().map(|value| self.in_unit(value, unit)).collect())
+            }
+            (Value::Matrix(rows), _) => Value::Matrix(
+                rows.into_iter()
+                    .map(|row| row.into_iter().map(|value| self.in_unit(value, unit)).collect())
+                    .collect(),
+            ),
+            (Value::Complex(value), _) => Value::Complex(value / scale),
+            (Value::Polynomial(value), _) => Value::Polynomial(value / scale),
+            (Value::Rational(value), Value::Rational(scale)) => Value::Rational(value / scale),
+            (Value::Decimal(value), Value::Decimal(scale)) => {
+                Value::Decimal(self.decimal_context().div(&value, &scale))
+            }
+            (Value::Interval(value), Value::Interval(scale)) => Value::Interval(
+                value
+                    .div_outward(scale, "/")
+                    .unwrap_or_else(|_| Interval::entire()),
+            ),
+            (Value::Uncertain(value), _) => Value::Uncertain(value / Uncertain::exact(scale)),
+            (value, _) => Value::Float(value.as_float() / scale),
+        }
+    }
+}
@@ -0,0 +1,440 @@
+use crate::analyzer::{
+    AnalyzedExpr, AnalyzedFactor, AnalyzedFunctionExpr, AnalyzedProgram, AnalyzedStatement,
+    AnalyzedTerm,
+};
+use crate::executor::{apply_expr_operator, apply_function, apply_term_operator};
+use crate::parser::{ExprOperator, FunctionOperator, StatisticOperator, TermOperator};
+use crate::decimal::Decimal;
+use crate::rational;
+use crate::symbol_table::SymbolTable;
+use crate::value::Value;
+
+// Constant folding and algebraic simplification.
+//
+// Operators are left-associative and evaluated left to right, so only a
+// literal prefix of a term or expression is folded: `2 * 3 * x` becomes
+// `6 * x`, while `x * 2 * 3` is left alone because `(x * 2) * 3` may round
+// differently from `x * 6`. The identities applied are exact in IEEE 754:
+// `x * 1`, `1 * x`, `x / 1`, `x ^ 1` and `x - 0` always give back `x`,
+// whereas `x + 0` and `0 + x` turn `-0` into `+0`, so they are only
+// simplified when `x` cannot be negative zero.
+
+fn literal_value(factor: &AnalyzedFactor) -> Option<Value> {
+    match factor {
+        AnalyzedFactor::Literal(value) => Some(value.clone()),
+        _ => None,
+    }
+}
+
+fn term_literal_value(term: &AnalyzedTerm) -> Option<Value> {
+    if term.1.is_empty() {
+        literal_value(&term.0)
+    } else {
+        None
+    }
+}
+
+fn is_literal(factor: &AnalyzedFactor, expected: f64) -> bool {
+    match literal_value(factor) {
+        Some(Value::Float(value)) => {
+            value == expected && value.is_sign_negative() == expected.is_sign_negative()
+        }
+        Some(Value::Int(value)) => value as f64 == expected && !expected.is_sign_negative(),
+        Some(Value::Rational(value)) => {
+            rational::from_f64(expected) == Some(value) && !expected.is_sign_negative()
+        }
+        Some(Value::Decimal(value)) => {
+            Decimal::from_f64(expected) == Some(value) && !expected.is_sign_negative()
+        }
+        Some(Value::Interval(value)) => {
+            value.is_point() && value.lo == expected && !expected.is_sign_negative()
+        }
+        Some(Value::Complex(_))
+        | Some(Value::Uncertain(_))
+        | Some(Value::List(_))
+        | Some(Value::Matrix(_))
+        | Some(Value::Polynomial(_))
+        | None => false,
+    }
+}
+
+fn is_literal_term(term: &AnalyzedTerm, expected: f64) -> bool {
+    term.1.is_empty() && is_literal(&term.0, expected)
+}
+
+fn may_be_negative_zero(term: &AnalyzedTerm) -> bool {
+    if !term.1.is_empty() {
+        return true;
+    }
+    match &term.0 {
+        AnalyzedFactor::Literal(Value::Float(value)) => *value == 0. && value.is_sign_negative(),
+        AnalyzedFactor::Literal(_) => false,
+        AnalyzedFactor::FunctionExpression(function_expr) => !matches!(
+            function_expr.0,
+            FunctionOperator::Abs
+                | FunctionOperator::Exp
+                | FunctionOperator::Exp2
+                | FunctionOperator::Cosh
+        ),
+        AnalyzedFactor::SubExpression(expr) => expr.1.iter().any(|term| term.0 == ExprOperator::Modulo)
+            || may_be_negative_zero(&expr.0)
+                && expr
+                    .1
+                    .iter()
+                    .all(|term| {
+                        matches!(term.0, ExprOperator::Subtract | ExprOperator::PlusMinus)
+                            || may_be_negative_zero(&term.1)
+                    }),
+        AnalyzedFactor::Length(_) => false,
+        AnalyzedFactor::Statistic(statistic) => statistic.0 != StatisticOperator::Count,
+        AnalyzedFactor::Identifier(_)
+        | AnalyzedFactor::Derivative(..)
+        | AnalyzedFactor::Integral(_)
+        | AnalyzedFactor::Series(_)
+        | AnalyzedFactor::MatrixFunction(_)
+        | AnalyzedFactor::Polynomial(..)
+        | AnalyzedFactor::PolynomialFunction(_)
+        | AnalyzedFactor::NumberTheoryFunction(_)
+        | AnalyzedFactor::Random(_)
+        | AnalyzedFactor::List(_)
+        | AnalyzedFactor::Matrix(_)
+        | AnalyzedFactor::Range(..)
+        | AnalyzedFactor::Index(..) => true,
+    }
+}
+
+pub fn is_constant_factor(variables: &SymbolTable, factor: &AnalyzedFactor) -> bool {
+    match factor {
+        AnalyzedFactor::Literal(_) => true,
+        AnalyzedFactor::Identifier(handle) => variables.is_constant(*handle),
+        AnalyzedFactor::FunctionExpression(function_expr) => {
+            is_constant_expr(variables, &function_expr.1)
+        }
+        AnalyzedFactor::SubExpression(expr) => is_constant_expr(variables, expr),
+        AnalyzedFactor::Derivative(..)
+        | AnalyzedFactor::Integral(_)
+        | AnalyzedFactor::Series(_)
+        | AnalyzedFactor::Statistic(_)
+        | AnalyzedFactor::MatrixFunction(_)
+        | AnalyzedFactor::Polynomial(..)
+        | AnalyzedFactor::PolynomialFunction(_)
+        | AnalyzedFactor::NumberTheoryFunction(_)
+        | AnalyzedFactor::Random(_)
+        | AnalyzedFactor::List(_)
+        | AnalyzedFactor::Matrix(_)
+        | AnalyzedFactor::Range(..)
+        | AnalyzedFactor::Index(..)
+        | AnalyzedFactor::Length(_) => false,
+    }
+}
+
+fn is_constant_term(variables: &SymbolTable, term: &AnalyzedTerm) -> bool {
+    is_constant_factor(variables, &term.0)
+        && term.1.iter().all(|factor| is_constant_factor(variables, &factor.1))
+}
+
+/// Tells whether the expression folds down to a single literal.
+pub fn is_constant_expr(variables: &SymbolTable, expr: &AnalyzedExpr) -> bool {
+    is_constant_term(variables, &expr.0)
+        && expr.1.iter().all(|term| is_constant_term(variables, &term.1))
+}
+
+fn optimize_factor(variables: &SymbolTable, factor: AnalyzedFactor) -> AnalyzedFactor {
+    match factor {
+        AnalyzedFactor::Literal(value) => AnalyzedFactor::Literal(value),
+        AnalyzedFactor::Identifier(handle) => {
+            if variables.is_constant(handle) {
+                AnalyzedFactor::Literal(variables.get_value(handle))
+            } else {
+                AnalyzedFactor::Identifier(handle)
+            }
+        }
+        AnalyzedFactor::FunctionExpression(function_expr) => {
+            let function_expr = optimize_function_expr(variables, *function_expr);
+            // Operations failing at run time, such as an integer overflow,
+            // are left for the executor to report.
+            match term_literal_value(&function_expr.1 .0) {
+                Some(value) if function_expr.1 .1.is_empty() => {
+                    match apply_function(variables.backend(), function_expr.0, value) {
+                        Ok(result) => AnalyzedFactor::Literal(result),
+                        Err(_) => AnalyzedFactor::FunctionExpression(Box::new(function_expr)),
+                    }
+                }
+                _ => AnalyzedFactor::FunctionExpression(Box::new(function_expr)),
+            }
+        }
+        AnalyzedFactor::SubExpression(expr) => {
+            let expr = optimize_expr(variables, *expr);
+            // A parenthesized single factor can replace the parentheses.
+            if expr.1.is_empty() && expr.0 .1.is_empty() {
+                expr.0 .0
+            } else {
+                AnalyzedFactor::SubExpression(Box::new(expr))
+            }
+        }
+        AnalyzedFactor::Derivative(expr, handle) => {
+            AnalyzedFactor::Derivative(Box::new(optimize_expr(variables, *expr)), handle)
+        }
+        AnalyzedFactor::Integral(integral) => {
+            let (integrand, handle, lo, hi) = *integral;
+            AnalyzedFactor::Integral(Box::new((
+                optimize_expr(variables, integrand),
+                handle,
+                optimize_expr(variables, lo),
+                optimize_expr(variables, hi),
+            )))
+        }
+        AnalyzedFactor::Series(series) => {
+            let (operator, handle, lo, hi, body) = *series;
+            AnalyzedFactor::Series(Box::new((
+                operator,
+                handle,
+                optimize_expr(variables, lo),
+                optimize_expr(variables, hi),
+                optimize_expr(variables, body),
+            )))
+        }
+        AnalyzedFactor::Statistic(statistic) => {
+            let (operator, arguments) = *statistic;
+            AnalyzedFactor::Statistic(Box::new((
+                operator,
+                arguments
+                    .into_iter()
+                    .map(|argument| optimize_expr(variables, argument))
+                    .collect(),
+            )))
+        }
+        AnalyzedFactor::MatrixFunction(function) => {
+            let (operator, arguments) = *function;
+            AnalyzedFactor::MatrixFunction(Box::new((
+                operator,
+                arguments
+                    .into_iter()
+                    .map(|argument| optimize_expr(variables, argument))
+                    .collect(),
+            )))
+        }
+        AnalyzedFactor::Polynomial(body, handle) => {
+            AnalyzedFactor::Polynomial(Box::new(optimize_expr(variables, *body)), handle)
+        }
+        AnalyzedFactor::PolynomialFunction(function) => {
+            let (operator, arguments) = *function;
+            AnalyzedFactor::PolynomialFunction(Box::new((
+                operator,
+                arguments
+                    .into_iter()
+                    .map(|argument| optimize_expr(variables, argument))
+                    .collect(),
+            )))
+        }
+        AnalyzedFactor::NumberTheoryFunction(function) => {
+            let (operator, arguments) = *function;
+            AnalyzedFactor::NumberTheoryFunction(Box::new((
+                operator,
+                arguments
+                    .into_iter()
+                    .map(|argument| optimize_expr(variables, argument))
+                    .collect(),
+            )))
+        }
+        // Only the parameters are folded; each evaluation draws a new number.
+        AnalyzedFactor::Random(random) => {
+            let (operator, arguments) = *random;
+            AnalyzedFactor::Random(Box::new((
+                operator,
+                arguments
+                    .into_iter()
+                    .map(|argument| optimize_expr(variables, argument))
+                    .collect(),
+            )))
+        }
+        AnalyzedFactor::List(elements) => AnalyzedFactor::List(
+            elements
+                .into_iter()
+                .map(|element| optimize_expr(variables, element))
+                .collect(),
+        ),
+        AnalyzedFactor::Matrix(rows) => AnalyzedFactor::Matrix(
+            rows.into_iter().map(|row| optimize_expr(variables, row)).collect(),
+        ),
+        AnalyzedFactor::Range(lo, hi) => AnalyzedFactor::Range(
+            Box::new(optimize_expr(variables, *lo)),
+            Box::new(optimize_expr(variables, *hi)),
+        ),
+        AnalyzedFactor::Index(list, index) => AnalyzedFactor::Index(
+            Box::new(optimize_expr(variables, *list)),
+            Box::new(optimize_expr(variables, *index)),
+        ),
+        AnalyzedFactor::Length(list) => AnalyzedFactor::Length(Box::new(optimize_expr(variables, *list))),
+    }
+}
+
+fn optimize_term(variables: &SymbolTable, term: AnalyzedTerm) -> AnalyzedTerm {
+    let mut first_factor = optimize_factor(variables, term.0);
+    let mut other_factors = Vec::<(TermOperator, AnalyzedFactor)>::new();
+    for (operator, factor) in term.1 {
+        let factor = optimize_factor(variables, factor);
+        if other_factors.is_empty() {
+            if let (Some(left), Some(right)) = (literal_value(&first_factor), literal_value(&factor)) {
+                if let Ok(result) = apply_term_operator(variables.backend(), operator, left, right) {
+                    first_factor = AnalyzedFactor::Literal(result);
+                    continue;
+                }
+            }
+            if operator == TermOperator::Multiply && is_literal(&first_factor, 1.) {
+                first_factor = factor;
+                continue;
+            }
+        }
+        if is_literal(&factor, 1.) && operator != TermOperator::IntegerDivide {
+            // x * 1, x / 1 and x ^ 1 are all exactly x, but x div 1 rounds a float down.
+            continue;
+        }
+        other_factors.push((operator, factor));
+    }
+    (first_factor, other_factors)
+}
+
+fn optimize_expr(variables: &SymbolTable, expr: AnalyzedExpr) -> AnalyzedExpr {
+    let mut first_term = optimize_term(variables, expr.0);
+    let mut other_terms = Vec::<(ExprOperator, AnalyzedTerm)>::new();
+    let mut result_may_be_negative_zero = may_be_negative_zero(&first_term);
+    for (operator, term) in expr.1 {
+        let term = optimize_term(variables, term);
+        // Each `+-` evaluated is a new measurement, left to the executor.
+        if other_terms.is_empty() && operator != ExprOperator::PlusMinus {
+            if let (Some(left), Some(right)) = (term_literal_value(&first_term), term_literal_value(&term)) {
+                if let Ok(result) = apply_expr_operator(variables.backend(), operator, left, right) {
+                    first_term = (AnalyzedFactor::Literal(result), vec![]);
+                    result_may_be_negative_zero = may_be_negative_zero(&first_term);
+                    continue;
+                }
+            }
+            if operator == ExprOperator::Add
+                && is_literal_term(&first_term, 0.)
+                && !may_be_negative_zero(&term)
+            {
+                result_may_be_negative_zero = false;
+                first_term = term;
+                continue;
+            }
+        }
+        let is_neutral = match operator {
+            ExprOperator::Add => {
+                is_literal_term(&term, -0.) || is_literal_term(&term, 0.) && !result_may_be_negative_zero
+            }
+            ExprOperator::Subtract => is_literal_term(&term, 0.),
+            ExprOperator::Modulo | ExprOperator::PlusMinus => false,
+        };
+        if is_neutral {
+            continue;
+        }
+        result_may_be_negative_zero = match operator {
+            ExprOperator::Add => result_may_be_negative_zero && may_be_negative_zero(&term),
+            ExprOperator::Subtract | ExprOperator::PlusMinus => result_may_be_negative_zero,
+            ExprOperator::Modulo => true,
+        };
+        other_terms.push((operator, term));
+    }
+    (first_term, other_terms)
+}
+
+fn optimize_function_expr(
+    variables: &SymbolTable,
+    function_expr: AnalyzedFunctionExpr,
+) -> AnalyzedFunctionExpr {
+    (function_expr.0, optimize_expr(variables, function_expr.1))
+}
+
+fn optimize_statement(variables: &SymbolTable, statement: AnalyzedStatement) -> AnalyzedStatement {
+    match statement {
+        AnalyzedStatement::Assignment(handle, expr) => {
+            AnalyzedStatement::Assignment(handle, optimize_expr(variables, expr))
+        }
+        AnalyzedStatement::DeclarationToAssignment(handle, expr) => {
+            AnalyzedStatement::DeclarationToAssignment(handle, optimize_expr(variables, expr))
+        }
+        AnalyzedStatement::OutputOperation(expr, unit, format, label) => {
+            AnalyzedStatement::OutputOperation(optimize_expr(variables, expr), unit, format, label)
+        }
+        AnalyzedStatement::GradientOutput(expr, partials) => {
+            AnalyzedStatement::GradientOutput(optimize_expr(variables, expr), partials)
+        }
+        // The derivative is printed as written, constants included.
+        AnalyzedStatement::SymbolicDerivative(expr, handle) => {
+            AnalyzedStatement::SymbolicDerivative(expr, handle)
+        }
+        AnalyzedStatement::Solve(solve) => {
+            let (lhs, rhs, handle, lo, hi) = *solve;
+            AnalyzedStatement::Solve(Box::new((
+                optimize_expr(variables, lhs),
+                optimize_expr(variables, rhs),
+                handle,
+                optimize_expr(variables, lo),
+                optimize_expr(variables, hi),
+            )))
+        }
+        AnalyzedStatement::Seed(expr) => AnalyzedStatement::Seed(optimize_expr(variables, expr)),
+        AnalyzedStatement::AngleMode(unit) => AnalyzedStatement::AngleMode(unit),
+        AnalyzedStatement::Declaration(handle) => AnalyzedStatement::Declaration(handle),
+        AnalyzedStatement::InputOperation(handle, range) => {
+            AnalyzedStatement::InputOperation(handle, range)
+        }
+    }
+}
+
+/// Folds constant sub-expressions, including the built-in constants, and
+/// drops operations that are exact identities. Both the executor and the
+/// Rust compiler run on the result.
+pub fn optimize_program(variables: &SymbolTable, program: AnalyzedProgram) -> AnalyzedProgram {
+    program
+        .into_iter()
+        .map(|statement| optimize_statement(variables, statement))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzer::analyze_program;
+    use crate::numeric::Backend;
+    use crate::parser::parse_program;
+    use crate::unparser::unparse_expr;
+
+    // The optimized form of `expr`, where `x` is a float variable.
+    fn optimized(expr: &str) -> String {
+        let mut variables = SymbolTable::with_backend(Backend::Float);
+        let source = format!("var x = 2\nout {}", expr);
+        let (_, parsed_program) = parse_program(&source).unwrap();
+        let analyzed_program = analyze_program(&mut variables, &parsed_program).unwrap();
+        match optimize_program(&variables, analyzed_program).pop() {
+            Some(AnalyzedStatement::OutputOperation(expr, ..)) => unparse_expr(&variables, &expr),
+            _ => unreachable!("the program ends with an output"),
+        }
+    }
+
+    #[test]
+    fn identities_that_keep_negative_zero_are_removed() {
+        assert_eq!(optimized("x - 0"), "x");
+        assert_eq!(optimized("x * 1"), "x");
+        assert_eq!(optimized("1 * x"), "x");
+        assert_eq!(optimized("x / 1"), "x");
+        assert_eq!(optimized("x ^ 1"), "x");
+        assert_eq!(optimized("x div 1"), "x div 1");
+    }
+
+    #[test]
+    fn adding_zero_is_kept_when_the_sum_may_be_negative_zero() {
+        assert_eq!(optimized("x + 0"), "x + 0");
+        assert_eq!(optimized("0 + x"), "0 + x");
+        assert_eq!(optimized("abs(x) + 0"), "abs(x)");
+        assert_eq!(optimized("0 + exp(x)"), "exp(x)");
+    }
+
+    #[test]
+    fn only_literal_prefixes_are_folded() {
+        assert_eq!(optimized("2 * 3 * x"), "6 * x");
+        assert_eq!(optimized("x * 2 * 3"), "x * 2 * 3");
+        assert_eq!(optimized("1 + 2 - x"), "3 - x");
+    }
+}
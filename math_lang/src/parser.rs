@@ -2,21 +2,54 @@ extern crate nom;
 use nom::{
     branch::alt,
     bytes::complete::tag,
-    bytes::complete::take_while,
-    character::complete::{alpha1, char},
-    combinator::map,
-    multi::many0,
+    bytes::complete::{take_while, take_while1},
+    character::complete::{alpha1, alphanumeric1, char, digit1, one_of},
+    combinator::{map, map_res, not, opt, recognize, verify},
+    multi::{many0, many1, separated_list0},
     number::complete::double,
-    sequence::{delimited, preceded, tuple},
+    sequence::{delimited, preceded, terminated, tuple},
     IResult,
 };
+use crate::units::{self, AngleUnit};
+use crate::value::Type;
 
 #[derive(Debug, PartialEq)]
 pub enum ParsedFactor<'a> {
     Literal(f64),
+    // A number written without a decimal point or an exponent.
+    IntLiteral(i64),
+    // A number followed by `i` or `j`, such as `2i`.
+    Imaginary(f64),
+    Quantity(f64, ParsedUnit<'a>),
     Identifier(&'a str),
     SubExpression(Box<ParsedExpr<'a>>),
-    FunctionExpression(Box<ParsedFunctionExpr<'a>>)
+    FunctionExpression(Box<ParsedFunctionExpr<'a>>),
+    // `diff(expr, x)`, the derivative of `expr` with respect to `x`.
+    Derivative(Box<ParsedExpr<'a>>, &'a str),
+    Integral(Box<ParsedIntegral<'a>>),
+    Series(Box<ParsedSeries<'a>>),
+    Statistic(Box<ParsedStatistic<'a>>),
+    MatrixFunction(Box<ParsedMatrixFunction<'a>>),
+    // `poly x: expr`, the polynomial in `x` computed by `expr`.
+    Polynomial(&'a str, Box<ParsedExpr<'a>>),
+    PolynomialFunction(Box<ParsedPolynomialFunction<'a>>),
+    // `binom(n, k)`, or `n!` for `factorial(n)`.
+    NumberTheoryFunction(Box<ParsedNumberTheoryFunction<'a>>),
+    // `rand()` or `normal(mu, sigma)`, a new number each time it is evaluated.
+    Random(Box<ParsedRandom<'a>>),
+    // `30°`, an angle in degrees.
+    DegreeLiteral(f64),
+    // `deg(x)` of an angle in radians, or `rad(x)` of an angle in degrees:
+    // the unit to convert to and the angle.
+    AngleConversion(AngleUnit, Box<ParsedExpr<'a>>),
+    // `[a, b, c]`, or `[[a, b], [c, d]]` for a matrix.
+    List(Vec<ParsedExpr<'a>>),
+    // `lo..hi`, the list of the numbers from `lo` to `hi` by steps of 1.
+    Range(Box<ParsedExpr<'a>>, Box<ParsedExpr<'a>>),
+    // `v[i]`, the `i`th element of a list or row of a matrix, counted from 1.
+    Index(Box<ParsedFactor<'a>>, Box<ParsedExpr<'a>>),
+    // `len(v)`, the number of elements of a list.
+    Length(Box<ParsedExpr<'a>>),
 }
 
 #[derive(Debug, PartialEq, Clone, Copy)]
@@ -24,6 +57,10 @@ pub enum TermOperator {
     Multiply,
     Divide,
     Exponent,
+    // `div`, the quotient matching the Euclidean `mod`.
+    IntegerDivide,
+    // `@`, the product of matrices, where lists are vectors.
+    MatrixMultiply,
 }
 
 
@@ -32,6 +69,7 @@ pub enum ExprOperator {
     Add,
     Subtract,
     Modulo,
+    PlusMinus,
 }
 
 #[derive(Debug, PartialEq, Clone, Copy)]
@@ -58,6 +96,86 @@ pub enum FunctionOperator {
     Floor,
     Signum,
     Sqrt,
+    ToInt,
+    ToFloat,
+    Re,
+    Im,
+    Conj,
+    Arg,
+}
+
+// `sum` and `prod`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SeriesOperator {
+    Sum,
+    Product,
+}
+
+// The statistics of the values of lists. The sample variants divide by
+// n - 1, and the population ones, whose names start with `p`, by n.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum StatisticOperator {
+    Mean,
+    Median,
+    StandardDeviation,
+    PopulationStandardDeviation,
+    Variance,
+    PopulationVariance,
+    Percentile,
+    Min,
+    Max,
+    Sum,
+    Count,
+    Covariance,
+    PopulationCovariance,
+    Correlation,
+}
+
+// The constructors of matrices and the operations of linear algebra.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum MatrixOperator {
+    Transpose,
+    Determinant,
+    Inverse,
+    Solve,
+    Identity,
+    Zeros,
+}
+
+// `poly(1, 0, -2)` builds a polynomial from its coefficients, from the
+// leading one down, and the others take a polynomial first.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum PolynomialOperator {
+    Coefficients,
+    Evaluate,
+    Roots,
+    Derivative,
+}
+
+// Exact functions of ints, and the gamma function and its logarithm, which
+// take floats.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum NumberTheoryOperator {
+    Factorial,
+    Gamma,
+    LogGamma,
+    Binomial,
+    Permutations,
+    IsPrime,
+    NextPrime,
+    Factor,
+    ModPow,
+    ModInverse,
+}
+
+// Pseudo-random numbers: floats, except for `randint`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum RandomOperator {
+    Rand,
+    RandInt,
+    Uniform,
+    Normal,
+    Exponential,
 }
 
 pub type ParsedTerm<'a> = (ParsedFactor<'a>, Vec<(TermOperator, ParsedFactor<'a>)>);
@@ -66,47 +184,242 @@ pub type ParsedExpr<'a> = (ParsedTerm<'a>, Vec<(ExprOperator, ParsedTerm<'a>)>);
 
 pub type ParsedFunctionExpr<'a> = (FunctionOperator, ParsedExpr<'a>);
 
+// The integrand, the variable it binds and the bounds.
+pub type ParsedIntegral<'a> = (ParsedExpr<'a>, &'a str, ParsedExpr<'a>, ParsedExpr<'a>);
+
+// `sum(k, lo, hi, body)` or `prod(k, lo, hi, body)`: the index, its bounds
+// and the body.
+pub type ParsedSeries<'a> = (SeriesOperator, &'a str, ParsedExpr<'a>, ParsedExpr<'a>, ParsedExpr<'a>);
+
+// `mean(v)` or `max(a, b, c)`: the statistic and its arguments, whose
+// values are taken together.
+pub type ParsedStatistic<'a> = (StatisticOperator, Vec<ParsedExpr<'a>>);
+
+// `det(a)` or `solve(a, b)`: the operation and its arguments.
+pub type ParsedMatrixFunction<'a> = (MatrixOperator, Vec<ParsedExpr<'a>>);
+
+// `eval(p, x)` or `roots(p)`: the operation and its arguments.
+pub type ParsedPolynomialFunction<'a> = (PolynomialOperator, Vec<ParsedExpr<'a>>);
+
+// `modpow(b, e, m)` or `factor(n)`: the function and its arguments.
+pub type ParsedNumberTheoryFunction<'a> = (NumberTheoryOperator, Vec<ParsedExpr<'a>>);
+
+// `uniform(a, b)` or `rand()`: the distribution and its parameters.
+pub type ParsedRandom<'a> = (RandomOperator, Vec<ParsedExpr<'a>>);
+
+// Unit names with their exponents, negated for the units after a `/`.
+pub type ParsedUnit<'a> = Vec<(&'a str, i32)>;
+
+// What may follow the name of a declared variable after a colon.
+#[derive(Debug)]
+pub enum ParsedAnnotation<'a> {
+    Type(Type),
+    Unit(ParsedUnit<'a>),
+}
+
 
 #[derive(Debug)]
 pub enum ParsedStatement<'a> {
-    Declaration(&'a str),
-    InputOperation(&'a str),
-    OutputOperation(ParsedExpr<'a>),
+    Declaration(&'a str, Option<ParsedAnnotation<'a>>),
+    InputOperation(&'a str, Option<(f64, f64)>),
+    // `out "label" = expr in unit : format`, where all but `expr` are optional.
+    OutputOperation(ParsedExpr<'a>, Option<ParsedUnit<'a>>, Option<&'a str>, Option<&'a str>),
+    // `out grad(expr, x, y)`, the partial derivatives of `expr`.
+    GradientOutput(ParsedExpr<'a>, Vec<&'a str>),
+    SymbolicDerivative(ParsedExpr<'a>, &'a str),
+    Solve(Box<ParsedSolve<'a>>),
+    // `seed(n)`, which restarts the pseudo-random numbers.
+    Seed(ParsedExpr<'a>),
+    // `mode deg`, the unit of the angles of the statements that follow.
+    AngleMode(AngleUnit),
     Assignment(&'a str, ParsedExpr<'a>),
-    DeclarationToAssignment(&'a str, ParsedExpr<'a>),
+    DeclarationToAssignment(&'a str, Option<ParsedAnnotation<'a>>, ParsedExpr<'a>),
 }
 
+// `solve lhs = rhs for x in [lo, hi]`.
+pub type ParsedSolve<'a> = (ParsedExpr<'a>, ParsedExpr<'a>, &'a str, ParsedExpr<'a>, ParsedExpr<'a>);
+
 pub type ParsedProgram<'a> = Vec<ParsedStatement<'a>>;
 
-pub fn parse_program(input: &str) -> IResult<&str, ParsedProgram> {
+pub fn parse_program(input: &str) -> IResult<&str, ParsedProgram<'_>> {
     many0(preceded(
         skip_spaces,
         alt((parse_declaration_to_assigment,
             parse_declaration,
             parse_input_statement,
+            parse_gradient_output_statement,
             parse_output_statement,
+            parse_symbolic_derivative_statement,
+            parse_solve_statement,
+            parse_seed_statement,
+            parse_angle_mode_statement,
             parse_assignment,
         )),
     ))(input)
 }
 
-fn parse_declaration(input: &str) -> IResult<&str, ParsedStatement> {
-    tuple((tag("var"), skip_spaces, parse_identifier))(input)
-        .map(|(input, output)| (input, ParsedStatement::Declaration(output.2)))
+fn parse_declaration(input: &str) -> IResult<&str, ParsedStatement<'_>> {
+    tuple((tag("var"), skip_spaces, parse_identifier, opt(parse_annotation)))(input)
+        .map(|(input, output)| (input, ParsedStatement::Declaration(output.2, output.3)))
 }
 
-fn parse_input_statement(input: &str) -> IResult<&str, ParsedStatement> {
-    tuple((tag("in"), skip_spaces, parse_identifier))(input)
-        .map(|(input, output)| (input, ParsedStatement::InputOperation(output.2)))
+// The optional `: int`, `: float`, `: complex`, `: poly` or `: unit` after the name of a declared variable.
+fn parse_annotation(input: &str) -> IResult<&str, ParsedAnnotation<'_>> {
+    preceded(
+        tuple((skip_spaces, char(':'), skip_spaces)),
+        alt((
+            map(terminated(tag("int"), not(alpha1)), |_| ParsedAnnotation::Type(Type::Int)),
+            map(terminated(tag("float"), not(alpha1)), |_| ParsedAnnotation::Type(Type::Float)),
+            map(terminated(tag("complex"), not(alpha1)), |_| ParsedAnnotation::Type(Type::Complex)),
+            map(terminated(tag("poly"), not(alpha1)), |_| ParsedAnnotation::Type(Type::Polynomial)),
+            map(parse_unit, ParsedAnnotation::Unit),
+        )),
+    )(input)
 }
 
-fn parse_output_statement(input: &str) -> IResult<&str, ParsedStatement> {
+fn parse_input_statement(input: &str) -> IResult<&str, ParsedStatement<'_>> {
+    tuple((tag("in"), skip_spaces, parse_identifier, opt(parse_input_range)))(input)
+        .map(|(input, output)| (input, ParsedStatement::InputOperation(output.2, output.3)))
+}
+
+// The optional `range [lo, hi]` after `in x`.
+fn parse_input_range(input: &str) -> IResult<&str, (f64, f64)> {
+    tuple((
+        skip_spaces,
+        tag("range"),
+        skip_spaces,
+        char('['),
+        skip_spaces,
+        double,
+        skip_spaces,
+        char(','),
+        skip_spaces,
+        double,
+        skip_spaces,
+        char(']'),
+    ))(input)
+    .map(|(input, output)| (input, (output.5, output.9)))
+}
+
+fn parse_output_statement(input: &str) -> IResult<&str, ParsedStatement<'_>> {
     //eprintln!("\nParsing out : {}\n", &input);
-    tuple((tag("out"), skip_spaces, parse_expr))(input)
-        .map(|(input, output)| (input, ParsedStatement::OutputOperation(output.2)))
+    tuple((
+        tag("out"),
+        skip_spaces,
+        opt(terminated(parse_output_label, tuple((skip_spaces, char('='))))),
+        parse_expr,
+        opt(parse_output_unit),
+        opt(parse_output_format),
+    ))(input)
+    .map(|(input, output)| {
+        (
+            input,
+            ParsedStatement::OutputOperation(output.3, output.4, output.5, output.2),
+        )
+    })
+}
+
+fn parse_output_label(input: &str) -> IResult<&str, &str> {
+    delimited(char('"'), take_while(|ch| ch != '"' && ch != '\n'), char('"'))(input)
 }
 
-fn parse_assignment(input: &str) -> IResult<&str, ParsedStatement> {
+// The name of the format, checked by the analyzer.
+fn parse_output_format(input: &str) -> IResult<&str, &str> {
+    preceded(
+        tuple((skip_blanks, char(':'), skip_blanks)),
+        take_while1(|ch: char| ch.is_ascii_alphanumeric() || ch == '.' || ch == '%'),
+    )(input)
+}
+
+fn parse_gradient_output_statement(input: &str) -> IResult<&str, ParsedStatement<'_>> {
+    tuple((
+        tag("out"),
+        skip_spaces,
+        tag("grad"),
+        skip_spaces,
+        char('('),
+        parse_expr,
+        many1(preceded(
+            tuple((skip_spaces, char(','), skip_spaces)),
+            parse_identifier,
+        )),
+        skip_spaces,
+        char(')'),
+    ))(input)
+    .map(|(input, output)| (input, ParsedStatement::GradientOutput(output.5, output.6)))
+}
+
+fn parse_symbolic_derivative_statement(input: &str) -> IResult<&str, ParsedStatement<'_>> {
+    tuple((
+        tag("derive"),
+        skip_spaces,
+        parse_expr,
+        skip_spaces,
+        tag("wrt"),
+        skip_spaces,
+        parse_identifier,
+    ))(input)
+    .map(|(input, output)| (input, ParsedStatement::SymbolicDerivative(output.2, output.6)))
+}
+
+fn parse_solve_statement(input: &str) -> IResult<&str, ParsedStatement<'_>> {
+    tuple((
+        tag("solve"),
+        skip_spaces,
+        parse_expr,
+        skip_spaces,
+        char('='),
+        parse_expr,
+        skip_spaces,
+        tag("for"),
+        skip_spaces,
+        parse_identifier,
+        skip_spaces,
+        tag("in"),
+        skip_spaces,
+        char('['),
+        parse_expr,
+        skip_spaces,
+        char(','),
+        parse_expr,
+        skip_spaces,
+        char(']'),
+    ))(input)
+    .map(|(input, output)| {
+        (
+            input,
+            ParsedStatement::Solve(Box::new((output.2, output.5, output.9, output.14, output.17))),
+        )
+    })
+}
+
+fn parse_seed_statement(input: &str) -> IResult<&str, ParsedStatement<'_>> {
+    tuple((tag("seed"), skip_spaces, char('('), parse_expr, skip_spaces, char(')')))(input)
+        .map(|(input, output)| (input, ParsedStatement::Seed(output.3)))
+}
+
+fn parse_angle_mode_statement(input: &str) -> IResult<&str, ParsedStatement<'_>> {
+    tuple((
+        tag("mode"),
+        take_while1(|ch| ch == ' ' || ch == '\t'),
+        terminated(
+            alt((
+                map(tag("deg"), |_| AngleUnit::Degree),
+                map(tag("rad"), |_| AngleUnit::Radian),
+                map(tag("grad"), |_| AngleUnit::Gradian),
+            )),
+            not(alphanumeric1),
+        ),
+    ))(input)
+    .map(|(input, output)| (input, ParsedStatement::AngleMode(output.2)))
+}
+
+// The optional `in unit` after `out expr`, on the same line.
+fn parse_output_unit(input: &str) -> IResult<&str, ParsedUnit<'_>> {
+    preceded(tuple((skip_blanks, tag("in"), skip_blanks)), parse_unit)(input)
+}
+
+fn parse_assignment(input: &str) -> IResult<&str, ParsedStatement<'_>> {
     tuple((
         parse_identifier,
         skip_spaces,
@@ -117,24 +430,37 @@ fn parse_assignment(input: &str) -> IResult<&str, ParsedStatement> {
     .map(|(input, output)| (input, ParsedStatement::Assignment(output.0, output.4)))
 }
 
-fn parse_declaration_to_assigment(input: &str) -> IResult<&str, ParsedStatement> {
+fn parse_declaration_to_assigment(input: &str) -> IResult<&str, ParsedStatement<'_>> {
     tuple((
         tag("var"), 
         skip_spaces,
         parse_identifier,
+        opt(parse_annotation),
         skip_spaces,
         tag("="),
         skip_spaces,
         parse_expr,
     ))(input)
-    .map(|(input, output)| (input, ParsedStatement::DeclarationToAssignment(output.2, output.6)))
+    .map(|(input, output)| (input, ParsedStatement::DeclarationToAssignment(output.2, output.3, output.7)))
 }
 
 fn parse_identifier(input: &str) -> IResult<&str, &str> {
     alpha1(input)
 }
 
-fn parse_subexpr(input: &str) -> IResult<&str, ParsedExpr> {
+// Integers too large for an int are parsed as floats by `double`.
+fn parse_int_literal(input: &str) -> IResult<&str, i64> {
+    map_res(
+        terminated(
+            recognize(tuple((opt(char('-')), digit1))),
+            // `1..5` is a range of ints.
+            not(alt((terminated(char('.'), not(char('.'))), char('e'), char('E')))),
+        ),
+        str::parse::<i64>,
+    )(input)
+}
+
+fn parse_subexpr(input: &str) -> IResult<&str, ParsedExpr<'_>> {
     delimited(
         preceded(skip_spaces, char('(')),
         parse_expr,
@@ -142,25 +468,274 @@ fn parse_subexpr(input: &str) -> IResult<&str, ParsedExpr> {
     )(input)
 }
 
-fn parse_factor(input: &str) -> IResult<&str, ParsedFactor> {
+// A factor followed by indices and `!`, which apply from left to right.
+fn parse_factor(input: &str) -> IResult<&str, ParsedFactor<'_>> {
+    tuple((
+        parse_primary_factor,
+        many0(alt((
+            map(delimited(char('['), parse_expr, preceded(skip_spaces, char(']'))), Some),
+            map(char('!'), |_| None),
+        ))),
+    ))(input)
+    .map(|(input, (factor, suffixes))| {
+        (
+            input,
+            suffixes.into_iter().fold(factor, |factor, suffix| match suffix {
+                Some(index) => ParsedFactor::Index(Box::new(factor), Box::new(index)),
+                None => ParsedFactor::NumberTheoryFunction(Box::new((
+                    NumberTheoryOperator::Factorial,
+                    vec![((factor, vec![]), vec![])],
+                ))),
+            }),
+        )
+    })
+}
+
+fn parse_primary_factor(input: &str) -> IResult<&str, ParsedFactor<'_>> {
     //eprintln!("\nParsing factor : {}\n", &input);
     preceded(
         skip_spaces,
         alt((
+            map(parse_derivative, |(expr, variable)| {
+                ParsedFactor::Derivative(Box::new(expr), variable)
+            }),
+            map(parse_integral, |integral| ParsedFactor::Integral(Box::new(integral))),
+            map(parse_series, |series| ParsedFactor::Series(Box::new(series))),
+            map(parse_statistic, |statistic| ParsedFactor::Statistic(Box::new(statistic))),
+            map(parse_matrix_function, |function| ParsedFactor::MatrixFunction(Box::new(function))),
+            map(parse_polynomial, |(variable, expr)| ParsedFactor::Polynomial(variable, Box::new(expr))),
+            map(parse_polynomial_function, |function| {
+                ParsedFactor::PolynomialFunction(Box::new(function))
+            }),
+            map(parse_number_theory_function, |function| {
+                ParsedFactor::NumberTheoryFunction(Box::new(function))
+            }),
+            map(parse_random, |random| ParsedFactor::Random(Box::new(random))),
+            map(parse_angle_conversion, |(unit, expr)| ParsedFactor::AngleConversion(unit, Box::new(expr))),
             map(parse_function_expr, |expr| {
                 ParsedFactor::FunctionExpression(Box::new(expr))
             }),
+            map(preceded(tag("len"), parse_subexpr), |expr| ParsedFactor::Length(Box::new(expr))),
             map(parse_identifier, ParsedFactor::Identifier),
-            map(double, ParsedFactor::Literal),
-            
+            map(terminated(double, terminated(one_of("ij"), not(alphanumeric1))), ParsedFactor::Imaginary),
+            map(terminated(double, char('°')), ParsedFactor::DegreeLiteral),
+            map(terminated(parse_int_literal, not(parse_unit_suffix)), ParsedFactor::IntLiteral),
+            map(tuple((double, opt(parse_unit_suffix))), |(value, unit)| match unit {
+                Some(unit) => ParsedFactor::Quantity(value, unit),
+                None => ParsedFactor::Literal(value),
+            }),
+
             map(parse_subexpr, |expr| {
                 ParsedFactor::SubExpression(Box::new(expr))
             }),
+            map(parse_list, ParsedFactor::List),
         )),
     )(input)
 }
 
-fn parse_term(input: &str) -> IResult<&str, ParsedTerm> {
+fn parse_list(input: &str) -> IResult<&str, Vec<ParsedExpr<'_>>> {
+    delimited(
+        char('['),
+        separated_list0(preceded(skip_spaces, char(',')), parse_expr),
+        preceded(skip_spaces, char(']')),
+    )(input)
+}
+
+fn parse_derivative(input: &str) -> IResult<&str, (ParsedExpr<'_>, &str)> {
+    tuple((
+        tag("diff"),
+        skip_spaces,
+        char('('),
+        parse_expr,
+        skip_spaces,
+        char(','),
+        skip_spaces,
+        parse_identifier,
+        skip_spaces,
+        char(')'),
+    ))(input)
+    .map(|(input, output)| (input, (output.3, output.7)))
+}
+
+fn parse_integral(input: &str) -> IResult<&str, ParsedIntegral<'_>> {
+    tuple((
+        tag("integrate"),
+        skip_spaces,
+        char('('),
+        parse_expr,
+        skip_spaces,
+        char(','),
+        skip_spaces,
+        parse_identifier,
+        skip_spaces,
+        char(','),
+        parse_expr,
+        skip_spaces,
+        char(','),
+        parse_expr,
+        skip_spaces,
+        char(')'),
+    ))(input)
+    .map(|(input, output)| (input, (output.3, output.7, output.10, output.13)))
+}
+
+fn parse_series(input: &str) -> IResult<&str, ParsedSeries<'_>> {
+    tuple((
+        alt((
+            map(tag("sum"), |_| SeriesOperator::Sum),
+            map(tag("prod"), |_| SeriesOperator::Product),
+        )),
+        skip_spaces,
+        char('('),
+        skip_spaces,
+        parse_identifier,
+        skip_spaces,
+        char(','),
+        parse_expr,
+        skip_spaces,
+        char(','),
+        parse_expr,
+        skip_spaces,
+        char(','),
+        parse_expr,
+        skip_spaces,
+        char(')'),
+    ))(input)
+    .map(|(input, output)| (input, (output.0, output.4, output.7, output.10, output.13)))
+}
+
+// `sum` with four arguments, the first being a name, is rather a series.
+fn parse_statistic(input: &str) -> IResult<&str, ParsedStatistic<'_>> {
+    tuple((
+        alt((
+            map(tag("mean"), |_| StatisticOperator::Mean),
+            map(tag("median"), |_| StatisticOperator::Median),
+            map(tag("stddev"), |_| StatisticOperator::StandardDeviation),
+            map(tag("pstddev"), |_| StatisticOperator::PopulationStandardDeviation),
+            map(tag("variance"), |_| StatisticOperator::Variance),
+            map(tag("pvariance"), |_| StatisticOperator::PopulationVariance),
+            map(tag("percentile"), |_| StatisticOperator::Percentile),
+            map(tag("min"), |_| StatisticOperator::Min),
+            map(tag("max"), |_| StatisticOperator::Max),
+            map(tag("sum"), |_| StatisticOperator::Sum),
+            map(tag("count"), |_| StatisticOperator::Count),
+            map(tag("cov"), |_| StatisticOperator::Covariance),
+            map(tag("pcov"), |_| StatisticOperator::PopulationCovariance),
+            map(tag("corr"), |_| StatisticOperator::Correlation),
+        )),
+        skip_spaces,
+        char('('),
+        separated_list0(preceded(skip_spaces, char(',')), parse_expr),
+        skip_spaces,
+        char(')'),
+    ))(input)
+    .map(|(input, output)| (input, (output.0, output.3)))
+}
+
+fn parse_matrix_function(input: &str) -> IResult<&str, ParsedMatrixFunction<'_>> {
+    tuple((
+        alt((
+            map(tag("transpose"), |_| MatrixOperator::Transpose),
+            map(tag("det"), |_| MatrixOperator::Determinant),
+            map(tag("inv"), |_| MatrixOperator::Inverse),
+            map(tag("solve"), |_| MatrixOperator::Solve),
+            map(tag("identity"), |_| MatrixOperator::Identity),
+            map(tag("zeros"), |_| MatrixOperator::Zeros),
+        )),
+        skip_spaces,
+        char('('),
+        separated_list0(preceded(skip_spaces, char(',')), parse_expr),
+        skip_spaces,
+        char(')'),
+    ))(input)
+    .map(|(input, output)| (input, (output.0, output.3)))
+}
+
+// The body of `poly x: body` extends as far as an expression does.
+fn parse_polynomial(input: &str) -> IResult<&str, (&str, ParsedExpr<'_>)> {
+    tuple((
+        tag("poly"),
+        one_of(" \t"),
+        skip_spaces,
+        parse_identifier,
+        skip_spaces,
+        char(':'),
+        parse_expr,
+    ))(input)
+    .map(|(input, output)| (input, (output.3, output.6)))
+}
+
+fn parse_polynomial_function(input: &str) -> IResult<&str, ParsedPolynomialFunction<'_>> {
+    tuple((
+        alt((
+            map(tag("poly"), |_| PolynomialOperator::Coefficients),
+            map(tag("eval"), |_| PolynomialOperator::Evaluate),
+            map(tag("roots"), |_| PolynomialOperator::Roots),
+            map(tag("derive"), |_| PolynomialOperator::Derivative),
+        )),
+        skip_spaces,
+        char('('),
+        separated_list0(preceded(skip_spaces, char(',')), parse_expr),
+        skip_spaces,
+        char(')'),
+    ))(input)
+    .map(|(input, output)| (input, (output.0, output.3)))
+}
+
+// `factorial` is tried before `factor`, and `lgamma` before `lg`.
+fn parse_number_theory_function(input: &str) -> IResult<&str, ParsedNumberTheoryFunction<'_>> {
+    tuple((
+        alt((
+            map(tag("factorial"), |_| NumberTheoryOperator::Factorial),
+            map(tag("gamma"), |_| NumberTheoryOperator::Gamma),
+            map(tag("lgamma"), |_| NumberTheoryOperator::LogGamma),
+            map(tag("binom"), |_| NumberTheoryOperator::Binomial),
+            map(tag("perm"), |_| NumberTheoryOperator::Permutations),
+            map(tag("isprime"), |_| NumberTheoryOperator::IsPrime),
+            map(tag("nextprime"), |_| NumberTheoryOperator::NextPrime),
+            map(tag("factor"), |_| NumberTheoryOperator::Factor),
+            map(tag("modpow"), |_| NumberTheoryOperator::ModPow),
+            map(tag("modinv"), |_| NumberTheoryOperator::ModInverse),
+        )),
+        skip_spaces,
+        char('('),
+        separated_list0(preceded(skip_spaces, char(',')), parse_expr),
+        skip_spaces,
+        char(')'),
+    ))(input)
+    .map(|(input, output)| (input, (output.0, output.3)))
+}
+
+fn parse_angle_conversion(input: &str) -> IResult<&str, (AngleUnit, ParsedExpr<'_>)> {
+    tuple((
+        alt((
+            map(tag("deg"), |_| AngleUnit::Degree),
+            map(tag("rad"), |_| AngleUnit::Radian),
+        )),
+        parse_subexpr,
+    ))(input)
+}
+
+// `randint` is tried before `rand`.
+fn parse_random(input: &str) -> IResult<&str, ParsedRandom<'_>> {
+    tuple((
+        alt((
+            map(tag("randint"), |_| RandomOperator::RandInt),
+            map(tag("rand"), |_| RandomOperator::Rand),
+            map(tag("uniform"), |_| RandomOperator::Uniform),
+            map(tag("normal"), |_| RandomOperator::Normal),
+            map(tag("exponential"), |_| RandomOperator::Exponential),
+        )),
+        skip_spaces,
+        char('('),
+        separated_list0(preceded(skip_spaces, char(',')), parse_expr),
+        skip_spaces,
+        char(')'),
+    ))(input)
+    .map(|(input, output)| (input, (output.0, output.3)))
+}
+
+fn parse_term(input: &str) -> IResult<&str, ParsedTerm<'_>> {
     //eprintln!("\nParsing term : {}\n", &input);
     tuple((
         parse_factor,
@@ -171,6 +746,8 @@ fn parse_term(input: &str) -> IResult<&str, ParsedTerm> {
                     map(char('*'), |_| TermOperator::Multiply),
                     map(char('/'), |_| TermOperator::Divide),
                     map(char('^'), |_| TermOperator::Exponent),
+                    map(tag("div"), |_| TermOperator::IntegerDivide),
+                    map(char('@'), |_| TermOperator::MatrixMultiply),
                 )),
             ),
             parse_factor,
@@ -178,7 +755,19 @@ fn parse_term(input: &str) -> IResult<&str, ParsedTerm> {
     ))(input)
 }
 
-fn parse_expr(input: &str) -> IResult<&str, ParsedExpr> {
+// `..` has a lower precedence than all the other operators.
+fn parse_expr(input: &str) -> IResult<&str, ParsedExpr<'_>> {
+    tuple((
+        parse_arithmetic_expr,
+        opt(preceded(tuple((skip_spaces, tag(".."))), parse_arithmetic_expr)),
+    ))(input)
+    .map(|(input, (lo, hi))| match hi {
+        Some(hi) => (input, ((ParsedFactor::Range(Box::new(lo), Box::new(hi)), vec![]), vec![])),
+        None => (input, lo),
+    })
+}
+
+fn parse_arithmetic_expr(input: &str) -> IResult<&str, ParsedExpr<'_>> {
     //eprintln!("\nParsing expression : {}\n", &input);
         tuple((
             parse_term,
@@ -186,9 +775,10 @@ fn parse_expr(input: &str) -> IResult<&str, ParsedExpr> {
                 preceded(
                     skip_spaces,
                     alt((
+                        map(alt((tag("+-"), tag("±"))), |_| ExprOperator::PlusMinus),
                         map(char('+'), |_| ExprOperator::Add),
                         map(char('-'), |_| ExprOperator::Subtract),
-                        map(tag("mod"), |_| ExprOperator::Modulo),
+                        map(terminated(tag("mod"), not(alphanumeric1)), |_| ExprOperator::Modulo),
                     )),
                 ),
                 parse_term,
@@ -197,7 +787,7 @@ fn parse_expr(input: &str) -> IResult<&str, ParsedExpr> {
     (input)
 }
 
-fn parse_function_expr(input: &str) -> IResult<&str, ParsedFunctionExpr> {
+fn parse_function_expr(input: &str) -> IResult<&str, ParsedFunctionExpr<'_>> {
     //eprintln!("\nParsing function : {}\n", &input);
     tuple((
         alt((
@@ -232,11 +822,59 @@ fn parse_function_expr(input: &str) -> IResult<&str, ParsedFunctionExpr> {
             map(tag("floor"), |_| FunctionOperator::Floor), 
             map(tag("sgn"), |_| FunctionOperator::Signum), 
             map(tag("sqrt"), |_| FunctionOperator::Sqrt), 
+            map(tag("int"), |_| FunctionOperator::ToInt),
+            map(tag("float"), |_| FunctionOperator::ToFloat),
+            alt((
+                map(tag("re"), |_| FunctionOperator::Re),
+                map(tag("im"), |_| FunctionOperator::Im),
+                map(tag("conj"), |_| FunctionOperator::Conj),
+                map(tag("arg"), |_| FunctionOperator::Arg),
+            )),
         )),
         parse_subexpr,
     ))(input)
 }
 
+// A unit such as `km/h` or `kg*m/s^2`, written without spaces.
+fn parse_unit(input: &str) -> IResult<&str, ParsedUnit<'_>> {
+    tuple((
+        parse_unit_power,
+        many0(tuple((alt((char('*'), char('/'))), parse_unit_power))),
+    ))(input)
+    .map(|(input, (first, others))| {
+        let mut unit = vec![first];
+        for (operator, (name, exponent)) in others {
+            unit.push((name, if operator == '/' { -exponent } else { exponent }));
+        }
+        (input, unit)
+    })
+}
+
+fn parse_unit_power(input: &str) -> IResult<&str, (&str, i32)> {
+    tuple((
+        verify(alpha1, |name: &str| units::is_unit_name(name)),
+        opt(preceded(
+            char('^'),
+            map_res(recognize(tuple((opt(char('-')), digit1))), str::parse::<i32>),
+        )),
+    ))(input)
+    .map(|(input, (name, exponent))| (input, (name, exponent.unwrap_or(1))))
+}
+
+// The unit of a number, e.g. the `m/s` of `3 m/s`. It must be on the same
+// line as the number, and a word followed by `=` is rather the target of an
+// assignment.
+fn parse_unit_suffix(input: &str) -> IResult<&str, ParsedUnit<'_>> {
+    terminated(
+        preceded(skip_blanks, parse_unit),
+        not(preceded(skip_spaces, char('='))),
+    )(input)
+}
+
+fn skip_blanks(input: &str) -> IResult<&str, &str> {
+    take_while(|ch| ch == ' ' || ch == '\t')(input)
+}
+
 fn skip_spaces(input: &str) -> IResult<&str, &str> {
     let chars = " \t\r\n";
     take_while(move |ch| chars.contains(ch))(input)
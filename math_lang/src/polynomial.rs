@@ -0,0 +1,396 @@
+// Polynomials in one variable with f64 coefficients, whatever the numeric
+// backend: their arithmetic, derivatives, evaluation and real roots.
+//
+// The real roots are found among the complex roots computed together by the
+// Durand–Kerner method: each approximation moves by the value of the
+// polynomial divided by its distances to the others, starting from points
+// spread on a circle that holds every root. The approximations of a root of
+// multiplicity m only agree to about the m-th root of the precision, so
+// approximations close together are taken as one root when the polynomial
+// and its derivatives below the m-th vanish there.
+
+use std::fmt;
+use std::ops::{Add, Div, Mul, Sub};
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Polynomial {
+    // From the constant term up, without zero leading coefficient.
+    coefficients: Vec<f64>,
+}
+
+impl From<f64> for Polynomial {
+    fn from(value: f64) -> Polynomial {
+        Polynomial::new(vec![value])
+    }
+}
+
+impl Add for Polynomial {
+    type Output = Polynomial;
+    fn add(self, other: Polynomial) -> Polynomial {
+        let size = self.coefficients.len().max(other.coefficients.len());
+        Polynomial::new(
+            (0..size)
+                .map(|power| self.coefficient(power) + other.coefficient(power))
+                .collect(),
+        )
+    }
+}
+
+impl Sub for Polynomial {
+    type Output = Polynomial;
+    fn sub(self, other: Polynomial) -> Polynomial {
+        self + other.scale(-1.)
+    }
+}
+
+impl Mul for Polynomial {
+    type Output = Polynomial;
+    fn mul(self, other: Polynomial) -> Polynomial {
+        if self.coefficients.is_empty() || other.coefficients.is_empty() {
+            return Polynomial::default();
+        }
+        let mut product = vec![0.; self.coefficients.len() + other.coefficients.len() - 1];
+        for (left_power, left) in self.coefficients.iter().enumerate() {
+            for (right_power, right) in other.coefficients.iter().enumerate() {
+                product[left_power + right_power] += left * right;
+            }
+        }
+        Polynomial::new(product)
+    }
+}
+
+impl Div<f64> for Polynomial {
+    type Output = Polynomial;
+    fn div(self, other: f64) -> Polynomial {
+        self.scale(1. / other)
+    }
+}
+
+// A float meeting a polynomial is first turned into a constant one.
+macro_rules! mixed_operators {
+    ($($operator:ident $method:ident),*) => {$(
+        impl $operator<f64> for Polynomial {
+            type Output = Polynomial;
+            fn $method(self, other: f64) -> Polynomial {
+                self.$method(Polynomial::from(other))
+            }
+        }
+        impl $operator<Polynomial> for f64 {
+            type Output = Polynomial;
+            fn $method(self, other: Polynomial) -> Polynomial {
+                Polynomial::from(self).$method(other)
+            }
+        }
+    )*};
+}
+
+mixed_operators!(Add add, Sub sub, Mul mul);
+
+// The highest degree of a power, so that a mistyped exponent is an error
+// rather than a product running for minutes.
+const MAX_DEGREE: usize = 1000;
+
+fn complex_mul(left: (f64, f64), right: (f64, f64)) -> (f64, f64) {
+    (left.0 * right.0 - left.1 * right.1, left.0 * right.1 + left.1 * right.0)
+}
+
+fn complex_div(left: (f64, f64), right: (f64, f64)) -> (f64, f64) {
+    let norm = right.0 * right.0 + right.1 * right.1;
+    ((left.0 * right.0 + left.1 * right.1) / norm, (left.1 * right.0 - left.0 * right.1) / norm)
+}
+
+impl Polynomial {
+    pub fn new(mut coefficients: Vec<f64>) -> Polynomial {
+        while coefficients.last() == Some(&0.) {
+            coefficients.pop();
+        }
+        Polynomial { coefficients }
+    }
+
+    /// The polynomial with these coefficients, from the leading one down.
+    pub fn from_highest(coefficients: &[f64]) -> Polynomial {
+        Polynomial::new(coefficients.iter().rev().cloned().collect())
+    }
+
+    /// The polynomial `x`.
+    pub fn variable() -> Polynomial {
+        Polynomial::new(vec![0., 1.])
+    }
+
+    fn coefficient(&self, power: usize) -> f64 {
+        self.coefficients.get(power).cloned().unwrap_or(0.)
+    }
+
+    fn scale(self, factor: f64) -> Polynomial {
+        Polynomial::new(self.coefficients.into_iter().map(|coefficient| coefficient * factor).collect())
+    }
+
+    /// Horner's scheme.
+    pub fn eval(&self, x: f64) -> f64 {
+        self.coefficients.iter().rev().fold(0., |value, coefficient| value * x + coefficient)
+    }
+
+    pub fn eval_each(&self, values: Vec<f64>) -> Vec<f64> {
+        values.into_iter().map(|x| self.eval(x)).collect()
+    }
+
+    fn eval_complex(&self, z: (f64, f64)) -> (f64, f64) {
+        self.coefficients.iter().rev().fold((0., 0.), |value, coefficient| {
+            let value = complex_mul(value, z);
+            (value.0 + coefficient, value.1)
+        })
+    }
+
+    pub fn derivative(&self) -> Polynomial {
+        Polynomial::new(
+            self.coefficients
+                .iter()
+                .enumerate()
+                .skip(1)
+                .map(|(power, coefficient)| power as f64 * coefficient)
+                .collect(),
+        )
+    }
+
+    /// Raises the polynomial to a whole power by repeated squaring.
+    pub fn pow(&self, exponent: f64) -> Result<Polynomial, String> {
+        if exponent < 0. || exponent.fract() != 0. || exponent > u32::MAX as f64 {
+            return Err(format!(
+                "Error: A polynomial can only be raised to a whole power, not {}.",
+                exponent
+            ));
+        }
+        let degree = self.coefficients.len().saturating_sub(1);
+        if degree as f64 * exponent > MAX_DEGREE as f64 {
+            return Err(format!(
+                "Error: A polynomial of degree {} raised to the power {} has a degree above {}.",
+                degree, exponent, MAX_DEGREE
+            ));
+        }
+        let mut exponent = exponent as u32;
+        let mut base = self.clone();
+        let mut result = Polynomial::from(1.);
+        while exponent > 0 {
+            if exponent % 2 == 1 {
+                result = result * base.clone();
+            }
+            exponent /= 2;
+            if exponent > 0 {
+                base = base.clone() * base;
+            }
+        }
+        Ok(result)
+    }
+
+    /// The quotient and the remainder of the long division by `divisor`.
+    pub fn div_rem(&self, divisor: &Polynomial) -> Result<(Polynomial, Polynomial), String> {
+        let leading = match divisor.coefficients.last() {
+            Some(leading) => *leading,
+            None => return Err("Error: Division by the zero polynomial.".to_string()),
+        };
+        let degree = divisor.coefficients.len() - 1;
+        let mut remainder = self.coefficients.clone();
+        let mut quotient = vec![0.; remainder.len().saturating_sub(degree)];
+        for power in (0..quotient.len()).rev() {
+            let factor = remainder[power + degree] / leading;
+            quotient[power] = factor;
+            for (offset, coefficient) in divisor.coefficients.iter().enumerate() {
+                remainder[power + offset] -= factor * coefficient;
+            }
+            remainder.pop();
+        }
+        Ok((Polynomial::new(quotient), Polynomial::new(remainder)))
+    }
+
+    // A root of the given multiplicity is a simple root of the derivative of
+    // one order less, found there by Newton's method.
+    fn polish(&self, mut root: (f64, f64), multiplicity: usize) -> (f64, f64) {
+        let mut derivative = self.clone();
+        for _ in 1..multiplicity {
+            derivative = derivative.derivative();
+        }
+        let slope = derivative.derivative();
+        for _ in 0..100 {
+            let step = complex_div(derivative.eval_complex(root), slope.eval_complex(root));
+            if !step.0.is_finite() || !step.1.is_finite() {
+                break;
+            }
+            root = (root.0 - step.0, root.1 - step.1);
+            if step.0.hypot(step.1) <= 1e-15 * (1. + root.0.hypot(root.1)) {
+                break;
+            }
+        }
+        root
+    }
+
+    // Whether the polynomial and its derivatives up to the order below the
+    // multiplicity vanish at `root`, up to rounding.
+    fn is_root(&self, root: (f64, f64), multiplicity: usize) -> bool {
+        let mut derivative = self.clone();
+        for _ in 0..multiplicity {
+            let value = derivative.eval_complex(root);
+            let magnitude = root.0.hypot(root.1);
+            let bound = derivative
+                .coefficients
+                .iter()
+                .rev()
+                .fold(0., |bound, coefficient| bound * magnitude + coefficient.abs());
+            if value.0.hypot(value.1) > 1e-8 * bound {
+                return false;
+            }
+            derivative = derivative.derivative();
+        }
+        true
+    }
+
+    /// The real roots in increasing order, with their multiplicity: the
+    /// complex roots whose imaginary part is negligible.
+    pub fn roots(&self) -> Result<Vec<f64>, String> {
+        let zeros = match self.coefficients.iter().position(|coefficient| *coefficient != 0.) {
+            Some(zeros) => zeros,
+            None => return Err("Error: Every number is a root of the zero polynomial.".to_string()),
+        };
+        let leading = self.coefficients[self.coefficients.len() - 1];
+        let monic = Polynomial::new(
+            self.coefficients[zeros..]
+                .iter()
+                .map(|coefficient| coefficient / leading)
+                .collect(),
+        );
+        let degree = monic.coefficients.len() - 1;
+        let radius = 1. + monic.coefficients[..degree].iter().fold(0., |max: f64, c| max.max(c.abs()));
+        let mut roots: Vec<(f64, f64)> = (0..degree)
+            .map(|index| {
+                let angle = 2. * std::f64::consts::PI * index as f64 / degree as f64 + 0.4;
+                (radius * angle.cos(), radius * angle.sin())
+            })
+            .collect();
+        for _ in 0..1000 {
+            let mut moved = 0f64;
+            for index in 0..degree {
+                let root = roots[index];
+                let denominator = (0..degree)
+                    .filter(|other| *other != index)
+                    .fold((1., 0.), |product, other| {
+                        complex_mul(product, (root.0 - roots[other].0, root.1 - roots[other].1))
+                    });
+                let step = complex_div(monic.eval_complex(root), denominator);
+                if !step.0.is_finite() || !step.1.is_finite() {
+                    continue;
+                }
+                roots[index] = (root.0 - step.0, root.1 - step.1);
+                moved = moved.max(step.0.hypot(step.1) / (1. + root.0.hypot(root.1)));
+            }
+            if moved <= 1e-15 {
+                break;
+            }
+        }
+        let mut real = vec![0.; zeros];
+        let mut taken = vec![false; degree];
+        for index in 0..degree {
+            if taken[index] {
+                continue;
+            }
+            taken[index] = true;
+            let mut cluster = vec![roots[index]];
+            let mut next = 0;
+            while next < cluster.len() {
+                let member = cluster[next];
+                for other in 0..degree {
+                    let distance = (member.0 - roots[other].0).hypot(member.1 - roots[other].1);
+                    if !taken[other] && distance <= 1e-2 * (1. + member.0.hypot(member.1)) {
+                        taken[other] = true;
+                        cluster.push(roots[other]);
+                    }
+                }
+                next += 1;
+            }
+            let count = cluster.len();
+            if count > 1 {
+                let sum = cluster.iter().fold((0., 0.), |sum, root| (sum.0 + root.0, sum.1 + root.1));
+                let root = monic.polish((sum.0 / count as f64, sum.1 / count as f64), count);
+                if monic.is_root(root, count) {
+                    cluster = vec![root; count];
+                }
+            }
+            real.extend(
+                cluster
+                    .into_iter()
+                    .filter(|root| root.1.abs() <= 1e-7 * (1. + root.0.abs()))
+                    .map(|root| root.0),
+            );
+        }
+        real.sort_by(|a, b| a.total_cmp(b));
+        Ok(real)
+    }
+}
+
+// From the leading term down, such as `2x^3 - x + 1.5`.
+impl fmt::Display for Polynomial {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.coefficients.is_empty() {
+            return write!(f, "0");
+        }
+        for (power, coefficient) in self.coefficients.iter().enumerate().rev() {
+            if *coefficient == 0. {
+                continue;
+            }
+            let first = power == self.coefficients.len() - 1;
+            match (first, coefficient.is_sign_negative()) {
+                (true, true) => write!(f, "-")?,
+                (true, false) => {}
+                (false, true) => write!(f, " - ")?,
+                (false, false) => write!(f, " + ")?,
+            }
+            let magnitude = coefficient.abs();
+            if magnitude != 1. || power == 0 {
+                write!(f, "{}", magnitude)?;
+            }
+            match power {
+                0 => {}
+                1 => write!(f, "x")?,
+                _ => write!(f, "x^{}", power)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_roots(polynomial: Polynomial, expected: &[f64]) {
+        let roots = polynomial.roots().unwrap();
+        assert_eq!(roots.len(), expected.len(), "{:?}", roots);
+        for (root, expected) in roots.iter().zip(expected) {
+            assert!((root - expected).abs() < 1e-9, "{:?}", roots);
+        }
+    }
+
+    #[test]
+    fn repeated_roots_are_counted_with_their_multiplicity() {
+        // (x - 1)^2 (x + 2) and (x - 3)^3 x^2.
+        assert_roots(Polynomial::from_highest(&[1., 0., -3., 2.]), &[-2., 1., 1.]);
+        assert_roots(
+            Polynomial::from_highest(&[1., -9., 27., -27., 0., 0.]),
+            &[0., 0., 3., 3., 3.],
+        );
+        assert_roots(Polynomial::from_highest(&[1., 0., 1.]), &[]);
+    }
+
+    #[test]
+    fn low_degrees() {
+        assert_roots(Polynomial::new(vec![5.]), &[]);
+        assert_roots(Polynomial::new(vec![-3., 2.]), &[1.5]);
+        assert!(Polynomial::new(vec![0.]).roots().is_err());
+    }
+
+    #[test]
+    fn powers_are_limited() {
+        let x = Polynomial::variable();
+        assert_eq!(x.pow(1000.).unwrap().coefficients.len(), 1001);
+        assert!(x.pow(1001.).is_err());
+        assert_eq!(Polynomial::from(2.).pow(10.).unwrap(), Polynomial::from(1024.));
+    }
+}
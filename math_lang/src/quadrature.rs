@@ -0,0 +1,143 @@
+// Adaptive Gauss-Kronrod quadrature for `integrate`, between finite bounds.
+//
+// Each segment is integrated with the 15-point Kronrod rule, and the
+// difference with the embedded 7-point Gauss rule estimates its error. The
+// segment with the largest error is bisected until the total error is within
+// the relative tolerance of the integral of |f|, which also covers integrals
+// that are 0.
+
+// Nodes of the Kronrod rule on [-1, 1], from 1 down to 0; those at odd
+// indices are the nodes of the Gauss rule.
+const KRONROD_NODES: [f64; 8] = [
+    0.9914553711208126,
+    0.9491079123427585,
+    0.8648644233597691,
+    0.7415311855993945,
+    0.5860872354676911,
+    0.4058451513773972,
+    0.20778495500789848,
+    0.,
+];
+
+const KRONROD_WEIGHTS: [f64; 8] = [
+    0.022935322010529224,
+    0.06309209262997856,
+    0.10479001032225019,
+    0.14065325971552592,
+    0.1690047266392679,
+    0.19035057806478542,
+    0.20443294007529889,
+    0.20948214108472782,
+];
+
+const GAUSS_WEIGHTS: [f64; 4] = [
+    0.1294849661688697,
+    0.27970539148927664,
+    0.3818300505051189,
+    0.4179591836734694,
+];
+
+const MAX_SEGMENTS: usize = 1000;
+
+struct Segment {
+    a: f64,
+    b: f64,
+    integral: f64,
+    // Integral of |f|, the scale of the tolerance.
+    magnitude: f64,
+    error: f64,
+}
+
+fn integrate_segment(
+    f: &mut impl FnMut(f64) -> Result<f64, String>,
+    a: f64,
+    b: f64,
+) -> Result<Segment, String> {
+    let center = 0.5 * (a + b);
+    let half_length = 0.5 * (b - a);
+    let mut kronrod = 0.;
+    let mut gauss = 0.;
+    let mut magnitude = 0.;
+    for (index, node) in KRONROD_NODES.iter().enumerate() {
+        let offsets: &[f64] = if *node == 0. { &[0.] } else { &[-node, *node] };
+        for offset in offsets {
+            let x = center + half_length * offset;
+            let value = f(x)?;
+            if value.is_nan() {
+                return Err(format!("Error: The integrand of integrate is not a number at {}.", x));
+            }
+            kronrod += KRONROD_WEIGHTS[index] * value;
+            magnitude += KRONROD_WEIGHTS[index] * value.abs();
+            if index % 2 == 1 {
+                gauss += GAUSS_WEIGHTS[index / 2] * value;
+            }
+        }
+    }
+    Ok(Segment {
+        a,
+        b,
+        integral: kronrod * half_length,
+        magnitude: magnitude * half_length.abs(),
+        error: ((kronrod - gauss) * half_length).abs(),
+    })
+}
+
+/// The integral of `f` from `a` to `b`, within the relative `tolerance`.
+pub fn integrate(
+    mut f: impl FnMut(f64) -> Result<f64, String>,
+    a: f64,
+    b: f64,
+    tolerance: f64,
+) -> Result<f64, String> {
+    if !a.is_finite() || !b.is_finite() {
+        return Err(format!("Error: The bounds of integrate must be finite, not {} and {}.", a, b));
+    }
+    if a == b {
+        return Ok(0.);
+    }
+    let mut segments = vec![integrate_segment(&mut f, a, b)?];
+    loop {
+        let integral: f64 = segments.iter().map(|segment| segment.integral).sum();
+        let magnitude: f64 = segments.iter().map(|segment| segment.magnitude).sum();
+        let error: f64 = segments.iter().map(|segment| segment.error).sum();
+        if error <= tolerance * magnitude && integral.is_finite() {
+            return Ok(integral);
+        }
+        let worst = (0..segments.len())
+            .max_by(|&i, &j| segments[i].error.total_cmp(&segments[j].error))
+            .unwrap();
+        let Segment { a, b, .. } = segments[worst];
+        let middle = 0.5 * (a + b);
+        if segments.len() >= MAX_SEGMENTS || middle == a || middle == b || !integral.is_finite() {
+            return Err(format!(
+                "Error: integrate did not converge: the integral is about {} with an estimated error of {}.",
+                integral, error
+            ));
+        }
+        segments.swap_remove(worst);
+        segments.push(integrate_segment(&mut f, a, middle)?);
+        segments.push(integrate_segment(&mut f, middle, b)?);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn smooth_integrands() {
+        let integral = integrate(|x| Ok(x.sin()), 0., std::f64::consts::PI, 1e-10).unwrap();
+        assert!((integral - 2.).abs() < 1e-9);
+        let integral = integrate(|x| Ok(x * x), 3., 0., 1e-10).unwrap();
+        assert!((integral + 9.).abs() < 1e-9);
+    }
+
+    #[test]
+    fn endpoint_singularities() {
+        // The nodes never reach the bounds, where these are infinite.
+        let integral = integrate(|x| Ok(1. / x.sqrt()), 0., 1., 1e-10).unwrap();
+        assert!((integral - 2.).abs() < 1e-8, "{}", integral);
+        let integral = integrate(|x| Ok(x.ln()), 0., 1., 1e-10).unwrap();
+        assert!((integral + 1.).abs() < 1e-8, "{}", integral);
+    }
+}
@@ -0,0 +1,103 @@
+// Pseudo-random numbers from xoshiro256**, whose state is expanded from a
+// 64-bit seed by SplitMix64, so that a program draws the same numbers when
+// interpreted and when compiled with the same seed.
+//
+// The state is per thread and seeded from the clock until `seed` is called.
+// Floats are drawn in [0, 1) from the top 53 bits of the output, normal
+// variates by the Box–Muller transform of two of them, exponential ones by
+// inverting the distribution function, and ints without bias by rejecting
+// the outputs that would wrap around unevenly.
+
+use std::cell::Cell;
+use std::f64::consts::PI;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+thread_local! {
+    static STATE: Cell<[u64; 4]> = Cell::new(expand(clock_seed()));
+}
+
+fn clock_seed() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_nanos() as u64)
+}
+
+// SplitMix64 never gives four zero words, which xoshiro could not leave.
+fn expand(mut seed: u64) -> [u64; 4] {
+    let mut state = [0; 4];
+    for word in state.iter_mut() {
+        seed = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = seed;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        *word = z ^ (z >> 31);
+    }
+    state
+}
+
+pub fn seed(seed: u64) {
+    STATE.with(|state| state.set(expand(seed)));
+}
+
+fn next_u64() -> u64 {
+    STATE.with(|cell| {
+        let mut state = cell.get();
+        let result = state[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let shifted = state[1] << 17;
+        state[2] ^= state[0];
+        state[3] ^= state[1];
+        state[1] ^= state[2];
+        state[0] ^= state[3];
+        state[2] ^= shifted;
+        state[3] = state[3].rotate_left(45);
+        cell.set(state);
+        result
+    })
+}
+
+/// A float in [0, 1).
+pub fn rand() -> f64 {
+    (next_u64() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// A float between `lo` and `hi`.
+pub fn uniform(lo: f64, hi: f64) -> f64 {
+    lo + (hi - lo) * rand()
+}
+
+/// An int in [lo, hi], each as likely.
+pub fn randint(lo: i64, hi: i64) -> Result<i64, String> {
+    if lo > hi {
+        return Err(format!("Error: randint needs lo <= hi, not {} > {}.", lo, hi));
+    }
+    let size = (hi as u64).wrapping_sub(lo as u64).wrapping_add(1);
+    if size == 0 {
+        return Ok(next_u64() as i64);
+    }
+    // The outputs below `threshold` would make the low values more likely.
+    let threshold = size.wrapping_neg() % size;
+    loop {
+        let output = next_u64();
+        if output >= threshold {
+            return Ok(lo.wrapping_add((output % size) as i64));
+        }
+    }
+}
+
+pub fn normal(mean: f64, deviation: f64) -> Result<f64, String> {
+    if deviation < 0. || deviation.is_nan() {
+        return Err(format!(
+            "Error: The standard deviation of normal must not be negative, not {}.",
+            deviation
+        ));
+    }
+    let radius = (-2. * (1. - rand()).ln()).sqrt();
+    Ok(mean + deviation * radius * (2. * PI * rand()).cos())
+}
+
+pub fn exponential(rate: f64) -> Result<f64, String> {
+    if rate <= 0. || rate.is_nan() {
+        return Err(format!("Error: The rate of exponential must be positive, not {}.", rate));
+    }
+    Ok(-(1. - rand()).ln() / rate)
+}
@@ -0,0 +1,396 @@
+use crate::analyzer::{
+    expr_type, term_type, AnalyzedExpr, AnalyzedFactor, AnalyzedFunctionExpr, AnalyzedProgram, AnalyzedStatement,
+    AnalyzedTerm,
+};
+use crate::diagnostics::{Diagnostic, Lint};
+use crate::interval::Interval;
+use crate::parser::{
+    ExprOperator, MatrixOperator, NumberTheoryOperator, RandomOperator, SeriesOperator, StatisticOperator,
+    TermOperator,
+};
+use crate::symbol_table::SymbolTable;
+use crate::unparser::function_name;
+use crate::value::{Type, Value};
+
+// Abstract interpretation of a program over intervals: every variable is
+// tracked as the range of values it may hold, starting from its current
+// value in the symbol table (constants, or values of earlier REPL lines),
+// and inputs range over their `range [lo, hi]` annotation or over any value.
+// Complex values and polynomials are not tracked: they range over any value,
+// and the operations on them are not checked.
+struct RangeAnalysis<'a> {
+    variables: &'a SymbolTable,
+    ranges: Vec<Interval>,
+    // Ranges are given in the declared unit of the variable.
+    input_scales: Vec<f64>,
+    statement_number: usize,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> RangeAnalysis<'a> {
+    fn report(&mut self, lint: Lint, message: String) {
+        self.diagnostics.push(Diagnostic::new(
+            lint,
+            format!("statement {}: {}", self.statement_number, message),
+        ));
+    }
+
+    fn check_divisor(&mut self, operator_name: &str, divisor: Interval) {
+        if divisor.contains(0.) {
+            self.report(
+                Lint::DivisionByZero,
+                format!("the divisor of `{}` ranges over {} and may be zero", operator_name, divisor),
+            );
+        }
+    }
+
+    fn factor(&mut self, factor: &AnalyzedFactor) -> Interval {
+        match factor {
+            AnalyzedFactor::Literal(value) => value_range(value),
+            AnalyzedFactor::Identifier(handle) => self.ranges[*handle],
+            AnalyzedFactor::FunctionExpression(function_expr) => self.function_expr(function_expr),
+            AnalyzedFactor::SubExpression(expr) => self.expr(expr),
+            AnalyzedFactor::Derivative(expr, _) => {
+                self.expr(expr);
+                Interval::entire()
+            }
+            // The integral is the length of the interval times the mean of
+            // the integrand.
+            AnalyzedFactor::Integral(integral) => {
+                let (integrand, handle, lo, hi) = &**integral;
+                let lo = self.expr(lo);
+                let hi = self.expr(hi);
+                self.ranges[*handle] = Interval::new(lo.lo.min(hi.lo), lo.hi.max(hi.hi));
+                let integrand = self.expr(integrand);
+                hi.sub(lo).mul(integrand)
+            }
+            // A sum has at most `hi - lo + 1` terms, and a product of
+            // factors of at least 1 is at least 1.
+            AnalyzedFactor::Series(series) => {
+                let (operator, handle, lo, hi, body) = &**series;
+                let lo = self.expr(lo);
+                let hi = self.expr(hi);
+                let identity = match operator {
+                    SeriesOperator::Sum => 0.,
+                    SeriesOperator::Product => 1.,
+                };
+                if lo.lo > hi.hi {
+                    return Interval::point(identity);
+                }
+                self.ranges[*handle] = Interval::new(lo.lo, hi.hi);
+                let body = self.expr(body);
+                let count = hi.hi - lo.lo + 1.;
+                match operator {
+                    SeriesOperator::Sum if count.is_finite() => Interval::new(0., count).mul(body),
+                    SeriesOperator::Product if body.lo >= 1. => Interval::new(1., f64::INFINITY),
+                    _ => Interval::entire(),
+                }
+            }
+            AnalyzedFactor::Statistic(statistic) => {
+                let ranges: Vec<Interval> = statistic.1.iter().map(|argument| self.expr(argument)).collect();
+                match statistic.0 {
+                    StatisticOperator::Mean
+                    | StatisticOperator::Median
+                    | StatisticOperator::Min
+                    | StatisticOperator::Max => hull(&ranges),
+                    StatisticOperator::Percentile => hull(&ranges[..ranges.len() - 1]),
+                    StatisticOperator::Correlation => Interval::new(-1., 1.),
+                    StatisticOperator::StandardDeviation
+                    | StatisticOperator::PopulationStandardDeviation
+                    | StatisticOperator::Variance
+                    | StatisticOperator::PopulationVariance
+                    | StatisticOperator::Count => Interval::new(0., f64::INFINITY),
+                    StatisticOperator::Sum
+                    | StatisticOperator::Covariance
+                    | StatisticOperator::PopulationCovariance => Interval::entire(),
+                }
+            }
+            AnalyzedFactor::MatrixFunction(function) => {
+                let ranges: Vec<Interval> = function.1.iter().map(|argument| self.expr(argument)).collect();
+                match function.0 {
+                    MatrixOperator::Transpose => ranges[0],
+                    MatrixOperator::Identity | MatrixOperator::Zeros => Interval::new(0., 1.),
+                    MatrixOperator::Determinant | MatrixOperator::Inverse | MatrixOperator::Solve => {
+                        Interval::entire()
+                    }
+                }
+            }
+            AnalyzedFactor::Polynomial(body, handle) => {
+                self.ranges[*handle] = Interval::entire();
+                self.expr(body);
+                Interval::entire()
+            }
+            AnalyzedFactor::PolynomialFunction(function) => {
+                for argument in &function.1 {
+                    self.expr(argument);
+                }
+                Interval::entire()
+            }
+            AnalyzedFactor::NumberTheoryFunction(function) => {
+                for argument in &function.1 {
+                    self.expr(argument);
+                }
+                match function.0 {
+                    NumberTheoryOperator::Factorial | NumberTheoryOperator::NextPrime => {
+                        Interval::new(1., f64::INFINITY)
+                    }
+                    NumberTheoryOperator::Factor => Interval::new(2., f64::INFINITY),
+                    NumberTheoryOperator::IsPrime => Interval::new(0., 1.),
+                    NumberTheoryOperator::Binomial
+                    | NumberTheoryOperator::Permutations
+                    | NumberTheoryOperator::ModPow
+                    | NumberTheoryOperator::ModInverse => Interval::new(0., f64::INFINITY),
+                    NumberTheoryOperator::Gamma | NumberTheoryOperator::LogGamma => Interval::entire(),
+                }
+            }
+            AnalyzedFactor::Random(random) => {
+                let ranges: Vec<Interval> = random.1.iter().map(|argument| self.expr(argument)).collect();
+                match random.0 {
+                    RandomOperator::Rand => Interval::new(0., 1.),
+                    RandomOperator::RandInt | RandomOperator::Uniform => hull(&ranges),
+                    RandomOperator::Normal => Interval::entire(),
+                    RandomOperator::Exponential => Interval::new(0., f64::INFINITY),
+                }
+            }
+            // Lists and matrices range over the hull of their elements.
+            AnalyzedFactor::List(elements) | AnalyzedFactor::Matrix(elements) => {
+                let ranges: Vec<Interval> = elements.iter().map(|element| self.expr(element)).collect();
+                hull(&ranges)
+            }
+            AnalyzedFactor::Range(lo, hi) => {
+                let lo = self.expr(lo);
+                let hi = self.expr(hi);
+                Interval::new(lo.lo, hi.hi.max(lo.lo))
+            }
+            AnalyzedFactor::Index(list, index) => {
+                self.expr(index);
+                self.expr(list)
+            }
+            AnalyzedFactor::Length(list) => {
+                self.expr(list);
+                Interval::new(0., f64::INFINITY)
+            }
+        }
+    }
+
+    fn term(&mut self, term: &AnalyzedTerm) -> Interval {
+        if matches!(term_type(self.variables, term), Type::Complex | Type::Polynomial) {
+            self.factor(&term.0);
+            for factor in &term.1 {
+                self.factor(&factor.1);
+            }
+            return Interval::entire();
+        }
+        let mut result = self.factor(&term.0);
+        for factor in &term.1 {
+            let operand = self.factor(&factor.1);
+            result = match factor.0 {
+                TermOperator::Multiply => result.mul(operand),
+                TermOperator::Divide => {
+                    self.check_divisor("/", operand);
+                    result.div(operand)
+                }
+                TermOperator::IntegerDivide => {
+                    self.check_divisor("div", operand);
+                    result.div_euclid(operand)
+                }
+                TermOperator::Exponent => {
+                    let integral_exponent = operand.is_point() && operand.lo.fract() == 0.;
+                    if result.lo < 0. && !integral_exponent {
+                        self.report(
+                            Lint::DomainError,
+                            format!(
+                                "`^` may raise a negative base in {} to the non-integer power {}",
+                                result, operand
+                            ),
+                        );
+                    }
+                    if result.contains(0.) && operand.lo < 0. {
+                        self.report(
+                            Lint::DivisionByZero,
+                            format!("`^` may raise zero to the negative power {}", operand),
+                        );
+                    }
+                    result.pow(operand)
+                }
+                TermOperator::MatrixMultiply => Interval::entire(),
+            };
+        }
+        result
+    }
+
+    fn expr(&mut self, expr: &AnalyzedExpr) -> Interval {
+        if matches!(expr_type(self.variables, expr), Type::Complex | Type::Polynomial) {
+            self.term(&expr.0);
+            for term in &expr.1 {
+                self.term(&term.1);
+            }
+            return Interval::entire();
+        }
+        let mut result = self.term(&expr.0);
+        for term in &expr.1 {
+            let operand = self.term(&term.1);
+            result = match term.0 {
+                ExprOperator::Add => result.add(operand),
+                ExprOperator::Subtract => result.sub(operand),
+                ExprOperator::Modulo => {
+                    self.check_divisor("mod", operand);
+                    result.rem(operand)
+                }
+                // The nominal value is the left operand; with `--intervals`,
+                // the value is within the uncertainty of it.
+                ExprOperator::PlusMinus => {
+                    let radius = operand.lo.abs().max(operand.hi.abs());
+                    result.add(Interval::new(-radius, radius))
+                }
+            };
+        }
+        result
+    }
+
+    fn function_expr(&mut self, function_expr: &AnalyzedFunctionExpr) -> Interval {
+        let argument = self.expr(&function_expr.1);
+        if matches!(expr_type(self.variables, &function_expr.1), Type::Complex | Type::Polynomial) {
+            return Interval::entire();
+        }
+        if argument.may_leave_domain(function_expr.0) {
+            self.report(
+                Lint::DomainError,
+                format!(
+                    "the argument of {} ranges over {}, but it must be {}",
+                    function_name(function_expr.0),
+                    argument,
+                    Interval::domain_description(function_expr.0)
+                ),
+            );
+        }
+        argument.apply_function(function_expr.0)
+    }
+
+    fn statement(&mut self, statement: &AnalyzedStatement) {
+        match statement {
+            AnalyzedStatement::Declaration(handle) => self.ranges[*handle] = Interval::point(0.),
+            AnalyzedStatement::DeclarationToAssignment(handle, expr)
+            | AnalyzedStatement::Assignment(handle, expr) => {
+                self.ranges[*handle] = self.expr(expr);
+            }
+            AnalyzedStatement::InputOperation(handle, range) => {
+                self.ranges[*handle] = match range {
+                    Some((lo, hi)) => {
+                        let scale = self.input_scales[*handle];
+                        Interval::new(lo * scale, hi * scale)
+                    }
+                    None => Interval::entire(),
+                };
+            }
+            AnalyzedStatement::OutputOperation(expr, ..)
+            | AnalyzedStatement::GradientOutput(expr, _)
+            | AnalyzedStatement::Seed(expr) => {
+                self.expr(expr);
+            }
+            AnalyzedStatement::SymbolicDerivative(..) | AnalyzedStatement::AngleMode(_) => {}
+            // The root is within the bounds.
+            AnalyzedStatement::Solve(solve) => {
+                let (lhs, rhs, handle, lo, hi) = &**solve;
+                let lo = self.expr(lo);
+                let hi = self.expr(hi);
+                self.ranges[*handle] = Interval::new(lo.lo.min(hi.lo), lo.hi.max(hi.hi));
+                self.expr(lhs);
+                self.expr(rhs);
+            }
+        }
+    }
+}
+
+// The smallest interval containing the ranges, or any value if there are
+// none.
+fn hull(ranges: &[Interval]) -> Interval {
+    ranges
+        .iter()
+        .copied()
+        .reduce(|left, right| Interval::new(left.lo.min(right.lo), left.hi.max(right.hi)))
+        .unwrap_or_else(Interval::entire)
+}
+
+fn value_range(value: &Value) -> Interval {
+    match value {
+        Value::Interval(value) => *value,
+        Value::List(values) => hull(&values.iter().map(value_range).collect::<Vec<_>>()),
+        Value::Matrix(rows) => hull(&rows.iter().flatten().map(value_range).collect::<Vec<_>>()),
+        Value::Polynomial(_) => Interval::entire(),
+        value => Interval::point(value.as_float()),
+    }
+}
+
+/// Flags the operations whose operands may fall outside of their domain,
+/// including divisions by a range containing zero.
+pub fn check_program(variables: &SymbolTable, program: &AnalyzedProgram) -> Vec<Diagnostic> {
+    let mut analysis = RangeAnalysis {
+        variables,
+        ranges: (0..variables.len())
+            .map(|handle| match variables.get_type(handle) {
+                Some(Type::Complex) | Some(Type::Polynomial) => Interval::entire(),
+                _ => value_range(&variables.get_value(handle)),
+            })
+            .collect(),
+        input_scales: (0..variables.len())
+            .map(|handle| variables.get_unit(handle).map_or(1., |unit| unit.scale))
+            .collect(),
+        statement_number: 0,
+        diagnostics: Vec::new(),
+    };
+    for (index, statement) in program.iter().enumerate() {
+        analysis.statement_number = index + 1;
+        analysis.statement(statement);
+    }
+    analysis.diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzer::analyze_program;
+    use crate::numeric::Backend;
+    use crate::parser::parse_program;
+
+    fn messages(source: &str) -> Vec<(Lint, String)> {
+        let mut variables = SymbolTable::with_backend(Backend::Float);
+        let (_, parsed_program) = parse_program(source).unwrap();
+        let analyzed_program = analyze_program(&mut variables, &parsed_program).unwrap();
+        check_program(&variables, &analyzed_program)
+            .into_iter()
+            .map(|diagnostic| (diagnostic.lint, diagnostic.message))
+            .collect()
+    }
+
+    #[test]
+    fn input_ranges_bound_the_domains() {
+        assert!(messages("var b\nin b range [3, 10]\nout sqrt(b - 3)").is_empty());
+        let diagnostics = messages("var b\nin b range [0, 10]\nout sqrt(b - 3)");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].0, Lint::DomainError);
+        assert!(diagnostics[0].1.starts_with("statement 3: the argument of sqrt ranges over [-3, 7]"));
+        assert_eq!(messages("var b\nin b\nout ln(b)")[0].0, Lint::DomainError);
+    }
+
+    #[test]
+    fn divisors_that_may_be_zero() {
+        assert_eq!(
+            messages("var b\nin b range [-1, 1]\nout 1 / b"),
+            vec![(
+                Lint::DivisionByZero,
+                "statement 3: the divisor of `/` ranges over [-1, 1] and may be zero".to_string()
+            )]
+        );
+        assert!(messages("var b\nin b range [1, 2]\nout 1 / b mod b").is_empty());
+    }
+
+    #[test]
+    fn assignments_update_the_ranges() {
+        assert!(messages("var b\nin b range [-2, 2]\nb = b ^ 2 + 1\nout ln(b)").is_empty());
+        // The two factors of `b * b` are taken as independent.
+        assert_eq!(messages("var b\nin b range [-2, 2]\nb = b * b + 1\nout ln(b)").len(), 1);
+        assert!(messages("var b\nin b range [-2, 2]\nb = abs(b)\nout sqrt(b)").is_empty());
+        assert_eq!(messages("var b\nin b range [-2, 2]\nb = b + 1\nout sqrt(b)").len(), 1);
+    }
+}
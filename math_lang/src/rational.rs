@@ -0,0 +1,369 @@
+// Exact arithmetic on fractions of big integers, used by `--rational`.
+// Floats of the program become fractions; the functions only have an exact
+// result for a few arguments and fail otherwise. The formats of `out` are
+// also applied here to the exact values of the other backends, so that their
+// digits are not limited to those of an f64.
+
+use crate::decimal::{Decimal, Rounding};
+use crate::number_format::Format;
+use crate::parser::{ExprOperator, FunctionOperator, TermOperator};
+use crate::unparser::function_name;
+use crate::value::Value;
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{Pow, Signed, ToPrimitive, Zero};
+
+fn power_of_ten(exponent: i32) -> BigRational {
+    BigRational::from_integer(BigInt::from(10)).pow(exponent)
+}
+
+/// The exact value of a decimal, unless its exponent is out of range.
+pub fn from_decimal(value: Decimal) -> Option<BigRational> {
+    let (mantissa, exponent) = value.into_parts();
+    Some(BigRational::from_integer(mantissa) * power_of_ten(exponent.to_i32()?))
+}
+
+fn parse_decimal(text: &str) -> Option<BigRational> {
+    from_decimal(Decimal::parse(text)?)
+}
+
+/// Reads a decimal number or a fraction such as `1/3`.
+pub fn parse_rational(text: &str) -> Option<BigRational> {
+    match text.find('/') {
+        Some(position) => {
+            let numerator = parse_decimal(text[..position].trim())?;
+            let denominator = parse_decimal(text[position + 1..].trim())?;
+            if denominator.is_zero() {
+                None
+            } else {
+                Some(numerator / denominator)
+            }
+        }
+        None => parse_decimal(text),
+    }
+}
+
+/// The fraction written by the shortest decimal giving back `value`, so that
+/// the literal `0.1` is exactly 1/10. Infinities and NaN have no fraction.
+pub fn from_f64(value: f64) -> Option<BigRational> {
+    if value.is_finite() {
+        parse_decimal(&value.to_string())
+    } else {
+        None
+    }
+}
+
+/// Writes the value rounded to `digits` decimals, without trailing zeros.
+pub fn format_decimal(value: &BigRational, digits: usize) -> String {
+    let scaled = (value * power_of_ten(digits as i32)).round().to_integer();
+    let magnitude = format!("{:0>width$}", scaled.abs(), width = digits + 1);
+    let (integer_part, fraction_part) = magnitude.split_at(magnitude.len() - digits);
+    let sign = if scaled.is_negative() { "-" } else { "" };
+    let fraction_part = fraction_part.trim_end_matches('0');
+    if fraction_part.is_empty() {
+        format!("{}{}", sign, integer_part)
+    } else {
+        format!("{}{}.{}", sign, integer_part, fraction_part)
+    }
+}
+
+// The whole number next to `value` chosen by `rounding`.
+fn round_to_integer(value: &BigRational, rounding: Rounding) -> BigInt {
+    let floor = value.floor();
+    let fraction = value - &floor;
+    let floor = floor.to_integer();
+    if fraction.is_zero() {
+        return floor;
+    }
+    let half = BigRational::new(BigInt::from(1), BigInt::from(2));
+    let up = match rounding {
+        Rounding::HalfEven => fraction > half || fraction == half && !(&floor % 2u32).is_zero(),
+        Rounding::HalfUp => fraction > half || fraction == half && !value.is_negative(),
+        Rounding::Down => value.is_negative(),
+        Rounding::Up => !value.is_negative(),
+        Rounding::Floor => false,
+        Rounding::Ceiling => true,
+    };
+    if up {
+        floor + 1
+    } else {
+        floor
+    }
+}
+
+fn fixed(value: &BigRational, decimals: usize, rounding: Rounding) -> String {
+    let scaled = round_to_integer(&(value * power_of_ten(decimals as i32)), rounding);
+    let magnitude = format!("{:0>width$}", scaled.abs(), width = decimals + 1);
+    let (integer_part, fraction_part) = magnitude.split_at(magnitude.len() - decimals);
+    let sign = if value.is_negative() { "-" } else { "" };
+    if decimals == 0 {
+        format!("{}{}", sign, integer_part)
+    } else {
+        format!("{}{}.{}", sign, integer_part, fraction_part)
+    }
+}
+
+// The exponent of the leading digit of `value`, which is not zero.
+fn leading_exponent(value: &BigRational) -> i32 {
+    let magnitude = value.abs();
+    let digits = |value: &BigInt| value.to_string().len() as i32;
+    let mut exponent = digits(magnitude.numer()) - digits(magnitude.denom());
+    if magnitude < power_of_ten(exponent) {
+        exponent -= 1;
+    }
+    exponent
+}
+
+fn scientific(value: &BigRational, decimals: usize, rounding: Rounding) -> String {
+    if value.is_zero() {
+        return format!("{}e0", fixed(value, decimals, rounding));
+    }
+    let mut exponent = leading_exponent(value);
+    let mut scaled = round_to_integer(&(value / power_of_ten(exponent - decimals as i32)), rounding);
+    // Rounding 9.99 up to 10.0 moves the leading digit.
+    if scaled.abs().to_string().len() > decimals + 1 {
+        exponent += 1;
+        scaled /= 10;
+    }
+    let digits = scaled.abs().to_string();
+    let (first, rest) = digits.split_at(1);
+    let sign = if value.is_negative() { "-" } else { "" };
+    if decimals == 0 {
+        format!("{}{}e{}", sign, first, exponent)
+    } else {
+        format!("{}{}.{}e{}", sign, first, rest, exponent)
+    }
+}
+
+// The number of decimals of `value`, if it has finitely many.
+fn decimal_places(value: &BigRational) -> Option<usize> {
+    let mut denominator = value.denom().clone();
+    let mut places = [0, 0];
+    for (index, factor) in [2u32, 5].iter().enumerate() {
+        while (&denominator % *factor).is_zero() {
+            denominator /= *factor;
+            places[index] += 1;
+        }
+    }
+    if denominator == BigInt::from(1) {
+        Some(places[0].max(places[1]))
+    } else {
+        None
+    }
+}
+
+/// Writes the value with a format as `number_format::float` writes an f64,
+/// but from its exact digits, rounded by `rounding`. `e` and `%` without a
+/// number of decimals only write values with finitely many decimals, and
+/// `hex` and `frac` are left to `number_format`.
+pub fn format_exact(value: &BigRational, format: Format, rounding: Rounding) -> Option<String> {
+    let hundred = || value * power_of_ten(2);
+    Some(match format {
+        Format::Fixed(decimals) => fixed(value, decimals, rounding),
+        Format::Scientific(Some(decimals)) => scientific(value, decimals, rounding),
+        Format::Scientific(None) => {
+            let places = decimal_places(value)?;
+            let digits = round_to_integer(&(value * power_of_ten(places as i32)), rounding);
+            let significant = digits.abs().to_string().trim_end_matches('0').len();
+            scientific(value, significant.max(1) - 1, rounding)
+        }
+        // Positional notation while the exponent is small, as for floats.
+        Format::Significant(digits) => {
+            let exponent = match scientific(value, digits - 1, rounding).split_once('e') {
+                Some((_, exponent)) => exponent.parse().unwrap_or(0),
+                None => 0,
+            };
+            if (-5..digits as i64).contains(&exponent) {
+                fixed(value, (digits as i64 - 1 - exponent) as usize, rounding)
+            } else {
+                scientific(value, digits - 1, rounding)
+            }
+        }
+        Format::Percent(Some(decimals)) => format!("{}%", fixed(&hundred(), decimals, rounding)),
+        Format::Percent(None) => format!("{}%", fixed(&hundred(), decimal_places(&hundred())?, rounding)),
+        Format::Hex | Format::Fraction => return None,
+    })
+}
+
+fn check_divisor(divisor: &BigRational, operator_name: &str) -> Result<(), String> {
+    if divisor.is_zero() {
+        Err(format!("Error: Division by zero in `{}`.", operator_name))
+    } else {
+        Ok(())
+    }
+}
+
+// Quotient rounded so that the remainder `left - right * quotient` lies in
+// `[0, |right|)`.
+fn div_euclid(left: &BigRational, right: &BigRational) -> BigRational {
+    let quotient = left / right;
+    if right.is_negative() {
+        quotient.ceil()
+    } else {
+        quotient.floor()
+    }
+}
+
+// The n-th root, when it is a fraction.
+fn exact_root(value: &BigRational, n: u32) -> Option<BigRational> {
+    if value.is_negative() {
+        return if n % 2 == 1 { exact_root(&-value, n).map(|root| -root) } else { None };
+    }
+    let integer_root = |integer: &BigInt| {
+        let root = integer.nth_root(n);
+        if Pow::pow(&root, n) == *integer {
+            Some(root)
+        } else {
+            None
+        }
+    };
+    Some(BigRational::new(integer_root(value.numer())?, integer_root(value.denom())?))
+}
+
+// The most bits of the numerator or the denominator of a power, so that a
+// mistyped exponent is an error rather than a product running for hours.
+const MAX_POWER_BITS: u64 = 1_000_000;
+
+fn power(base: &BigRational, exponent: &BigRational) -> Result<BigRational, String> {
+    let not_exact = || format!("Error: ({}) ^ ({}) has no exact value.", base, exponent);
+    let numerator = exponent.numer().to_i32().ok_or_else(not_exact)?;
+    let denominator = exponent.denom().to_u32().ok_or_else(not_exact)?;
+    if base.is_zero() && numerator < 0 {
+        return Err("Error: Division by zero in `^`.".to_string());
+    }
+    let root = exact_root(base, denominator).ok_or_else(not_exact)?;
+    let bits = root.numer().bits().max(root.denom().bits()).saturating_sub(1);
+    if bits * numerator.unsigned_abs() as u64 > MAX_POWER_BITS {
+        return Err(format!(
+            "Error: ({}) ^ ({}) has more than {} bits.",
+            base, exponent, MAX_POWER_BITS
+        ));
+    }
+    Ok(root.pow(numerator))
+}
+
+pub fn apply_term_operator(
+    operator: TermOperator,
+    left: &BigRational,
+    right: &BigRational,
+) -> Result<BigRational, String> {
+    match operator {
+        TermOperator::Multiply => Ok(left * right),
+        TermOperator::Divide => {
+            check_divisor(right, "/")?;
+            Ok(left / right)
+        }
+        TermOperator::IntegerDivide => {
+            check_divisor(right, "div")?;
+            Ok(div_euclid(left, right))
+        }
+        TermOperator::Exponent => power(left, right),
+        TermOperator::MatrixMultiply => unreachable!("`@` only applies to lists and matrices"),
+    }
+}
+
+pub fn apply_expr_operator(
+    operator: ExprOperator,
+    left: &BigRational,
+    right: &BigRational,
+) -> Result<BigRational, String> {
+    match operator {
+        ExprOperator::Add => Ok(left + right),
+        ExprOperator::Subtract => Ok(left - right),
+        ExprOperator::Modulo => {
+            check_divisor(right, "mod")?;
+            Ok(left - right * div_euclid(left, right))
+        }
+        // The executor makes a measured value of `x +- u`, whose nominal
+        // value is `x`.
+        ExprOperator::PlusMinus => Ok(left.clone()),
+    }
+}
+
+pub fn apply_function(function: FunctionOperator, argument: &BigRational) -> Result<Value, String> {
+    let not_exact = || {
+        format!(
+            "Error: {}({}) has no exact value; with --rational, only abs, ceil, floor, sgn, int, float, re, im, conj, sqrt of squares and 2p of integers are supported.",
+            function_name(function),
+            argument
+        )
+    };
+    let result = match function {
+        FunctionOperator::Abs => argument.abs(),
+        FunctionOperator::Ceil => argument.ceil(),
+        FunctionOperator::Floor => argument.floor(),
+        FunctionOperator::Signum => argument.signum(),
+        FunctionOperator::ToFloat | FunctionOperator::Re | FunctionOperator::Conj => argument.clone(),
+        FunctionOperator::Im => BigRational::zero(),
+        FunctionOperator::Arg if !argument.is_negative() => BigRational::zero(),
+        FunctionOperator::ToInt => {
+            return argument
+                .trunc()
+                .to_integer()
+                .to_i64()
+                .map(Value::Int)
+                .ok_or_else(|| format!("Error: Cannot convert {} to an int.", argument))
+        }
+        FunctionOperator::Sqrt => exact_root(argument, 2).ok_or_else(not_exact)?,
+        FunctionOperator::Exp2 if argument.is_integer() => {
+            let exponent = argument.to_integer().to_i32().ok_or_else(not_exact)?;
+            if exponent.unsigned_abs() as u64 > MAX_POWER_BITS {
+                return Err(format!("Error: 2p({}) has more than {} bits.", argument, MAX_POWER_BITS));
+            }
+            BigRational::from_integer(BigInt::from(2)).pow(exponent)
+        }
+        _ => return Err(not_exact()),
+    };
+    Ok(Value::Rational(result))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ratio(numerator: i64, denominator: i64) -> BigRational {
+        BigRational::new(BigInt::from(numerator), BigInt::from(denominator))
+    }
+
+    fn format(value: BigRational, format: &str) -> Option<String> {
+        format_exact(&value, format.parse().unwrap(), Rounding::HalfEven)
+    }
+
+    #[test]
+    fn formats_use_every_digit() {
+        assert_eq!(format(ratio(1, 3), ".25f").unwrap(), format!("0.{}", "3".repeat(25)));
+        assert_eq!(format(ratio(-2, 3), ".3e").unwrap(), "-6.667e-1");
+        assert_eq!(format(ratio(123456789, 1000), "sig4").unwrap(), "1.235e5");
+        assert_eq!(format(ratio(12345, 1000000000), "sig3").unwrap(), "0.0000123");
+        assert_eq!(format(ratio(1, 8), ".1%").unwrap(), "12.5%");
+        assert_eq!(format(ratio(0, 1), ".2e").unwrap(), "0.00e0");
+    }
+
+    #[test]
+    fn ties_round_to_even() {
+        assert_eq!(format(ratio(5, 2), ".0f").unwrap(), "2");
+        assert_eq!(format(ratio(-7, 2), ".0f").unwrap(), "-4");
+        assert_eq!(format(ratio(9995, 1000), ".2f").unwrap(), "10.00");
+        assert_eq!(format(ratio(99995, 10000), "sig4").unwrap(), "10.00");
+        assert_eq!(format(ratio(99995, 10000), ".3e").unwrap(), "1.000e1");
+    }
+
+    #[test]
+    fn shortest_formats_need_finite_decimals() {
+        assert_eq!(format(ratio(1, 8), "e").unwrap(), "1.25e-1");
+        assert_eq!(format(ratio(1, 8), "%").unwrap(), "12.5%");
+        assert_eq!(format(ratio(1, 3), "e"), None);
+        assert_eq!(format(ratio(1, 3), "%"), None);
+        assert_eq!(format(ratio(1, 3), "frac"), None);
+    }
+
+    #[test]
+    fn powers_are_limited() {
+        let power = |base: BigRational, exponent: i64| power(&base, &ratio(exponent, 1));
+        assert_eq!(power(ratio(2, 1), 1_000_000).unwrap().numer().bits(), 1_000_001);
+        assert!(power(ratio(2, 1), 1_000_001).is_err());
+        assert!(power(ratio(3, 2), -100_000_000).is_err());
+        assert_eq!(power(ratio(-1, 1), 2_000_000_001).unwrap(), ratio(-1, 1));
+        assert!(apply_function(FunctionOperator::Exp2, &ratio(100_000_000, 1)).is_err());
+    }
+}
@@ -0,0 +1,177 @@
+// Root finding for `solve`, between the finite bounds it is given.
+//
+// The equation is given as a function returning its value and its
+// derivative. When it changes sign over the bracket, Brent's method finds a
+// root in it; otherwise Newton's method starts from the middle of the
+// bracket, and switches to Brent's method as soon as it finds a sign change.
+// The root is found within the relative tolerance of the size of the bounds.
+
+const MAX_ITERATIONS: usize = 1000;
+
+const MAX_NEWTON_ITERATIONS: usize = 100;
+
+fn evaluate_equation(
+    f: &mut impl FnMut(f64) -> Result<(f64, f64), String>,
+    x: f64,
+) -> Result<(f64, f64), String> {
+    let (value, derivative) = f(x)?;
+    if value.is_nan() {
+        return Err(format!("Error: The equation of solve is not a number at {}.", x));
+    }
+    Ok((value, derivative))
+}
+
+// The bracket [a, b] has `fa` and `fb` of opposite signs. `c` is the end of
+// the bracket that is not `b`, the best estimate so far, and `a` is the
+// previous value of `b`. A sign change where the equation grows larger than
+// at the ends of the bracket is a discontinuity, such as a pole, rather than
+// a root.
+fn brent(
+    f: &mut impl FnMut(f64) -> Result<(f64, f64), String>,
+    mut a: f64,
+    mut fa: f64,
+    mut b: f64,
+    mut fb: f64,
+    x_tolerance: f64,
+) -> Result<f64, String> {
+    let (mut c, mut fc) = (b, fb);
+    let scale = fa.abs().max(fb.abs());
+    let mut step = b - a;
+    let mut previous_step = step;
+    for _ in 0..MAX_ITERATIONS {
+        if (fb > 0.) == (fc > 0.) {
+            c = a;
+            fc = fa;
+            step = b - a;
+            previous_step = step;
+        }
+        if fc.abs() < fb.abs() {
+            a = b;
+            b = c;
+            c = a;
+            fa = fb;
+            fb = fc;
+            fc = fa;
+        }
+        let tolerance = 2. * f64::EPSILON * b.abs() + 0.5 * x_tolerance;
+        let middle = 0.5 * (c - b);
+        if middle.abs() <= tolerance && fb.abs() > scale {
+            return Err(format!(
+                "Error: solve found a sign change but no root at {}, where the equation is discontinuous.",
+                b
+            ));
+        }
+        if middle.abs() <= tolerance || fb == 0. {
+            return Ok(b);
+        }
+        if previous_step.abs() < tolerance || fa.abs() <= fb.abs() {
+            step = middle;
+            previous_step = middle;
+        } else {
+            // Inverse quadratic interpolation, or the secant method when
+            // only two points are known.
+            let s = fb / fa;
+            let (mut p, mut q) = if a == c {
+                (2. * middle * s, 1. - s)
+            } else {
+                let q = fa / fc;
+                let r = fb / fc;
+                (
+                    s * (2. * middle * q * (q - r) - (b - a) * (r - 1.)),
+                    (q - 1.) * (r - 1.) * (s - 1.),
+                )
+            };
+            if p > 0. {
+                q = -q;
+            } else {
+                p = -p;
+            }
+            if 2. * p < 3. * middle * q - (tolerance * q).abs() && p < (0.5 * previous_step * q).abs() {
+                previous_step = step;
+                step = p / q;
+            } else {
+                step = middle;
+                previous_step = middle;
+            }
+        }
+        a = b;
+        fa = fb;
+        b += if step.abs() > tolerance { step } else { tolerance.copysign(middle) };
+        fb = evaluate_equation(f, b)?.0;
+    }
+    Err(format!(
+        "Error: solve did not converge: the root is in [{}, {}].",
+        b.min(c),
+        b.max(c)
+    ))
+}
+
+/// A root of `f`, which gives the value of the equation and its derivative,
+/// between `a` and `b`.
+pub fn solve(
+    mut f: impl FnMut(f64) -> Result<(f64, f64), String>,
+    a: f64,
+    b: f64,
+    tolerance: f64,
+) -> Result<f64, String> {
+    if !a.is_finite() || !b.is_finite() {
+        return Err(format!("Error: The bounds of solve must be finite, not {} and {}.", a, b));
+    }
+    let (lo, hi) = (a.min(b), a.max(b));
+    let x_tolerance = tolerance * lo.abs().max(hi.abs());
+    let f_lo = evaluate_equation(&mut f, lo)?.0;
+    if f_lo == 0. {
+        return Ok(lo);
+    }
+    let f_hi = evaluate_equation(&mut f, hi)?.0;
+    if f_hi == 0. {
+        return Ok(hi);
+    }
+    if (f_lo > 0.) != (f_hi > 0.) {
+        return brent(&mut f, lo, f_lo, hi, f_hi, x_tolerance);
+    }
+    let mut x = 0.5 * (lo + hi);
+    for _ in 0..MAX_NEWTON_ITERATIONS {
+        let (value, derivative) = evaluate_equation(&mut f, x)?;
+        if value == 0. {
+            return Ok(x);
+        }
+        if (value > 0.) != (f_lo > 0.) {
+            return brent(&mut f, lo, f_lo, x, value, x_tolerance);
+        }
+        let next = x - value / derivative;
+        if !(lo..=hi).contains(&next) {
+            break;
+        }
+        if (next - x).abs() <= 2. * f64::EPSILON * x.abs() + 0.5 * x_tolerance {
+            return Ok(next);
+        }
+        x = next;
+    }
+    Err(format!(
+        "Error: solve found no sign change over [{}, {}], and Newton's method did not converge to a root in it.",
+        lo, hi
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sign_change_over_the_bracket() {
+        let root = solve(|x| Ok((x * x - 2., 2. * x)), 0., 2., 1e-10).unwrap();
+        assert!((root - std::f64::consts::SQRT_2).abs() < 1e-9);
+        let root = solve(|x| Ok((x.cos() - x, -x.sin() - 1.)), 1., 0., 1e-10).unwrap();
+        assert!((root - 0.7390851332151607).abs() < 1e-9);
+    }
+
+    #[test]
+    fn no_sign_change() {
+        // Newton's method finds the double root of x^2, but x^2 + 1 has none.
+        let root = solve(|x| Ok((x * x, 2. * x)), -1., 3., 1e-10).unwrap();
+        assert!(root.abs() < 1e-5);
+        let error = solve(|x| Ok((x * x + 1., 2. * x)), -1., 1., 1e-10).unwrap_err();
+        assert!(error.contains("no sign change"), "{}", error);
+    }
+}
@@ -0,0 +1,113 @@
+// Statistics over lists of floats: means, spreads, percentiles and the
+// relations between two lists.
+//
+// Means, variances and covariances are updated one value at a time with
+// Welford's method, which avoids the cancellation of the textbook formula
+// subtracting the square of the mean from the mean of the squares. Sample
+// statistics divide by n - 1, and population ones by n. Percentiles
+// interpolate linearly between the sorted values.
+
+use crate::summation::Neumaier;
+
+fn check_length(data: &[f64], minimum: usize) -> Result<(), String> {
+    if data.is_empty() {
+        Err("Error: Cannot compute statistics of an empty list.".to_string())
+    } else if data.len() < minimum {
+        Err(format!(
+            "Error: Sample statistics need at least {} values, not {}.",
+            minimum,
+            data.len()
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+// The means of `x` and `y` and the sum of the products of their deviations
+// from them.
+fn co_moment(x: &[f64], y: &[f64]) -> (f64, f64, f64) {
+    let (mut mean_x, mut mean_y, mut moment) = (0., 0., 0.);
+    for (count, (x, y)) in x.iter().zip(y).enumerate() {
+        let delta_x = x - mean_x;
+        mean_x += delta_x / (count + 1) as f64;
+        mean_y += (y - mean_y) / (count + 1) as f64;
+        moment += delta_x * (y - mean_y);
+    }
+    (mean_x, mean_y, moment)
+}
+
+fn check_pair(x: &[f64], y: &[f64], minimum: usize) -> Result<(), String> {
+    if x.len() != y.len() {
+        return Err(format!(
+            "Error: Cannot pair lists of lengths {} and {}.",
+            x.len(),
+            y.len()
+        ));
+    }
+    check_length(x, minimum)
+}
+
+fn divisor(count: usize, sample: bool) -> f64 {
+    if sample {
+        (count - 1) as f64
+    } else {
+        count as f64
+    }
+}
+
+pub fn sum(data: &[f64]) -> f64 {
+    data.iter().fold(Neumaier::default(), |total, value| total.add(*value)).total()
+}
+
+pub fn mean(data: &[f64]) -> Result<f64, String> {
+    check_length(data, 1)?;
+    Ok(co_moment(data, data).0)
+}
+
+pub fn variance(data: &[f64], sample: bool) -> Result<f64, String> {
+    check_length(data, if sample { 2 } else { 1 })?;
+    Ok(co_moment(data, data).2 / divisor(data.len(), sample))
+}
+
+pub fn covariance(x: &[f64], y: &[f64], sample: bool) -> Result<f64, String> {
+    check_pair(x, y, if sample { 2 } else { 1 })?;
+    Ok(co_moment(x, y).2 / divisor(x.len(), sample))
+}
+
+/// Pearson's correlation coefficient, NaN when a list is constant.
+pub fn correlation(x: &[f64], y: &[f64]) -> Result<f64, String> {
+    check_pair(x, y, 1)?;
+    let moment_x = co_moment(x, x).2;
+    let moment_y = co_moment(y, y).2;
+    Ok(co_moment(x, y).2 / (moment_x * moment_y).sqrt())
+}
+
+/// The value below which `percent` percent of the data lie, such that the
+/// 50th percentile is the median.
+pub fn percentile(data: &[f64], percent: f64) -> Result<f64, String> {
+    check_length(data, 1)?;
+    if !(0. ..=100.).contains(&percent) {
+        return Err(format!(
+            "Error: The percentile must be between 0 and 100, not {}.",
+            percent
+        ));
+    }
+    let mut sorted = data.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let rank = percent / 100. * (sorted.len() - 1) as f64;
+    let below = rank.floor() as usize;
+    match sorted.get(below + 1) {
+        Some(above) => Ok(sorted[below] + (rank - below as f64) * (above - sorted[below])),
+        None => Ok(sorted[below]),
+    }
+}
+
+pub fn min(data: &[f64]) -> Result<f64, String> {
+    check_length(data, 1)?;
+    Ok(data.iter().copied().fold(f64::INFINITY, f64::min))
+}
+
+pub fn max(data: &[f64]) -> Result<f64, String> {
+    check_length(data, 1)?;
+    Ok(data.iter().copied().fold(f64::NEG_INFINITY, f64::max))
+}
@@ -0,0 +1,31 @@
+// Compensated summation for `sum`, and for the statistics over lists.
+//
+// Neumaier's variant of Kahan summation keeps the low-order bits lost by
+// each addition in `compensation`, whichever of the sum and the term is the
+// largest, so that the total is as accurate as if it was computed with twice
+// the precision of f64.
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Neumaier {
+    sum: f64,
+    compensation: f64,
+}
+
+impl Neumaier {
+    pub fn add(self, term: f64) -> Neumaier {
+        let sum = self.sum + term;
+        let lost = if self.sum.abs() >= term.abs() {
+            (self.sum - sum) + term
+        } else {
+            (term - sum) + self.sum
+        };
+        Neumaier {
+            sum,
+            compensation: self.compensation + lost,
+        }
+    }
+
+    pub fn total(self) -> f64 {
+        self.sum + self.compensation
+    }
+}
@@ -1,32 +1,147 @@
+use crate::number_format::Format;
+use crate::numeric::Backend;
+use crate::polynomial::Polynomial;
+use crate::units::{AngleUnit, Dimension, Unit, DIMENSIONLESS};
+use crate::value::{Type, Value};
+
+pub const DEFAULT_TOLERANCE: f64 = 1e-10;
+
+#[derive(Debug)]
+struct Symbol {
+    name: String,
+    value: Value,
+    // Known once the variable is declared with a type or first assigned.
+    value_type: Option<Type>,
+    // Built-in constants such as `pi` cannot be assigned.
+    constant: bool,
+    // Set by the analyzer once the variable is definitely assigned.
+    initialized: bool,
+    // Dimension of the values stored in the variable, known once it is
+    // declared with a unit or first assigned.
+    dimension: Option<Dimension>,
+    // Unit given in the declaration, in which input values are read.
+    unit: Option<Unit>,
+    // Number of elements of the list, or of rows of the matrix, stored in
+    // the variable, and number of columns of the matrix, when the analyzer
+    // knows them.
+    length: Option<usize>,
+    columns: Option<usize>,
+    // Variables bound by `integrate` can only be named inside of it. They
+    // keep their entry afterwards, since the analyzed program refers to it.
+    in_scope: bool,
+}
+
 #[derive(Debug)]
 pub struct SymbolTable {
-    entries: Vec<(String, f64)>,
+    entries: Vec<Symbol>,
+    // Representation of the floats stored in the table.
+    backend: Backend,
+    // Relative tolerance of `integrate` and `solve`.
+    tolerance: f64,
+    // Seed of the pseudo-random numbers given by `--seed`, which the
+    // compiler passes on to the emitted program.
+    seed: Option<u64>,
+    // Unit of the angles of trigonometric functions, set by `mode`.
+    angle_unit: AngleUnit,
+    // Format of the outputs without one, given by `--format`.
+    format: Option<Format>,
 }
 
 impl SymbolTable {
-    pub fn new() -> SymbolTable {
+    pub fn with_backend(backend: Backend) -> SymbolTable {
         SymbolTable {
-            entries: Vec::<(String, f64)>::new(),
+            entries: Vec::<Symbol>::new(),
+            backend,
+            tolerance: DEFAULT_TOLERANCE,
+            seed: None,
+            angle_unit: AngleUnit::Radian,
+            format: None,
         }
     }
+    pub fn backend(&self) -> Backend {
+        self.backend
+    }
+    pub fn tolerance(&self) -> f64 {
+        self.tolerance
+    }
+    pub fn set_tolerance(&mut self, tolerance: f64) {
+        self.tolerance = tolerance;
+    }
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+    pub fn set_seed(&mut self, seed: Option<u64>) {
+        self.seed = seed;
+    }
+    pub fn format(&self) -> Option<Format> {
+        self.format
+    }
+    pub fn set_format(&mut self, format: Option<Format>) {
+        self.format = format;
+    }
+    pub fn angle_unit(&self) -> AngleUnit {
+        self.angle_unit
+    }
+    pub fn set_angle_unit(&mut self, unit: AngleUnit) {
+        self.angle_unit = unit;
+    }
     pub fn insert_symbol(&mut self, identifier: &str) -> Result<usize, String> {
-        if self
-            .entries
-            .iter()
-            .find(|item| item.0 == identifier)
-            .is_some()
-        {
+        if self.entries.iter().any(|item| item.in_scope && item.name == identifier) {
             Err(format!(
                 "Error: Identifier '{}' declared several times.",
                 identifier
             ))
         } else {
-            self.entries.push((identifier.to_string(), 0.));
+            self.entries.push(Symbol {
+                name: identifier.to_string(),
+                value: self.backend.float(0.),
+                value_type: None,
+                constant: false,
+                initialized: false,
+                dimension: None,
+                unit: None,
+                length: None,
+                columns: None,
+                in_scope: true,
+            });
             Ok(self.entries.len() - 1)
         }
     }
+    /// Opens a scope in which the variable `identifier` hides any variable
+    /// of the same name, until `pop_scope`.
+    pub fn push_scope(&mut self, identifier: &str, value_type: Type, dimension: Dimension) -> usize {
+        self.entries.push(Symbol {
+            name: identifier.to_string(),
+            value: if value_type == Type::Int { Value::Int(0) } else { Value::Float(0.) },
+            value_type: Some(value_type),
+            constant: false,
+            initialized: true,
+            dimension: Some(dimension),
+            unit: None,
+            length: None,
+            columns: None,
+            in_scope: true,
+        });
+        self.entries.len() - 1
+    }
+    pub fn pop_scope(&mut self, handle: usize) {
+        self.entries[handle].in_scope = false;
+    }
+    pub fn insert_constant(&mut self, identifier: &str, value: Value) -> Result<usize, String> {
+        let handle = self.insert_symbol(identifier)?;
+        self.entries[handle].value = value;
+        self.entries[handle].value_type = Some(Type::Float);
+        self.entries[handle].constant = true;
+        self.entries[handle].initialized = true;
+        self.entries[handle].dimension = Some(DIMENSIONLESS);
+        Ok(handle)
+    }
     pub fn find_symbol(&self, identifier: &str) -> Result<usize, String> {
-        if let Some(pos) = self.entries.iter().position(|item| item.0 == identifier) {
+        if let Some(pos) = self
+            .entries
+            .iter()
+            .rposition(|item| item.in_scope && item.name == identifier)
+        {
             Ok(pos)
         } else {
             Err(format!(
@@ -35,16 +150,73 @@ impl SymbolTable {
             ))
         }
     }
-    pub fn get_value(&self, handle: usize) -> f64 {
-        self.entries[handle].1
+    pub fn get_value(&self, handle: usize) -> Value {
+        self.entries[handle].value.clone()
+    }
+    pub fn set_value(&mut self, handle: usize, value: Value) {
+        self.entries[handle].value = value;
+    }
+    pub fn get_type(&self, handle: usize) -> Option<Type> {
+        self.entries[handle].value_type
     }
-    pub fn set_value(&mut self, handle: usize, value: f64) {
-        self.entries[handle].1 = value;
+    /// Fixes the type of the variable, converting its current value.
+    pub fn set_type(&mut self, handle: usize, value_type: Type) {
+        let backend = self.backend;
+        let entry = &mut self.entries[handle];
+        entry.value_type = Some(value_type);
+        entry.value = match value_type {
+            Type::Int => Value::Int(entry.value.as_float() as i64),
+            Type::Float => backend.to_float(entry.value.clone()),
+            Type::Complex => Value::Complex(entry.value.as_complex()),
+            Type::List => Value::List(Vec::new()),
+            Type::Matrix => Value::Matrix(Vec::new()),
+            Type::Polynomial => Value::Polynomial(Polynomial::default()),
+        };
     }
     pub fn get_name(&self, handle: usize) -> String {
-        self.entries[handle].0.clone()
+        self.entries[handle].name.clone()
+    }
+    pub fn is_constant(&self, handle: usize) -> bool {
+        self.entries[handle].constant
+    }
+    pub fn is_initialized(&self, handle: usize) -> bool {
+        self.entries[handle].initialized
+    }
+    pub fn set_initialized(&mut self, handle: usize, initialized: bool) {
+        self.entries[handle].initialized = initialized;
+    }
+    pub fn get_dimension(&self, handle: usize) -> Option<Dimension> {
+        self.entries[handle].dimension
+    }
+    pub fn set_dimension(&mut self, handle: usize, dimension: Dimension) {
+        self.entries[handle].dimension = Some(dimension);
     }
-    pub fn iter(&self) -> std::slice::Iter<(String, f64)> {
-        self.entries.iter()
+    pub fn get_unit(&self, handle: usize) -> Option<&Unit> {
+        self.entries[handle].unit.as_ref()
     }
-}
\ No newline at end of file
+    pub fn set_unit(&mut self, handle: usize, unit: Unit) {
+        self.entries[handle].dimension = Some(unit.dimension);
+        self.entries[handle].unit = Some(unit);
+    }
+    pub fn get_length(&self, handle: usize) -> Option<usize> {
+        self.entries[handle].length
+    }
+    pub fn set_length(&mut self, handle: usize, length: Option<usize>) {
+        self.entries[handle].length = length;
+    }
+    pub fn get_columns(&self, handle: usize) -> Option<usize> {
+        self.entries[handle].columns
+    }
+    pub fn set_columns(&mut self, handle: usize, columns: Option<usize>) {
+        self.entries[handle].columns = columns;
+    }
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    pub fn iter(&self) -> impl Iterator<Item = (&str, Value)> {
+        self.entries
+            .iter()
+            .filter(|item| item.in_scope)
+            .map(|item| (item.name.as_str(), item.value.clone()))
+    }
+}
@@ -0,0 +1,467 @@
+use crate::analyzer::{expr_type, factor_type, term_type, AnalyzedExpr, AnalyzedFactor, AnalyzedTerm};
+use crate::parser::{ExprOperator, FunctionOperator, TermOperator};
+use crate::symbol_table::SymbolTable;
+use crate::unparser::unparse_expr;
+use crate::value::{Type, Value};
+
+// Symbolic differentiation for `derive expr wrt x`. The analyzed expression
+// is turned into a binary tree, differentiated with the usual rules, and
+// turned back into an analyzed expression that `unparser` prints as source.
+// Both trees are built through constructors that rewrite it as they go: they
+// fold numbers, drop neutral operands, move numeric coefficients to the front
+// and turn additions of negative terms into subtractions. Numbers are only
+// folded when the result is exact, so `ln(2)` or `1 / 3` stay as written.
+//
+// The parts of type int are constants with respect to a float variable, and
+// are kept as they are, in a call to `float()`. Where a function has no
+// derivative, the subgradients of `diff` are used.
+
+#[derive(Debug, PartialEq, Clone)]
+enum Node {
+    Number(f64),
+    Identifier(usize),
+    Int(AnalyzedExpr),
+    Function(FunctionOperator, Box<Node>),
+    Term(Box<Node>, TermOperator, Box<Node>),
+    Expr(Box<Node>, ExprOperator, Box<Node>),
+}
+
+fn number(node: &Node) -> Option<f64> {
+    match node {
+        Node::Number(value) => Some(*value),
+        _ => None,
+    }
+}
+
+// The coefficient and the rest of a product such as `-2 * x`.
+fn coefficient(node: &Node) -> Option<(f64, &Node)> {
+    match node {
+        Node::Term(left, TermOperator::Multiply, right) => number(left).map(|value| (value, &**right)),
+        _ => None,
+    }
+}
+
+// The results of the operations on numbers, when they are exact in f64.
+fn exact_sum(a: f64, b: f64) -> Option<f64> {
+    let sum = a + b;
+    let b_part = sum - a;
+    Some(sum).filter(|sum| sum.is_finite() && a - (sum - b_part) + (b - b_part) == 0.)
+}
+
+fn exact_product(a: f64, b: f64) -> Option<f64> {
+    let product = a * b;
+    Some(product).filter(|product| product.is_finite() && a.mul_add(b, -product) == 0.)
+}
+
+fn exact_quotient(a: f64, b: f64) -> Option<f64> {
+    let quotient = a / b;
+    Some(quotient).filter(|quotient| quotient.is_finite() && quotient.mul_add(b, -a) == 0.)
+}
+
+fn exact_power(a: f64, b: f64) -> Option<f64> {
+    if b.fract() != 0. || !(0. ..=64.).contains(&b) {
+        return None;
+    }
+    (0..b as i32).try_fold(1., |power, _| exact_product(power, a))
+}
+
+fn term(left: Node, operator: TermOperator, right: Node) -> Node {
+    Node::Term(Box::new(left), operator, Box::new(right))
+}
+
+fn expr(left: Node, operator: ExprOperator, right: Node) -> Node {
+    Node::Expr(Box::new(left), operator, Box::new(right))
+}
+
+fn function(function: FunctionOperator, argument: Node) -> Node {
+    Node::Function(function, Box::new(argument))
+}
+
+fn add(left: Node, right: Node) -> Node {
+    match (number(&left), number(&right)) {
+        (Some(a), Some(b)) if exact_sum(a, b).is_some() => return Node::Number(a + b),
+        (Some(0.), _) => return right,
+        (_, Some(0.)) => return left,
+        (_, Some(b)) if b < 0. => return sub(left, Node::Number(-b)),
+        _ => {}
+    }
+    if let Some((value, rest)) = coefficient(&right) {
+        if value < 0. {
+            let rest = rest.clone();
+            return sub(left, mul(Node::Number(-value), rest));
+        }
+    }
+    if left == right {
+        return mul(Node::Number(2.), left);
+    }
+    expr(left, ExprOperator::Add, right)
+}
+
+fn sub(left: Node, right: Node) -> Node {
+    match (number(&left), number(&right)) {
+        (Some(a), Some(b)) if exact_sum(a, -b).is_some() => return Node::Number(a - b),
+        (_, Some(0.)) => return left,
+        (Some(0.), _) => return mul(Node::Number(-1.), right),
+        (_, Some(b)) if b < 0. => return add(left, Node::Number(-b)),
+        _ => {}
+    }
+    if let Some((value, rest)) = coefficient(&right) {
+        if value < 0. {
+            let rest = rest.clone();
+            return add(left, mul(Node::Number(-value), rest));
+        }
+    }
+    if left == right {
+        return Node::Number(0.);
+    }
+    expr(left, ExprOperator::Subtract, right)
+}
+
+fn mul(left: Node, right: Node) -> Node {
+    match (number(&left), number(&right)) {
+        (Some(a), Some(b)) if exact_product(a, b).is_some() => return Node::Number(a * b),
+        (Some(0.), _) | (_, Some(0.)) => return Node::Number(0.),
+        (Some(1.), _) => return right,
+        (_, Some(1.)) => return left,
+        (None, Some(_)) => return mul(right, left),
+        _ => {}
+    }
+    if let (Some(a), Some((b, rest))) = (number(&left), coefficient(&right)) {
+        if let Some(product) = exact_product(a, b) {
+            let rest = rest.clone();
+            return mul(Node::Number(product), rest);
+        }
+    }
+    if let Some((a, rest)) = coefficient(&left) {
+        if number(&right).is_none() {
+            let product = mul(rest.clone(), right);
+            return mul(Node::Number(a), product);
+        }
+    }
+    if let Some((b, rest)) = coefficient(&right) {
+        if number(&left).is_none() {
+            let product = mul(left, rest.clone());
+            return mul(Node::Number(b), product);
+        }
+    }
+    if left == right {
+        return pow(left, Node::Number(2.));
+    }
+    match (left, right) {
+        (Node::Term(base, TermOperator::Exponent, exponent), right) if *base == right => {
+            pow(right, add(*exponent, Node::Number(1.)))
+        }
+        (left, Node::Term(base, TermOperator::Exponent, exponent)) if *base == left => {
+            pow(left, add(*exponent, Node::Number(1.)))
+        }
+        (Node::Term(one, TermOperator::Divide, denominator), right) if *one == Node::Number(1.) => {
+            div(right, *denominator)
+        }
+        (left, Node::Term(one, TermOperator::Divide, denominator)) if *one == Node::Number(1.) => {
+            div(left, *denominator)
+        }
+        (left, right) => term(left, TermOperator::Multiply, right),
+    }
+}
+
+fn div(left: Node, right: Node) -> Node {
+    match (number(&left), number(&right)) {
+        (Some(a), Some(b)) if exact_quotient(a, b).is_some() => return Node::Number(a / b),
+        (Some(0.), _) => return Node::Number(0.),
+        (_, Some(1.)) => return left,
+        _ => {}
+    }
+    if let (Some((a, rest)), Some(b)) = (coefficient(&left), number(&right)) {
+        if let Some(quotient) = exact_quotient(a, b) {
+            let rest = rest.clone();
+            return mul(Node::Number(quotient), rest);
+        }
+    }
+    if left == right {
+        return Node::Number(1.);
+    }
+    term(left, TermOperator::Divide, right)
+}
+
+fn pow(base: Node, exponent: Node) -> Node {
+    match (number(&base), number(&exponent)) {
+        (Some(a), Some(b)) if exact_power(a, b).is_some() => return Node::Number(a.powf(b)),
+        (_, Some(0.)) => return Node::Number(1.),
+        (_, Some(1.)) => return base,
+        (Some(1.), _) => return base,
+        _ => {}
+    }
+    term(base, TermOperator::Exponent, exponent)
+}
+
+fn apply_term_operator(left: Node, operator: TermOperator, right: Node) -> Node {
+    match operator {
+        TermOperator::Multiply => mul(left, right),
+        TermOperator::Divide => div(left, right),
+        TermOperator::Exponent => pow(left, right),
+        TermOperator::IntegerDivide => term(left, operator, right),
+        TermOperator::MatrixMultiply => unreachable!("matrices are not differentiated"),
+    }
+}
+
+fn apply_expr_operator(left: Node, operator: ExprOperator, right: Node) -> Node {
+    match operator {
+        ExprOperator::Add => add(left, right),
+        ExprOperator::Subtract => sub(left, right),
+        ExprOperator::Modulo | ExprOperator::PlusMinus => expr(left, operator, right),
+    }
+}
+
+fn from_factor(variables: &SymbolTable, factor: &AnalyzedFactor) -> Node {
+    if factor_type(variables, factor) == Type::Int {
+        return Node::Int(((factor.clone(), vec![]), vec![]));
+    }
+    match factor {
+        AnalyzedFactor::Literal(value) => Node::Number(value.as_float()),
+        AnalyzedFactor::Identifier(handle) => Node::Identifier(*handle),
+        AnalyzedFactor::FunctionExpression(function_expr) => {
+            function(function_expr.0, from_expr(variables, &function_expr.1))
+        }
+        AnalyzedFactor::SubExpression(expr) => from_expr(variables, expr),
+        AnalyzedFactor::Derivative(..) => unreachable!("derivatives are not nested"),
+        AnalyzedFactor::Integral(_) => unreachable!("integrals are not differentiated"),
+        AnalyzedFactor::Series(_) => unreachable!("sums and products are not differentiated"),
+        AnalyzedFactor::Statistic(_) => unreachable!("statistics are not differentiated"),
+        AnalyzedFactor::MatrixFunction(_) | AnalyzedFactor::Matrix(_) => {
+            unreachable!("matrices are not differentiated")
+        }
+        AnalyzedFactor::Polynomial(..) | AnalyzedFactor::PolynomialFunction(_) => {
+            unreachable!("polynomials are not differentiated")
+        }
+        AnalyzedFactor::NumberTheoryFunction(_) => unreachable!("only the functions of ints are differentiated"),
+        AnalyzedFactor::Random(_) => unreachable!("only random ints are differentiated"),
+        AnalyzedFactor::List(_)
+        | AnalyzedFactor::Range(..)
+        | AnalyzedFactor::Index(..)
+        | AnalyzedFactor::Length(_) => unreachable!("lists are not differentiated"),
+    }
+}
+
+fn from_term(variables: &SymbolTable, analyzed_term: &AnalyzedTerm) -> Node {
+    if term_type(variables, analyzed_term) == Type::Int {
+        return Node::Int((analyzed_term.clone(), vec![]));
+    }
+    let mut result = from_factor(variables, &analyzed_term.0);
+    for factor in &analyzed_term.1 {
+        result = apply_term_operator(result, factor.0, from_factor(variables, &factor.1));
+    }
+    result
+}
+
+fn from_expr(variables: &SymbolTable, analyzed_expr: &AnalyzedExpr) -> Node {
+    if expr_type(variables, analyzed_expr) == Type::Int {
+        return Node::Int(analyzed_expr.clone());
+    }
+    let mut result = from_term(variables, &analyzed_expr.0);
+    for term in &analyzed_expr.1 {
+        result = apply_expr_operator(result, term.0, from_term(variables, &term.1));
+    }
+    result
+}
+
+// The derivative of `function` at `u`.
+fn function_derivative(function_operator: FunctionOperator, u: &Node) -> Node {
+    let one = || Node::Number(1.);
+    let square = || pow(u.clone(), Node::Number(2.));
+    let apply = |function_operator| function(function_operator, u.clone());
+    match function_operator {
+        FunctionOperator::Sin => apply(FunctionOperator::Cos),
+        FunctionOperator::Cos => mul(Node::Number(-1.), apply(FunctionOperator::Sin)),
+        FunctionOperator::Tan => add(one(), pow(apply(FunctionOperator::Tan), Node::Number(2.))),
+        FunctionOperator::ArcSin => div(one(), function(FunctionOperator::Sqrt, sub(one(), square()))),
+        FunctionOperator::ArcCos => {
+            div(Node::Number(-1.), function(FunctionOperator::Sqrt, sub(one(), square())))
+        }
+        FunctionOperator::ArcTan => div(one(), add(one(), square())),
+        FunctionOperator::Sinh => apply(FunctionOperator::Cosh),
+        FunctionOperator::Cosh => apply(FunctionOperator::Sinh),
+        FunctionOperator::Tanh => sub(one(), pow(apply(FunctionOperator::Tanh), Node::Number(2.))),
+        FunctionOperator::ArcSinh => div(one(), function(FunctionOperator::Sqrt, add(square(), one()))),
+        FunctionOperator::ArcCosh => div(one(), function(FunctionOperator::Sqrt, sub(square(), one()))),
+        FunctionOperator::ArcTanh => div(one(), sub(one(), square())),
+        FunctionOperator::Exp => apply(FunctionOperator::Exp),
+        FunctionOperator::Exp2 => mul(
+            apply(FunctionOperator::Exp2),
+            function(FunctionOperator::Log, Node::Number(2.)),
+        ),
+        FunctionOperator::Log => div(one(), u.clone()),
+        FunctionOperator::Log10 => div(
+            one(),
+            mul(u.clone(), function(FunctionOperator::Log, Node::Number(10.))),
+        ),
+        FunctionOperator::Log2 => div(
+            one(),
+            mul(u.clone(), function(FunctionOperator::Log, Node::Number(2.))),
+        ),
+        FunctionOperator::Sqrt => div(one(), mul(Node::Number(2.), apply(FunctionOperator::Sqrt))),
+        FunctionOperator::Abs => apply(FunctionOperator::Signum),
+        FunctionOperator::ToFloat | FunctionOperator::Re | FunctionOperator::Conj => one(),
+        FunctionOperator::Ceil
+        | FunctionOperator::Floor
+        | FunctionOperator::Signum
+        | FunctionOperator::ToInt
+        | FunctionOperator::Im
+        | FunctionOperator::Arg => Node::Number(0.),
+    }
+}
+
+fn derive(node: &Node, variable: usize) -> Node {
+    match node {
+        Node::Number(_) | Node::Int(_) => Node::Number(0.),
+        Node::Identifier(handle) => Node::Number(if *handle == variable { 1. } else { 0. }),
+        Node::Function(function_operator, u) => {
+            let du = derive(u, variable);
+            if du == Node::Number(0.) {
+                return du;
+            }
+            mul(function_derivative(*function_operator, u), du)
+        }
+        Node::Term(a, operator, b) => {
+            let (a, b) = (&**a, &**b);
+            let (da, db) = (derive(a, variable), derive(b, variable));
+            match operator {
+                TermOperator::Multiply => add(mul(da, b.clone()), mul(a.clone(), db)),
+                TermOperator::Divide if db == Node::Number(0.) => div(da, b.clone()),
+                TermOperator::Divide => div(
+                    sub(mul(da, b.clone()), mul(a.clone(), db)),
+                    pow(b.clone(), Node::Number(2.)),
+                ),
+                TermOperator::Exponent if db == Node::Number(0.) => mul(
+                    mul(b.clone(), pow(a.clone(), sub(b.clone(), Node::Number(1.)))),
+                    da,
+                ),
+                TermOperator::Exponent => {
+                    let ln_a = function(FunctionOperator::Log, a.clone());
+                    let power = pow(a.clone(), b.clone());
+                    if da == Node::Number(0.) {
+                        mul(mul(power, ln_a), db)
+                    } else {
+                        mul(power, add(mul(db, ln_a), div(mul(b.clone(), da), a.clone())))
+                    }
+                }
+                TermOperator::IntegerDivide => Node::Number(0.),
+                TermOperator::MatrixMultiply => unreachable!("matrices are not differentiated"),
+            }
+        }
+        Node::Expr(a, operator, b) => {
+            let (da, db) = (derive(a, variable), derive(b, variable));
+            match operator {
+                ExprOperator::Add => add(da, db),
+                ExprOperator::Subtract => sub(da, db),
+                // a mod b = a - b * (a div b), where `a div b` is constant.
+                ExprOperator::Modulo => sub(
+                    da,
+                    mul(db, term((**a).clone(), TermOperator::IntegerDivide, (**b).clone())),
+                ),
+                ExprOperator::PlusMinus => da,
+            }
+        }
+    }
+}
+
+fn to_factor(node: Node) -> AnalyzedFactor {
+    match node {
+        Node::Number(value) => AnalyzedFactor::Literal(Value::Float(value)),
+        Node::Identifier(handle) => AnalyzedFactor::Identifier(handle),
+        Node::Int(expr) => AnalyzedFactor::FunctionExpression(Box::new((FunctionOperator::ToFloat, expr))),
+        Node::Function(FunctionOperator::ToFloat, argument) => match *argument {
+            Node::Int(expr) => AnalyzedFactor::FunctionExpression(Box::new((FunctionOperator::ToFloat, expr))),
+            argument => to_factor(argument),
+        },
+        Node::Function(function_operator, argument) => {
+            AnalyzedFactor::FunctionExpression(Box::new((function_operator, to_expr(*argument))))
+        }
+        node => AnalyzedFactor::SubExpression(Box::new(to_expr(node))),
+    }
+}
+
+fn to_term(node: Node) -> AnalyzedTerm {
+    match node {
+        Node::Term(left, operator, right) => {
+            let mut result = to_term(*left);
+            result.1.push((operator, to_factor(*right)));
+            result
+        }
+        node => (to_factor(node), vec![]),
+    }
+}
+
+fn to_expr(node: Node) -> AnalyzedExpr {
+    match node {
+        Node::Expr(left, operator, right) => {
+            let mut result = to_expr(*left);
+            result.1.push((operator, to_term(*right)));
+            result
+        }
+        node => (to_term(node), vec![]),
+    }
+}
+
+/// The simplified derivative of the expression with respect to the variable,
+/// as MathLang source.
+pub fn derive_source(variables: &SymbolTable, analyzed_expr: &AnalyzedExpr, variable: usize) -> String {
+    let derivative = derive(&from_expr(variables, analyzed_expr), variable);
+    unparse_expr(variables, &to_expr(derivative))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzer::{analyze_program, AnalyzedStatement};
+    use crate::executor;
+    use crate::numeric::Backend;
+    use crate::parser::parse_program;
+
+    // The value of `expr` for the variables declared so far.
+    fn evaluate(variables: &mut SymbolTable, expr: &str) -> f64 {
+        let source = format!("out {}", expr);
+        let (rest, parsed_program) = parse_program(&source).unwrap();
+        assert!(rest.is_empty(), "{}", source);
+        match analyze_program(variables, &parsed_program).unwrap().pop() {
+            Some(AnalyzedStatement::OutputOperation(expr, ..)) => {
+                executor::evaluate_expr(variables, &expr).unwrap().as_float()
+            }
+            _ => unreachable!("the program is an output"),
+        }
+    }
+
+    #[test]
+    fn derivatives_read_back_give_the_values_of_diff() {
+        let exprs = [
+            "x^3 - 2 * x",
+            "sin(x) * exp(x)",
+            "ln(x^2 + 1) / x",
+            "sqrt(x) * cos(2 * x)",
+            "atan(x)^2 - 1 / x",
+            "x^x",
+            "tanh(3 * x + 1) / (1 + x^2)",
+        ];
+        for expr in &exprs {
+            let mut variables = SymbolTable::with_backend(Backend::Float);
+            let source = format!("var x\nout {}", expr);
+            let (_, parsed_program) = parse_program(&source).unwrap();
+            let analyzed_program = analyze_program(&mut variables, &parsed_program).unwrap();
+            let x = variables.find_symbol("x").unwrap();
+            variables.set_value(x, Value::Float(0.7));
+            let derivative = match &analyzed_program[1] {
+                AnalyzedStatement::OutputOperation(expr, ..) => derive_source(&variables, expr, x),
+                _ => unreachable!("the second statement is an output"),
+            };
+            let symbolic = evaluate(&mut variables, &derivative);
+            let automatic = evaluate(&mut variables, &format!("diff({}, x)", expr));
+            assert!(
+                (symbolic - automatic).abs() <= 1e-12 * automatic.abs(),
+                "{}: {} gives {}, not {}",
+                expr,
+                derivative,
+                symbolic,
+                automatic
+            );
+        }
+    }
+}
@@ -0,0 +1,142 @@
+// Measured values with a standard uncertainty, propagated to first order.
+// Every `+-` evaluated and every measured value read is an independent
+// source of uncertainty, and a value keeps the part of its uncertainty that
+// comes from each source. The uses of a variable therefore stay correlated:
+// `g - g` is exactly 0 and `g * g` has twice the relative uncertainty of `g`.
+// The functions are applied by the caller, from the value and the derivative
+// `Dual` gives.
+
+static NEXT_SOURCE: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Uncertain {
+    pub value: f64,
+    // Source of uncertainty -> its contribution, with the sign of the
+    // derivative of the value with respect to it.
+    components: std::collections::BTreeMap<usize, f64>,
+    // Whether the value depends on a measured value, even when no
+    // uncertainty is left, as in `g - g`. Other values print as floats.
+    pub measured: bool,
+}
+
+impl std::ops::Add for Uncertain {
+    type Output = Uncertain;
+    fn add(self, other: Uncertain) -> Uncertain {
+        let value = self.value + other.value;
+        self.combine(&other, value, 1., 1.)
+    }
+}
+
+impl std::ops::Sub for Uncertain {
+    type Output = Uncertain;
+    fn sub(self, other: Uncertain) -> Uncertain {
+        let value = self.value - other.value;
+        self.combine(&other, value, 1., -1.)
+    }
+}
+
+impl std::ops::Mul for Uncertain {
+    type Output = Uncertain;
+    fn mul(self, other: Uncertain) -> Uncertain {
+        let (a, b) = (self.value, other.value);
+        self.combine(&other, a * b, b, a)
+    }
+}
+
+impl std::ops::Div for Uncertain {
+    type Output = Uncertain;
+    fn div(self, other: Uncertain) -> Uncertain {
+        let (a, b) = (self.value, other.value);
+        self.combine(&other, a / b, 1. / b, -a / (b * b))
+    }
+}
+
+impl Uncertain {
+    pub fn exact(value: f64) -> Uncertain {
+        Uncertain {
+            value,
+            components: std::collections::BTreeMap::new(),
+            measured: false,
+        }
+    }
+
+    /// A new measurement, independent from all the others.
+    pub fn measured(value: f64, uncertainty: f64) -> Uncertain {
+        let mut result = Uncertain::exact(value);
+        result.measured = true;
+        if uncertainty != 0. {
+            let source = NEXT_SOURCE.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            result.components.insert(source, uncertainty.abs());
+        }
+        result
+    }
+
+    /// The standard uncertainty, from the independent sources.
+    pub fn uncertainty(&self) -> f64 {
+        self.components.values().fold(0., |total, component| total.hypot(*component))
+    }
+
+    // The value `value` of f(self, other), whose partial derivatives are
+    // `d_self` and `d_other`.
+    fn combine(&self, other: &Uncertain, value: f64, d_self: f64, d_other: f64) -> Uncertain {
+        let mut components = std::collections::BTreeMap::new();
+        for (derivative, operand) in [(d_self, self), (d_other, other)] {
+            for (source, component) in &operand.components {
+                *components.entry(*source).or_insert(0.) += derivative * component;
+            }
+        }
+        components.retain(|_, component| *component != 0.);
+        Uncertain {
+            value,
+            components,
+            measured: self.measured || other.measured,
+        }
+    }
+
+    /// f(self), given the value of f and its derivative at the value of
+    /// `self`.
+    pub fn map(&self, value: f64, derivative: f64) -> Uncertain {
+        self.combine(&Uncertain::exact(0.), value, derivative, 0.)
+    }
+
+    pub fn powf(self, exponent: Uncertain) -> Uncertain {
+        let (a, b) = (self.value, exponent.value);
+        let value = a.powf(b);
+        self.combine(&exponent, value, b * a.powf(b - 1.), value * a.ln())
+    }
+
+    pub fn div_euclid(self, other: Uncertain) -> Uncertain {
+        self.combine(&other, self.value.div_euclid(other.value), 0., 0.)
+    }
+
+    pub fn rem_euclid(self, other: Uncertain) -> Uncertain {
+        let (a, b) = (self.value, other.value);
+        self.combine(&other, a.rem_euclid(b), 1., -a.div_euclid(b))
+    }
+
+    /// `x +- u` has the value of `x`, with `u` as the uncertainty from a new
+    /// source; the uncertainty of `u` itself is ignored.
+    pub fn plus_minus(self, uncertainty: Uncertain) -> Uncertain {
+        let value = self.value;
+        self.combine(&Uncertain::measured(0., uncertainty.value), value, 1., 1.)
+    }
+
+    /// Reads `9.81 +- 0.02` or `9.81 ± 0.02`.
+    pub fn parse(text: &str) -> Option<Uncertain> {
+        let (value, uncertainty) = text.split_once("+-").or_else(|| text.split_once('±'))?;
+        Some(Uncertain::measured(
+            value.trim().parse().ok()?,
+            uncertainty.trim().parse().ok()?,
+        ))
+    }
+}
+
+impl std::fmt::Display for Uncertain {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if self.measured {
+            write!(f, "{} +- {}", self.value, self.uncertainty())
+        } else {
+            write!(f, "{}", self.value)
+        }
+    }
+}
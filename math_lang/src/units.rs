@@ -0,0 +1,268 @@
+// Physical units. Values are always stored in SI base units, and a
+// `Dimension` holds the exponents of the base units metre, kilogram,
+// second, ampere, kelvin, mole and candela.
+
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::One;
+
+const BASE_UNIT_NAMES: [&str; 7] = ["m", "kg", "s", "A", "K", "mol", "cd"];
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Dimension([i32; 7]);
+
+pub const DIMENSIONLESS: Dimension = Dimension([0; 7]);
+
+const LENGTH: Dimension = Dimension([1, 0, 0, 0, 0, 0, 0]);
+const MASS: Dimension = Dimension([0, 1, 0, 0, 0, 0, 0]);
+const TIME: Dimension = Dimension([0, 0, 1, 0, 0, 0, 0]);
+const CURRENT: Dimension = Dimension([0, 0, 0, 1, 0, 0, 0]);
+const TEMPERATURE: Dimension = Dimension([0, 0, 0, 0, 1, 0, 0]);
+const AMOUNT: Dimension = Dimension([0, 0, 0, 0, 0, 1, 0]);
+const LUMINOSITY: Dimension = Dimension([0, 0, 0, 0, 0, 0, 1]);
+const FREQUENCY: Dimension = Dimension([0, 0, -1, 0, 0, 0, 0]);
+const VOLUME: Dimension = Dimension([3, 0, 0, 0, 0, 0, 0]);
+const FORCE: Dimension = Dimension([1, 1, -2, 0, 0, 0, 0]);
+const ENERGY: Dimension = Dimension([2, 1, -2, 0, 0, 0, 0]);
+const POWER: Dimension = Dimension([2, 1, -3, 0, 0, 0, 0]);
+const PRESSURE: Dimension = Dimension([-1, 1, -2, 0, 0, 0, 0]);
+const CHARGE: Dimension = Dimension([0, 0, 1, 1, 0, 0, 0]);
+const VOLTAGE: Dimension = Dimension([2, 1, -3, -1, 0, 0, 0]);
+const RESISTANCE: Dimension = Dimension([2, 1, -3, -2, 0, 0, 0]);
+
+// (name, value in SI base units as a numerator and a denominator, dimension)
+const UNITS: [(&str, i64, i64, Dimension); 37] = [
+    ("m", 1, 1, LENGTH),
+    ("km", 1000, 1, LENGTH),
+    ("cm", 1, 100, LENGTH),
+    ("mm", 1, 1000, LENGTH),
+    ("um", 1, 1000000, LENGTH),
+    ("nm", 1, 1000000000, LENGTH),
+    ("ft", 3048, 10000, LENGTH),
+    ("mi", 1609344, 1000, LENGTH),
+    ("kg", 1, 1, MASS),
+    ("g", 1, 1000, MASS),
+    ("mg", 1, 1000000, MASS),
+    ("lb", 45359237, 100000000, MASS),
+    ("s", 1, 1, TIME),
+    ("ms", 1, 1000, TIME),
+    ("us", 1, 1000000, TIME),
+    ("ns", 1, 1000000000, TIME),
+    ("min", 60, 1, TIME),
+    ("h", 3600, 1, TIME),
+    ("day", 86400, 1, TIME),
+    ("A", 1, 1, CURRENT),
+    ("mA", 1, 1000, CURRENT),
+    ("K", 1, 1, TEMPERATURE),
+    ("mol", 1, 1, AMOUNT),
+    ("cd", 1, 1, LUMINOSITY),
+    ("Hz", 1, 1, FREQUENCY),
+    ("L", 1, 1000, VOLUME),
+    ("N", 1, 1, FORCE),
+    ("kN", 1000, 1, FORCE),
+    ("J", 1, 1, ENERGY),
+    ("kJ", 1000, 1, ENERGY),
+    ("W", 1, 1, POWER),
+    ("kW", 1000, 1, POWER),
+    ("Pa", 1, 1, PRESSURE),
+    ("kPa", 1000, 1, PRESSURE),
+    ("bar", 100000, 1, PRESSURE),
+    ("C", 1, 1, CHARGE),
+    ("V", 1, 1, VOLTAGE),
+];
+
+const OHM: (&str, i64, i64, Dimension) = ("ohm", 1, 1, RESISTANCE);
+
+fn find_unit(name: &str) -> Option<(&'static str, i64, i64, Dimension)> {
+    UNITS
+        .iter()
+        .chain(std::iter::once(&OHM))
+        .find(|unit| unit.0 == name)
+        .copied()
+}
+
+pub fn is_unit_name(name: &str) -> bool {
+    find_unit(name).is_some()
+}
+
+impl Dimension {
+    pub fn is_dimensionless(self) -> bool {
+        self == DIMENSIONLESS
+    }
+    pub fn mul(self, other: Dimension) -> Dimension {
+        let mut exponents = self.0;
+        for (exponent, other_exponent) in exponents.iter_mut().zip(other.0.iter()) {
+            *exponent += other_exponent;
+        }
+        Dimension(exponents)
+    }
+    pub fn div(self, other: Dimension) -> Dimension {
+        self.mul(other.powi(-1))
+    }
+    pub fn powi(self, power: i32) -> Dimension {
+        let mut exponents = self.0;
+        for exponent in exponents.iter_mut() {
+            *exponent *= power;
+        }
+        Dimension(exponents)
+    }
+    /// Name of the dimension in error messages.
+    pub fn describe(self) -> String {
+        if self.is_dimensionless() {
+            "dimensionless".to_string()
+        } else {
+            self.to_string()
+        }
+    }
+    /// Square root, which only exists when all exponents are even.
+    pub fn sqrt(self) -> Option<Dimension> {
+        if self.0.iter().all(|exponent| exponent % 2 == 0) {
+            let mut exponents = self.0;
+            for exponent in exponents.iter_mut() {
+                *exponent /= 2;
+            }
+            Some(Dimension(exponents))
+        } else {
+            None
+        }
+    }
+}
+
+impl std::fmt::Display for Dimension {
+    /// Writes the dimension as SI base units in a form `parse_unit` accepts,
+    /// e.g. `kg*m/s^2` or `s^-1`.
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if self.is_dimensionless() {
+            return write!(f, "1");
+        }
+        let power = |name: &str, exponent: i32| {
+            if exponent == 1 { name.to_string() } else { format!("{}^{}", name, exponent) }
+        };
+        let numerator: Vec<String> = BASE_UNIT_NAMES
+            .iter()
+            .zip(self.0.iter())
+            .filter(|(_, exponent)| **exponent > 0)
+            .map(|(name, exponent)| power(name, *exponent))
+            .collect();
+        let denominator = BASE_UNIT_NAMES
+            .iter()
+            .zip(self.0.iter())
+            .filter(|(_, exponent)| **exponent < 0);
+        if numerator.is_empty() {
+            let factors: Vec<String> = denominator.map(|(name, exponent)| power(name, *exponent)).collect();
+            write!(f, "{}", factors.join("*"))
+        } else {
+            write!(f, "{}", numerator.join("*"))?;
+            for (name, exponent) in denominator {
+                write!(f, "/{}", power(name, -exponent))?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// A unit written in the source, such as `km/h`: `scale` is its value in SI
+/// base units, and `ratio` the same value exactly, for `--rational` and
+/// `--precision`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Unit {
+    pub name: String,
+    pub scale: f64,
+    pub ratio: BigRational,
+    pub dimension: Dimension,
+}
+
+impl Unit {
+    /// The SI unit used to print values of the given dimension.
+    pub fn base(dimension: Dimension) -> Unit {
+        Unit {
+            name: dimension.to_string(),
+            scale: 1.,
+            ratio: BigRational::one(),
+            dimension,
+        }
+    }
+}
+
+/// Builds a unit from the factors given by the parser, as (name, exponent)
+/// pairs where units after a `/` have a negated exponent.
+pub fn resolve_unit(factors: &[(&str, i32)]) -> Result<Unit, String> {
+    let mut name = String::new();
+    let mut scale = 1.;
+    let mut ratio = BigRational::one();
+    let mut dimension = DIMENSIONLESS;
+    for (index, (unit_name, exponent)) in factors.iter().enumerate() {
+        let (_, numerator, denominator, unit_dimension) = find_unit(unit_name)
+            .ok_or_else(|| format!("Error: Unknown unit '{}'.", unit_name))?;
+        scale *= (numerator as f64 / denominator as f64).powi(*exponent);
+        ratio *= BigRational::new(BigInt::from(numerator), BigInt::from(denominator)).pow(*exponent);
+        dimension = dimension.mul(unit_dimension.powi(*exponent));
+        let written_exponent = if index > 0 && *exponent < 0 {
+            name += "/";
+            -exponent
+        } else {
+            if index > 0 {
+                name += "*";
+            }
+            *exponent
+        };
+        name += unit_name;
+        if written_exponent != 1 {
+            name += &format!("^{}", written_exponent);
+        }
+    }
+    Ok(Unit {
+        name,
+        scale,
+        ratio,
+        dimension,
+    })
+}
+
+/// The unit of the angles taken by `sin`, `cos` and `tan` and given by their
+/// inverses, chosen by `mode`. The analyzer converts angles to radians, as it
+/// converts quantities to SI base units.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum AngleUnit {
+    Radian,
+    Degree,
+    Gradian,
+}
+
+impl AngleUnit {
+    pub fn name(self) -> &'static str {
+        match self {
+            AngleUnit::Radian => "rad",
+            AngleUnit::Degree => "deg",
+            AngleUnit::Gradian => "grad",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn units_combine_their_scales_exactly() {
+        let speed = resolve_unit(&[("km", 1), ("h", -1)]).unwrap();
+        assert_eq!(speed.name, "km/h");
+        assert_eq!(speed.dimension, LENGTH.div(TIME));
+        assert_eq!(speed.ratio, BigRational::new(BigInt::from(5), BigInt::from(18)));
+        assert_eq!(speed.scale, 1000. / 3600.);
+        let acceleration = resolve_unit(&[("m", 1), ("s", -2)]).unwrap();
+        assert_eq!(acceleration.name, "m/s^2");
+        assert_eq!(acceleration.dimension.mul(MASS), FORCE);
+        assert!(resolve_unit(&[("furlong", 1)]).is_err());
+    }
+
+    #[test]
+    fn dimensions_print_as_base_units() {
+        assert_eq!(FORCE.to_string(), "m*kg/s^2");
+        assert_eq!(FREQUENCY.to_string(), "s^-1");
+        assert_eq!(DIMENSIONLESS.describe(), "dimensionless");
+        assert_eq!(VOLUME.powi(2).sqrt(), Some(VOLUME));
+        assert_eq!(LENGTH.sqrt(), None);
+        assert!(ENERGY.div(FORCE.mul(LENGTH)).is_dimensionless());
+    }
+}
@@ -0,0 +1,367 @@
+use crate::analyzer::{
+    AnalyzedExpr, AnalyzedFactor, AnalyzedFunctionExpr, AnalyzedProgram, AnalyzedStatement,
+    AnalyzedTerm,
+};
+use crate::parser::{
+    ExprOperator, FunctionOperator, MatrixOperator, NumberTheoryOperator, PolynomialOperator, RandomOperator,
+    SeriesOperator, StatisticOperator, TermOperator,
+};
+use crate::symbol_table::SymbolTable;
+use crate::value::{Type, Value};
+
+// Turns analyzed trees back into MathLang source that `parser::parse_program`
+// accepts, for dumping intermediate stages. Quantities have been converted to
+// SI base units by the analyzer, so literals are written without their unit.
+
+fn unparse_literal(value: f64) -> String {
+    if value.is_nan() {
+        "(0/0)".to_string()
+    } else if value.is_infinite() {
+        if value > 0. { "(1/0)".to_string() } else { "(-1/0)".to_string() }
+    } else {
+        value.to_string()
+    }
+}
+
+fn unparse_imaginary(value: f64) -> String {
+    if value.is_finite() {
+        format!("{}i", value)
+    } else {
+        format!("{} * 1i", unparse_literal(value))
+    }
+}
+
+pub fn function_name(function: FunctionOperator) -> &'static str {
+    match function {
+        FunctionOperator::Sin => "sin",
+        FunctionOperator::Cos => "cos",
+        FunctionOperator::Tan => "tan",
+        FunctionOperator::ArcSin => "asin",
+        FunctionOperator::ArcCos => "acos",
+        FunctionOperator::ArcTan => "atan",
+        FunctionOperator::Sinh => "sinh",
+        FunctionOperator::Cosh => "cosh",
+        FunctionOperator::Tanh => "tanh",
+        FunctionOperator::ArcSinh => "asinh",
+        FunctionOperator::ArcCosh => "acosh",
+        FunctionOperator::ArcTanh => "atanh",
+        FunctionOperator::Exp => "exp",
+        FunctionOperator::Exp2 => "2p",
+        FunctionOperator::Log => "ln",
+        FunctionOperator::Log10 => "log",
+        FunctionOperator::Log2 => "lg",
+        FunctionOperator::Abs => "abs",
+        FunctionOperator::Ceil => "ceil",
+        FunctionOperator::Floor => "floor",
+        FunctionOperator::Signum => "sgn",
+        FunctionOperator::Sqrt => "sqrt",
+        FunctionOperator::ToInt => "int",
+        FunctionOperator::ToFloat => "float",
+        FunctionOperator::Re => "re",
+        FunctionOperator::Im => "im",
+        FunctionOperator::Conj => "conj",
+        FunctionOperator::Arg => "arg",
+    }
+}
+
+pub fn statistic_name(operator: StatisticOperator) -> &'static str {
+    match operator {
+        StatisticOperator::Mean => "mean",
+        StatisticOperator::Median => "median",
+        StatisticOperator::StandardDeviation => "stddev",
+        StatisticOperator::PopulationStandardDeviation => "pstddev",
+        StatisticOperator::Variance => "variance",
+        StatisticOperator::PopulationVariance => "pvariance",
+        StatisticOperator::Percentile => "percentile",
+        StatisticOperator::Min => "min",
+        StatisticOperator::Max => "max",
+        StatisticOperator::Sum => "sum",
+        StatisticOperator::Count => "count",
+        StatisticOperator::Covariance => "cov",
+        StatisticOperator::PopulationCovariance => "pcov",
+        StatisticOperator::Correlation => "corr",
+    }
+}
+
+pub fn matrix_function_name(operator: MatrixOperator) -> &'static str {
+    match operator {
+        MatrixOperator::Transpose => "transpose",
+        MatrixOperator::Determinant => "det",
+        MatrixOperator::Inverse => "inv",
+        MatrixOperator::Solve => "solve",
+        MatrixOperator::Identity => "identity",
+        MatrixOperator::Zeros => "zeros",
+    }
+}
+
+pub fn polynomial_function_name(operator: PolynomialOperator) -> &'static str {
+    match operator {
+        PolynomialOperator::Coefficients => "poly",
+        PolynomialOperator::Evaluate => "eval",
+        PolynomialOperator::Roots => "roots",
+        PolynomialOperator::Derivative => "derive",
+    }
+}
+
+pub fn number_theory_name(operator: NumberTheoryOperator) -> &'static str {
+    match operator {
+        NumberTheoryOperator::Factorial => "factorial",
+        NumberTheoryOperator::Gamma => "gamma",
+        NumberTheoryOperator::LogGamma => "lgamma",
+        NumberTheoryOperator::Binomial => "binom",
+        NumberTheoryOperator::Permutations => "perm",
+        NumberTheoryOperator::IsPrime => "isprime",
+        NumberTheoryOperator::NextPrime => "nextprime",
+        NumberTheoryOperator::Factor => "factor",
+        NumberTheoryOperator::ModPow => "modpow",
+        NumberTheoryOperator::ModInverse => "modinv",
+    }
+}
+
+pub fn random_name(operator: RandomOperator) -> &'static str {
+    match operator {
+        RandomOperator::Rand => "rand",
+        RandomOperator::RandInt => "randint",
+        RandomOperator::Uniform => "uniform",
+        RandomOperator::Normal => "normal",
+        RandomOperator::Exponential => "exponential",
+    }
+}
+
+pub fn series_name(operator: SeriesOperator) -> &'static str {
+    match operator {
+        SeriesOperator::Sum => "sum",
+        SeriesOperator::Product => "prod",
+    }
+}
+
+fn unparse_factor(variables: &SymbolTable, factor: &AnalyzedFactor) -> String {
+    match factor {
+        AnalyzedFactor::Literal(Value::Int(value)) => value.to_string(),
+        AnalyzedFactor::Literal(Value::Complex(value)) => format!(
+            "({} + {})",
+            unparse_literal(value.re),
+            unparse_imaginary(value.im)
+        ),
+        AnalyzedFactor::Literal(value) => unparse_literal(value.as_float()),
+        AnalyzedFactor::Identifier(handle) => variables.get_name(*handle),
+        AnalyzedFactor::FunctionExpression(function_expr) => {
+            unparse_function_expr(variables, function_expr)
+        }
+        AnalyzedFactor::SubExpression(expr) => format!("({})", unparse_expr(variables, expr)),
+        AnalyzedFactor::Derivative(expr, handle) => format!(
+            "diff({}, {})",
+            unparse_expr(variables, expr),
+            variables.get_name(*handle)
+        ),
+        AnalyzedFactor::Integral(integral) => format!(
+            "integrate({}, {}, {}, {})",
+            unparse_expr(variables, &integral.0),
+            variables.get_name(integral.1),
+            unparse_expr(variables, &integral.2),
+            unparse_expr(variables, &integral.3)
+        ),
+        AnalyzedFactor::Series(series) => format!(
+            "{}({}, {}, {}, {})",
+            series_name(series.0),
+            variables.get_name(series.1),
+            unparse_expr(variables, &series.2),
+            unparse_expr(variables, &series.3),
+            unparse_expr(variables, &series.4)
+        ),
+        AnalyzedFactor::Statistic(statistic) => format!(
+            "{}({})",
+            statistic_name(statistic.0),
+            statistic
+                .1
+                .iter()
+                .map(|argument| unparse_expr(variables, argument))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        AnalyzedFactor::MatrixFunction(function) => format!(
+            "{}({})",
+            matrix_function_name(function.0),
+            function
+                .1
+                .iter()
+                .map(|argument| unparse_expr(variables, argument))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        // The body extends as far as an expression does.
+        AnalyzedFactor::Polynomial(body, handle) => format!(
+            "(poly {}: {})",
+            variables.get_name(*handle),
+            unparse_expr(variables, body)
+        ),
+        AnalyzedFactor::PolynomialFunction(function) => format!(
+            "{}({})",
+            polynomial_function_name(function.0),
+            function
+                .1
+                .iter()
+                .map(|argument| unparse_expr(variables, argument))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        // `n!` reads back as `factorial(n)`.
+        AnalyzedFactor::NumberTheoryFunction(function) => format!(
+            "{}({})",
+            number_theory_name(function.0),
+            function
+                .1
+                .iter()
+                .map(|argument| unparse_expr(variables, argument))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        AnalyzedFactor::Random(random) => format!(
+            "{}({})",
+            random_name(random.0),
+            random
+                .1
+                .iter()
+                .map(|argument| unparse_expr(variables, argument))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        AnalyzedFactor::List(elements) | AnalyzedFactor::Matrix(elements) => format!(
+            "[{}]",
+            elements
+                .iter()
+                .map(|element| unparse_expr(variables, element))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        // `..` has the lowest precedence.
+        AnalyzedFactor::Range(lo, hi) => format!(
+            "({}..{})",
+            unparse_expr(variables, lo),
+            unparse_expr(variables, hi)
+        ),
+        AnalyzedFactor::Index(list, index) => {
+            let list = if list.1.is_empty() && list.0 .1.is_empty() {
+                unparse_factor(variables, &list.0 .0)
+            } else {
+                format!("({})", unparse_expr(variables, list))
+            };
+            format!("{}[{}]", list, unparse_expr(variables, index))
+        }
+        AnalyzedFactor::Length(list) => format!("len({})", unparse_expr(variables, list)),
+    }
+}
+
+fn unparse_term(variables: &SymbolTable, term: &AnalyzedTerm) -> String {
+    let mut result = unparse_factor(variables, &term.0);
+    for factor in &term.1 {
+        result += match factor.0 {
+            TermOperator::Multiply => " * ",
+            TermOperator::Divide => " / ",
+            TermOperator::Exponent => " ^ ",
+            TermOperator::IntegerDivide => " div ",
+            TermOperator::MatrixMultiply => " @ ",
+        };
+        result += &unparse_factor(variables, &factor.1);
+    }
+    result
+}
+
+pub fn unparse_expr(variables: &SymbolTable, expr: &AnalyzedExpr) -> String {
+    let mut result = unparse_term(variables, &expr.0);
+    for term in &expr.1 {
+        result += match term.0 {
+            ExprOperator::Add => " + ",
+            ExprOperator::Subtract => " - ",
+            ExprOperator::Modulo => " mod ",
+            ExprOperator::PlusMinus => " +- ",
+        };
+        result += &unparse_term(variables, &term.1);
+    }
+    result
+}
+
+fn unparse_function_expr(variables: &SymbolTable, function_expr: &AnalyzedFunctionExpr) -> String {
+    format!(
+        "{}({})",
+        function_name(function_expr.0),
+        unparse_expr(variables, &function_expr.1)
+    )
+}
+
+fn unparse_annotation(variables: &SymbolTable, handle: usize) -> String {
+    match (variables.get_unit(handle), variables.get_type(handle)) {
+        (Some(unit), _) => format!(": {}", unit.name),
+        (None, Some(Type::Int)) => ": int".to_string(),
+        (None, Some(Type::Complex)) => ": complex".to_string(),
+        (None, Some(Type::Polynomial)) => ": poly".to_string(),
+        _ => String::new(),
+    }
+}
+
+fn unparse_statement(variables: &SymbolTable, statement: &AnalyzedStatement) -> String {
+    match statement {
+        AnalyzedStatement::Declaration(handle) => {
+            format!("var {}{}", variables.get_name(*handle), unparse_annotation(variables, *handle))
+        }
+        AnalyzedStatement::DeclarationToAssignment(handle, expr) => format!(
+            "var {}{} = {}",
+            variables.get_name(*handle),
+            unparse_annotation(variables, *handle),
+            unparse_expr(variables, expr)
+        ),
+        AnalyzedStatement::Assignment(handle, expr) => format!(
+            "{} = {}",
+            variables.get_name(*handle),
+            unparse_expr(variables, expr)
+        ),
+        AnalyzedStatement::InputOperation(handle, None) => format!("in {}", variables.get_name(*handle)),
+        AnalyzedStatement::InputOperation(handle, Some((lo, hi))) => format!(
+            "in {} range [{}, {}]",
+            variables.get_name(*handle),
+            lo,
+            hi
+        ),
+        AnalyzedStatement::OutputOperation(expr, unit, format, label) => format!(
+            "out {}{}{}{}",
+            label.as_ref().map_or(String::new(), |label| format!("\"{}\" = ", label)),
+            unparse_expr(variables, expr),
+            unit.as_ref().map_or(String::new(), |unit| format!(" in {}", unit.name)),
+            format.map_or(String::new(), |format| format!(" : {}", format))
+        ),
+        AnalyzedStatement::GradientOutput(expr, partials) => format!(
+            "out grad({}, {})",
+            unparse_expr(variables, expr),
+            partials
+                .iter()
+                .map(|partial| variables.get_name(partial.0))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        AnalyzedStatement::SymbolicDerivative(expr, handle) => format!(
+            "derive {} wrt {}",
+            unparse_expr(variables, expr),
+            variables.get_name(*handle)
+        ),
+        AnalyzedStatement::Seed(expr) => format!("seed({})", unparse_expr(variables, expr)),
+        // The analyzer has converted the angles to radians.
+        AnalyzedStatement::AngleMode(_) => "mode rad".to_string(),
+        AnalyzedStatement::Solve(solve) => format!(
+            "solve {} = {} for {} in [{}, {}]",
+            unparse_expr(variables, &solve.0),
+            unparse_expr(variables, &solve.1),
+            variables.get_name(solve.2),
+            unparse_expr(variables, &solve.3),
+            unparse_expr(variables, &solve.4)
+        ),
+    }
+}
+
+pub fn unparse_program(variables: &SymbolTable, program: &AnalyzedProgram) -> String {
+    let mut source = String::new();
+    for statement in program {
+        source += &unparse_statement(variables, statement);
+        source += "\n";
+    }
+    source
+}
@@ -0,0 +1,130 @@
+// Run-time values. The analyzer gives every expression one of these types;
+// ints never mix with floats without an explicit conversion, while floats
+// meeting complex numbers become complex. Lists hold floats, and operators
+// apply to them element-wise, as they do to matrices, which hold rows of
+// floats of the same length. Polynomials have f64 coefficients.
+
+use crate::complex::Complex;
+use crate::decimal::Decimal;
+use crate::interval::Interval;
+use crate::linear_algebra;
+use crate::polynomial::Polynomial;
+use crate::uncertain::Uncertain;
+use num_rational::BigRational;
+use num_traits::ToPrimitive;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Type {
+    Int,
+    Float,
+    Complex,
+    List,
+    Matrix,
+    Polynomial,
+}
+
+impl std::fmt::Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Type::Int => write!(f, "int"),
+            Type::Float => write!(f, "float"),
+            Type::Complex => write!(f, "complex"),
+            Type::List => write!(f, "list"),
+            Type::Matrix => write!(f, "matrix"),
+            Type::Polynomial => write!(f, "polynomial"),
+        }
+    }
+}
+
+// With `--rational`, the values of type float are exact fractions, and with
+// `--precision`, decimals, and with `--intervals`, enclosures of them. Floats
+// with an uncertainty are measured values.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Value {
+    Int(i64),
+    Float(f64),
+    Rational(BigRational),
+    Decimal(Decimal),
+    Complex(Complex),
+    Interval(Interval),
+    Uncertain(Uncertain),
+    List(Vec<Value>),
+    // The rows of a matrix.
+    Matrix(Vec<Vec<Value>>),
+    Polynomial(Polynomial),
+}
+
+impl Value {
+    pub fn get_type(&self) -> Type {
+        match self {
+            Value::Int(_) => Type::Int,
+            Value::Float(_)
+            | Value::Rational(_)
+            | Value::Decimal(_)
+            | Value::Interval(_)
+            | Value::Uncertain(_) => Type::Float,
+            Value::Complex(_) => Type::Complex,
+            Value::List(_) => Type::List,
+            Value::Matrix(_) => Type::Matrix,
+            Value::Polynomial(_) => Type::Polynomial,
+        }
+    }
+    /// The value itself, the real part of a complex number, the midpoint of
+    /// an interval or the nominal value of a measurement. Lists, matrices and
+    /// polynomials are not numbers.
+    pub fn as_float(&self) -> f64 {
+        match self {
+            Value::Int(value) => *value as f64,
+            Value::Float(value) => *value,
+            Value::Rational(value) => value.to_f64().unwrap_or(f64::NAN),
+            Value::Decimal(value) => value.to_f64(),
+            Value::Complex(value) => value.re,
+            Value::Interval(value) => value.midpoint(),
+            Value::Uncertain(value) => value.value,
+            Value::List(_) | Value::Matrix(_) | Value::Polynomial(_) => f64::NAN,
+        }
+    }
+    pub fn as_complex(&self) -> Complex {
+        match self {
+            Value::Complex(value) => *value,
+            value => Complex::from(value.as_float()),
+        }
+    }
+    pub fn as_uncertain(&self) -> Uncertain {
+        match self {
+            Value::Uncertain(value) => value.clone(),
+            value => Uncertain::exact(value.as_float()),
+        }
+    }
+}
+
+impl std::fmt::Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Value::Int(value) => write!(f, "{}", value),
+            Value::Float(value) => write!(f, "{}", value),
+            Value::Rational(value) => write!(f, "{}", value),
+            Value::Decimal(value) => write!(f, "{}", value),
+            Value::Complex(value) => write!(f, "{}", value),
+            Value::Interval(value) => write!(f, "{}", value),
+            Value::Uncertain(value) => write!(f, "{}", value),
+            Value::List(values) => {
+                let values: Vec<String> = values.iter().map(|value| value.to_string()).collect();
+                write!(f, "[{}]", values.join(", "))
+            }
+            Value::Matrix(rows) => write!(f, "{}", linear_algebra::format(rows)),
+            Value::Polynomial(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+/// Truncates toward zero, failing for NaN and values out of the range of i64.
+pub fn float_to_int(value: f64) -> Result<i64, String> {
+    let truncated = value.trunc();
+    // i64::MIN is a power of two, so both bounds are exact in f64.
+    if truncated >= i64::MIN as f64 && truncated < -(i64::MIN as f64) {
+        Ok(truncated as i64)
+    } else {
+        Err(format!("Error: Cannot convert {} to an int.", value))
+    }
+}
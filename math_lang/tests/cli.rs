@@ -0,0 +1,109 @@
+// Runs the `math_lang` binary on small programs written to a scratch
+// directory, and compares what the ways of running them print.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+fn scratch_dir(test: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("math_lang_{}_{}", test, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn write_program(test: &str, source: &str) -> PathBuf {
+    let path = scratch_dir(test).join("program.math");
+    std::fs::write(&path, source).unwrap();
+    path
+}
+
+// The standard output of `command`, given `input` on its standard input.
+fn output_of(command: &mut Command, input: &str) -> String {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+    let output = child.wait_with_output().unwrap();
+    String::from_utf8(output.stdout).unwrap()
+}
+
+fn math_lang(args: &[&str], path: &Path, input: &str) -> String {
+    output_of(Command::new(env!("CARGO_BIN_EXE_math_lang")).args(args).arg(path), input)
+}
+
+// Compiles the program to Rust with the flags `args`, builds it with rustc
+// and runs it.
+fn compiled(args: &[&str], path: &Path, input: &str) -> String {
+    math_lang(&[args, &["--compile"]].concat(), path, "");
+    let binary = path.with_extension("bin");
+    let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let output = Command::new(rustc)
+        .args(["--edition", "2018", "-o"])
+        .arg(&binary)
+        .arg(path.with_extension("rs"))
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    output_of(&mut Command::new(binary), input)
+}
+
+#[test]
+fn compiled_programs_read_measured_values_like_the_interpreter() {
+    let path = write_program(
+        "measured",
+        "var x\nin x\nout x * 2\nout sin(x)\nvar n: int\nin n\nout x * float(n)\n",
+    );
+    // Text that is not a value is asked for again.
+    let input = "abc\n2 +- 0.5\n2.5\n3\n";
+    let interpreted = math_lang(&["--run"], &path, input);
+    assert!(interpreted.starts_with("<output>: 4 +- 1\n<output>: 0.9092974268256817 +- 0.2080734182735712\n"));
+    assert_eq!(compiled(&[], &path, input), interpreted);
+}
+
+#[test]
+fn compiled_programs_only_measure_the_variables_that_measured_values_reach() {
+    let path = write_program(
+        "plus_minus",
+        "var a = 3\nvar g = 9.81 +- 0.02\nvar h = g * a\nout a * 2\nout h\nout a +- 0.1\n",
+    );
+    let interpreted = math_lang(&["--run"], &path, "");
+    assert!(interpreted.ends_with("<output>: 6\n<output>: 29.43 +- 0.06\n<output>: 3 +- 0.1\n"));
+    assert_eq!(compiled(&[], &path, ""), interpreted);
+    let source = std::fs::read_to_string(path.with_extension("rs")).unwrap();
+    assert!(source.contains("let mut h_uncertain"));
+    assert!(!source.contains("a_uncertain"));
+}
+
+#[test]
+fn seeded_programs_draw_the_same_numbers_interpreted_and_compiled() {
+    let path = write_program("seed", "out rand()\nout randint(1, 100)\nseed(7)\nout rand()\n");
+    let interpreted = math_lang(&["--seed", "42", "--run"], &path, "");
+    assert_eq!(
+        interpreted,
+        "<output>: 0.08386297105988216\n<output>: 3\n<output>: 0.7005764821796896\n"
+    );
+    assert_eq!(compiled(&["--seed", "42"], &path, ""), interpreted);
+}
+
+#[cfg(feature = "jit")]
+#[test]
+fn jit_prints_what_the_interpreter_prints() {
+    let programs = [
+        // Lowered by the JIT.
+        ("var a = 1.5\nvar b\nb = a * 2 + sin(a)\nout b\nout b mod 2\nvar d = 3 m\nout d in cm\n", ""),
+        // Ints and lists are run by the interpreter instead.
+        ("var n: int = 7\nout n div 2\nout [1, 2, 3] * 2\n", ""),
+        // And so are measured values, and inputs which may be measured.
+        ("var g = 9.81 +- 0.02\nvar t = 2\nout g * t * t / 2\n", ""),
+        ("var g\nin g\nout g * 2\n", "9.81 +- 0.02\n"),
+    ];
+    for (index, (source, input)) in programs.iter().enumerate() {
+        let path = write_program(&format!("jit_{}", index), source);
+        let interpreted = math_lang(&["--run"], &path, input);
+        assert!(!interpreted.is_empty());
+        assert_eq!(math_lang(&["--jit"], &path, input), interpreted);
+    }
+}